@@ -0,0 +1,51 @@
+//! Times custom formulas iterated through their closures against their native code, over a
+//! frame of the usual view. Run with `cargo bench --features jit`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mandelbrot::{Complex, Expression, Fractal};
+
+const SIZE: u32 = 200;
+const MAX_ITER: usize = 1024;
+const ESCAPE_NORM: f32 = 8192.0;
+
+const FORMULAS: [&str; 3] = ["z^2 + c", "z^3 + c*sin(z)", "z^2 + c/(z + 0.1) - 0.25*z^4"];
+
+/// Time taken to iterate a `SIZE` by `SIZE` frame, and the iterations done in it.
+fn frame(formula: &Expression) -> (Duration, usize) {
+    let start = Instant::now();
+    let mut iterations = 0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let c = Complex::new(
+                x as f32 / SIZE as f32 * 3.5 - 2.5,
+                y as f32 / SIZE as f32 * 3.5 - 1.75,
+            );
+            let z0 = Complex::new(0.0, 0.0);
+            iterations += black_box(formula.iterate(c, z0, MAX_ITER, ESCAPE_NORM)).iterations;
+        }
+    }
+    (start.elapsed(), iterations)
+}
+
+/// The fastest of a few frames, to leave out warm-up and noise.
+fn fastest(formula: &Expression) -> (Duration, usize) {
+    (0..5).map(|_| frame(formula)).min().unwrap()
+}
+
+fn main() {
+    for text in FORMULAS {
+        let native = Expression::parse(text).expect("valid formula");
+        let closures = native.without_jit();
+        let (native_time, iterations) = fastest(&native);
+        let (closures_time, _) = fastest(&closures);
+        let per_iteration = |time: Duration| time.as_nanos() as f64 / iterations as f64;
+        println!(
+            "{text:32} closures {:6.2} ns/iteration, native {:6.2} ns/iteration, {:.1}x faster",
+            per_iteration(closures_time),
+            per_iteration(native_time),
+            closures_time.as_secs_f64() / native_time.as_secs_f64(),
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::png::{header, image_data, write_chunk, SIGNATURE};
+use crate::{AnimationError, Pixel, Repeat};
+
+/// An animated PNG, in full 24 bit color instead of the 256 colors per frame of a GIF. Frames
+/// are compressed as they are pushed and written by `write_animation`, as the header has to
+/// count them before the first.
+pub struct Apng<W: Write = BufWriter<File>> {
+    writer: W,
+    width: u16,
+    height: u16,
+    /// Frame delay as a fraction of a second.
+    delay: (u16, u16),
+    repeat: Repeat,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Apng {
+    pub fn new(
+        path: impl AsRef<Path>,
+        width: u16,
+        height: u16,
+        framerate: f32,
+    ) -> Result<Self, AnimationError> {
+        let file = File::create(path).map_err(|_| AnimationError::FileCreateError)?;
+        Ok(Self::from_writer(
+            BufWriter::new(file),
+            width,
+            height,
+            framerate,
+        ))
+    }
+}
+
+impl<W: Write> Apng<W> {
+    pub fn from_writer(writer: W, width: u16, height: u16, framerate: f32) -> Self {
+        Self {
+            writer,
+            width,
+            height,
+            delay: ((1000.0 / framerate).round() as u16, 1000),
+            repeat: Repeat::Once,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets how often the APNG plays, `Repeat::Once` unless changed.
+    pub fn repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    /// Compresses the `width` by `height` `pixels` of the next frame. Alpha is dropped, as the
+    /// frames are written opaque.
+    pub fn push_frame(&mut self, pixels: &[Pixel]) -> Result<(), AnimationError> {
+        if pixels.len() != self.width as usize * self.height as usize {
+            return Err(AnimationError::FrameCreateError);
+        }
+        let raw: Vec<u8> = pixels.iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+        self.frames
+            .push(image_data(&raw, 3 * self.width as usize, 3));
+        Ok(())
+    }
+
+    pub fn write_animation(self) -> Result<(), AnimationError> {
+        let mut writer = self.writer;
+        let (width, height) = (self.width as u32, self.height as u32);
+        writer
+            .write_all(&SIGNATURE)
+            .map_err(|_| AnimationError::FrameEncodeError)?;
+        let mut write = |kind: &[u8; 4], data: &[u8]| {
+            write_chunk(&mut writer, kind, data).map_err(|_| AnimationError::FrameEncodeError)
+        };
+
+        write(b"IHDR", &header(width, height, 8, 2))?;
+        let count = self.frames.len() as u32;
+        let plays = self.repeat.plays();
+        write(
+            b"acTL",
+            &[count.to_be_bytes(), plays.to_be_bytes()].concat(),
+        )?;
+
+        // Frame controls and the data of all frames but the first share one sequence.
+        let mut sequence = 0u32;
+        for (index, data) in self.frames.iter().enumerate() {
+            let mut control = Vec::with_capacity(26);
+            control.extend(sequence.to_be_bytes());
+            control.extend(width.to_be_bytes());
+            control.extend(height.to_be_bytes());
+            // At the origin, without disposal, replacing the previous frame.
+            control.extend([0; 8]);
+            control.extend(self.delay.0.to_be_bytes());
+            control.extend(self.delay.1.to_be_bytes());
+            control.extend([0, 0]);
+            write(b"fcTL", &control)?;
+            sequence += 1;
+
+            // The first frame is the still image that viewers without APNG support show.
+            if index == 0 {
+                write(b"IDAT", data)?;
+            } else {
+                write(b"fdAT", &[&sequence.to_be_bytes()[..], data].concat())?;
+                sequence += 1;
+            }
+        }
+        write(b"IEND", &[])?;
+        writer.flush().map_err(|_| AnimationError::FrameEncodeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{inflate, read_chunks, unfilter};
+
+    #[test]
+    fn frames_decode_to_their_pixels() {
+        let frame = |shift: usize| -> Vec<Pixel> {
+            (0..6 * 4)
+                .map(|i| Pixel::from_rgb(((i + shift) % 5) as f32 / 4.0, 0.5, i as f32 / 23.0))
+                .collect()
+        };
+        let frames = [frame(0), frame(3)];
+        let mut out = Vec::new();
+        let mut apng = Apng::from_writer(&mut out, 6, 4, 10.0);
+        apng.repeat(Repeat::Times(3));
+        for pixels in &frames {
+            apng.push_frame(pixels).unwrap();
+        }
+        apng.write_animation().unwrap();
+
+        let chunks = read_chunks(&mut &out[..]).unwrap();
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(
+            kinds,
+            [b"IHDR", b"acTL", b"fcTL", b"IDAT", b"fcTL", b"fdAT"]
+        );
+        // Two frames, played three times.
+        assert_eq!(chunks[1].1, [0, 0, 0, 2, 0, 0, 0, 3]);
+        // A tenth of a second per frame.
+        assert_eq!(chunks[2].1[20..24], [0, 100, 3, 232]);
+
+        let data = [&chunks[3].1[..], &chunks[5].1[4..]];
+        for (data, pixels) in data.into_iter().zip(&frames) {
+            let raw = unfilter(&inflate(data).unwrap(), 3 * 6, 3).unwrap();
+            let rgb: Vec<u8> = pixels.iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+            assert_eq!(raw, rgb);
+        }
+    }
+}
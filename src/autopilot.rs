@@ -0,0 +1,74 @@
+use crate::Keyframe;
+
+/// Samples per side of the grid probed at every step.
+const PROBE_SIZE: u32 = 48;
+/// Cells per side the probe grid is split into when looking for detail.
+const CELLS: u32 = 6;
+
+/// Generates keyframes that keep zooming toward the most detailed part of the view, XaoS
+/// style, without any user-specified targets. `probe` returns the (smooth) iteration count of a
+/// point; detail is measured as the variance of those counts over each cell of a coarse grid.
+pub fn autopilot(
+    start: Keyframe,
+    steps: usize,
+    frames_per_step: usize,
+    zoom_per_step: f32,
+    probe: impl Fn(f32, f32) -> f32,
+) -> Vec<Keyframe> {
+    let mut view = Keyframe { index: 0, ..start };
+    let mut keyframes = vec![view];
+
+    for step in 1..=steps {
+        let (cx, cy) = most_detailed_cell(&view, &probe);
+        view = Keyframe {
+            x_center: cx,
+            y_center: cy,
+            x_size: view.x_size / zoom_per_step as f64,
+            y_size: view.y_size / zoom_per_step as f64,
+            index: step * frames_per_step,
+            ..view
+        };
+        keyframes.push(view);
+    }
+    keyframes
+}
+
+pub(crate) fn most_detailed_cell(view: &Keyframe, probe: &impl Fn(f32, f32) -> f32) -> (f64, f64) {
+    let samples: Vec<f32> = (0..PROBE_SIZE)
+        .flat_map(|y| (0..PROBE_SIZE).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (cx, cy) = view.get_coordinate(x, y, PROBE_SIZE, PROBE_SIZE);
+            probe(cx, cy)
+        })
+        .collect();
+
+    let cell = PROBE_SIZE / CELLS;
+    let mut best = (f32::MIN, view.x_center, view.y_center);
+    for cell_y in 0..CELLS {
+        for cell_x in 0..CELLS {
+            let values: Vec<f32> = (0..cell)
+                .flat_map(|y| (0..cell).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let x = cell_x * cell + x;
+                    let y = cell_y * cell + y;
+                    samples[(y * PROBE_SIZE + x) as usize]
+                })
+                .collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            let variance =
+                values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32;
+
+            // Favor cells near the middle so the camera path doesn't jerk around.
+            let offset = |c: u32| (c as f32 + 0.5) / CELLS as f32 - 0.5;
+            let distance = offset(cell_x).hypot(offset(cell_y));
+            let score = variance * (1.0 - distance);
+            if score > best.0 {
+                let center_x = cell_x * cell + cell / 2;
+                let center_y = cell_y * cell + cell / 2;
+                let (cx, cy) = view.get_coordinate_f64(center_x, center_y, PROBE_SIZE, PROBE_SIZE);
+                best = (score, cx, cy);
+            }
+        }
+    }
+    (best.1, best.2)
+}
@@ -0,0 +1,459 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::{Complex, EscapeResult};
+
+/// Signed fixed-point number with a 32-bit integer part and any number of 32-bit fraction limbs,
+/// in two's complement with the least significant limb first. The integer part covers every
+/// orbit value up to the escape radius, the fraction as deep a zoom as it has bits for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigFixed {
+    limbs: Vec<u32>,
+}
+
+impl BigFixed {
+    pub fn zero(fraction_bits: u32) -> Self {
+        Self {
+            limbs: vec![0; fraction_bits.div_ceil(32) as usize + 1],
+        }
+    }
+
+    /// `value`, exactly if `fraction_bits` are enough to hold it and it is below 2^31.
+    pub fn from_f64(value: f64, fraction_bits: u32) -> Self {
+        let mut number = Self::zero(fraction_bits);
+        let fraction_limbs = number.limbs.len() - 1;
+        let magnitude = value.abs();
+        number.limbs[fraction_limbs] = magnitude.trunc() as u32;
+        let mut rest = magnitude.fract();
+        for limb in number.limbs[..fraction_limbs].iter_mut().rev() {
+            rest *= 4294967296.0;
+            *limb = rest.trunc() as u32;
+            rest = rest.fract();
+        }
+        match value < 0.0 {
+            true => number.negate(),
+            false => number,
+        }
+    }
+
+    /// Parses a decimal number such as `-0.7436438870371587047521915` or `1.5e-40`, to the
+    /// nearest multiple of 2^-fraction_bits. `None` if it isn't one, or it is out of the range
+    /// of the integer part.
+    pub fn parse_decimal(text: &str, fraction_bits: u32) -> Option<Self> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits: Vec<u32> = integer
+            .chars()
+            .chain(fraction.chars())
+            .map(|digit| digit.to_digit(10))
+            .collect::<Option<_>>()?;
+        if digits.is_empty() {
+            return None;
+        }
+        // `0.d1 d2 d3 ...`, then shifted to where the point is, with a limb below the precision
+        // to round off the truncating divisions with.
+        let mut number = Self::zero(fraction_bits + 32);
+        let integer_limb = number.limbs.len() - 1;
+        for &digit in digits.iter().rev() {
+            number.limbs[integer_limb] += digit;
+            number.div_small(10);
+        }
+        let point = integer.len() as i32 + exponent;
+        for _ in 0..point.max(0) {
+            if number.limbs[integer_limb] >= i32::MAX as u32 / 10 {
+                return None;
+            }
+            number.mul_small(10);
+        }
+        for _ in point..0 {
+            number.div_small(10);
+        }
+        let guard = number.limbs.remove(0);
+        if guard >= 1 << 31 {
+            for limb in &mut number.limbs {
+                let (sum, overflow) = limb.overflowing_add(1);
+                *limb = sum;
+                if !overflow {
+                    break;
+                }
+            }
+        }
+        match negative {
+            true => Some(number.negate()),
+            false => Some(number),
+        }
+    }
+
+    /// The decimal expansion to `digits` places after the point, truncated and without
+    /// trailing zeros, in the form read by `parse_decimal`.
+    pub fn to_decimal(&self, digits: usize) -> String {
+        let mut magnitude = match self.is_negative() {
+            true => self.negate(),
+            false => self.clone(),
+        };
+        let integer = std::mem::take(magnitude.limbs.last_mut().unwrap());
+        let mut text = format!("{}{integer}.", if self.is_negative() { "-" } else { "" });
+        for _ in 0..digits {
+            magnitude.mul_small(10);
+            let digit = std::mem::take(magnitude.limbs.last_mut().unwrap());
+            text.push(char::from_digit(digit, 10).unwrap());
+        }
+        let text = text.trim_end_matches('0');
+        text.strip_suffix('.').unwrap_or(text).to_owned()
+    }
+
+    /// Multiplies a non-negative `self` by `factor`, dropping any overflow of the integer part.
+    fn mul_small(&mut self, factor: u32) {
+        let mut carry = 0u64;
+        for limb in &mut self.limbs {
+            let product = *limb as u64 * factor as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+    }
+
+    /// Divides a non-negative `self` by `divisor`, truncating.
+    fn div_small(&mut self, divisor: u32) {
+        let mut remainder = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let dividend = remainder << 32 | *limb as u64;
+            *limb = (dividend / divisor as u64) as u32;
+            remainder = dividend % divisor as u64;
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = match self.is_negative() {
+            true => self.negate(),
+            false => self.clone(),
+        };
+        let fraction_limbs = self.limbs.len() as i32 - 1;
+        let value = magnitude
+            .limbs
+            .iter()
+            .enumerate()
+            .map(|(i, &limb)| limb as f64 * 2f64.powi(32 * (i as i32 - fraction_limbs)))
+            .sum::<f64>();
+        match self.is_negative() {
+            true => -value,
+            false => value,
+        }
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.limbs.last().is_some_and(|limb| limb >> 31 == 1)
+    }
+
+    pub fn negate(&self) -> Self {
+        let mut carry = true;
+        let limbs = self
+            .limbs
+            .iter()
+            .map(|&limb| {
+                let (sum, overflow) = (!limb).overflowing_add(carry as u32);
+                carry = overflow;
+                sum
+            })
+            .collect();
+        Self { limbs }
+    }
+
+    /// Twice `self`, cheaper than adding it to itself.
+    pub fn double(&self) -> Self {
+        let mut carry = 0;
+        let limbs = self
+            .limbs
+            .iter()
+            .map(|&limb| {
+                let doubled = (limb << 1) | carry;
+                carry = limb >> 31;
+                doubled
+            })
+            .collect();
+        Self { limbs }
+    }
+}
+
+impl Add for &BigFixed {
+    type Output = BigFixed;
+
+    fn add(self, rhs: &BigFixed) -> BigFixed {
+        let mut carry = false;
+        let limbs = self
+            .limbs
+            .iter()
+            .zip(&rhs.limbs)
+            .map(|(&a, &b)| {
+                let (sum, first) = a.overflowing_add(b);
+                let (sum, second) = sum.overflowing_add(carry as u32);
+                carry = first || second;
+                sum
+            })
+            .collect();
+        BigFixed { limbs }
+    }
+}
+
+impl Sub for &BigFixed {
+    type Output = BigFixed;
+
+    fn sub(self, rhs: &BigFixed) -> BigFixed {
+        let mut borrow = false;
+        let limbs = self
+            .limbs
+            .iter()
+            .zip(&rhs.limbs)
+            .map(|(&a, &b)| {
+                let (difference, first) = a.overflowing_sub(b);
+                let (difference, second) = difference.overflowing_sub(borrow as u32);
+                borrow = first || second;
+                difference
+            })
+            .collect();
+        BigFixed { limbs }
+    }
+}
+
+impl Mul for &BigFixed {
+    type Output = BigFixed;
+
+    /// Truncates the product toward 0 to the precision of `self`.
+    fn mul(self, rhs: &BigFixed) -> BigFixed {
+        let negative = self.is_negative() != rhs.is_negative();
+        let a = if self.is_negative() {
+            self.negate()
+        } else {
+            self.clone()
+        };
+        let b = if rhs.is_negative() {
+            rhs.negate()
+        } else {
+            rhs.clone()
+        };
+
+        let n = a.limbs.len();
+        let mut product = vec![0u32; 2 * n];
+        for (i, &x) in a.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &y) in b.limbs.iter().enumerate() {
+                let sum = product[i + j] as u64 + x as u64 * y as u64 + carry;
+                product[i + j] = sum as u32;
+                carry = sum >> 32;
+            }
+            product[i + n] = carry as u32;
+        }
+        // Drops the extra fraction limbs below the precision and the overflow above the integer.
+        let magnitude = BigFixed {
+            limbs: product[n - 1..2 * n - 1].to_vec(),
+        };
+        match negative {
+            true => magnitude.negate(),
+            false => magnitude,
+        }
+    }
+}
+
+/// Iterates `z^2 + c` from `z0` with both in fixed point, for views too deep for f64.
+pub fn iterate_mandelbrot(
+    (cx, cy): (&BigFixed, &BigFixed),
+    (z0_x, z0_y): (BigFixed, BigFixed),
+    max_iter: usize,
+    escape_norm: f32,
+) -> EscapeResult {
+    let (mut x, mut y) = (z0_x, z0_y);
+    let mut iters = 0;
+
+    while iters < max_iter {
+        let (xx, yy) = (&x * &x, &y * &y);
+        if xx.to_f64() + yy.to_f64() >= escape_norm as f64 {
+            break;
+        }
+        y = &(&x * &y).double() + cy;
+        x = &(&xx - &yy) + cx;
+        iters += 1;
+    }
+    let z = Complex::new(x.to_f64() as f32, y.to_f64() as f32);
+    EscapeResult::from_orbit(z, iters, iters < max_iter, escape_norm, 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use dashu_int::IBig;
+
+    use super::*;
+    use crate::buddhabrot::SplitMix;
+
+    fn fixed(value: f64) -> BigFixed {
+        BigFixed::from_f64(value, 96)
+    }
+
+    /// `number` times 2^fraction_bits, the integer its limbs hold.
+    fn scaled(number: &BigFixed) -> IBig {
+        let value = number
+            .limbs
+            .iter()
+            .rev()
+            .fold(IBig::ZERO, |value, &limb| (value << 32) + IBig::from(limb));
+        match number.is_negative() {
+            true => value - (IBig::ONE << (32 * number.limbs.len())),
+            false => value,
+        }
+    }
+
+    /// A number of `limbs` fraction limbs with random bits, and an integer part below 2^15 in
+    /// magnitude so sums and products don't overflow.
+    fn random(rng: &mut SplitMix, limbs: usize) -> BigFixed {
+        let mut limbs: Vec<u32> = (0..=limbs).map(|_| rng.next_u64() as u32).collect();
+        // Deep zoom values are mostly zero limbs with a few set ones.
+        for limb in &mut limbs {
+            if rng.next_u64().is_multiple_of(3) {
+                *limb = 0;
+            }
+        }
+        let integer = limbs.last_mut().unwrap();
+        *integer = (*integer as i32 >> 17) as u32;
+        BigFixed { limbs }
+    }
+
+    /// `digits` decimal places of `value / 2^bits`, truncated, as `to_decimal` writes them.
+    fn decimal(value: &IBig, bits: usize, digits: usize) -> String {
+        let sign = if *value < IBig::ZERO { "-" } else { "" };
+        let magnitude = if *value < IBig::ZERO {
+            -value
+        } else {
+            value.clone()
+        };
+        let integer = &magnitude >> bits;
+        let fraction = &magnitude - (&integer << bits);
+        let places = (fraction * IBig::from(10u8).pow(digits)) >> bits;
+        let text = format!("{sign}{integer}.{places:0>digits$}");
+        let text = text.trim_end_matches('0');
+        text.strip_suffix('.').unwrap_or(text).to_owned()
+    }
+
+    #[test]
+    fn arithmetic_matches_exact_integers() {
+        let mut rng = SplitMix(1);
+        for limbs in [1, 2, 4, 7] {
+            let bits = 32 * limbs;
+            for _ in 0..500 {
+                let (a, b) = (random(&mut rng, limbs), random(&mut rng, limbs));
+                let (x, y) = (scaled(&a), scaled(&b));
+                assert_eq!(scaled(&(&a + &b)), &x + &y);
+                assert_eq!(scaled(&(&a - &b)), &x - &y);
+                assert_eq!(scaled(&a.negate()), -&x);
+                assert_eq!(scaled(&a.double()), &x + &x);
+                // Truncated toward 0, which `/` does too.
+                let product = &x * &y / (IBig::ONE << bits);
+                assert_eq!(scaled(&(&a * &b)), product, "{:?} * {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn decimals_match_exact_integers() {
+        let mut rng = SplitMix(2);
+        for limbs in [1, 3, 5] {
+            let bits = 32 * limbs;
+            for _ in 0..200 {
+                let number = random(&mut rng, limbs);
+                let digits = (rng.next_u64() % 60) as usize;
+                assert_eq!(
+                    number.to_decimal(digits),
+                    decimal(&scaled(&number), bits, digits)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn parsing_rounds_to_the_nearest() {
+        let mut rng = SplitMix(3);
+        for limbs in [1, 2, 4] {
+            let bits = 32 * limbs;
+            for _ in 0..300 {
+                let digits: String = (0..1 + rng.next_u64() % 40)
+                    .map(|_| char::from(b'0' + (rng.next_u64() % 10) as u8))
+                    .collect();
+                let point = (rng.next_u64() % 5) as usize;
+                let exponent = (rng.next_u64() % 50) as i32 - 45;
+                let negative = rng.next_u64() >> 63 == 1;
+                let point = point.min(digits.len());
+                let text = format!(
+                    "{}{}.{}e{exponent}",
+                    if negative { "-" } else { "" },
+                    &digits[..point],
+                    &digits[point..]
+                );
+                // digits * 10^(exponent - fraction digits), times 2^bits, to the nearest integer.
+                let places = exponent - (digits.len() - point) as i32;
+                let mantissa: IBig = digits.parse().unwrap();
+                let scale = IBig::from(10u8).pow(places.unsigned_abs() as usize);
+                let (numerator, denominator): (IBig, IBig) = match places >= 0 {
+                    true => ((mantissa << bits) * scale, IBig::ONE),
+                    false => (mantissa << bits, scale),
+                };
+                let nearest: IBig = (numerator * 2 + &denominator) / (denominator * 2);
+                let expected = if negative { -nearest } else { nearest };
+                let parsed = BigFixed::parse_decimal(&text, bits as u32).unwrap();
+                assert_eq!(scaled(&parsed), expected, "{}", text);
+            }
+        }
+    }
+
+    #[test]
+    fn f64_values_convert_exactly() {
+        for value in [0.0, 1.0, -1.0, 0.1, -2.75, 1e-10, -123456.789] {
+            assert_eq!(fixed(value).to_f64(), value);
+        }
+        assert!(fixed(-0.5).is_negative() && !fixed(0.5).is_negative());
+    }
+
+    #[test]
+    fn decimals_keep_digits_past_f64() {
+        let text = "-0.7436438870371587047521915061147";
+        let number = BigFixed::parse_decimal(text, 128).unwrap();
+        assert_eq!(number.to_decimal(31), text);
+        assert_eq!(number.to_f64(), -0.7436438870371587);
+
+        let small = BigFixed::parse_decimal("1.5e-20", 128).unwrap();
+        assert_eq!(small.to_decimal(21), "0.000000000000000000015");
+        assert_eq!(BigFixed::parse_decimal("+12.5", 32).unwrap().to_f64(), 12.5);
+        assert!(BigFixed::parse_decimal("1e10", 32).is_none());
+        assert!(BigFixed::parse_decimal("0.1x", 32).is_none());
+        assert!(BigFixed::parse_decimal("", 32).is_none());
+    }
+
+    #[test]
+    fn arithmetic_matches_f64() {
+        let (a, b) = (1.25, -0.375);
+        assert_eq!((&fixed(a) + &fixed(b)).to_f64(), a + b);
+        assert_eq!((&fixed(a) - &fixed(b)).to_f64(), a - b);
+        assert_eq!((&fixed(a) * &fixed(b)).to_f64(), a * b);
+        assert_eq!((&fixed(b) * &fixed(b)).to_f64(), b * b);
+        assert_eq!(fixed(b).double().to_f64(), 2.0 * b);
+        assert_eq!(fixed(a).negate(), fixed(-a));
+        assert_eq!(fixed(0.0).negate(), fixed(0.0));
+    }
+
+    #[test]
+    fn products_keep_the_low_bits() {
+        // (1 + 2^-60)^2 = 1 + 2^-59 + 2^-120, the last bit past f64 but within 128 bits.
+        let x = BigFixed::parse_decimal(
+            "1.000000000000000000867361737988403547205962240695953369140625",
+            128,
+        )
+        .unwrap();
+        let square = &x * &x;
+        let expected = &(&x + &x) - &BigFixed::from_f64(1.0, 128);
+        let epsilon = &square - &expected;
+        assert_eq!(epsilon.to_f64(), 2f64.powi(-120));
+    }
+}
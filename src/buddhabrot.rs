@@ -0,0 +1,189 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use rayon::prelude::*;
+
+use crate::{Bailout, Complex, Fractal, Keyframe, Pixel};
+
+/// Orbits traced per parallel work item, each drawing from its own random stream.
+const BATCH: usize = 4096;
+/// Sampled points lie in the square of this half width around 0, which holds every point whose
+/// orbit stays bounded for more than one iteration.
+const SAMPLE_RADIUS: f32 = 2.0;
+const ESCAPE_NORM: f32 = 4.0;
+
+/// Density rendering: instead of coloring each pixel by its own orbit, random points are
+/// iterated and every pixel the orbits of escaping points pass through is counted. With
+/// different iteration caps per color channel this is the "Nebulabrot".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Buddhabrot {
+    /// Points iterated per frame. Deep views see only a small part of every orbit and need a
+    /// lot more of them.
+    pub samples: usize,
+    /// Red, green and blue count orbits that escape within these many iterations.
+    pub max_iter: [usize; 3],
+    pub seed: u64,
+}
+
+impl Buddhabrot {
+    /// Grayscale density of orbits escaping within `max_iter` iterations.
+    pub const fn new(samples: usize, max_iter: usize) -> Self {
+        Self::nebulabrot(samples, [max_iter; 3])
+    }
+
+    pub const fn nebulabrot(samples: usize, max_iter: [usize; 3]) -> Self {
+        Self {
+            samples,
+            max_iter,
+            seed: 0,
+        }
+    }
+
+    pub fn render(
+        &self,
+        fractal: &dyn Fractal,
+        view: &Keyframe,
+        width: u32,
+        height: u32,
+    ) -> Vec<Pixel> {
+        let histogram = self.accumulate(fractal, view, width, height);
+        let mut peak = [1u32; 3];
+        for counts in histogram.chunks_exact(3) {
+            for channel in 0..3 {
+                peak[channel] = peak[channel].max(counts[channel]);
+            }
+        }
+        // The square root brings out the faint outer orbits next to the dense core.
+        let tone = |count: u32, channel: usize| (count as f32 / peak[channel] as f32).sqrt();
+        histogram
+            .chunks_exact(3)
+            .map(|counts| {
+                Pixel::from_rgb(tone(counts[0], 0), tone(counts[1], 1), tone(counts[2], 2))
+            })
+            .collect()
+    }
+
+    /// Orbit visits per pixel of `view`, row by row with the red, green and blue counts of each
+    /// pixel next to each other.
+    pub fn accumulate(
+        &self,
+        fractal: &dyn Fractal,
+        view: &Keyframe,
+        width: u32,
+        height: u32,
+    ) -> Vec<u32> {
+        let histogram: Vec<AtomicU32> =
+            (0..width * height * 3).map(|_| AtomicU32::new(0)).collect();
+        let cap = self.max_iter.into_iter().max().unwrap_or(0);
+        let z0 = Complex::new(view.z0_x, view.z0_y);
+
+        (0..self.samples.div_ceil(BATCH))
+            .into_par_iter()
+            .for_each(|batch| {
+                let mut random =
+                    SplitMix(self.seed ^ (batch as u64).wrapping_mul(0x9e3779b97f4a7c15));
+                let count = BATCH.min(self.samples - batch * BATCH);
+                for _ in 0..count {
+                    let c = Complex::new(
+                        (random.next_f32() * 2.0 - 1.0) * SAMPLE_RADIUS,
+                        (random.next_f32() * 2.0 - 1.0) * SAMPLE_RADIUS,
+                    );
+                    let Some(escape) = escape_time(fractal, c, z0, cap) else {
+                        continue;
+                    };
+                    let channels = self.max_iter.map(|limit| escape <= limit);
+
+                    // Replays the orbit rather than storing it, most samples don't escape.
+                    let mut z = z0;
+                    for _ in 0..escape {
+                        z = fractal.step(z, c);
+                        let (x, y) = view.get_pixel(z.x, z.y, width, height);
+                        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+                            continue;
+                        }
+                        let pixel = (y as u32 * width + x as u32) as usize * 3;
+                        for (channel, counted) in channels.iter().enumerate() {
+                            if *counted {
+                                histogram[pixel + channel].fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                }
+            });
+        histogram.into_iter().map(AtomicU32::into_inner).collect()
+    }
+}
+
+/// Iterations until the orbit of `c` escapes, `None` if it converges or doesn't escape within
+/// `cap`.
+fn escape_time(fractal: &dyn Fractal, c: Complex, z0: Complex, cap: usize) -> Option<usize> {
+    let mut z = z0;
+    for iteration in 1..=cap {
+        z = fractal.step(z, c);
+        if fractal.bailout(z, ESCAPE_NORM) == Some(Bailout::Escaped) {
+            return Some(iteration);
+        }
+    }
+    None
+}
+
+/// Small, fast and good enough for sampling; the output only has to be deterministic.
+pub(crate) struct SplitMix(pub(crate) u64);
+
+impl SplitMix {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..1`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mandelbrot;
+
+    /// Pulls every orbit toward a fifth of its `c`, inside the view but never escaping.
+    struct Settling;
+
+    impl Fractal for Settling {
+        fn name(&self) -> String {
+            "settling".to_owned()
+        }
+
+        fn step(&self, z: Complex, c: Complex) -> Complex {
+            Complex::new(0.5 * z.x + 0.1 * c.x, 0.5 * z.y + 0.1 * c.y)
+        }
+    }
+
+    #[test]
+    fn bounded_orbits_leave_no_trace() {
+        let view = Keyframe::view(0.0, 0.0, 4.0, 4.0);
+        let histogram = Buddhabrot::new(10_000, 100).accumulate(&Settling, &view, 32, 32);
+        assert!(histogram.iter().all(|&count| count == 0));
+        let histogram = Buddhabrot::new(10_000, 100).accumulate(&Mandelbrot, &view, 32, 32);
+        assert!(histogram.iter().any(|&count| count > 0));
+    }
+
+    #[test]
+    fn lower_caps_count_fewer_orbits() {
+        let view = Keyframe::view(-0.5, 0.0, 3.0, 3.0);
+        let nebulabrot = Buddhabrot::nebulabrot(20_000, [20, 200, 200]);
+        let histogram = nebulabrot.accumulate(&Mandelbrot, &view, 32, 32);
+        let mut totals = [0u64; 3];
+        for counts in histogram.chunks_exact(3) {
+            assert!(counts[0] <= counts[1]);
+            assert_eq!(counts[1], counts[2]);
+            for channel in 0..3 {
+                totals[channel] += counts[channel] as u64;
+            }
+        }
+        assert!(0 < totals[0] && totals[0] < totals[1]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::{AnimationError, Frame};
+
+/// Shared memory accounting for buffered frames and in-flight renders.
+pub struct MemoryBudget {
+    limit: usize,
+    used: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Bytes held against a `MemoryBudget`, given back when dropped.
+pub struct Reservation {
+    budget: Arc<MemoryBudget>,
+    bytes: usize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        })
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        *self.used.lock().unwrap()
+    }
+
+    /// Blocks until `bytes` fit in the budget. A request larger than the whole budget is let
+    /// through once nothing else is reserved, so it can't wait forever.
+    pub fn acquire(self: &Arc<Self>, bytes: usize) -> Reservation {
+        let mut used = self.used.lock().unwrap();
+        while *used > 0 && *used + bytes > self.limit {
+            used = self.released.wait(used).unwrap();
+        }
+        *used += bytes;
+        Reservation {
+            budget: Arc::clone(self),
+            bytes,
+        }
+    }
+
+    /// Reserves `bytes` only if that keeps usage at or below `ceiling`.
+    pub fn try_acquire(self: &Arc<Self>, bytes: usize, ceiling: usize) -> Option<Reservation> {
+        let mut used = self.used.lock().unwrap();
+        if *used + bytes > ceiling.min(self.limit) {
+            return None;
+        }
+        *used += bytes;
+        Some(Reservation {
+            budget: Arc::clone(self),
+            bytes,
+        })
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        *self.budget.used.lock().unwrap() -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+enum Slot {
+    Empty,
+    Memory(gif::Frame<'static>, Option<Reservation>),
+    Spilled { offset: u64 },
+}
+
+struct Spill {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    len: u64,
+}
+
+struct StoreState {
+    slots: Vec<Slot>,
+    spill: Option<Spill>,
+}
+
+/// Index-addressed frame buffer that can be filled from several workers. Under a memory budget
+/// it holds at most half the budget in memory, leaving the rest as working space for renderers,
+/// and spills the overflow to a temporary file.
+pub struct FrameStore {
+    budget: Option<Arc<MemoryBudget>>,
+    spill_dir: PathBuf,
+    state: Mutex<StoreState>,
+}
+
+impl FrameStore {
+    /// A store that keeps every frame in memory.
+    pub fn new(len: usize) -> Self {
+        Self::build(len, None, std::env::temp_dir())
+    }
+
+    pub fn with_budget(len: usize, budget: Arc<MemoryBudget>, spill_dir: impl AsRef<Path>) -> Self {
+        Self::build(len, Some(budget), spill_dir.as_ref().to_path_buf())
+    }
+
+    fn build(len: usize, budget: Option<Arc<MemoryBudget>>, spill_dir: PathBuf) -> Self {
+        let slots = (0..len).map(|_| Slot::Empty).collect();
+        Self {
+            budget,
+            spill_dir,
+            state: Mutex::new(StoreState { slots, spill: None }),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of leading slots that are filled, i.e. how much of the animation is playable.
+    pub fn completed_prefix(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state
+            .slots
+            .iter()
+            .take_while(|slot| !matches!(slot, Slot::Empty))
+            .count()
+    }
+
+    /// Drops every slot from `len` on, keeping the store contiguous after a cancelled render.
+    pub fn truncate(&self, len: usize) {
+        self.state.lock().unwrap().slots.truncate(len);
+    }
+
+    pub fn insert(&self, index: usize, frame: Frame) -> Result<(), AnimationError> {
+        let frame = frame.inner;
+        let bytes = frame_bytes(&frame);
+        let reservation = match &self.budget {
+            Some(budget) => match budget.try_acquire(bytes, budget.limit() / 2) {
+                Some(reservation) => Some(reservation),
+                None => return self.spill(index, &frame),
+            },
+            None => None,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        state.slots[index] = Slot::Memory(frame, reservation);
+        Ok(())
+    }
+
+    fn spill(&self, index: usize, frame: &gif::Frame) -> Result<(), AnimationError> {
+        let mut state = self.state.lock().unwrap();
+        if state.spill.is_none() {
+            state.spill = Some(self.create_spill()?);
+        }
+
+        let spill = state.spill.as_mut().unwrap();
+        let offset = spill.len;
+        let encoded = encode_frame(frame);
+        spill
+            .writer
+            .write_all(&encoded)
+            .map_err(|_| AnimationError::FrameSpillError)?;
+        spill.len += encoded.len() as u64;
+
+        state.slots[index] = Slot::Spilled { offset };
+        Ok(())
+    }
+
+    fn create_spill(&self) -> Result<Spill, AnimationError> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "mandelbrot-spill-{}-{}.bin",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = self.spill_dir.join(name);
+        let file = File::create(&path).map_err(|_| AnimationError::FrameSpillError)?;
+        Ok(Spill {
+            path,
+            writer: BufWriter::new(file),
+            len: 0,
+        })
+    }
+
+    /// Visits the stored frames in index order, reading spilled ones back one at a time.
+    pub(crate) fn for_each(
+        self,
+        mut f: impl FnMut(gif::Frame<'static>) -> Result<(), AnimationError>,
+    ) -> Result<(), AnimationError> {
+        let mut state = self.state.into_inner().unwrap();
+        let mut reader = match state.spill.as_mut() {
+            Some(spill) => {
+                spill
+                    .writer
+                    .flush()
+                    .map_err(|_| AnimationError::FrameSpillError)?;
+                let file = File::open(&spill.path).map_err(|_| AnimationError::FrameSpillError)?;
+                Some(BufReader::new(file))
+            }
+            None => None,
+        };
+
+        for slot in state.slots.drain(..) {
+            match slot {
+                Slot::Empty => {}
+                Slot::Memory(frame, _reservation) => f(frame)?,
+                Slot::Spilled { offset } => {
+                    let reader = reader.as_mut().ok_or(AnimationError::FrameSpillError)?;
+                    reader
+                        .seek(SeekFrom::Start(offset))
+                        .map_err(|_| AnimationError::FrameSpillError)?;
+                    f(decode_frame(reader)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StoreState {
+    fn drop(&mut self) {
+        if let Some(spill) = &self.spill {
+            let _ = fs::remove_file(&spill.path);
+        }
+    }
+}
+
+fn frame_bytes(frame: &gif::Frame) -> usize {
+    frame.buffer.len() + frame.palette.as_ref().map_or(0, Vec::len)
+}
+
+fn encode_frame(frame: &gif::Frame) -> Vec<u8> {
+    let palette = frame.palette.as_deref().unwrap_or_default();
+    let mut out = Vec::with_capacity(frame_bytes(frame) + 16);
+    out.extend_from_slice(&frame.width.to_le_bytes());
+    out.extend_from_slice(&frame.height.to_le_bytes());
+    out.extend_from_slice(&frame.left.to_le_bytes());
+    out.extend_from_slice(&frame.top.to_le_bytes());
+    match frame.transparent {
+        Some(index) => out.extend_from_slice(&[1, index]),
+        None => out.extend_from_slice(&[0, 0]),
+    }
+    out.extend_from_slice(&(palette.len() as u32).to_le_bytes());
+    out.extend_from_slice(palette);
+    out.extend_from_slice(&(frame.buffer.len() as u32).to_le_bytes());
+    out.extend_from_slice(&frame.buffer);
+    out
+}
+
+fn decode_frame(reader: &mut impl Read) -> Result<gif::Frame<'static>, AnimationError> {
+    let mut read = |len: usize| {
+        let mut bytes = vec![0; len];
+        reader
+            .read_exact(&mut bytes)
+            .map(|_| bytes)
+            .map_err(|_| AnimationError::FrameSpillError)
+    };
+    let u16_at = |bytes: &[u8], i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+    let u32_of = |bytes: Vec<u8>| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+
+    let header = read(10)?;
+    let palette_len = u32_of(read(4)?) as usize;
+    let palette = read(palette_len)?;
+    let buffer_len = u32_of(read(4)?) as usize;
+    let buffer = read(buffer_len)?;
+
+    Ok(gif::Frame {
+        width: u16_at(&header, 0),
+        height: u16_at(&header, 2),
+        left: u16_at(&header, 4),
+        top: u16_at(&header, 6),
+        transparent: (header[8] == 1).then_some(header[9]),
+        palette: (!palette.is_empty()).then_some(palette),
+        buffer: Cow::Owned(buffer),
+        ..gif::Frame::default()
+    })
+}
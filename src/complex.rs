@@ -0,0 +1,169 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Complex {
+    pub fn new(x: f32, y: f32) -> Self {
+        Complex { x, y }
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.x * self.x + self.y * self.y
+    }
+
+    pub fn arg(&self) -> f32 {
+        self.y.atan2(self.x)
+    }
+
+    /// A real number, or a complex one written as `a+bi`, `a-bi` or `bi`.
+    pub fn parse(text: &str) -> Option<Self> {
+        if let Ok(x) = text.parse() {
+            return Some(Complex::new(x, 0.0));
+        }
+        let text = text.strip_suffix('i')?;
+        // The sign of the imaginary part is the last one not starting the number or an exponent.
+        let split = text
+            .char_indices()
+            .rev()
+            .find(|&(i, c)| (c == '+' || c == '-') && i > 0 && !text[..i].ends_with(['e', 'E']))
+            .map_or(0, |(i, _)| i);
+        let x = match split {
+            0 => 0.0,
+            _ => text[..split].parse().ok()?,
+        };
+        let y = text[split..].parse().ok()?;
+        Some(Complex::new(x, y))
+    }
+
+    /// `self^n` by repeated squaring.
+    pub fn powi(self, n: u32) -> Self {
+        let mut result = Complex::new(1.0, 0.0);
+        let mut base = self;
+        let mut n = n;
+        while n > 0 {
+            if n & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            n >>= 1;
+        }
+        result
+    }
+
+    /// `self^d` on the principal branch, exact for small integer exponents.
+    pub fn powf(self, d: f32) -> Self {
+        if d.fract() == 0.0 && (0.0..=64.0).contains(&d) {
+            return self.powi(d as u32);
+        }
+        let magnitude = self.norm().powf(d / 2.0);
+        let angle = self.arg() * d;
+        Complex::new(magnitude * angle.cos(), magnitude * angle.sin())
+    }
+
+    /// `self^w` on the principal branch, `exp(w ln self)`.
+    pub fn pow(self, w: Complex) -> Self {
+        match w.y == 0.0 {
+            true => self.powf(w.x),
+            false => (w * self.ln()).exp(),
+        }
+    }
+
+    pub fn conj(self) -> Self {
+        Complex::new(self.x, -self.y)
+    }
+
+    pub fn exp(self) -> Self {
+        let magnitude = self.x.exp();
+        Complex::new(magnitude * self.y.cos(), magnitude * self.y.sin())
+    }
+
+    /// Principal branch, with the cut along the negative reals.
+    pub fn ln(self) -> Self {
+        Complex::new(self.norm().ln() / 2.0, self.arg())
+    }
+
+    /// Principal square root.
+    pub fn sqrt(self) -> Self {
+        self.powf(0.5)
+    }
+
+    pub fn sin(self) -> Self {
+        Complex::new(self.x.sin() * self.y.cosh(), self.x.cos() * self.y.sinh())
+    }
+
+    pub fn cos(self) -> Self {
+        Complex::new(self.x.cos() * self.y.cosh(), -self.x.sin() * self.y.sinh())
+    }
+
+    pub fn tan(self) -> Self {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(self) -> Self {
+        Complex::new(self.x.sinh() * self.y.cos(), self.x.cosh() * self.y.sin())
+    }
+
+    pub fn cosh(self) -> Self {
+        Complex::new(self.x.cosh() * self.y.cos(), self.x.sinh() * self.y.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Complex {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Complex {
+            x: self.x * rhs.x - self.y * rhs.y,
+            y: self.x * rhs.y + self.y * rhs.x,
+        }
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Complex {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let norm = rhs.norm();
+        Complex {
+            x: (self.x * rhs.x + self.y * rhs.y) / norm,
+            y: (self.y * rhs.x - self.x * rhs.y) / norm,
+        }
+    }
+}
+
+/// The form read by `Complex::parse`.
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.y == 0.0 {
+            true => write!(f, "{}", self.x),
+            false => write!(f, "{}{:+}i", self.x, self.y),
+        }
+    }
+}
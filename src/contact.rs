@@ -0,0 +1,162 @@
+use crate::{Image, Pixel};
+
+/// Gap between thumbnails and around the sheet.
+const SPACING: u32 = 4;
+/// Glyphs are 3x5 cells, drawn this many pixels per cell.
+const FONT_SCALE: u32 = 2;
+const LABEL_HEIGHT: u32 = 5 * FONT_SCALE + SPACING;
+
+/// Lays out `frames` as a grid of `thumb_width` pixel wide thumbnails, `columns` per row, each
+/// with its label printed underneath. Labels are limited to digits, spaces and `#.-+ex`. No
+/// columns at all are taken as one.
+pub fn contact_sheet(frames: &[(Image, String)], columns: u32, thumb_width: u32) -> Image {
+    let columns = columns.max(1);
+    let background = Pixel::from_rgb(0.1, 0.1, 0.1);
+    let ink = Pixel::from_rgb(1.0, 1.0, 1.0);
+    let (source_width, source_height) = frames
+        .first()
+        .map_or((1, 1), |(image, _)| (image.width, image.height));
+    let thumb_height = (thumb_width * source_height / source_width.max(1)).max(1);
+
+    let rows = (frames.len() as u32).div_ceil(columns);
+    let cell_width = thumb_width + SPACING;
+    let cell_height = thumb_height + LABEL_HEIGHT + SPACING;
+    let width = columns * cell_width + SPACING;
+    let height = rows * cell_height + SPACING;
+    let mut sheet = Image {
+        width,
+        height,
+        pixels: vec![background; (width * height) as usize],
+    };
+
+    for (i, (image, label)) in frames.iter().enumerate() {
+        let left = SPACING + i as u32 % columns * cell_width;
+        let top = SPACING + i as u32 / columns * cell_height;
+        for y in 0..thumb_height {
+            for x in 0..thumb_width {
+                let pixel = box_sample(image, x, y, thumb_width, thumb_height);
+                sheet.pixels[((top + y) * width + left + x) as usize] = pixel;
+            }
+        }
+        draw_text(
+            &mut sheet,
+            label,
+            left,
+            top + thumb_height + SPACING / 2,
+            ink,
+        );
+    }
+    sheet
+}
+
+/// Average of the source pixels covered by thumbnail pixel `(x, y)`.
+fn box_sample(image: &Image, x: u32, y: u32, width: u32, height: u32) -> Pixel {
+    let x0 = x * image.width / width;
+    let x1 = ((x + 1) * image.width / width).max(x0 + 1);
+    let y0 = y * image.height / height;
+    let y1 = ((y + 1) * image.height / height).max(y0 + 1);
+
+    let mut sum = [0u32; 3];
+    for sy in y0..y1 {
+        for sx in x0..x1 {
+            let pixel = image.get(sx, sy);
+            sum[0] += pixel.r as u32;
+            sum[1] += pixel.g as u32;
+            sum[2] += pixel.b as u32;
+        }
+    }
+    let count = (x1 - x0) * (y1 - y0);
+    Pixel {
+        r: (sum[0] / count) as u8,
+        g: (sum[1] / count) as u8,
+        b: (sum[2] / count) as u8,
+        a: 255,
+    }
+}
+
+fn draw_text(image: &mut Image, text: &str, left: u32, top: u32, ink: Pixel) {
+    for (i, c) in text.chars().enumerate() {
+        let rows = glyph(c);
+        let glyph_left = left + i as u32 * 4 * FONT_SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..FONT_SCALE {
+                    for dx in 0..FONT_SCALE {
+                        let x = glyph_left + column * FONT_SCALE + dx;
+                        let y = top + row as u32 * FONT_SCALE + dy;
+                        if x < image.width && y < image.height {
+                            image.pixels[(y * image.width + x) as usize] = ink;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 3x5 bitmap of `c`, one row per entry with the leftmost column in the highest bit.
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        'e' => [0b000, 0b111, 0b111, 0b100, 0b111],
+        'x' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        _ => [0; 5],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(color: Pixel) -> Image {
+        Image {
+            width: 8,
+            height: 4,
+            pixels: vec![color; 32],
+        }
+    }
+
+    #[test]
+    fn thumbnails_fill_the_grid_row_by_row() {
+        let colors = [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)]
+            .map(|(r, g, b)| Pixel::from_rgb(r, g, b));
+        let frames: Vec<_> = colors
+            .iter()
+            .map(|&color| (solid(color), String::new()))
+            .collect();
+        let sheet = contact_sheet(&frames, 2, 4);
+        // Thumbnails 4 by 2, two to a row, with a label and spacing under each.
+        let (cell_width, cell_height) = (4 + SPACING, 2 + LABEL_HEIGHT + SPACING);
+        assert_eq!(sheet.width, 2 * cell_width + SPACING);
+        assert_eq!(sheet.height, 2 * cell_height + SPACING);
+        assert_eq!(sheet.pixels.len(), (sheet.width * sheet.height) as usize);
+        for (i, &color) in colors.iter().enumerate() {
+            let left = SPACING + i as u32 % 2 * cell_width;
+            let top = SPACING + i as u32 / 2 * cell_height;
+            assert_eq!(sheet.get(left, top), color);
+            assert_eq!(sheet.get(left + 3, top + 1), color);
+            assert_ne!(sheet.get(left + 4, top), color);
+            assert_ne!(sheet.get(left, top + 2), color);
+        }
+
+        let column = contact_sheet(&frames, 0, 4);
+        assert_eq!(column.width, cell_width + SPACING);
+        assert_eq!(column.height, 3 * cell_height + SPACING);
+    }
+}
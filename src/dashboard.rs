@@ -0,0 +1,370 @@
+//! Live terminal view of a render: what every worker is on, throughput, memory use and the time
+//! left. Built with the `dashboard` feature it is drawn with ratatui widgets below the output
+//! printed so far, otherwise as lines of text redrawn in place with ANSI escapes.
+
+use std::fs;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "dashboard")]
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Gauge, Row, Table},
+    Frame, Terminal, TerminalOptions, Viewport,
+};
+
+const REFRESH: Duration = Duration::from_millis(250);
+/// Workers listed individually, the rest are summarized on one line.
+const WORKER_ROWS: usize = 16;
+
+struct State {
+    started: Instant,
+    total: usize,
+    done: AtomicUsize,
+    /// Id of the next `Working` guard.
+    next: AtomicUsize,
+    /// Guards of busy workers, with their index in the rayon pool if they belong to one, and
+    /// their frame.
+    workers: Mutex<Vec<(usize, Option<usize>, usize)>>,
+    finished: AtomicBool,
+    drawer: Mutex<Option<JoinHandle<()>>>,
+}
+
+static STATE: OnceLock<State> = OnceLock::new();
+
+/// Marks the calling worker busy on a frame until dropped, when the frame counts as done.
+pub struct Working {
+    id: usize,
+    done: bool,
+}
+
+/// Starts redrawing the dashboard for a render of `total` frames.
+pub fn start(total: usize) {
+    let state = State {
+        started: Instant::now(),
+        total,
+        done: AtomicUsize::new(0),
+        next: AtomicUsize::new(0),
+        workers: Mutex::new(Vec::new()),
+        finished: AtomicBool::new(false),
+        drawer: Mutex::new(None),
+    };
+    if STATE.set(state).is_err() {
+        return;
+    }
+    let drawer = thread::spawn(|| {
+        let state = STATE.get().unwrap();
+        let mut screen = Screen::new();
+        while !state.finished.load(Ordering::Relaxed) {
+            screen.draw(&Snapshot::take(state));
+            thread::sleep(REFRESH);
+        }
+        screen.draw(&Snapshot::take(state));
+        screen.close();
+    });
+    *STATE.get().unwrap().drawer.lock().unwrap() = Some(drawer);
+}
+
+/// Stops redrawing after one last update with the final numbers.
+pub fn finish() {
+    if let Some(state) = STATE.get() {
+        state.finished.store(true, Ordering::Relaxed);
+        if let Some(drawer) = state.drawer.lock().unwrap().take() {
+            let _ = drawer.join();
+        }
+    }
+}
+
+pub fn working(frame: usize) -> Option<Working> {
+    let state = STATE.get()?;
+    let id = state.next.fetch_add(1, Ordering::Relaxed);
+    let index = rayon::current_thread_index();
+    state.workers.lock().unwrap().push((id, index, frame));
+    Some(Working { id, done: true })
+}
+
+impl Working {
+    /// Drops the guard without counting its frame as done, for a frame another guard takes
+    /// over.
+    pub fn hand_over(mut self) {
+        self.done = false;
+    }
+}
+
+impl Drop for Working {
+    fn drop(&mut self) {
+        let state = STATE.get().unwrap();
+        state
+            .workers
+            .lock()
+            .unwrap()
+            .retain(|(worker, _, _)| *worker != self.id);
+        if self.done {
+            state.done.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// What the dashboard shows at one moment.
+struct Snapshot {
+    done: usize,
+    total: usize,
+    /// Frames per second.
+    throughput: f32,
+    elapsed: f32,
+    /// Bytes resident.
+    memory: Option<usize>,
+    /// Busy workers with their index in the rayon pool if they belong to one, and their frame,
+    /// in order.
+    workers: Vec<(Option<usize>, usize)>,
+}
+
+impl Snapshot {
+    fn take(state: &State) -> Self {
+        let elapsed = state.started.elapsed().as_secs_f32();
+        let done = state.done.load(Ordering::Relaxed);
+        let mut workers: Vec<_> = (state.workers.lock().unwrap().iter())
+            .map(|&(_, index, frame)| (index, frame))
+            .collect();
+        workers.sort();
+        Self {
+            done,
+            total: state.total,
+            throughput: done as f32 / elapsed.max(f32::EPSILON),
+            elapsed,
+            memory: resident_memory(),
+            workers,
+        }
+    }
+
+    fn progress(&self) -> String {
+        format!(
+            "Frames {}/{} ({:.1}%)",
+            self.done,
+            self.total,
+            self.fraction() * 100.0
+        )
+    }
+
+    fn fraction(&self) -> f32 {
+        (self.done as f32 / self.total.max(1) as f32).min(1.0)
+    }
+
+    fn timing(&self) -> String {
+        let eta = match self.done {
+            0 => "unknown".to_owned(),
+            _ => format_duration((self.total - self.done.min(self.total)) as f32 / self.throughput),
+        };
+        format!(
+            "{:.2} frames/s, elapsed {}, ETA {}",
+            self.throughput,
+            format_duration(self.elapsed),
+            eta
+        )
+    }
+
+    fn memory(&self) -> String {
+        match self.memory {
+            Some(bytes) => format!("Memory {:.1} MiB resident", bytes as f32 / 1048576.0),
+            None => "Memory unknown".to_owned(),
+        }
+    }
+}
+
+/// Where the dashboard is drawn.
+enum Screen {
+    /// Lines of text, with how many were printed last time to redraw over.
+    Text(usize),
+    #[cfg(feature = "dashboard")]
+    Widgets(Terminal<CrosstermBackend<io::Stdout>>),
+}
+
+/// Lines `Screen::Widgets` takes: the bordered progress, timing and memory, a header and a row
+/// per worker.
+#[cfg(feature = "dashboard")]
+const WIDGET_ROWS: u16 = 2 + 3 + 1 + WORKER_ROWS as u16 + 1;
+
+impl Screen {
+    fn new() -> Self {
+        #[cfg(feature = "dashboard")]
+        {
+            let options = TerminalOptions {
+                viewport: Viewport::Inline(WIDGET_ROWS),
+            };
+            // Raw mode is left off, so Ctrl-C still interrupts the render.
+            if let Ok(terminal) =
+                Terminal::with_options(CrosstermBackend::new(io::stdout()), options)
+            {
+                return Screen::Widgets(terminal);
+            }
+        }
+        Screen::Text(0)
+    }
+
+    fn draw(&mut self, snapshot: &Snapshot) {
+        match self {
+            Screen::Text(previous) => *previous = draw_text(snapshot, *previous),
+            #[cfg(feature = "dashboard")]
+            Screen::Widgets(terminal) => {
+                let _ = terminal.draw(|frame| draw_widgets(snapshot, frame));
+            }
+        }
+    }
+
+    /// Leaves the cursor below the last drawing, for the output that follows.
+    fn close(self) {
+        #[cfg(feature = "dashboard")]
+        if let Screen::Widgets(mut terminal) = self {
+            let area = terminal.get_frame().area();
+            let _ = terminal.set_cursor_position((0, area.bottom().saturating_sub(1)));
+            let _ = terminal.show_cursor();
+            println!();
+        }
+    }
+}
+
+/// Redraws over the `previous` lines printed last time and returns how many were printed now.
+fn draw_text(snapshot: &Snapshot, previous: usize) -> usize {
+    let mut lines = vec![
+        format!("{}, {}", snapshot.progress(), snapshot.timing()),
+        snapshot.memory(),
+        format!("Workers busy: {}", snapshot.workers.len()),
+    ];
+    for (index, frame) in snapshot.workers.iter().take(WORKER_ROWS) {
+        lines.push(match index {
+            Some(index) => format!("  worker {:>3}: frame {}", index, frame),
+            // Threads spawned per frame have no stable identity worth showing.
+            None => format!("  thread    : frame {}", frame),
+        });
+    }
+    if snapshot.workers.len() > WORKER_ROWS {
+        lines.push(format!(
+            "  ... and {} more",
+            snapshot.workers.len() - WORKER_ROWS
+        ));
+    }
+
+    let mut out = io::stdout().lock();
+    if previous > 0 {
+        // Back to the first line of the previous draw, then clear everything below it.
+        let _ = write!(out, "\x1b[{}F\x1b[J", previous);
+    }
+    for line in &lines {
+        let _ = writeln!(out, "{}", line);
+    }
+    let _ = out.flush();
+    lines.len()
+}
+
+#[cfg(feature = "dashboard")]
+fn draw_widgets(snapshot: &Snapshot, frame: &mut Frame) {
+    let block = Block::bordered().title(" Render ");
+    let inner = block.inner(frame.area());
+    frame.render_widget(block, frame.area());
+    let [progress, timing, memory, workers] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+    ])
+    .areas(inner);
+
+    let gauge = Gauge::default()
+        .gauge_style(Style::new().fg(Color::Cyan))
+        .ratio(snapshot.fraction() as f64)
+        .label(snapshot.progress());
+    frame.render_widget(gauge, progress);
+    frame.render_widget(Line::from(snapshot.timing()), timing);
+    frame.render_widget(Line::from(snapshot.memory()), memory);
+
+    let mut rows: Vec<Row> = (snapshot.workers.iter().take(WORKER_ROWS))
+        .map(|(index, frame)| {
+            let worker = index.map_or("thread".to_owned(), |index| index.to_string());
+            Row::new([worker, frame.to_string()])
+        })
+        .collect();
+    if snapshot.workers.len() > WORKER_ROWS {
+        let more = format!("and {} more", snapshot.workers.len() - WORKER_ROWS);
+        rows.push(Row::new(["...".to_owned(), more]));
+    }
+    let header = format!("Frame ({} busy)", snapshot.workers.len());
+    let table = Table::new(rows, [Constraint::Length(8), Constraint::Fill(1)])
+        .header(Row::new(["Worker".to_owned(), header]).style(Style::new().bold()));
+    frame.render_widget(table, workers);
+}
+
+fn format_duration(seconds: f32) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Resident set size from `/proc`, where available.
+fn resident_memory() -> Option<usize> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            done: 5,
+            total: 20,
+            throughput: 0.5,
+            elapsed: 10.0,
+            memory: Some(64 << 20),
+            workers: (0..WORKER_ROWS + 2).map(|i| (Some(i), 6 + i)).collect(),
+        }
+    }
+
+    #[test]
+    fn lines_sum_up_the_render() {
+        let snapshot = snapshot();
+        assert_eq!(snapshot.progress(), "Frames 5/20 (25.0%)");
+        assert_eq!(
+            snapshot.timing(),
+            "0.50 frames/s, elapsed 0:00:10, ETA 0:00:30"
+        );
+        assert_eq!(snapshot.memory(), "Memory 64.0 MiB resident");
+    }
+
+    #[cfg(feature = "dashboard")]
+    #[test]
+    fn widgets_fit_the_viewport() {
+        use ratatui::backend::TestBackend;
+
+        let mut terminal = Terminal::new(TestBackend::new(60, WIDGET_ROWS)).unwrap();
+        terminal
+            .draw(|frame| draw_widgets(&snapshot(), frame))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: Vec<String> = (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect()
+            })
+            .collect();
+        assert!(text[1].contains("Frames 5/20 (25.0%)"));
+        assert!(text[2].contains("ETA 0:00:30"));
+        assert!(text[4].contains("Frame (18 busy)"));
+        assert!(text[5].contains("0") && text[5].contains("6"));
+        // The last row summarizes the workers past `WORKER_ROWS`, right above the border.
+        assert!(text[WIDGET_ROWS as usize - 2].contains("and 2 more"));
+    }
+}
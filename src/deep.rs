@@ -0,0 +1,140 @@
+use crate::{zoom_remaining, BigFixed, Keyframe};
+
+/// Extra fraction bits `DeepKeyframe::fraction_bits` keeps below the view size, for pixels and
+/// orbits to resolve.
+const DEEP_MARGIN: u32 = 64;
+
+/// A keyframe deeper than f64 centers can place: the center as decimal text, which can carry any
+/// number of digits, and the view as a zoom exponent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeepKeyframe {
+    pub re: String,
+    pub im: String,
+    /// Base 10 logarithm of the view height, e.g. -50 for a view 10^-50 high.
+    pub zoom: f64,
+    /// View width over height.
+    pub aspect: f64,
+    /// Everything else about the frame. Its center and sizes are ignored.
+    pub keyframe: Keyframe,
+}
+
+impl DeepKeyframe {
+    /// `None` if `re` or `im` isn't a decimal number.
+    pub fn new(re: &str, im: &str, zoom: f64, aspect: f64, keyframe: Keyframe) -> Option<Self> {
+        BigFixed::parse_decimal(re, 0)?;
+        BigFixed::parse_decimal(im, 0)?;
+        Some(Self {
+            re: re.trim().to_owned(),
+            im: im.trim().to_owned(),
+            zoom,
+            aspect,
+            keyframe,
+        })
+    }
+
+    /// Fraction bits the center needs at this zoom.
+    pub fn fraction_bits(&self) -> u32 {
+        let bits = (-self.zoom * std::f64::consts::LOG2_10).ceil().max(0.0) as u32 + DEEP_MARGIN;
+        bits.div_ceil(32) * 32
+    }
+
+    pub fn center(&self, fraction_bits: u32) -> (BigFixed, BigFixed) {
+        let parse = |text| {
+            BigFixed::parse_decimal(text, fraction_bits)
+                .unwrap_or_else(|| BigFixed::zero(fraction_bits))
+        };
+        (parse(&self.re), parse(&self.im))
+    }
+
+    /// The frame with its center rounded to f64, as far as f64 reaches.
+    pub fn to_keyframe(&self) -> Keyframe {
+        let y_size = 10f64.powf(self.zoom);
+        Keyframe {
+            x_center: self.re.parse().unwrap_or(0.0),
+            y_center: self.im.parse().unwrap_or(0.0),
+            x_size: y_size * self.aspect,
+            y_size,
+            ..self.keyframe
+        }
+    }
+
+    /// Interpolates toward `other` with the zoom exponent moving linearly, so the zoom speed
+    /// is constant. The center moves along the path that keeps `other`'s center still on the
+    /// screen, like zooming into a point does.
+    fn lerp(&self, other: &DeepKeyframe, t: f64, idx: usize) -> Self {
+        let fraction_bits = self.fraction_bits().max(other.fraction_bits());
+        // The part of the way still ahead, which is as small as the view is compared to the
+        // start's and must keep its digits.
+        let ratio = 10f64.powf(other.zoom - self.zoom);
+        let remaining = BigFixed::from_f64(zoom_remaining(ratio, t), fraction_bits);
+        let digits = (fraction_bits as f64 / std::f64::consts::LOG2_10).ceil() as usize;
+        let ((x0, y0), (x1, y1)) = (self.center(fraction_bits), other.center(fraction_bits));
+        let lerp = |a: &BigFixed, b: &BigFixed| (b - &(&(b - a) * &remaining)).to_decimal(digits);
+        Self {
+            re: lerp(&x0, &x1),
+            im: lerp(&y0, &y1),
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+            aspect: self.aspect + (other.aspect - self.aspect) * t,
+            keyframe: self.keyframe.lerp(other.keyframe, t as f32, idx),
+        }
+    }
+}
+
+/// `get_interpolated_frames` for deep keyframes, by their `keyframe.index`.
+pub fn get_interpolated_deep_frames(keyframes: &[DeepKeyframe]) -> Vec<DeepKeyframe> {
+    let mut frames = Vec::new();
+    let mut held = 0;
+    let hold = |frame: &DeepKeyframe, index: usize| -> Vec<DeepKeyframe> {
+        frame
+            .keyframe
+            .held(index)
+            .map(|keyframe| DeepKeyframe {
+                keyframe,
+                ..frame.clone()
+            })
+            .collect()
+    };
+    for window in keyframes.windows(2) {
+        let (start, end) = (&window[0], &window[1]);
+        let (first, last) = (start.keyframe.index, end.keyframe.index);
+        frames.extend(hold(start, first + held));
+        held += start.keyframe.hold;
+        frames.extend((first..last).map(|idx| {
+            let t = (idx - first) as f64 / (last - first) as f64;
+            let mut frame = start.lerp(end, t, idx);
+            frame.keyframe.index += held;
+            frame
+        }));
+    }
+    if let Some(last) = keyframes.last().filter(|_| keyframes.len() > 1) {
+        frames.extend(hold(last, last.keyframe.index + held));
+    }
+    frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolated_centers_keep_their_digits() {
+        // 1e-32 apart, far below what an f64 near 0.75 can tell apart.
+        let end = "-0.75000000000000000000000000000001";
+        let keyframe = |index| Keyframe {
+            index,
+            ..Keyframe::view(0.0, 0.0, 1.0, 1.0)
+        };
+        let keyframes = [
+            DeepKeyframe::new("-0.75", "0", -1.0, 1.0, keyframe(0)).unwrap(),
+            DeepKeyframe::new(end, "0", -35.0, 1.0, keyframe(60)).unwrap(),
+        ];
+        let frames = get_interpolated_deep_frames(&keyframes);
+        assert_eq!(frames.len(), 60);
+        let end = BigFixed::parse_decimal(end, 256).unwrap();
+        for frame in &frames {
+            let (re, _) = frame.center(256);
+            let offset = (&re - &end).to_f64().abs();
+            assert!(offset <= 10f64.powf(frame.zoom) / 100.0, "{}", frame.re);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::{Condvar, Mutex};
+
+use crate::{Fractal, IterationBuffer, Keyframe};
+
+/// Everything a `Device` needs to iterate one frame.
+pub struct DeviceFrame<'a> {
+    pub keyframe: &'a Keyframe,
+    pub width: u32,
+    pub height: u32,
+    pub max_iter: usize,
+    pub escape_norm: f32,
+    pub fractal: &'a dyn Fractal,
+    /// How far the frame is morphed toward the Julia set of the keyframe's `c`.
+    pub morph: f32,
+}
+
+/// Hardware besides the CPU that iterates whole frames for a `Renderer` with the
+/// `kernel_source` of the frame's formula, such as an `OpenClDevice` or a `WgpuDevice`.
+pub trait Device: Send + Sync {
+    /// Identifies the device in logs, e.g. by its adapter name.
+    fn name(&self) -> String;
+
+    /// Iteration results of `frame`, or `None` for frames this device can't iterate, which the
+    /// renderer then iterates on the CPU.
+    fn iterate(&self, frame: &DeviceFrame) -> Option<IterationBuffer>;
+}
+
+/// The devices of a renderer, shared by the frames it iterates in parallel. Each frame waits
+/// for whichever device is idle first, so the frames are spread over all devices with one on
+/// each at a time, and only those a device declines are iterated on the CPU.
+#[derive(Default)]
+pub struct DevicePool {
+    devices: Vec<Box<dyn Device>>,
+    idle: Mutex<Vec<usize>>,
+    released: Condvar,
+}
+
+impl DevicePool {
+    pub fn add(&mut self, device: Box<dyn Device>) {
+        self.idle.get_mut().unwrap().push(self.devices.len());
+        self.devices.push(device);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Iteration results of `frame` from the next device to be idle, `None` without devices or
+    /// if the one it got declines the frame.
+    pub fn iterate(&self, frame: &DeviceFrame) -> Option<IterationBuffer> {
+        if self.devices.is_empty() {
+            return None;
+        }
+        let mut idle = self.idle.lock().unwrap();
+        let index = loop {
+            match idle.pop() {
+                Some(index) => break index,
+                None => idle = self.released.wait(idle).unwrap(),
+            }
+        };
+        drop(idle);
+        let buffer = self.devices[index].iterate(frame);
+        self.idle.lock().unwrap().push(index);
+        self.released.notify_one();
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::Mandelbrot;
+
+    /// Counts the frames it gets, failing if it ever gets two at once.
+    #[derive(Default)]
+    struct Counter {
+        busy: AtomicBool,
+        frames: AtomicUsize,
+    }
+
+    struct Counting(Arc<Counter>);
+
+    impl Device for Counting {
+        fn name(&self) -> String {
+            "counting".to_owned()
+        }
+
+        fn iterate(&self, frame: &DeviceFrame) -> Option<IterationBuffer> {
+            assert!(!self.0.busy.swap(true, Ordering::SeqCst));
+            thread::sleep(Duration::from_millis(5));
+            self.0.frames.fetch_add(1, Ordering::SeqCst);
+            self.0.busy.store(false, Ordering::SeqCst);
+            Some(IterationBuffer {
+                width: frame.width,
+                height: frame.height,
+                results: Vec::new(),
+            })
+        }
+    }
+
+    struct Declining;
+
+    impl Device for Declining {
+        fn name(&self) -> String {
+            "declining".to_owned()
+        }
+
+        fn iterate(&self, _: &DeviceFrame) -> Option<IterationBuffer> {
+            None
+        }
+    }
+
+    fn iterate(pool: &DevicePool) -> Option<IterationBuffer> {
+        let keyframe = Keyframe::view(-0.75, 0.0, 3.5, 3.5);
+        pool.iterate(&DeviceFrame {
+            keyframe: &keyframe,
+            width: 8,
+            height: 8,
+            max_iter: 16,
+            escape_norm: 4.0,
+            fractal: &Mandelbrot,
+            morph: 0.0,
+        })
+    }
+
+    #[test]
+    fn frames_are_spread_over_the_devices() {
+        let counters: [Arc<Counter>; 2] = Default::default();
+        let mut pool = DevicePool::default();
+        for counter in &counters {
+            pool.add(Box::new(Counting(Arc::clone(counter))));
+        }
+        thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| assert!(iterate(&pool).is_some()));
+            }
+        });
+        let frames = counters
+            .each_ref()
+            .map(|counter| counter.frames.load(Ordering::SeqCst));
+        assert_eq!(frames.iter().sum::<usize>(), 16);
+        assert!(frames.iter().all(|&frames| frames > 0));
+    }
+
+    #[test]
+    fn declined_frames_go_to_the_cpu() {
+        assert!(iterate(&DevicePool::default()).is_none());
+        let mut pool = DevicePool::default();
+        pool.add(Box::new(Declining));
+        assert!(iterate(&pool).is_none());
+    }
+}
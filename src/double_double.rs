@@ -0,0 +1,184 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Unevaluated sum `hi + lo` of two f64s with `|lo|` at most half an ulp of `hi`, for about 106
+/// bits of mantissa at a fraction of the cost of `BigFixed`-style arbitrary precision. The
+/// exponent range stays that of f64.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+/// `a + b` and its rounding error, exactly.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+/// `two_sum` for `|a| >= |b|`.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+/// `a * b` and its rounding error, exactly.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub fn new(hi: f64, lo: f64) -> Self {
+        let (hi, lo) = two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    /// Twice `self`, exactly.
+    pub fn double(self) -> Self {
+        DoubleDouble {
+            hi: 2.0 * self.hi,
+            lo: 2.0 * self.lo,
+        }
+    }
+
+    pub fn square(self) -> Self {
+        let (p, e) = two_prod(self.hi, self.hi);
+        let (hi, lo) = quick_two_sum(p, e + 2.0 * self.hi * self.lo);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(hi: f64) -> Self {
+        DoubleDouble { hi, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let (s, e) = two_sum(self.hi, rhs.hi);
+        let (t, f) = two_sum(self.lo, rhs.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        let (hi, lo) = quick_two_sum(s, e + f);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        let (s, e) = two_sum(self.hi, -rhs.hi);
+        let (t, f) = two_sum(self.lo, -rhs.lo);
+        let (s, e) = quick_two_sum(s, e + t);
+        let (hi, lo) = quick_two_sum(s, e + f);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (p, e) = two_prod(self.hi, rhs.hi);
+        let (hi, lo) = quick_two_sum(p, e + (self.hi * rhs.lo + self.lo * rhs.hi));
+        DoubleDouble { hi, lo }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dashu_int::IBig;
+
+    use super::*;
+    use crate::buddhabrot::SplitMix;
+
+    /// Fraction bits of the integers standing in for the exact values.
+    const BITS: usize = 400;
+
+    /// `value` times 2^BITS, exactly.
+    fn scaled(value: f64) -> IBig {
+        if value == 0.0 {
+            return IBig::ZERO;
+        }
+        let bits = value.to_bits();
+        let exponent = (bits >> 52 & 0x7ff) as i64;
+        let mantissa = IBig::from(bits & ((1 << 52) - 1) | 1 << 52);
+        let magnitude = mantissa << (exponent - 1075 + BITS as i64) as usize;
+        if value < 0.0 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    fn exact(number: DoubleDouble) -> IBig {
+        scaled(number.hi) + scaled(number.lo)
+    }
+
+    /// A normalized number of about 1 with all 106 bits random.
+    fn random(rng: &mut SplitMix) -> DoubleDouble {
+        let mut uniform = || (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 - 0.5;
+        let hi = 4.0 * uniform();
+        DoubleDouble::new(hi, hi * f64::EPSILON * uniform())
+    }
+
+    fn magnitude(value: IBig) -> IBig {
+        if value < IBig::ZERO {
+            -value
+        } else {
+            value
+        }
+    }
+
+    /// Whether `number` is within 2^-100 of `wanted` relative to it.
+    fn within(number: DoubleDouble, wanted: &IBig) -> bool {
+        magnitude(exact(number) - wanted) << 100 <= magnitude(wanted.clone())
+    }
+
+    #[test]
+    fn arithmetic_keeps_106_bits() {
+        let mut rng = SplitMix(3);
+        for _ in 0..2000 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let (x, y) = (exact(a), exact(b));
+            // Results stay normalized, with `lo` below half an ulp of `hi`.
+            let sum = a + b;
+            assert_eq!(sum.hi, sum.hi + sum.lo);
+            assert!(within(sum, &(&x + &y)), "{a:?} + {b:?}");
+            assert!(within(a - b, &(&x - &y)), "{a:?} - {b:?}");
+            let product = (&x * &y) >> BITS;
+            assert!(within(a * b, &product), "{a:?} * {b:?}");
+            let square = (&x * &x) >> BITS;
+            assert!(within(a.square(), &square), "{a:?}^2");
+            assert_eq!(exact(a.double()), &x + &x);
+            assert_eq!(exact(-a), -&x);
+        }
+    }
+
+    #[test]
+    fn sums_recover_what_f64_rounds_away() {
+        let tiny = DoubleDouble::from(1e-20);
+        let sum = DoubleDouble::from(1.0) + tiny;
+        assert_eq!(sum.to_f64(), 1.0);
+        assert_eq!((sum - DoubleDouble::from(1.0)).to_f64(), 1e-20);
+    }
+}
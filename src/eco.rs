@@ -0,0 +1,72 @@
+//! Low-power rendering: a few low-priority workers that idle between frames and optionally wait
+//! out periods on battery power, so long renders don't get in the way on a laptop.
+
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use crate::interrupt;
+
+/// How often a render paused on battery checks whether power is back.
+const BATTERY_POLL: Duration = Duration::from_secs(30);
+
+pub struct Eco {
+    pub threads: usize,
+    /// Idle time after every frame.
+    pub sleep: Duration,
+    pub pause_on_battery: bool,
+}
+
+#[cfg(unix)]
+mod ffi {
+    extern "C" {
+        pub fn nice(increment: i32) -> i32;
+    }
+}
+
+impl Eco {
+    /// Runs `render` on a pool of `threads` workers at the lowest scheduling priority.
+    pub fn run<T: Send>(&self, render: impl FnOnce() -> T + Send) -> T {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .start_handler(|_| lower_priority())
+            .build()
+            .expect("Error creating eco thread pool.")
+            .install(render)
+    }
+
+    /// Called by a worker after finishing a frame.
+    pub fn idle(&self) {
+        thread::sleep(self.sleep);
+        while self.pause_on_battery && on_battery() && !interrupt::requested() {
+            thread::sleep(BATTERY_POLL);
+        }
+    }
+}
+
+/// On Linux niceness is per thread, so this only affects the calling worker.
+fn lower_priority() {
+    #[cfg(unix)]
+    unsafe {
+        ffi::nice(19);
+    }
+}
+
+/// True when a battery is present and no external power supply is online. Systems without
+/// `/sys/class/power_supply` are assumed to be plugged in.
+fn on_battery() -> bool {
+    let supplies = match fs::read_dir("/sys/class/power_supply") {
+        Ok(supplies) => supplies,
+        Err(_) => return false,
+    };
+    let mut battery = false;
+    for supply in supplies.flatten() {
+        let read = |name: &str| fs::read_to_string(supply.path().join(name)).unwrap_or_default();
+        match read("type").trim() {
+            "Battery" => battery = true,
+            "Mains" | "USB" if read("online").trim() == "1" => return false,
+            _ => {}
+        }
+    }
+    battery
+}
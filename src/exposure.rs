@@ -0,0 +1,61 @@
+use crate::EscapeResult;
+
+/// Range of smooth iteration counts stretched over the whole palette.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Exposure {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Exposure {
+    /// Range between the `clip` and `1 - clip` quantiles of the escaped points, or `None` when
+    /// nothing escaped.
+    pub fn measure(results: &[EscapeResult], clip: f32) -> Option<Self> {
+        let mut counts: Vec<f32> = results
+            .iter()
+            .filter(|result| result.escaped)
+            .map(|result| result.smooth)
+            .collect();
+        if counts.is_empty() {
+            return None;
+        }
+        counts.sort_by(f32::total_cmp);
+
+        let quantile = |q: f32| counts[((counts.len() - 1) as f32 * q).round() as usize];
+        let low = quantile(clip);
+        let high = quantile(1.0 - clip).max(low + 1.0);
+        Some(Self { low, high })
+    }
+
+    /// Palette position of a smooth iteration count.
+    pub fn apply(&self, smooth: f32) -> f32 {
+        ((smooth - self.low) / (self.high - self.low)).clamp(0.0, 1.0)
+    }
+}
+
+/// Exponential moving average of per-frame exposures in animation order, so the brightness
+/// follows the content without flickering from frame to frame. `weight` is how much each new
+/// frame contributes; frames where nothing escaped keep the previous exposure.
+pub fn smooth_exposures(exposures: &[Option<Exposure>], weight: f32) -> Vec<Exposure> {
+    let mut current = exposures
+        .iter()
+        .flatten()
+        .next()
+        .copied()
+        .unwrap_or(Exposure {
+            low: 0.0,
+            high: 1.0,
+        });
+    exposures
+        .iter()
+        .map(|exposure| {
+            if let Some(exposure) = exposure {
+                current = Exposure {
+                    low: current.low + (exposure.low - current.low) * weight,
+                    high: current.high + (exposure.high - current.high) * weight,
+                };
+            }
+            current
+        })
+        .collect()
+}
@@ -0,0 +1,614 @@
+use std::fmt;
+use std::sync::Arc;
+
+#[cfg(feature = "jit")]
+use crate::jit::NativeFormula;
+#[cfg(feature = "jit")]
+use crate::Bailout;
+use crate::{Complex, EscapeResult, Fractal};
+
+/// A custom iteration formula such as `z = z^3 + c*sin(z)`, parsed at runtime. Formulas use `z`,
+/// `c`, numbers including imaginary ones like `0.5i`, the constants `i`, `pi` and `e`, the
+/// operators `+ - * / ^` and the functions in `Expression::FUNCTIONS`. Smooth coloring assumes
+/// the formula is quadratic in `z`.
+///
+/// Parsing turns the formula into nested closures, one per operation, with constant parts
+/// folded and small integer powers multiplied out, which avoids matching on the syntax tree at
+/// every step but still calls through a closure per operation. Built with `--features jit`, the
+/// formula is also compiled to native code with cranelift, which `Fractal::iterate` runs a whole
+/// orbit in, and the closures are only the fallback on hosts cranelift doesn't support.
+#[derive(Clone)]
+pub struct Expression {
+    source: String,
+    closures: Closure,
+    #[cfg(feature = "jit")]
+    native: Option<Arc<NativeFormula>>,
+}
+
+type Closure = Arc<dyn Fn(Complex, Complex) -> Complex + Send + Sync>;
+
+/// Why a formula couldn't be parsed. Positions are byte offsets into the text given to
+/// `Expression::parse`, not counting leading whitespace.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExpressionError {
+    UnexpectedCharacter(usize, char),
+    /// A token where none of its kind can go, e.g. a second operator.
+    UnexpectedToken(usize),
+    UnexpectedEnd,
+    UnknownName(usize, String),
+    /// A `(` without its `)`, at the position of the `(`.
+    UnclosedParenthesis(usize),
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(at, c) => write!(f, "unexpected '{c}' at {at}"),
+            Self::UnexpectedToken(at) => write!(f, "unexpected token at {at}"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of formula"),
+            Self::UnknownName(at, name) => write!(f, "unknown name '{name}' at {at}"),
+            Self::UnclosedParenthesis(at) => write!(f, "parenthesis at {at} is never closed"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Node {
+    Z,
+    C,
+    Constant(Complex),
+    Negate(Box<Node>),
+    Add(Box<Node>, Box<Node>),
+    Sub(Box<Node>, Box<Node>),
+    Mul(Box<Node>, Box<Node>),
+    Div(Box<Node>, Box<Node>),
+    Pow(Box<Node>, Box<Node>),
+    Call(&'static str, Box<Node>),
+}
+
+impl Node {
+    /// Whether the node depends on neither `z` nor `c`.
+    pub(crate) fn is_constant(&self) -> bool {
+        match self {
+            Node::Z | Node::C => false,
+            Node::Constant(_) => true,
+            Node::Negate(node) | Node::Call(_, node) => node.is_constant(),
+            Node::Add(a, b) | Node::Sub(a, b) | Node::Mul(a, b) | Node::Div(a, b) => {
+                a.is_constant() && b.is_constant()
+            }
+            Node::Pow(base, exponent) => base.is_constant() && exponent.is_constant(),
+        }
+    }
+
+    /// Interprets the tree, only used to fold constants while compiling.
+    pub(crate) fn evaluate(&self, z: Complex, c: Complex) -> Complex {
+        match self {
+            Node::Z => z,
+            Node::C => c,
+            Node::Constant(constant) => *constant,
+            Node::Negate(node) => Complex::new(0.0, 0.0) - node.evaluate(z, c),
+            Node::Add(a, b) => a.evaluate(z, c) + b.evaluate(z, c),
+            Node::Sub(a, b) => a.evaluate(z, c) - b.evaluate(z, c),
+            Node::Mul(a, b) => a.evaluate(z, c) * b.evaluate(z, c),
+            Node::Div(a, b) => a.evaluate(z, c) / b.evaluate(z, c),
+            Node::Pow(base, exponent) => base.evaluate(z, c).pow(exponent.evaluate(z, c)),
+            Node::Call(name, argument) => function(name)(argument.evaluate(z, c)),
+        }
+    }
+}
+
+fn compile_closures(node: &Node) -> Closure {
+    let zero = Complex::new(0.0, 0.0);
+    if node.is_constant() {
+        let value = node.evaluate(zero, zero);
+        return Arc::new(move |_, _| value);
+    }
+    let binary = |a: &Node, b: &Node, operation: fn(Complex, Complex) -> Complex| -> Closure {
+        let (a, b) = (compile_closures(a), compile_closures(b));
+        Arc::new(move |z, c| operation(a(z, c), b(z, c)))
+    };
+    match node {
+        Node::Z => Arc::new(|z, _| z),
+        Node::C => Arc::new(|_, c| c),
+        // Caught by the folding above.
+        Node::Constant(value) => {
+            let value = *value;
+            Arc::new(move |_, _| value)
+        }
+        Node::Negate(node) => {
+            let node = compile_closures(node);
+            Arc::new(move |z, c| zero - node(z, c))
+        }
+        Node::Add(a, b) => binary(a, b, |a, b| a + b),
+        Node::Sub(a, b) => binary(a, b, |a, b| a - b),
+        Node::Mul(a, b) => binary(a, b, |a, b| a * b),
+        Node::Div(a, b) => binary(a, b, |a, b| a / b),
+        Node::Pow(base, exponent) if exponent.is_constant() => {
+            let exponent = exponent.evaluate(zero, zero);
+            let base = compile_closures(base);
+            match (exponent.x, exponent.y) {
+                (2.0, 0.0) => Arc::new(move |z, c| {
+                    let w = base(z, c);
+                    w * w
+                }),
+                (3.0, 0.0) => Arc::new(move |z, c| {
+                    let w = base(z, c);
+                    w * w * w
+                }),
+                (d, 0.0) => Arc::new(move |z, c| base(z, c).powf(d)),
+                _ => Arc::new(move |z, c| base(z, c).pow(exponent)),
+            }
+        }
+        Node::Pow(base, exponent) => binary(base, exponent, Complex::pow),
+        Node::Call(name, argument) => {
+            let function = function(name);
+            let argument = compile_closures(argument);
+            Arc::new(move |z, c| function(argument(z, c)))
+        }
+    }
+}
+
+pub(crate) fn function(name: &str) -> fn(Complex) -> Complex {
+    match name {
+        "sin" => Complex::sin,
+        "cos" => Complex::cos,
+        "tan" => Complex::tan,
+        "sinh" => Complex::sinh,
+        "cosh" => Complex::cosh,
+        "exp" => Complex::exp,
+        "ln" => Complex::ln,
+        "sqrt" => Complex::sqrt,
+        "abs" => |w| Complex::new(w.x.abs(), w.y.abs()),
+        "conj" => Complex::conj,
+        "re" => |w| Complex::new(w.x, 0.0),
+        "im" => |w| Complex::new(w.y, 0.0),
+        "norm" => |w| Complex::new(w.norm(), 0.0),
+        _ => unreachable!("only names from `Expression::FUNCTIONS` are parsed as calls"),
+    }
+}
+
+impl Expression {
+    /// The functions formulas can call, with one argument each. `abs` takes the absolute value
+    /// of both parts, as the burning ship does, rather than the modulus.
+    pub const FUNCTIONS: [&'static str; 13] = [
+        "sin", "cos", "tan", "sinh", "cosh", "exp", "ln", "sqrt", "abs", "conj", "re", "im", "norm",
+    ];
+
+    /// Parses `text`, with or without the leading `z =`.
+    pub fn parse(text: &str) -> Result<Self, ExpressionError> {
+        let source = text.trim();
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        if let [(_, Token::Name(name)), (_, Token::Assign), ..] = &tokens[..] {
+            if name == "z" {
+                parser.position = 2;
+            }
+        }
+        let formula_start = parser.offset();
+        let root = parser.sum()?;
+        if parser.position < tokens.len() {
+            return Err(ExpressionError::UnexpectedToken(parser.offset()));
+        }
+        Ok(Self {
+            source: source[formula_start..].to_owned(),
+            closures: compile_closures(&root),
+            #[cfg(feature = "jit")]
+            native: NativeFormula::compile(&root).map(Arc::new),
+        })
+    }
+
+    /// This formula without its native code, run through the closures alone, e.g. to compare
+    /// the two.
+    #[cfg(feature = "jit")]
+    pub fn without_jit(&self) -> Self {
+        Self {
+            native: None,
+            ..self.clone()
+        }
+    }
+
+    /// The right side of the formula, as written.
+    pub fn formula(&self) -> &str {
+        &self.source
+    }
+
+    pub fn evaluate(&self, z: Complex, c: Complex) -> Complex {
+        #[cfg(feature = "jit")]
+        if let Some(native) = &self.native {
+            return native.step(z, c);
+        }
+        (self.closures)(z, c)
+    }
+}
+
+impl fmt::Debug for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Expression").field(&self.source).finish()
+    }
+}
+
+impl Fractal for Expression {
+    /// `"z = "` followed by the formula, which `fractal_from_name` parses back.
+    fn name(&self) -> String {
+        format!("z = {}", self.source)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        self.evaluate(z, c)
+    }
+
+    /// Runs the whole loop in native code when there is some, instead of one call per step.
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        #[cfg(feature = "jit")]
+        if let Some(native) = &self.native {
+            let (z, iters) = native.iterate(c, z0, max_iter, escape_norm);
+            let bailout = (iters < max_iter).then_some(Bailout::Escaped);
+            return EscapeResult::from_bailout(z, iters, bailout, escape_norm, self.power());
+        }
+        let mut z = z0;
+        let mut iters = 0;
+        let mut bailout = None;
+        while iters < max_iter {
+            bailout = self.bailout(z, escape_norm);
+            if bailout.is_some() {
+                break;
+            }
+            z = (self.closures)(z, c);
+            iters += 1;
+        }
+        EscapeResult::from_bailout(z, iters, bailout, escape_norm, self.power())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(Complex),
+    Name(String),
+    Operator(char),
+    Open,
+    Close,
+    Assign,
+}
+
+/// Tokens with the byte offsets they start at.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c.is_ascii_digit() || c == '.' {
+            let bytes = text.as_bytes();
+            let digits = |from: usize| {
+                bytes[from..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit() || **b == b'.')
+                    .count()
+            };
+            let mut end = start + digits(start);
+            // An `e` is only an exponent with digits after it, so `2e` and `2exp(z)` aren't
+            // read as numbers running into the name.
+            if matches!(bytes.get(end), Some(b'e' | b'E')) {
+                let sign = matches!(bytes.get(end + 1), Some(b'+' | b'-')) as usize;
+                let exponent = bytes[end + 1 + sign..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_digit())
+                    .count();
+                if exponent > 0 {
+                    end += 1 + sign + exponent;
+                }
+            }
+            while chars.next_if(|&(i, _)| i < end).is_some() {}
+            let value: f32 = text[start..end]
+                .parse()
+                .map_err(|_| ExpressionError::UnexpectedCharacter(start, c))?;
+            // A directly following `i` makes the number imaginary.
+            let imaginary = text[end..].starts_with('i')
+                && !text[end + 1..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_');
+            if imaginary {
+                chars.next();
+                Token::Number(Complex::new(0.0, value))
+            } else {
+                Token::Number(Complex::new(value, 0.0))
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = i + 1;
+                chars.next();
+            }
+            Token::Name(text[start..end].to_owned())
+        } else {
+            chars.next();
+            match c {
+                '+' | '-' | '*' | '/' | '^' => Token::Operator(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                '=' => Token::Assign,
+                _ => return Err(ExpressionError::UnexpectedCharacter(start, c)),
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent, one method per precedence level from `sum` down to `atom`.
+struct Parser<'a> {
+    tokens: &'a [(usize, Token)],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    /// Byte offset of the next token, or of the end.
+    fn offset(&self) -> usize {
+        match self.tokens.get(self.position) {
+            Some((offset, _)) => *offset,
+            None => self.tokens.last().map_or(0, |(offset, _)| offset + 1),
+        }
+    }
+
+    fn operator(&mut self, operators: &[char]) -> Option<char> {
+        match self.peek() {
+            Some(Token::Operator(operator)) if operators.contains(operator) => {
+                let operator = *operator;
+                self.position += 1;
+                Some(operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.product()?;
+        while let Some(operator) = self.operator(&['+', '-']) {
+            let (a, b) = (Box::new(node), Box::new(self.product()?));
+            node = match operator {
+                '+' => Node::Add(a, b),
+                _ => Node::Sub(a, b),
+            };
+        }
+        Ok(node)
+    }
+
+    fn product(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.unary()?;
+        while let Some(operator) = self.operator(&['*', '/']) {
+            let (a, b) = (Box::new(node), Box::new(self.unary()?));
+            node = match operator {
+                '*' => Node::Mul(a, b),
+                _ => Node::Div(a, b),
+            };
+        }
+        Ok(node)
+    }
+
+    /// Negation binds looser than powers, so `-z^2` is `-(z^2)`.
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        match self.operator(&['-', '+']) {
+            Some('-') => Ok(Node::Negate(Box::new(self.unary()?))),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    /// Right associative, `z^2^3` is `z^(2^3)`.
+    fn power(&mut self) -> Result<Node, ExpressionError> {
+        let base = self.atom()?;
+        match self.operator(&['^']) {
+            Some(_) => Ok(Node::Pow(Box::new(base), Box::new(self.unary()?))),
+            None => Ok(base),
+        }
+    }
+
+    fn atom(&mut self) -> Result<Node, ExpressionError> {
+        let offset = self.offset();
+        let Some(token) = self.peek().cloned() else {
+            return Err(ExpressionError::UnexpectedEnd);
+        };
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(Node::Constant(value)),
+            Token::Open => self.parenthesized(offset),
+            Token::Name(name) => match name.as_str() {
+                "z" => Ok(Node::Z),
+                "c" => Ok(Node::C),
+                "i" => Ok(Node::Constant(Complex::new(0.0, 1.0))),
+                "pi" => Ok(Node::Constant(Complex::new(std::f32::consts::PI, 0.0))),
+                "e" => Ok(Node::Constant(Complex::new(std::f32::consts::E, 0.0))),
+                _ => {
+                    let Some(function) = Expression::FUNCTIONS
+                        .iter()
+                        .find(|function| **function == name)
+                    else {
+                        return Err(ExpressionError::UnknownName(offset, name));
+                    };
+                    let open = self.offset();
+                    if self.peek() != Some(&Token::Open) {
+                        return Err(match self.peek() {
+                            Some(_) => ExpressionError::UnexpectedToken(open),
+                            None => ExpressionError::UnexpectedEnd,
+                        });
+                    }
+                    self.position += 1;
+                    Ok(Node::Call(function, Box::new(self.parenthesized(open)?)))
+                }
+            },
+            _ => Err(ExpressionError::UnexpectedToken(offset)),
+        }
+    }
+
+    /// The rest of a parenthesized expression whose `(` at `open` was just read.
+    fn parenthesized(&mut self, open: usize) -> Result<Node, ExpressionError> {
+        let node = self.sum()?;
+        match self.peek() {
+            Some(Token::Close) => {
+                self.position += 1;
+                Ok(node)
+            }
+            Some(_) => Err(ExpressionError::UnexpectedToken(self.offset())),
+            None => Err(ExpressionError::UnclosedParenthesis(open)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str, z: Complex, c: Complex) -> Complex {
+        Expression::parse(text).unwrap().evaluate(z, c)
+    }
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a - b).norm() < 1e-8
+    }
+
+    #[test]
+    fn exponents_need_digits() {
+        let one = Complex::new(1.0, 0.0);
+        assert!(close(at("1e3*z", one, one), Complex::new(1000.0, 0.0)));
+        assert!(close(at("2.5E-1 + z", one, one), Complex::new(1.25, 0.0)));
+        assert!(close(at("z*2e+1i", one, one), Complex::new(0.0, 20.0)));
+        for text in ["2e", "2exp(z)", "2e+", "2e-z", "3E"] {
+            assert_eq!(
+                Expression::parse(text).unwrap_err(),
+                ExpressionError::UnexpectedToken(1),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = |text| Expression::parse(text).unwrap_err();
+        assert_eq!(error("z^2 +"), ExpressionError::UnexpectedEnd);
+        assert_eq!(error("z^2 + + "), ExpressionError::UnexpectedEnd);
+        assert_eq!(error("z * ) "), ExpressionError::UnexpectedToken(4));
+        assert_eq!(error("sin(z"), ExpressionError::UnclosedParenthesis(3));
+        assert_eq!(error("(z + c"), ExpressionError::UnclosedParenthesis(0));
+        assert_eq!(error("sin z"), ExpressionError::UnexpectedToken(4));
+        assert_eq!(error("z $ c"), ExpressionError::UnexpectedCharacter(2, '$'));
+        assert_eq!(
+            error("z^2 + foo(c)"),
+            ExpressionError::UnknownName(6, "foo".to_owned())
+        );
+        assert_eq!(error("z c"), ExpressionError::UnexpectedToken(2));
+    }
+
+    #[test]
+    fn formulas_follow_precedence() {
+        let z = Complex::new(0.5, -1.5);
+        let c = Complex::new(-0.25, 2.0);
+        assert!(close(at("-z^2", z, c), Complex::new(0.0, 0.0) - z * z));
+        assert!(close(at("z = z^2 + c", z, c), z * z + c));
+        assert!(close(at("z - c - 1", z, c), z - c - Complex::new(1.0, 0.0)));
+        assert!(close(at("2^3^2", z, c), Complex::new(512.0, 0.0)));
+        assert!(close(at("z*c/z", z, c), c));
+        assert!(close(
+            at("(z + c)*2i", z, c),
+            (z + c) * Complex::new(0.0, 2.0)
+        ));
+        assert!(close(at("conj(z) + re(c) + im(c)*i", z, c), z.conj() + c));
+    }
+
+    const FORMULAS: [&str; 8] = [
+        "z^2 + c",
+        "z^3 + c*sin(z)",
+        "z^5 - z/c + 2^0.5",
+        "z^-2 + c",
+        "z^1.5 + c",
+        "z^c + conj(c)*exp(-z)",
+        "(abs(re(z)) + abs(im(z))*i)^2 + c",
+        "tan(z)/cosh(c) + sqrt(z)*ln(c) + norm(z)*pi",
+    ];
+
+    /// Points inside, on the edge of and well outside the usual views.
+    fn points() -> impl Iterator<Item = Complex> {
+        (0..9).flat_map(|i| {
+            (0..7).map(move |j| Complex::new(i as f32 * 0.4 - 1.7, j as f32 * 0.35 - 1.05))
+        })
+    }
+
+    #[cfg(feature = "jit")]
+    fn same(a: Complex, b: Complex) -> bool {
+        (a.x.to_bits(), a.y.to_bits()) == (b.x.to_bits(), b.y.to_bits())
+            || (a.x.is_nan() || a.y.is_nan()) && (b.x.is_nan() || b.y.is_nan())
+    }
+
+    #[test]
+    fn closures_match_the_syntax_tree() {
+        for text in FORMULAS {
+            let tokens = tokenize(text).unwrap();
+            let root = Parser {
+                tokens: &tokens,
+                position: 0,
+            }
+            .sum()
+            .unwrap();
+            let closures = compile_closures(&root);
+            for (z, c) in points().zip(points().skip(5)) {
+                let (want, got) = (root.evaluate(z, c), closures(z, c));
+                // Integer powers are multiplied out rather than taken in polar form.
+                assert!((want - got).norm() <= 1e-6 * want.norm().max(1.0), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn iterate_stops_on_escape_or_max_iter() {
+        let formula = Expression::parse("z^2 + c").unwrap();
+        let inside = formula.iterate(Complex::new(-0.5, 0.0), Complex::new(0.0, 0.0), 50, 4.0);
+        assert_eq!(inside.iterations, 50);
+        assert!(!inside.escaped);
+        let outside = formula.iterate(Complex::new(1.0, 0.0), Complex::new(0.0, 0.0), 50, 4.0);
+        // 0, 1, 2, where |z|^2 reaches the escape norm.
+        assert_eq!(outside.iterations, 2);
+        assert!(outside.escaped);
+        assert_eq!(outside.z, (2.0, 0.0));
+    }
+
+    #[cfg(feature = "jit")]
+    #[test]
+    fn native_code_matches_the_closures() {
+        for text in FORMULAS {
+            let native = Expression::parse(text).unwrap();
+            assert!(native.native.is_some(), "{text} didn't compile");
+            let closures = native.without_jit();
+            for (z, c) in points().zip(points().skip(5)) {
+                assert!(same(native.step(z, c), closures.step(z, c)), "{text}");
+                let native = native.iterate(c, z, 200, 64.0);
+                let closures = closures.iterate(c, z, 200, 64.0);
+                assert_eq!(native.iterations, closures.iterations, "{text}");
+                assert_eq!(native.escaped, closures.escaped, "{text}");
+                let (native, closures) = (native.z, closures.z);
+                let native = Complex::new(native.0, native.1);
+                assert!(same(native, Complex::new(closures.0, closures.1)), "{text}");
+            }
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        let formula = Expression::parse(" z =  z^3 + c*sin(z) ").unwrap();
+        assert_eq!(formula.formula(), "z^3 + c*sin(z)");
+        assert_eq!(formula.name(), "z = z^3 + c*sin(z)");
+        assert_eq!(
+            Expression::parse(&formula.name()).unwrap().formula(),
+            formula.formula()
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{ImageError, IterationBuffer};
+
+/// Writes the iteration results of a frame as an uncompressed OpenEXR image of 32 bit floats,
+/// for grading the colors outside the crate without the banding of 8 bit frames. Channel `Y`
+/// holds the smooth iteration count of escaped pixels and 0 inside the set, and `A` is 1 for
+/// escaped pixels and 0 inside, to matte the set out.
+pub fn write_exr(buffer: &IterationBuffer, path: impl AsRef<Path>) -> Result<(), ImageError> {
+    let file = File::create(path).map_err(|_| ImageError::FileCreateError)?;
+    let mut writer = BufWriter::new(file);
+    let (width, height) = (buffer.width as usize, buffer.height as usize);
+
+    // OpenEXR 2, single part scanlines.
+    let mut header = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        header.extend([name.as_bytes(), &[0], kind.as_bytes(), &[0]].concat());
+        header.extend((value.len() as i32).to_le_bytes());
+        header.extend(value);
+    };
+    // Channels in alphabetical order, each a float sampled at every pixel.
+    let mut channels = Vec::new();
+    for name in ["A", "Y"] {
+        channels.extend(name.as_bytes());
+        channels.push(0);
+        channels.extend(2i32.to_le_bytes());
+        channels.extend([0; 4]);
+        channels.extend([1i32.to_le_bytes(), 1i32.to_le_bytes()].concat());
+    }
+    channels.push(0);
+    attribute("channels", "chlist", &channels);
+    attribute("compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    header.push(0);
+
+    // Every scanline is a block of its own, found through a table of their offsets.
+    let line_size = 8 + 2 * 4 * width;
+    let first = header.len() + 8 * height;
+    for y in 0..height {
+        header.extend(((first + y * line_size) as u64).to_le_bytes());
+    }
+    writer
+        .write_all(&header)
+        .map_err(|_| ImageError::EncodeError)?;
+
+    for (y, row) in buffer.results.chunks(width).enumerate() {
+        let mut line = Vec::with_capacity(line_size);
+        line.extend((y as i32).to_le_bytes());
+        line.extend(((2 * 4 * width) as i32).to_le_bytes());
+        line.extend(
+            row.iter()
+                .flat_map(|r| f32::from(u8::from(r.escaped)).to_le_bytes()),
+        );
+        line.extend(row.iter().flat_map(|r| {
+            let smooth = if r.escaped { r.smooth } else { 0.0 };
+            smooth.to_le_bytes()
+        }));
+        writer
+            .write_all(&line)
+            .map_err(|_| ImageError::EncodeError)?;
+    }
+    writer.flush().map_err(|_| ImageError::EncodeError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EscapeResult;
+
+    #[test]
+    fn scanlines_hold_the_matte_and_smooth_counts() {
+        let results: Vec<EscapeResult> = (0..6 * 4)
+            .map(|i| EscapeResult {
+                escaped: i % 3 != 0,
+                iterations: i,
+                smooth: i as f32 + 0.5,
+                z: (0.0, 0.0),
+            })
+            .collect();
+        let buffer = IterationBuffer {
+            width: 6,
+            height: 4,
+            results,
+        };
+        let path = std::env::temp_dir().join("mandelbrot-exr-test.exr");
+        write_exr(&buffer, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bytes[..8], [0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        let int = |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let float = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+        let text = |at: usize| {
+            let end = at + bytes[at..].iter().position(|&b| b == 0).unwrap();
+            (String::from_utf8(bytes[at..end].to_vec()).unwrap(), end + 1)
+        };
+        let mut attributes = Vec::new();
+        let mut at = 8;
+        loop {
+            let (name, next) = text(at);
+            if name.is_empty() {
+                at = next;
+                break;
+            }
+            let (_, next) = text(next);
+            let size = int(next) as usize;
+            attributes.push((name, next + 4));
+            at = next + 4 + size;
+        }
+        let value = |name: &str| attributes.iter().find(|(n, _)| n == name).unwrap().1;
+        let window = value("dataWindow");
+        assert_eq!([0, 4, 8, 12].map(|i| int(window + i)), [0, 0, 5, 3]);
+        assert_eq!(bytes[value("compression")], 0);
+
+        let offset = |y: usize| u64::from_le_bytes(bytes[at + 8 * y..][..8].try_into().unwrap());
+        let line_size = 8 + 2 * 4 * 6;
+        for y in 0..4 {
+            let line = offset(y) as usize;
+            assert_eq!((int(line), int(line + 4)), (y as i32, 2 * 4 * 6));
+            assert_eq!(line, at + 8 * 4 + y * line_size);
+        }
+        assert_eq!(bytes.len(), offset(3) as usize + line_size);
+
+        // A, then Y, of the pixels of the third scanline.
+        let line = offset(2) as usize + 8;
+        for x in 0..6 {
+            let result = &buffer.results[2 * 6 + x];
+            let (a, y) = (float(line + 4 * x), float(line + 4 * (6 + x)));
+            match result.escaped {
+                true => assert_eq!((a, y), (1.0, result.smooth)),
+                false => assert_eq!((a, y), (0.0, 0.0)),
+            }
+        }
+    }
+}
@@ -0,0 +1,624 @@
+use crate::{
+    Complex, EscapeResult, Expression, Hybrid, Keyframe, Magnet, Newton, Nova, QuaternionJulia,
+};
+
+/// Why an orbit stopped before reaching `max_iter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bailout {
+    Escaped,
+    /// Settled on a finite attractor, which escape-time coloring treats like an escape.
+    Converged,
+}
+
+/// An escape-time formula, iterated by the renderer in place of the Mandelbrot set.
+pub trait Fractal: Send + Sync {
+    /// Identifies the formula and its parameters in cache keys, e.g. `"multibrot 3"`.
+    fn name(&self) -> String;
+
+    /// The orbit value following `z` for the point `c`.
+    fn step(&self, z: Complex, c: Complex) -> Complex;
+
+    /// `step` in f64, for precision checks. Goes through `step` unless overridden, so such
+    /// formulas always agree with themselves.
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        let z = self.step(
+            Complex::new(zx as f32, zy as f32),
+            Complex::new(cx as f32, cy as f32),
+        );
+        (z.x as f64, z.y as f64)
+    }
+
+    /// The formula with its parameters taken from `keyframe`, for fractals animated through
+    /// keyframes.
+    fn animate(&self, _keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        None
+    }
+
+    /// Whether orbits are iterated by `step` alone until they escape. Formulas that depend on
+    /// more of the orbit, or that converge, override `iterate` instead, and can't be used with
+    /// iteration budgets or density renders.
+    fn stepwise(&self) -> bool {
+        true
+    }
+
+    /// Number of attractors of a convergent fractal, which is colored by the basin each point
+    /// converges to instead of by escape time. 0 for escape-time fractals.
+    fn basins(&self) -> usize {
+        0
+    }
+
+    /// Which of the `basins` the orbit that ended in `result` converged to, if any.
+    fn basin(&self, _result: &EscapeResult) -> Option<usize> {
+        None
+    }
+
+    /// Whether the orbit stops at `z`. By default orbits only stop once `|z|^2` reaches
+    /// `escape_norm`; formulas with a finite attractor also stop when they reach it.
+    fn bailout(&self, z: Complex, escape_norm: f32) -> Option<Bailout> {
+        (z.norm() >= escape_norm).then_some(Bailout::Escaped)
+    }
+
+    /// `step` for compute kernels, see `kernel_source`: the expressions of the real and the
+    /// imaginary part of the orbit value following `x + iy` for the point `cx + i cy`, in the
+    /// f32 arithmetic and `abs` that WGSL and OpenCL C share. `None` for formulas only the CPU
+    /// iterates.
+    fn kernel(&self) -> Option<[String; 2]> {
+        None
+    }
+
+    /// Whether the orbit of `c` from `z0` is known to stay bounded without iterating it, so
+    /// renderers can count it as interior right away. No orbit is by default.
+    fn interior(&self, _c: (f64, f64), _z0: (f64, f64)) -> bool {
+        false
+    }
+
+    /// Degree of the formula in `z`, which sets how fast escaping orbits grow and so how the
+    /// smooth iteration count is renormalized.
+    fn power(&self) -> f64 {
+        2.0
+    }
+
+    /// Iterates `c` starting from `z0` until `bailout`, for at most `max_iter` iterations.
+    /// Budgeted renders only use `step` and `bailout`.
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        let mut z = z0;
+        let mut iters = 0;
+        let mut bailout = None;
+
+        while iters < max_iter {
+            bailout = self.bailout(z, escape_norm);
+            if bailout.is_some() {
+                break;
+            }
+            z = self.step(z, c);
+            iters += 1;
+        }
+        EscapeResult::from_bailout(z, iters, bailout, escape_norm, self.power())
+    }
+}
+
+impl<F: Fractal + ?Sized> Fractal for Box<F> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        (**self).step(z, c)
+    }
+
+    fn step_f64(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        (**self).step_f64(z, c)
+    }
+
+    fn animate(&self, keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        (**self).animate(keyframe)
+    }
+
+    fn stepwise(&self) -> bool {
+        (**self).stepwise()
+    }
+
+    fn basins(&self) -> usize {
+        (**self).basins()
+    }
+
+    fn basin(&self, result: &EscapeResult) -> Option<usize> {
+        (**self).basin(result)
+    }
+
+    fn bailout(&self, z: Complex, escape_norm: f32) -> Option<Bailout> {
+        (**self).bailout(z, escape_norm)
+    }
+
+    fn kernel(&self) -> Option<[String; 2]> {
+        (**self).kernel()
+    }
+
+    fn interior(&self, c: (f64, f64), z0: (f64, f64)) -> bool {
+        (**self).interior(c, z0)
+    }
+
+    fn power(&self) -> f64 {
+        (**self).power()
+    }
+
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        (**self).iterate(c, z0, max_iter, escape_norm)
+    }
+}
+
+/// The built-in formula called `name`, as returned by its `Fractal::name`. A bare
+/// `"multibrot"` or `"phoenix"` takes its parameter from the keyframes, a bare `"newton"` solves
+/// `z^3 - 1` and a bare `"nova"` is the cubic one without relaxation. The magnet formulas are
+/// `"magnet 1"` and `"magnet 2"`, the abs variants go by the names in `AbsVariant::NAMED`, and
+/// `"hybrid "` is followed by a `Hybrid` sequence. Names starting with `"z ="` are custom
+/// `Expression` formulas. A bare `"quaternion"` is the `QuaternionJulia` set of the keyframes'
+/// `c`.
+pub fn fractal_from_name(name: &str) -> Option<Box<dyn Fractal>> {
+    match name {
+        "mandelbrot" => Some(Box::new(Mandelbrot)),
+        "burning ship" => Some(Box::new(BurningShip)),
+        "tricorn" => Some(Box::new(Tricorn)),
+        "magnet 1" => Some(Box::new(Magnet::One)),
+        "magnet 2" => Some(Box::new(Magnet::Two)),
+        _ if name.starts_with("z =") => Some(Box::new(Expression::parse(name).ok()?)),
+        "quaternion" => Some(Box::new(QuaternionJulia::animated())),
+        _ if name.starts_with("quaternion ") => {
+            Some(Box::new(QuaternionJulia::parse(&name[11..])?))
+        }
+        _ if name.starts_with("hybrid ") => Some(Box::new(Hybrid::parse(&name[7..])?)),
+        _ if name.starts_with("abs") => Some(Box::new(AbsVariant::parse(name)?)),
+        "multibrot" => Some(Box::new(Multibrot::animated())),
+        "newton" => Some(Box::new(Newton::roots_of_unity(3))),
+        "nova" => Some(Box::new(Nova::new(3, Complex::new(1.0, 0.0)))),
+        _ if name.starts_with("nova ") => Some(Box::new(Nova::parse(&name[5..])?)),
+        "phoenix" => Some(Box::new(Phoenix::animated())),
+        _ if name.starts_with("phoenix ") => {
+            Some(Box::new(Phoenix::new(Complex::parse(&name[8..])?)))
+        }
+        _ if name.starts_with("newton ") => Some(Box::new(Newton::parse(&name[7..])?)),
+        _ => {
+            if let Some(variant) = AbsVariant::named(name) {
+                return Some(Box::new(variant));
+            }
+            let power = name.strip_prefix("multibrot ")?.parse().ok()?;
+            Some(Box::new(Multibrot::new(power)))
+        }
+    }
+}
+
+/// `z^2 + c`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mandelbrot;
+
+impl Fractal for Mandelbrot {
+    fn name(&self) -> String {
+        "mandelbrot".to_owned()
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z * z + c
+    }
+
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        (zx * zx - zy * zy + cx, 2.0 * zx * zy + cy)
+    }
+
+    fn kernel(&self) -> Option<[String; 2]> {
+        Some([
+            "x * x - y * y + cx".to_owned(),
+            "2.0 * x * y + cy".to_owned(),
+        ])
+    }
+
+    /// Points of the main cardioid and of the period-2 bulb, which make up most of the interior
+    /// of wide views. Only orbits from 0 are covered, those from other `z0` don't stay in them.
+    fn interior(&self, (cx, cy): (f64, f64), z0: (f64, f64)) -> bool {
+        if z0 != (0.0, 0.0) {
+            return false;
+        }
+        let x = cx - 0.25;
+        let q = x * x + cy * cy;
+        let cardioid = q * (q + x) <= 0.25 * cy * cy;
+        let bulb = (cx + 1.0) * (cx + 1.0) + cy * cy <= 0.0625;
+        cardioid || bulb
+    }
+}
+
+/// `(|Re z| + i|Im z|)^2 + c`. The ship's hull points toward positive imaginary parts, so it
+/// shows upside down unless the view is flipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BurningShip;
+
+impl Fractal for BurningShip {
+    fn name(&self) -> String {
+        "burning ship".to_owned()
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let z = Complex::new(z.x.abs(), z.y.abs());
+        z * z + c
+    }
+
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        (zx * zx - zy * zy + cx, 2.0 * (zx * zy).abs() + cy)
+    }
+
+    fn kernel(&self) -> Option<[String; 2]> {
+        Some([
+            "x * x - y * y + cx".to_owned(),
+            "2.0 * abs(x * y) + cy".to_owned(),
+        ])
+    }
+}
+
+/// `conj(z)^2 + c`, also known as the Mandelbar set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tricorn;
+
+impl Fractal for Tricorn {
+    fn name(&self) -> String {
+        "tricorn".to_owned()
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let z = Complex::new(z.x, -z.y);
+        z * z + c
+    }
+
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        (zx * zx - zy * zy + cx, -2.0 * zx * zy + cy)
+    }
+
+    fn kernel(&self) -> Option<[String; 2]> {
+        Some([
+            "x * x - y * y + cx".to_owned(),
+            "-2.0 * x * y + cy".to_owned(),
+        ])
+    }
+}
+
+/// `z^2 + c` with absolute values taken of some parts of `z` or `z^2`, the family the burning ship
+/// and the tricorn belong to. With `z = x + iy`, one step is
+/// `x' = [|](x^2 - y^2)[|] + Re c` and `y' = [-]2 [|]x[|] [|]y[|] + Im c`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AbsVariant {
+    /// Takes `|x|` before squaring.
+    pub abs_x: bool,
+    /// Takes `|y|` before squaring.
+    pub abs_y: bool,
+    /// Takes the absolute value of the real part of `z^2`.
+    pub abs_real: bool,
+    /// Negates the imaginary part of `z^2`.
+    pub conjugate: bool,
+}
+
+impl AbsVariant {
+    pub const CELTIC: Self = Self::new(false, false, true, false);
+    pub const PERPENDICULAR_MANDELBROT: Self = Self::new(true, false, false, true);
+    pub const PERPENDICULAR_BURNING_SHIP: Self = Self::new(false, true, false, true);
+    pub const HEART: Self = Self::new(true, false, false, false);
+    pub const BUFFALO: Self = Self::new(true, true, true, true);
+
+    /// The variants known by name, which is also what their `Fractal::name` returns. Any other
+    /// combination is called `"abs"` followed by its flags, e.g. `"abs x real"`.
+    pub const NAMED: [(&'static str, Self); 5] = [
+        ("celtic", Self::CELTIC),
+        ("perpendicular mandelbrot", Self::PERPENDICULAR_MANDELBROT),
+        (
+            "perpendicular burning ship",
+            Self::PERPENDICULAR_BURNING_SHIP,
+        ),
+        ("heart", Self::HEART),
+        ("buffalo", Self::BUFFALO),
+    ];
+
+    pub const fn new(abs_x: bool, abs_y: bool, abs_real: bool, conjugate: bool) -> Self {
+        Self {
+            abs_x,
+            abs_y,
+            abs_real,
+            conjugate,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        let (_, variant) = Self::NAMED.iter().find(|(known, _)| *known == name)?;
+        Some(*variant)
+    }
+
+    /// Parses an `"abs ..."` name, whose flags are any of `x`, `y`, `real` and `conjugate`.
+    fn parse(name: &str) -> Option<Self> {
+        let mut variant = Self::default();
+        for flag in name.strip_prefix("abs")?.split_whitespace() {
+            match flag {
+                "x" => variant.abs_x = true,
+                "y" => variant.abs_y = true,
+                "real" => variant.abs_real = true,
+                "conjugate" => variant.conjugate = true,
+                _ => return None,
+            }
+        }
+        Some(variant)
+    }
+}
+
+impl Fractal for AbsVariant {
+    fn name(&self) -> String {
+        if let Some((name, _)) = Self::NAMED.iter().find(|(_, variant)| variant == self) {
+            return (*name).to_owned();
+        }
+        let flags = [
+            (self.abs_x, " x"),
+            (self.abs_y, " y"),
+            (self.abs_real, " real"),
+            (self.conjugate, " conjugate"),
+        ];
+        flags
+            .into_iter()
+            .filter(|(set, _)| *set)
+            .fold("abs".to_owned(), |name, (_, flag)| name + flag)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let x = if self.abs_x { z.x.abs() } else { z.x };
+        let y = if self.abs_y { z.y.abs() } else { z.y };
+        let real = x * x - y * y;
+        let real = if self.abs_real { real.abs() } else { real };
+        let imaginary = 2.0 * x * y;
+        let imaginary = if self.conjugate {
+            -imaginary
+        } else {
+            imaginary
+        };
+        Complex::new(real, imaginary) + c
+    }
+
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        let x = if self.abs_x { zx.abs() } else { zx };
+        let y = if self.abs_y { zy.abs() } else { zy };
+        let real = x * x - y * y;
+        let real = if self.abs_real { real.abs() } else { real };
+        let imaginary = 2.0 * x * y;
+        let imaginary = if self.conjugate {
+            -imaginary
+        } else {
+            imaginary
+        };
+        (real + cx, imaginary + cy)
+    }
+
+    fn kernel(&self) -> Option<[String; 2]> {
+        let x = if self.abs_x { "abs(x)" } else { "x" };
+        let y = if self.abs_y { "abs(y)" } else { "y" };
+        let real = match self.abs_real {
+            true => format!("abs({x} * {x} - {y} * {y})"),
+            false => format!("{x} * {x} - {y} * {y}"),
+        };
+        let sign = if self.conjugate { "-" } else { "" };
+        Some([
+            format!("{real} + cx"),
+            format!("{sign}2.0 * {x} * {y} + cy"),
+        ])
+    }
+}
+
+/// `z^d + c` for any real `d > 1`. Integer exponents are computed by repeated multiplication,
+/// others in polar form.
+#[derive(Clone, Copy, Debug)]
+pub struct Multibrot {
+    power: f32,
+    animated: bool,
+}
+
+impl Multibrot {
+    pub fn new(power: f32) -> Self {
+        Self {
+            power,
+            animated: false,
+        }
+    }
+
+    /// Takes the exponent from every keyframe's `power`, interpolated between keyframes.
+    pub fn animated() -> Self {
+        Self {
+            power: 2.0,
+            animated: true,
+        }
+    }
+}
+
+impl Fractal for Multibrot {
+    fn name(&self) -> String {
+        match self.animated {
+            true => "multibrot".to_owned(),
+            false => format!("multibrot {}", self.power),
+        }
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z.powf(self.power) + c
+    }
+
+    fn step_f64(&self, (zx, zy): (f64, f64), (cx, cy): (f64, f64)) -> (f64, f64) {
+        let power = self.power as f64;
+        let magnitude = (zx * zx + zy * zy).powf(power / 2.0);
+        let angle = zy.atan2(zx) * power;
+        (magnitude * angle.cos() + cx, magnitude * angle.sin() + cy)
+    }
+
+    fn animate(&self, keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        match self.animated {
+            true => Some(Box::new(Multibrot::new(keyframe.power))),
+            false => None,
+        }
+    }
+
+    fn power(&self) -> f64 {
+        self.power as f64
+    }
+}
+
+/// `z^2 + c + p z_prev`, where `z_prev` is the orbit value before `z`. Usually rendered as a Julia
+/// set, the classic one with `c = 0.5667` and `p = -0.5`.
+#[derive(Clone, Copy, Debug)]
+pub struct Phoenix {
+    p: Complex,
+    animated: bool,
+}
+
+impl Phoenix {
+    pub fn new(p: Complex) -> Self {
+        Self { p, animated: false }
+    }
+
+    /// Takes `p` from every keyframe's `p_x, p_y`, interpolated between keyframes.
+    pub fn animated() -> Self {
+        Self {
+            p: Complex::new(0.0, 0.0),
+            animated: true,
+        }
+    }
+}
+
+impl Fractal for Phoenix {
+    fn name(&self) -> String {
+        match self.animated {
+            true => "phoenix".to_owned(),
+            false => format!("phoenix {}", self.p),
+        }
+    }
+
+    /// Without the previous orbit value, this is just `z^2 + c`.
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        z * z + c
+    }
+
+    fn stepwise(&self) -> bool {
+        false
+    }
+
+    fn animate(&self, keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        match self.animated {
+            true => Some(Box::new(Phoenix::new(Complex::new(
+                keyframe.p_x,
+                keyframe.p_y,
+            )))),
+            false => None,
+        }
+    }
+
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        let mut z = z0;
+        let mut previous = Complex::new(0.0, 0.0);
+        let mut iters = 0;
+
+        while z.norm() < escape_norm && iters < max_iter {
+            (z, previous) = (z * z + c + self.p * previous, z);
+            iters += 1;
+        }
+        EscapeResult::from_orbit(z, iters, iters < max_iter, escape_norm, self.power())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Renderer;
+
+    /// `z^2 + c` as an application would plug it in, with nothing but the formula.
+    struct Squared;
+
+    impl Fractal for Squared {
+        fn name(&self) -> String {
+            "squared".to_owned()
+        }
+
+        fn step(&self, z: Complex, c: Complex) -> Complex {
+            z * z + c
+        }
+    }
+
+    fn escapes(fractal: impl Fractal + 'static) -> Vec<(bool, usize)> {
+        let renderer = Renderer::new(48, 32, 300).with_fractal(fractal);
+        let buffer = renderer.iterate_frame(&Keyframe::view(-0.5, 0.0, 3.0, 2.0));
+        let results = buffer.results.iter();
+        results.map(|r| (r.escaped, r.iterations)).collect()
+    }
+
+    #[test]
+    fn formulas_plug_into_the_renderer() {
+        assert_eq!(escapes(Squared), escapes(Mandelbrot));
+        assert_ne!(escapes(Tricorn), escapes(Mandelbrot));
+    }
+
+    /// Points spread over the plane, on either side of both axes.
+    fn points() -> Vec<(f32, f32)> {
+        let spread = [-1.5, -0.7, -0.2, 0.3, 1.1];
+        spread
+            .iter()
+            .flat_map(|&x| spread.iter().map(move |&y| (x, y)))
+            .collect()
+    }
+
+    /// Whether `fractal` takes the same steps as `reference` in f32 and f64.
+    fn steps_like(fractal: &dyn Fractal, reference: &dyn Fractal) -> bool {
+        let c = (0.25, -0.5);
+        points().into_iter().all(|(x, y)| {
+            let step = fractal.step(Complex::new(x, y), Complex::new(c.0, c.1));
+            let wanted = reference.step_f64((x as f64, y as f64), (c.0 as f64, c.1 as f64));
+            let step_f64 = fractal.step_f64((x as f64, y as f64), (c.0 as f64, c.1 as f64));
+            step_f64 == wanted
+                && (step.x as f64 - wanted.0).abs() < 1e-6
+                && (step.y as f64 - wanted.1).abs() < 1e-6
+        })
+    }
+
+    #[test]
+    fn burning_ship_folds_before_squaring() {
+        // (|-1| + |2|i)^2 = -3 + 4i, where z^2 = -3 - 4i.
+        let c = Complex::new(0.5, 0.25);
+        let ship = BurningShip.step(Complex::new(-1.0, 2.0), c);
+        assert_eq!((ship.x, ship.y), (-2.5, 4.25));
+        let mandelbrot = Mandelbrot.step(Complex::new(-1.0, 2.0), c);
+        assert_eq!((mandelbrot.x, mandelbrot.y), (-2.5, -3.75));
+        assert!(steps_like(&BurningShip, &BurningShip));
+        assert_eq!(
+            fractal_from_name("burning ship").unwrap().name(),
+            "burning ship"
+        );
+    }
+
+    #[test]
+    fn abs_variants_go_by_their_names() {
+        for (name, variant) in AbsVariant::NAMED {
+            assert_eq!(variant.name(), name);
+            assert_eq!(fractal_from_name(name).unwrap().name(), name);
+        }
+        let unnamed = AbsVariant::new(true, false, true, false);
+        assert_eq!(unnamed.name(), "abs x real");
+        assert_eq!(AbsVariant::parse("abs x real"), Some(unnamed));
+        assert_eq!(AbsVariant::parse("abs z"), None);
+    }
+
+    #[test]
+    fn abs_variants_generalize_the_classic_formulas() {
+        assert!(steps_like(&AbsVariant::default(), &Mandelbrot));
+        assert!(steps_like(
+            &AbsVariant::new(true, true, false, false),
+            &BurningShip
+        ));
+        assert!(steps_like(
+            &AbsVariant::new(false, false, false, true),
+            &Tricorn
+        ));
+        for flags in 0..16 {
+            let [abs_x, abs_y, abs_real, conjugate] = [0, 1, 2, 3].map(|bit| flags >> bit & 1 == 1);
+            let variant = AbsVariant::new(abs_x, abs_y, abs_real, conjugate);
+            assert!(steps_like(&variant, &variant));
+        }
+        // |x^2 - y^2| + 2xy i for the celtic.
+        let celtic = AbsVariant::CELTIC.step(Complex::new(1.0, 2.0), Complex::new(0.0, 0.0));
+        assert_eq!((celtic.x, celtic.y), (3.0, 4.0));
+    }
+}
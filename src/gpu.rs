@@ -0,0 +1,275 @@
+//! `Device` running the `KernelLanguage::Wgsl` kernel on whatever adapters wgpu finds, through
+//! Vulkan, Metal, DirectX or OpenGL. Machines without one get no devices, and their frames are
+//! iterated on the CPU.
+
+use std::sync::mpsc;
+use std::sync::Mutex;
+
+use crate::{
+    kernel_source, Complex, Device, DeviceFrame, EscapeResult, IterationBuffer, KernelLanguage,
+};
+
+/// Bytes of the kernel's `Frame` uniform, as WGSL lays it out: four `vec2<f32>`, three `u32`
+/// and two `f32`, padded to the 8-byte alignment of the vectors.
+const FRAME_BYTES: usize = 56;
+/// Bytes of the kernel's `Orbit`.
+const ORBIT_BYTES: u64 = 16;
+/// Pixels per side of the kernel's workgroups.
+const WORKGROUP: u32 = 8;
+
+/// A GPU, or any other adapter wgpu finds, with its own device and queue. Pipelines are built
+/// the first time a formula is iterated and kept for the frames after, along with formulas
+/// whose shader the device rejects.
+pub struct WgpuDevice {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    built: Mutex<Vec<(String, Option<wgpu::ComputePipeline>)>>,
+}
+
+impl WgpuDevice {
+    /// Every adapter of every backend, none without a driver for one.
+    pub fn all() -> Vec<WgpuDevice> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapters = pollster::block_on(instance.enumerate_adapters(wgpu::Backends::all()));
+        adapters
+            .into_iter()
+            .filter_map(|adapter| {
+                let descriptor = wgpu::DeviceDescriptor::default();
+                let (device, queue) =
+                    pollster::block_on(adapter.request_device(&descriptor)).ok()?;
+                Some(WgpuDevice {
+                    name: adapter.get_info().name,
+                    device,
+                    queue,
+                    built: Mutex::new(Vec::new()),
+                })
+            })
+            .collect()
+    }
+
+    /// The pipeline of `source`, built now if it hasn't been. `None` if it doesn't build.
+    fn pipeline(&self, source: String) -> Option<wgpu::ComputePipeline> {
+        let mut built = self.built.lock().unwrap();
+        if let Some((_, pipeline)) = built.iter().find(|(built, _)| *built == source) {
+            return pipeline.clone();
+        }
+        let scope = self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(source.as_str().into()),
+            });
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+        let pipeline = pollster::block_on(scope.pop())
+            .is_none()
+            .then_some(pipeline);
+        built.push((source, pipeline.clone()));
+        pipeline
+    }
+
+    /// Runs `pipeline` over `frame` and reads back the bytes of its orbits. `None` for frames
+    /// larger than the device's buffers or anything else the device fails at.
+    fn run(&self, pipeline: &wgpu::ComputePipeline, frame: &DeviceFrame) -> Option<Vec<u8>> {
+        let bytes = (frame.width * frame.height) as u64 * ORBIT_BYTES;
+        let limits = self.device.limits();
+        let groups = [frame.width, frame.height].map(|side| side.div_ceil(WORKGROUP));
+        if bytes == 0
+            || bytes > limits.max_storage_buffer_binding_size
+            || bytes > limits.max_buffer_size
+            || groups
+                .iter()
+                .any(|&g| g > limits.max_compute_workgroups_per_dimension)
+        {
+            return None;
+        }
+
+        let scope = self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let buffer = |size, usage| {
+            self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let uniform = buffer(
+            FRAME_BYTES as u64,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        let orbits = buffer(
+            bytes,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        );
+        let readback = buffer(
+            bytes,
+            wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        );
+        self.queue.write_buffer(&uniform, 0, &frame_bytes(frame));
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: orbits.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups[0], groups[1], 1);
+        }
+        encoder.copy_buffer_to_buffer(&orbits, 0, &readback, 0, bytes);
+        self.queue.submit([encoder.finish()]);
+        if pollster::block_on(scope.pop()).is_some() {
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receiver.recv().ok()?.ok()?;
+        let data = readback.get_mapped_range(..).ok()?.to_vec();
+        readback.unmap();
+        Some(data)
+    }
+}
+
+impl Device for WgpuDevice {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn iterate(&self, frame: &DeviceFrame) -> Option<IterationBuffer> {
+        let source = kernel_source(frame.fractal, KernelLanguage::Wgsl)?;
+        let pipeline = self.pipeline(source)?;
+        let orbits = self.run(&pipeline, frame)?;
+        let power = frame.fractal.power();
+        let field = |orbit: &[u8], i: usize| orbit[4 * i..4 * i + 4].try_into().unwrap();
+        let results = orbits
+            .chunks_exact(ORBIT_BYTES as usize)
+            .map(|orbit| {
+                let z = Complex::new(
+                    f32::from_le_bytes(field(orbit, 0)),
+                    f32::from_le_bytes(field(orbit, 1)),
+                );
+                let iterations = u32::from_le_bytes(field(orbit, 2)) as usize;
+                let escaped = u32::from_le_bytes(field(orbit, 3)) != 0;
+                EscapeResult::from_orbit(z, iterations, escaped, frame.escape_norm, power)
+            })
+            .collect();
+        Some(IterationBuffer {
+            width: frame.width,
+            height: frame.height,
+            results,
+        })
+    }
+}
+
+/// The kernel's `Frame` for `frame`.
+fn frame_bytes(frame: &DeviceFrame) -> [u8; FRAME_BYTES] {
+    let keyframe = frame.keyframe;
+    let words = [
+        (keyframe.x_center as f32).to_bits(),
+        (keyframe.y_center as f32).to_bits(),
+        (keyframe.x_size as f32).to_bits(),
+        (keyframe.y_size as f32).to_bits(),
+        keyframe.c_x.to_bits(),
+        keyframe.c_y.to_bits(),
+        keyframe.z0_x.to_bits(),
+        keyframe.z0_y.to_bits(),
+        frame.width,
+        frame.height,
+        frame.max_iter.min(u32::MAX as usize) as u32,
+        frame.escape_norm.to_bits(),
+        frame.morph.to_bits(),
+    ];
+    let mut bytes = [0; FRAME_BYTES];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fractal_from_name, Keyframe, Mandelbrot, Renderer};
+
+    #[test]
+    fn frame_matches_the_kernel_layout() {
+        let source = kernel_source(&Mandelbrot, KernelLanguage::Wgsl).unwrap();
+        let module = wgpu::naga::front::wgsl::parse_str(&source).unwrap();
+        let frame = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("Frame"))
+            .unwrap();
+        assert_eq!(frame.1.inner.size(module.to_ctx()) as usize, FRAME_BYTES);
+        let orbit = module
+            .types
+            .iter()
+            .find(|(_, ty)| ty.name.as_deref() == Some("Orbit"))
+            .unwrap();
+        assert_eq!(orbit.1.inner.size(module.to_ctx()) as u64, ORBIT_BYTES);
+    }
+
+    #[test]
+    fn kernels_validate() {
+        for name in ["mandelbrot", "burning ship", "tricorn", "celtic", "heart"] {
+            let fractal = fractal_from_name(name).unwrap();
+            let source = kernel_source(fractal.as_ref(), KernelLanguage::Wgsl).unwrap();
+            let module = wgpu::naga::front::wgsl::parse_str(&source).unwrap();
+            let flags = wgpu::naga::valid::ValidationFlags::all();
+            let capabilities = wgpu::naga::valid::Capabilities::empty();
+            wgpu::naga::valid::Validator::new(flags, capabilities)
+                .validate(&module)
+                .unwrap_or_else(|error| panic!("{name}: {error:?}"));
+        }
+    }
+
+    /// Only runs where wgpu finds an adapter.
+    #[test]
+    fn devices_agree_with_the_cpu() {
+        let view = Keyframe::view(-0.5, 0.0, 3.0, 2.0);
+        let cpu = Renderer::new(64, 48, 200).iterate_frame(&view);
+        for device in WgpuDevice::all() {
+            let frame = DeviceFrame {
+                keyframe: &view,
+                width: 64,
+                height: 48,
+                max_iter: 200,
+                escape_norm: 8192.0,
+                fractal: &Mandelbrot,
+                morph: 0.0,
+            };
+            let gpu = device.iterate(&frame).unwrap();
+            // f32 rounding on the device moves the odd boundary pixel by an iteration.
+            let mismatched = (gpu.results.iter().zip(&cpu.results))
+                .filter(|(a, b)| a.escaped != b.escaped)
+                .count();
+            assert!(mismatched * 100 <= cpu.results.len(), "{}", device.name());
+        }
+    }
+}
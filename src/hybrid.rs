@@ -0,0 +1,156 @@
+use crate::{fractal_from_name, Complex, EscapeResult, Fractal, Keyframe};
+
+/// A repeating sequence of formulas, each applied for some steps before the next one takes
+/// over, e.g. two Mandelbrot steps then one burning ship step. Only the `step` of every formula
+/// is used, and each one's `bailout` while it is current.
+pub struct Hybrid {
+    sequence: Vec<(usize, Box<dyn Fractal>)>,
+}
+
+impl Hybrid {
+    /// Steps `formula` for its count of iterations, for every entry of `sequence` in turn.
+    /// Entries with a count of 0 are dropped, and `None` is returned when none are left.
+    pub fn new(sequence: Vec<(usize, Box<dyn Fractal>)>) -> Option<Self> {
+        let sequence: Vec<_> = sequence
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .collect();
+        (!sequence.is_empty()).then_some(Self { sequence })
+    }
+
+    /// Parses the part of a `Fractal::name` after `"hybrid "`: entries separated by `;`, each a
+    /// formula name optionally preceded by its count, e.g. `"2 mandelbrot; burning ship"`.
+    pub(crate) fn parse(sequence: &str) -> Option<Self> {
+        let sequence = sequence
+            .split(';')
+            .map(|entry| {
+                let entry = entry.trim();
+                match entry.split_once(' ') {
+                    Some((count, name)) if count.parse::<usize>().is_ok() => {
+                        Some((count.parse().ok()?, fractal_from_name(name.trim())?))
+                    }
+                    _ => Some((1, fractal_from_name(entry)?)),
+                }
+            })
+            .collect::<Option<_>>()?;
+        Self::new(sequence)
+    }
+
+    /// The formula for iteration `iteration`, counting from 0.
+    fn formula(&self, iteration: usize) -> &dyn Fractal {
+        let period: usize = self.sequence.iter().map(|(count, _)| count).sum();
+        let mut offset = iteration % period;
+        for (count, formula) in &self.sequence {
+            if offset < *count {
+                return &**formula;
+            }
+            offset -= count;
+        }
+        unreachable!("the offset is below the sum of the counts")
+    }
+}
+
+impl Fractal for Hybrid {
+    fn name(&self) -> String {
+        let entries: Vec<String> = self
+            .sequence
+            .iter()
+            .map(|(count, formula)| format!("{count} {}", formula.name()))
+            .collect();
+        format!("hybrid {}", entries.join("; "))
+    }
+
+    /// The first formula's step; whole orbits need `iterate`, which knows the iteration.
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        self.sequence[0].1.step(z, c)
+    }
+
+    fn stepwise(&self) -> bool {
+        false
+    }
+
+    /// Rebuilds the formulas that are animated, by name for the others.
+    fn animate(&self, keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        let animated: Vec<_> = self
+            .sequence
+            .iter()
+            .map(|(_, formula)| formula.animate(keyframe))
+            .collect();
+        if animated.iter().all(Option::is_none) {
+            return None;
+        }
+        let sequence = self
+            .sequence
+            .iter()
+            .zip(animated)
+            .map(|((count, formula), animated)| {
+                let formula = animated.or_else(|| fractal_from_name(&formula.name()))?;
+                Some((*count, formula))
+            })
+            .collect::<Option<_>>()?;
+        Some(Box::new(Self::new(sequence)?))
+    }
+
+    /// Renormalizes the smooth count by the formula that made the final step.
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        let mut z = z0;
+        let mut iters = 0;
+        let mut bailout = None;
+        let mut last = self.formula(0);
+
+        while iters < max_iter {
+            bailout = last.bailout(z, escape_norm);
+            if bailout.is_some() {
+                break;
+            }
+            let formula = self.formula(iters);
+            z = formula.step(z, c);
+            last = formula;
+            iters += 1;
+        }
+        EscapeResult::from_bailout(z, iters, bailout, escape_norm, last.power())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BurningShip, Mandelbrot};
+
+    #[test]
+    fn names_parse_back() {
+        let hybrid = fractal_from_name("hybrid 2 mandelbrot; burning ship").unwrap();
+        assert_eq!(hybrid.name(), "hybrid 2 mandelbrot; 1 burning ship");
+        let parsed = fractal_from_name(&hybrid.name()).unwrap();
+        assert_eq!(parsed.name(), hybrid.name());
+        // Entries of no steps are dropped, and sequences need at least one step.
+        let dropped = fractal_from_name("hybrid 0 tricorn; mandelbrot").unwrap();
+        assert_eq!(dropped.name(), "hybrid 1 mandelbrot");
+        assert!(fractal_from_name("hybrid 0 mandelbrot").is_none());
+        assert!(fractal_from_name("hybrid 2 mandelbrot; unknown").is_none());
+    }
+
+    #[test]
+    fn orbits_cycle_through_the_sequence() {
+        let hybrid = Hybrid::parse("2 mandelbrot; burning ship").unwrap();
+        let c = Complex::new(-0.3, 0.6);
+        let max_iter = 40;
+        let result = hybrid.iterate(c, Complex::new(0.0, 0.0), max_iter, 4.0);
+
+        let mut z = Complex::new(0.0, 0.0);
+        let mut iterations = 0;
+        while iterations < max_iter && z.norm() < 4.0 {
+            z = match iterations % 3 {
+                2 => BurningShip.step(z, c),
+                _ => Mandelbrot.step(z, c),
+            };
+            iterations += 1;
+        }
+        assert_eq!(result.iterations, iterations);
+        assert_eq!(result.z, (z.x, z.y));
+        // Another order of the same formulas makes another orbit.
+        let reordered = Hybrid::parse("burning ship; 2 mandelbrot").unwrap();
+        let other = reordered.iterate(c, Complex::new(0.0, 0.0), max_iter, 4.0);
+        assert_ne!(other.z, result.z);
+    }
+}
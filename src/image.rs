@@ -0,0 +1,391 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::png::{inflate, read_chunks, unfilter, write_png};
+use crate::Pixel;
+
+#[derive(Debug)]
+pub enum ImageError {
+    FileOpenError,
+    DecodeError,
+    UnsupportedFormat,
+    FileCreateError,
+    EncodeError,
+}
+
+/// Text of the comment extensions of a GIF, in the order they appear, such as the render job
+/// `Animation::comment` embeds.
+pub fn read_gif_comments(path: impl AsRef<Path>) -> Result<Vec<String>, ImageError> {
+    let file = File::open(path).map_err(|_| ImageError::FileOpenError)?;
+    let mut reader = BufReader::new(file);
+    // Color tables hold 2 to 256 colors after the flags of their descriptor.
+    let table_size = |flags: u8| match flags & 0x80 {
+        0 => 0,
+        _ => 3 << ((flags & 0x07) + 1),
+    };
+
+    let header = read_bytes(&mut reader, 13)?;
+    if !header.starts_with(b"GIF") {
+        return Err(ImageError::UnsupportedFormat);
+    }
+    read_bytes(&mut reader, table_size(header[10]))?;
+    let mut comments = Vec::new();
+    loop {
+        match read_bytes(&mut reader, 1)?[0] {
+            0x21 => {
+                let label = read_bytes(&mut reader, 1)?[0];
+                let data = read_sub_blocks(&mut reader)?;
+                if label == 0xfe {
+                    comments.push(String::from_utf8_lossy(&data).into_owned());
+                }
+            }
+            0x2c => {
+                let descriptor = read_bytes(&mut reader, 9)?;
+                // The table, then the LZW code size ahead of the image data.
+                read_bytes(&mut reader, table_size(descriptor[8]) + 1)?;
+                read_sub_blocks(&mut reader)?;
+            }
+            0x3b => return Ok(comments),
+            _ => return Err(ImageError::DecodeError),
+        }
+    }
+}
+
+fn read_bytes(reader: &mut impl Read, count: usize) -> Result<Vec<u8>, ImageError> {
+    let mut buffer = vec![0; count];
+    reader
+        .read_exact(&mut buffer)
+        .map_err(|_| ImageError::DecodeError)?;
+    Ok(buffer)
+}
+
+/// The data of GIF sub-blocks, each led by its length, up to the one of length 0.
+fn read_sub_blocks(reader: &mut impl Read) -> Result<Vec<u8>, ImageError> {
+    let mut data = Vec::new();
+    loop {
+        let length = read_bytes(reader, 1)?[0] as usize;
+        if length == 0 {
+            return Ok(data);
+        }
+        data.extend(read_bytes(reader, length)?);
+    }
+}
+
+/// A decoded RGBA image, used as input for palettes and textures.
+#[derive(Clone)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Pixel>,
+}
+
+impl Image {
+    /// Loads a GIF (first frame), PNG or binary PPM (`P6`) image.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let file = File::open(path).map_err(|_| ImageError::FileOpenError)?;
+        let mut reader = BufReader::new(file);
+
+        let magic = reader.fill_buf().map_err(|_| ImageError::DecodeError)?;
+        if magic.starts_with(b"GIF") {
+            let mut frames = Self::decode_gif(reader, Some(1))?;
+            frames.pop().ok_or(ImageError::DecodeError)
+        } else if magic.starts_with(b"P6") {
+            Self::decode_ppm(reader)
+        } else if magic.starts_with(b"\x89PNG") {
+            Self::decode_png(reader)
+        } else {
+            Err(ImageError::UnsupportedFormat)
+        }
+    }
+
+    /// Loads every frame of a GIF animation as it is displayed, each one drawn over the last.
+    pub fn open_frames(path: impl AsRef<Path>) -> Result<Vec<Self>, ImageError> {
+        let file = File::open(path).map_err(|_| ImageError::FileOpenError)?;
+        Self::decode_gif(BufReader::new(file), None)
+    }
+
+    /// Writes the image as a PNG or binary PPM, after the extension of `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("png") => self.save_png(path),
+            Some("ppm") => self.save_ppm(path),
+            _ => Err(ImageError::UnsupportedFormat),
+        }
+    }
+
+    pub fn save_ppm(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let file = File::create(path).map_err(|_| ImageError::FileCreateError)?;
+        let mut writer = BufWriter::new(file);
+        let raw: Vec<u8> = self.pixels.iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)
+            .and_then(|_| writer.write_all(&raw))
+            .and_then(|_| writer.flush())
+            .map_err(|_| ImageError::EncodeError)
+    }
+
+    /// Writes the image as an RGB PNG, in full color unlike a frame of the animation.
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), ImageError> {
+        let file = File::create(path).map_err(|_| ImageError::FileCreateError)?;
+        let raw: Vec<u8> = self.pixels.iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+        let size = (self.width, self.height);
+        write_png(&mut BufWriter::new(file), size, 8, 2, &raw, 3)
+            .map_err(|_| ImageError::EncodeError)
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Pixel {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Bilinearly samples the image at texture coordinates `(u, v)`, tiling outside `0.0..1.0`.
+    pub fn sample(&self, u: f32, v: f32) -> Pixel {
+        let px = u.rem_euclid(1.0) * self.width as f32 - 0.5;
+        let py = v.rem_euclid(1.0) * self.height as f32 - 0.5;
+        let fx = px - px.floor();
+        let fy = py - py.floor();
+
+        let wrap = |p: f32, size: u32| (p as i64).rem_euclid(size as i64) as u32;
+        let x0 = wrap(px.floor(), self.width);
+        let y0 = wrap(py.floor(), self.height);
+        let x1 = (x0 + 1) % self.width;
+        let y1 = (y0 + 1) % self.height;
+
+        let channel = |f: fn(&Pixel) -> u8| {
+            let at = |x, y| f(&self.get(x, y)) as f32;
+            let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+            let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+            (top + (bottom - top) * fy).round() as u8
+        };
+        Pixel {
+            r: channel(|p| p.r),
+            g: channel(|p| p.g),
+            b: channel(|p| p.b),
+            a: channel(|p| p.a),
+        }
+    }
+
+    fn decode_gif(reader: impl Read, limit: Option<usize>) -> Result<Vec<Self>, ImageError> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options
+            .read_info(reader)
+            .map_err(|_| ImageError::DecodeError)?;
+
+        let width = decoder.width() as u32;
+        let height = decoder.height() as u32;
+        let mut pixels = vec![Pixel::from_rgb(0.0, 0.0, 0.0); (width * height) as usize];
+        let mut frames = Vec::new();
+        while limit.is_none_or(|limit| frames.len() < limit) {
+            let frame = match decoder
+                .read_next_frame()
+                .map_err(|_| ImageError::DecodeError)?
+            {
+                Some(frame) => frame,
+                None => break,
+            };
+
+            // Frames may cover only part of the logical screen.
+            for (i, rgba) in frame.buffer.chunks_exact(4).enumerate() {
+                let x = frame.left as u32 + i as u32 % frame.width as u32;
+                let y = frame.top as u32 + i as u32 / frame.width as u32;
+                let transparent = frame.transparent.is_some() && rgba[3] == 0;
+                if x < width && y < height && !transparent {
+                    pixels[(y * width + x) as usize] = Pixel {
+                        r: rgba[0],
+                        g: rgba[1],
+                        b: rgba[2],
+                        a: rgba[3],
+                    };
+                }
+            }
+            frames.push(Self {
+                width,
+                height,
+                pixels: pixels.clone(),
+            });
+        }
+
+        if frames.is_empty() {
+            return Err(ImageError::DecodeError);
+        }
+        Ok(frames)
+    }
+
+    /// Decodes a PNG of any color type and bit depth, but not an interlaced one. Samples of 16
+    /// bits are cut to their high byte, and palette and grayscale ones brought up to RGB.
+    fn decode_png(mut reader: impl Read) -> Result<Self, ImageError> {
+        let chunks = read_chunks(&mut reader).map_err(|_| ImageError::DecodeError)?;
+        let data = |kind: &[u8; 4]| {
+            let mut data = chunks.iter().filter(|(other, _)| other == kind);
+            data.next().map(|(_, data)| data.as_slice())
+        };
+        let header = data(b"IHDR").filter(|header| header.len() == 13);
+        let header = header.ok_or(ImageError::DecodeError)?;
+        let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let (bit_depth, color_type, interlace) = (header[8], header[9], header[12]);
+        let channels = match color_type {
+            0 | 3 => 1,
+            2 => 3,
+            4 => 2,
+            6 => 4,
+            _ => return Err(ImageError::DecodeError),
+        };
+        if interlace != 0 || ![1, 2, 4, 8, 16].contains(&bit_depth) {
+            return Err(ImageError::UnsupportedFormat);
+        }
+
+        let compressed: Vec<u8> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == b"IDAT")
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect();
+        let filtered = inflate(&compressed).ok_or(ImageError::DecodeError)?;
+        // Rows end on whole bytes, whatever the bit depth.
+        let stride = (width as usize * channels * bit_depth as usize).div_ceil(8);
+        let pixel_size = (channels * bit_depth as usize).div_ceil(8);
+        let raw = unfilter(&filtered, stride, pixel_size)
+            .filter(|raw| raw.len() == stride * height as usize)
+            .ok_or(ImageError::DecodeError)?;
+
+        // Samples scaled to 8 bits, but palette indices as they are.
+        let sample = |x: usize, y: usize, channel: usize| -> u8 {
+            let row = &raw[y * stride..(y + 1) * stride];
+            let index = x * channels + channel;
+            match bit_depth {
+                16 => row[2 * index],
+                8 => row[index],
+                depth => {
+                    let depth = depth as usize;
+                    let byte = row[index * depth / 8];
+                    let shift = 8 - depth - index * depth % 8;
+                    let value = (byte >> shift) & ((1 << depth) - 1) as u8;
+                    match color_type {
+                        3 => value,
+                        _ => value * (255 / ((1 << depth) - 1)) as u8,
+                    }
+                }
+            }
+        };
+        let palette = data(b"PLTE").unwrap_or_default();
+        let alpha = data(b"tRNS").unwrap_or_default();
+        let pixels = (0..height as usize)
+            .flat_map(|y| (0..width as usize).map(move |x| (x, y)))
+            .map(|(x, y)| match color_type {
+                3 => {
+                    let index = sample(x, y, 0) as usize;
+                    let rgb = palette.get(3 * index..3 * index + 3).unwrap_or(&[0; 3]);
+                    let a = alpha.get(index).copied().unwrap_or(255);
+                    Pixel {
+                        r: rgb[0],
+                        g: rgb[1],
+                        b: rgb[2],
+                        a,
+                    }
+                }
+                0 | 4 => {
+                    let gray = sample(x, y, 0);
+                    let a = if color_type == 4 {
+                        sample(x, y, 1)
+                    } else {
+                        255
+                    };
+                    Pixel {
+                        r: gray,
+                        g: gray,
+                        b: gray,
+                        a,
+                    }
+                }
+                _ => Pixel {
+                    r: sample(x, y, 0),
+                    g: sample(x, y, 1),
+                    b: sample(x, y, 2),
+                    a: if color_type == 6 {
+                        sample(x, y, 3)
+                    } else {
+                        255
+                    },
+                },
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn decode_ppm(mut reader: impl BufRead) -> Result<Self, ImageError> {
+        let mut fields = Vec::new();
+        while fields.len() < 4 {
+            let mut line = String::new();
+            if reader
+                .read_line(&mut line)
+                .map_err(|_| ImageError::DecodeError)?
+                == 0
+            {
+                return Err(ImageError::DecodeError);
+            }
+            let line = line.split('#').next().unwrap_or_default();
+            fields.extend(line.split_whitespace().map(str::to_owned));
+        }
+
+        let parse = |field: &str| field.parse::<u32>().map_err(|_| ImageError::DecodeError);
+        let width = parse(&fields[1])?;
+        let height = parse(&fields[2])?;
+        if parse(&fields[3])? != 255 {
+            return Err(ImageError::UnsupportedFormat);
+        }
+
+        let mut data = vec![0; (width * height * 3) as usize];
+        reader
+            .read_exact(&mut data)
+            .map_err(|_| ImageError::DecodeError)?;
+        let pixels = data
+            .chunks_exact(3)
+            .map(|rgb| Pixel {
+                r: rgb[0],
+                g: rgb[1],
+                b: rgb[2],
+                a: 255,
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn png_opens_as_saved() {
+        let pixels = (0..12 * 9)
+            .map(|i| Pixel {
+                r: (i * 2) as u8,
+                g: (i * 7 % 256) as u8,
+                b: 255 - i as u8,
+                a: 255,
+            })
+            .collect();
+        let image = Image {
+            width: 12,
+            height: 9,
+            pixels,
+        };
+        let path = std::env::temp_dir().join("mandelbrot-image-test.png");
+        image.save_png(&path).unwrap();
+        let opened = Image::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((opened.width, opened.height), (12, 9));
+        assert_eq!(opened.pixels, image.pixels);
+    }
+}
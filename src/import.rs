@@ -0,0 +1,755 @@
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "bigfloat")]
+use crate::DeepKeyframe;
+use crate::{Keyframe, Palette, Pixel};
+
+#[derive(Debug)]
+pub enum ImportError {
+    FileReadError,
+    MissingField(&'static str),
+    InvalidField(&'static str),
+}
+
+/// A view imported from another fractal program's parameter file.
+#[derive(Clone, Debug)]
+pub struct Location {
+    pub name: Option<String>,
+    /// Center coordinates in their original decimal form, which can carry far more precision
+    /// than a `Keyframe`.
+    pub re: String,
+    pub im: String,
+    /// Height of the view in the complex plane.
+    pub height: f64,
+    /// Width over height of the image the location was saved with, if known.
+    pub aspect: Option<f64>,
+    pub max_iter: Option<usize>,
+    /// Formula name in the source program's vocabulary, e.g. `mandel`.
+    pub fractal: Option<String>,
+    pub palette: Option<Palette>,
+    /// External palette file the location refers to instead of embedding colors.
+    pub palette_file: Option<String>,
+}
+
+impl Location {
+    /// Keyframe showing this location in an image with the given width over height.
+    pub fn to_keyframe(&self, index: usize, aspect: f32) -> Keyframe {
+        let y_size = self.height;
+        Keyframe {
+            x_center: self.re.trim().parse().unwrap_or(0.0),
+            y_center: self.im.trim().parse().unwrap_or(0.0),
+            x_size: y_size * aspect as f64,
+            y_size,
+            rotation: 0.0,
+            z0_x: 0.0,
+            z0_y: 0.0,
+            c_x: 0.0,
+            c_y: 0.0,
+            power: 2.0,
+            p_x: 0.0,
+            p_y: 0.0,
+            morph: 0.0,
+            slice_angle: 0.0,
+            slice_offset: 0.0,
+            hold: 0,
+            index,
+        }
+    }
+
+    /// `to_keyframe` with every digit of the center kept, for locations too deep for f64.
+    #[cfg(feature = "bigfloat")]
+    pub fn to_deep_keyframe(&self, index: usize, aspect: f32) -> Option<DeepKeyframe> {
+        let keyframe = self.to_keyframe(index, aspect);
+        DeepKeyframe::new(
+            &self.re,
+            &self.im,
+            self.height.log10(),
+            aspect as f64,
+            keyframe,
+        )
+    }
+}
+
+/// Reads a location from any supported parameter file, picked by extension. For files holding
+/// several entries, the first one is used.
+pub fn read_location(path: impl AsRef<Path>) -> Result<Location, ImportError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|_| ImportError::FileReadError)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "par" => parse_par(&text)?
+            .into_iter()
+            .next()
+            .ok_or(ImportError::MissingField("entry")),
+        "upr" => parse_upr(&text)?
+            .into_iter()
+            .next()
+            .ok_or(ImportError::MissingField("entry")),
+        _ => parse_kfr(&text),
+    }
+}
+
+/// Parses a Kalles Fraktaler `.kfr` location or `.kfs` settings file (`Key: value` lines).
+/// KF's `Zoom: 1` shows the view 4 units tall.
+pub fn parse_kfr(text: &str) -> Result<Location, ImportError> {
+    let field = |key: &str| {
+        text.lines().find_map(|line| {
+            let (k, v) = line.split_once(':')?;
+            (k.trim().eq_ignore_ascii_case(key)).then(|| v.trim().to_owned())
+        })
+    };
+
+    let re = field("Re").ok_or(ImportError::MissingField("Re"))?;
+    let im = field("Im").ok_or(ImportError::MissingField("Im"))?;
+    let zoom: f64 = parse_number(field("Zoom").as_deref().unwrap_or("1"), "Zoom")?;
+    if zoom <= 0.0 {
+        return Err(ImportError::InvalidField("Zoom"));
+    }
+    let max_iter = field("Iterations")
+        .map(|v| parse_number::<usize>(&v, "Iterations"))
+        .transpose()?;
+    let aspect = match (field("ImageWidth"), field("ImageHeight")) {
+        (Some(w), Some(h)) => {
+            let w: f64 = parse_number(&w, "ImageWidth")?;
+            let h: f64 = parse_number(&h, "ImageHeight")?;
+            (h > 0.0).then_some(w / h)
+        }
+        _ => None,
+    };
+
+    Ok(Location {
+        name: None,
+        re,
+        im,
+        height: 4.0 / zoom,
+        aspect,
+        max_iter,
+        fractal: None,
+        palette: None,
+        palette_file: None,
+    })
+}
+
+/// Parses the entries of a Fractint `.par` file. `center-mag` magnification 1 shows the view
+/// 2 units tall.
+pub fn parse_par(text: &str) -> Result<Vec<Location>, ImportError> {
+    let text: String = text
+        .lines()
+        .map(|line| line.split(';').next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+        // Long values (mostly `colors=`) are continued with a trailing backslash.
+        .replace("\\\n", "");
+
+    entries(&text)?
+        .into_iter()
+        .map(|(name, body)| parse_par_entry(name, body))
+        .collect()
+}
+
+/// Splits `name { body }` entries, the layout shared by Fractint and Ultra Fractal files.
+fn entries(text: &str) -> Result<Vec<(Option<String>, &str)>, ImportError> {
+    let mut entries = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let name = rest[..open].split_whitespace().last().map(str::to_owned);
+        let close = rest[open..]
+            .find('}')
+            .ok_or(ImportError::MissingField("}"))?;
+        entries.push((name, &rest[open + 1..open + close]));
+        rest = &rest[open + close + 1..];
+    }
+    Ok(entries)
+}
+
+fn parse_par_entry(name: Option<String>, body: &str) -> Result<Location, ImportError> {
+    let field = |key: &str| {
+        body.split_whitespace().find_map(|token| {
+            let (k, v) = token.split_once('=')?;
+            k.eq_ignore_ascii_case(key).then_some(v)
+        })
+    };
+    let numbers = |value: &str, field: &'static str| {
+        value
+            .split('/')
+            .map(|v| parse_number::<f64>(v, field))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    let (re, im, height, aspect) = if let Some(value) = field("center-mag") {
+        let parts: Vec<&str> = value.split('/').collect();
+        if parts.len() < 3 {
+            return Err(ImportError::InvalidField("center-mag"));
+        }
+        let mag: f64 = parse_number(parts[2], "center-mag")?;
+        let x_mag: f64 = match parts.get(3) {
+            Some(v) => parse_number(v, "center-mag")?,
+            None => 1.0,
+        };
+        if mag <= 0.0 || x_mag <= 0.0 {
+            return Err(ImportError::InvalidField("center-mag"));
+        }
+        // Fractint's screens are 4:3 at an x magnification factor of 1.
+        let aspect = 4.0 / 3.0 / x_mag;
+        (parts[0].to_owned(), parts[1].to_owned(), 2.0 / mag, aspect)
+    } else if let Some(value) = field("corners") {
+        let c = numbers(value, "corners")?;
+        if c.len() < 4 || c[3] <= c[2] {
+            return Err(ImportError::InvalidField("corners"));
+        }
+        let height = c[3] - c[2];
+        let re = ((c[0] + c[1]) / 2.0).to_string();
+        let im = ((c[2] + c[3]) / 2.0).to_string();
+        (re, im, height, (c[1] - c[0]) / height)
+    } else {
+        return Err(ImportError::MissingField("center-mag"));
+    };
+
+    let max_iter = field("maxiter")
+        .map(|v| parse_number::<usize>(v, "maxiter"))
+        .transpose()?;
+    let (palette, palette_file) = match field("colors") {
+        Some(map) if map.starts_with('@') => (None, Some(map[1..].to_owned())),
+        Some(encoded) => (Some(decode_fractint_colors(encoded)?), None),
+        None => (None, None),
+    };
+
+    Ok(Location {
+        name,
+        re,
+        im,
+        height,
+        aspect: Some(aspect),
+        max_iter,
+        fractal: field("type").map(str::to_owned),
+        palette,
+        palette_file,
+    })
+}
+
+/// Parses the entries of an Ultra Fractal `.upr` file: the `mapping:` coordinates, the
+/// `formula:` iteration count and the `gradient:` stops. `magn=1` is taken as a view 3 units
+/// tall.
+pub fn parse_upr(text: &str) -> Result<Vec<Location>, ImportError> {
+    entries(text)?
+        .into_iter()
+        .map(|(name, body)| parse_upr_entry(name, body))
+        .collect()
+}
+
+fn parse_upr_entry(name: Option<String>, body: &str) -> Result<Location, ImportError> {
+    // (section, key, value) for every `key=value`, values may be quoted and contain spaces.
+    let mut fields: Vec<(&str, &str, &str)> = Vec::new();
+    let mut section = "";
+    let mut rest = body.trim_start();
+    while !rest.is_empty() {
+        let token_end = match rest.find('=') {
+            Some(eq) if !rest[..eq].contains(char::is_whitespace) => {
+                let value = &rest[eq + 1..];
+                let len = if let Some(quoted) = value.strip_prefix('"') {
+                    quoted.find('"').map_or(value.len(), |end| end + 2)
+                } else {
+                    value.find(char::is_whitespace).unwrap_or(value.len())
+                };
+                let value = value[..len].trim_matches('"');
+                fields.push((section, &rest[..eq], value));
+                eq + 1 + len
+            }
+            _ => {
+                let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                if let Some(name) = rest[..len].strip_suffix(':') {
+                    section = name;
+                }
+                len
+            }
+        };
+        rest = rest[token_end..].trim_start();
+    }
+
+    let field = |section: &str, key: &str| {
+        fields
+            .iter()
+            .find(|(s, k, _)| s.eq_ignore_ascii_case(section) && k.eq_ignore_ascii_case(key))
+            .map(|(_, _, v)| *v)
+    };
+
+    let center = field("mapping", "center").ok_or(ImportError::MissingField("center"))?;
+    let (re, im) = center
+        .split_once('/')
+        .ok_or(ImportError::InvalidField("center"))?;
+    let magn: f64 = parse_number(field("mapping", "magn").unwrap_or("1"), "magn")?;
+    if magn <= 0.0 {
+        return Err(ImportError::InvalidField("magn"));
+    }
+    let aspect = match (field("fractal", "width"), field("fractal", "height")) {
+        (Some(w), Some(h)) => {
+            let w: f64 = parse_number(w, "width")?;
+            let h: f64 = parse_number(h, "height")?;
+            (h > 0.0).then_some(w / h)
+        }
+        _ => None,
+    };
+    let max_iter = field("formula", "maxiter")
+        .map(|v| parse_number::<usize>(v, "maxiter"))
+        .transpose()?;
+
+    // Gradient stops are `index=` / `color=` pairs in order, colors are decimal 0xBBGGRR.
+    let mut stops: Vec<(f32, u32)> = Vec::new();
+    let mut index = None;
+    for (_, key, value) in fields.iter().filter(|(s, _, _)| *s == "gradient") {
+        match *key {
+            "index" => index = Some(parse_number::<f32>(value, "index")?),
+            "color" => {
+                if let Some(index) = index.take() {
+                    stops.push((index, parse_number(value, "color")?));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Location {
+        name,
+        re: re.to_owned(),
+        im: im.to_owned(),
+        height: 3.0 / magn,
+        aspect,
+        max_iter,
+        fractal: field("formula", "entry").map(str::to_owned),
+        palette: (!stops.is_empty()).then(|| resample_gradient(stops)),
+        palette_file: None,
+    })
+}
+
+/// Reads a palette from a GIMP `.ggr` gradient, or else a Fractint `.map` file, picked by
+/// extension.
+pub fn read_palette(path: impl AsRef<Path>) -> Result<Palette, ImportError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path).map_err(|_| ImportError::FileReadError)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "ggr" => parse_ggr(&text),
+        _ => parse_map(&text),
+    }
+}
+
+/// Parses a Fractint `.map` palette, a line of red, green and blue from 0 to 255 per color
+/// with anything after them a comment. Its first color is the one Fractint gives the set.
+pub fn parse_map(text: &str) -> Result<Palette, ImportError> {
+    let colors = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut channels = line
+                .split_whitespace()
+                .map(|v| parse_number::<u8>(v, "color"));
+            let mut channel = || {
+                channels
+                    .next()
+                    .unwrap_or(Err(ImportError::MissingField("color")))
+            };
+            Ok(Pixel {
+                r: channel()?,
+                g: channel()?,
+                b: channel()?,
+                a: 255,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if colors.is_empty() {
+        return Err(ImportError::MissingField("color"));
+    }
+    Ok(Palette::Gradient(colors))
+}
+
+/// Parses a GIMP `.ggr` gradient, whose segments each blend from a left to a right color
+/// around a midpoint, sampled onto evenly spaced stops. Segments asking for HSV blends are
+/// blended in RGB like the rest, and the alpha of the colors is left out.
+pub fn parse_ggr(text: &str) -> Result<Palette, ImportError> {
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("GIMP Gradient") {
+        return Err(ImportError::MissingField("GIMP Gradient"));
+    }
+    let mut lines = lines.filter(|line| !line.starts_with("Name:"));
+    let count = parse_number::<usize>(
+        lines.next().ok_or(ImportError::MissingField("segments"))?,
+        "segments",
+    )?;
+    let segments = lines
+        .take(count)
+        .map(|line| {
+            let values = line
+                .split_whitespace()
+                .map(|v| parse_number::<f32>(v, "segment"))
+                .collect::<Result<Vec<_>, _>>()?;
+            // Positions, both RGBA colors and the blending function, ahead of the coloring.
+            if values.len() < 12 {
+                return Err(ImportError::InvalidField("segment"));
+            }
+            Ok(values)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if segments.len() != count || count == 0 {
+        return Err(ImportError::MissingField("segment"));
+    }
+
+    Ok(sample_gradient(|t| {
+        let segment = segments
+            .iter()
+            .find(|segment| t <= segment[2])
+            .unwrap_or(&segments[count - 1]);
+        let (left, middle, right) = (segment[0], segment[1], segment[2]);
+        let length = right - left;
+        let (middle, position) = match length > f32::EPSILON {
+            true => (
+                (middle - left) / length,
+                ((t - left) / length).clamp(0.0, 1.0),
+            ),
+            false => (0.5, 0.5),
+        };
+        // GIMP's blending functions, all of which pass the midpoint half way.
+        let linear = match position <= middle {
+            true if middle > f32::EPSILON => 0.5 * position / middle,
+            true => 0.0,
+            false if 1.0 - middle > f32::EPSILON => {
+                0.5 + 0.5 * (position - middle) / (1.0 - middle)
+            }
+            false => 1.0,
+        };
+        let factor = match segment[11] as u32 {
+            1 if middle > f32::EPSILON => position.powf(0.5f32.ln() / middle.ln()),
+            1 => 1.0,
+            2 => ((linear - 0.5) * std::f32::consts::PI).sin() / 2.0 + 0.5,
+            3 => (1.0 - (linear - 1.0).powi(2)).sqrt(),
+            4 => 1.0 - (1.0 - linear.powi(2)).sqrt(),
+            _ => linear,
+        };
+        std::array::from_fn(|c| segment[3 + c] + (segment[7 + c] - segment[3 + c]) * factor)
+    }))
+}
+
+/// Parses a list of colors such as `"#000000,#ff8000@0.2,#ffffff"`: hex colors separated by
+/// commas, each optionally at a position from 0 to 1 after an `@`. Colors without a position
+/// are spread evenly between their neighbors, and the ends default to 0 and 1.
+pub fn parse_color_stops(text: &str) -> Result<Palette, ImportError> {
+    let stops = text
+        .split(',')
+        .map(|stop| {
+            let (color, position) = match stop.split_once('@') {
+                Some((color, position)) => (color, Some(parse_number::<f32>(position, "stop")?)),
+                None => (stop, None),
+            };
+            let hex = color.trim().trim_start_matches('#');
+            let rgb = u32::from_str_radix(hex, 16)
+                .ok()
+                .filter(|_| hex.len() == 6)
+                .ok_or(ImportError::InvalidField("color"))?;
+            let color = [16, 8, 0].map(|shift| ((rgb >> shift) & 0xff) as f32 / 255.0);
+            Ok((position, color))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if stops.iter().all(|(position, _)| position.is_none()) {
+        let colors = stops
+            .into_iter()
+            .map(|(_, [r, g, b])| Pixel::from_rgb(r, g, b));
+        return Ok(Palette::Gradient(colors.collect()));
+    }
+    let mut positions: Vec<Option<f32>> = stops.iter().map(|(position, _)| *position).collect();
+    let last = positions.len() - 1;
+    positions[0].get_or_insert(0.0);
+    positions[last].get_or_insert(1.0);
+    let known: Vec<usize> = (0..=last).filter(|&i| positions[i].is_some()).collect();
+    let mut placed: Vec<(f32, [f32; 3])> = Vec::with_capacity(stops.len());
+    for pair in known.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let (start, end) = (positions[from].unwrap(), positions[to].unwrap());
+        placed.extend((from..to).map(|i| {
+            let t = start + (end - start) * (i - from) as f32 / (to - from) as f32;
+            (t, stops[i].1)
+        }));
+    }
+    placed.push((positions[last].unwrap(), stops[last].1));
+
+    Ok(sample_gradient(|t| {
+        let next = placed.iter().position(|(position, _)| *position > t);
+        match next {
+            Some(0) => placed[0].1,
+            None => placed[last].1,
+            Some(n) => {
+                let ((a, from), (b, to)) = (placed[n - 1], placed[n]);
+                let f = (t - a) / (b - a);
+                std::array::from_fn(|c| from[c] + (to[c] - from[c]) * f)
+            }
+        }
+    }))
+}
+
+/// `color` sampled from 0 to 1 onto evenly spaced stops, fine enough that the gradient between
+/// them looks the same.
+fn sample_gradient(color: impl Fn(f32) -> [f32; 3]) -> Palette {
+    const SAMPLES: usize = 64;
+    Palette::Gradient(
+        (0..SAMPLES)
+            .map(|i| {
+                let [r, g, b] = color(i as f32 / (SAMPLES - 1) as f32);
+                Pixel::from_rgb(r, g, b)
+            })
+            .collect(),
+    )
+}
+
+/// Resamples Ultra Fractal's cyclic 400-entry gradient onto evenly spaced stops.
+fn resample_gradient(mut stops: Vec<(f32, u32)>) -> Palette {
+    const LENGTH: f32 = 400.0;
+    const SAMPLES: usize = 64;
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let rgb = |color: u32| [0, 8, 16].map(|shift| ((color >> shift) & 0xff) as f32 / 255.0);
+
+    let colors = (0..SAMPLES)
+        .map(|i| {
+            let position = i as f32 / SAMPLES as f32 * LENGTH;
+            let next = stops.iter().position(|(index, _)| *index > position);
+            let (before, after) = match next {
+                Some(0) | None => (stops[stops.len() - 1], stops[0]),
+                Some(n) => (stops[n - 1], stops[n]),
+            };
+            // Distances wrap around the end of the gradient.
+            let span = (after.0 - before.0).rem_euclid(LENGTH);
+            let offset = (position - before.0).rem_euclid(LENGTH);
+            let t = if span > 0.0 { offset / span } else { 0.0 };
+            let (a, b) = (rgb(before.1), rgb(after.1));
+            Pixel::from_rgb(
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            )
+        })
+        .collect();
+    Palette::Gradient(colors)
+}
+
+/// Decodes Fractint's inline palette encoding: three characters of 6-bit channels per color,
+/// with `<n>` standing for `n` colors interpolated between its neighbors.
+fn decode_fractint_colors(encoded: &str) -> Result<Palette, ImportError> {
+    let digit = |c: u8| match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'A'..=b'Z' => Ok(c - b'A' + 10),
+        b'_' => Ok(36),
+        b'`' => Ok(37),
+        b'a'..=b'z' => Ok(c - b'a' + 38),
+        _ => Err(ImportError::InvalidField("colors")),
+    };
+
+    let bytes = encoded.as_bytes();
+    let mut colors: Vec<[f32; 3]> = Vec::new();
+    let mut pending_gap = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'<' {
+            let end = encoded[i..]
+                .find('>')
+                .ok_or(ImportError::InvalidField("colors"))?;
+            pending_gap = parse_number::<usize>(&encoded[i + 1..i + end], "colors")?;
+            i += end + 1;
+            continue;
+        }
+        if i + 3 > bytes.len() {
+            return Err(ImportError::InvalidField("colors"));
+        }
+        let mut color = [0.0; 3];
+        for (c, byte) in color.iter_mut().zip(&bytes[i..i + 3]) {
+            *c = digit(*byte)? as f32 / 63.0;
+        }
+        if let Some(&previous) = colors.last() {
+            for step in 1..=pending_gap {
+                let t = step as f32 / (pending_gap + 1) as f32;
+                colors.push(std::array::from_fn(|c| {
+                    previous[c] + (color[c] - previous[c]) * t
+                }));
+            }
+        }
+        pending_gap = 0;
+        colors.push(color);
+        i += 3;
+    }
+    if colors.is_empty() {
+        return Err(ImportError::InvalidField("colors"));
+    }
+
+    Ok(Palette::Gradient(
+        colors
+            .into_iter()
+            .map(|[r, g, b]| Pixel::from_rgb(r, g, b))
+            .collect(),
+    ))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, field: &'static str) -> Result<T, ImportError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ImportError::InvalidField(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(palette: Palette) -> Vec<Pixel> {
+        match palette {
+            Palette::Gradient(colors) => colors,
+            _ => panic!("not a gradient"),
+        }
+    }
+
+    fn rgb(r: u8, g: u8, b: u8) -> Pixel {
+        Pixel { r, g, b, a: 255 }
+    }
+
+    #[test]
+    fn kfr_zoom_sets_the_height() {
+        let text = "Re: -0.743643887037158704752191506114774\r\n\
+                    Im: 0.131825904205311970493132056385139\r\n\
+                    Zoom: 8\r\nIterations: 5000\r\nImageWidth: 640\r\nImageHeight: 360\r\n";
+        let location = parse_kfr(text).unwrap();
+        assert_eq!(location.re, "-0.743643887037158704752191506114774");
+        assert_eq!(location.im, "0.131825904205311970493132056385139");
+        assert_eq!(location.height, 0.5);
+        assert_eq!(location.aspect, Some(640.0 / 360.0));
+        assert_eq!(location.max_iter, Some(5000));
+
+        assert!(matches!(
+            parse_kfr("Re: 0\nZoom: 1\n"),
+            Err(ImportError::MissingField("Im"))
+        ));
+        assert!(matches!(
+            parse_kfr("Re: 0\nIm: 0\nZoom: 0\n"),
+            Err(ImportError::InvalidField("Zoom"))
+        ));
+    }
+
+    #[test]
+    fn upr_entries_read_mapping_formula_and_gradient() {
+        let text = r#"
+            First {
+            fractal:
+              title="first" width=800 height=600
+            mapping:
+              center=-0.75/0.1 magn=2
+            formula:
+              maxiter=300 filename="Standard.ufm" entry="Mandelbrot"
+            gradient:
+              smooth=yes index=0 color=255 index=200 color=16711680
+            }
+            Second {
+            mapping:
+              center=0/0
+            }
+        "#;
+        let locations = parse_upr(text).unwrap();
+        assert_eq!(locations.len(), 2);
+        let first = &locations[0];
+        assert_eq!(first.name.as_deref(), Some("First"));
+        assert_eq!((first.re.as_str(), first.im.as_str()), ("-0.75", "0.1"));
+        assert_eq!(first.height, 1.5);
+        assert_eq!(first.aspect, Some(800.0 / 600.0));
+        assert_eq!(first.max_iter, Some(300));
+        assert_eq!(first.fractal.as_deref(), Some("Mandelbrot"));
+        // Stop colors are 0xBBGGRR: red at 0, blue half way round.
+        let gradient = colors(first.palette.clone().unwrap());
+        assert_eq!(gradient[0], rgb(255, 0, 0));
+        assert_eq!(gradient[32], rgb(0, 0, 255));
+
+        let second = &locations[1];
+        assert_eq!(second.height, 3.0);
+        assert!(second.palette.is_none() && second.max_iter.is_none());
+    }
+
+    #[test]
+    fn par_entries_read_center_mag_and_colors() {
+        let text = "First { ; the whole set\n\
+                    reset=2004 type=mandel center-mag=-0.75/0.1/4 maxiter=300\n\
+                    colors=000<1>\\\n\
+                    zzz\n\
+                    }\n\
+                    Second {\n\
+                    center-mag=0/0/1/2 colors=@default.map\n\
+                    }\n";
+        let locations = parse_par(text).unwrap();
+        assert_eq!(locations.len(), 2);
+        let first = &locations[0];
+        assert_eq!(first.name.as_deref(), Some("First"));
+        assert_eq!((first.re.as_str(), first.im.as_str()), ("-0.75", "0.1"));
+        assert_eq!(first.height, 0.5);
+        assert_eq!(first.aspect, Some(4.0 / 3.0));
+        assert_eq!(first.max_iter, Some(300));
+        assert_eq!(first.fractal.as_deref(), Some("mandel"));
+        // `<1>` puts one color half way between black and white.
+        let gradient = colors(first.palette.clone().unwrap());
+        assert_eq!(gradient.len(), 3);
+        assert_eq!(gradient[0], rgb(0, 0, 0));
+        assert!(gradient[1].r.abs_diff(128) <= 1 && gradient[1].r == gradient[1].b);
+        assert_eq!(gradient[2], rgb(255, 255, 255));
+
+        let second = &locations[1];
+        assert_eq!(second.height, 2.0);
+        assert_eq!(second.aspect, Some(2.0 / 3.0));
+        assert!(second.palette.is_none());
+        assert_eq!(second.palette_file.as_deref(), Some("default.map"));
+
+        assert!(matches!(
+            parse_par("Empty { maxiter=10 }"),
+            Err(ImportError::MissingField("center-mag"))
+        ));
+        assert!(parse_par("Bad { center-mag=0/0/1 colors=00 }").is_err());
+    }
+
+    #[test]
+    fn map_lines_are_colors() {
+        let gradient = colors(parse_map("0 0 0 the set\n255 128 0\n\n10 20 30\n").unwrap());
+        assert_eq!(gradient, [rgb(0, 0, 0), rgb(255, 128, 0), rgb(10, 20, 30)]);
+        assert!(parse_map("255 128\n").is_err());
+        assert!(parse_map("\n").is_err());
+    }
+
+    #[test]
+    fn ggr_segments_blend_their_ends() {
+        let text = "GIMP Gradient\nName: Test\n2\n\
+                    0 0.25 0.5 0 0 0 1 1 0 0 1 0 0\n\
+                    0.5 0.75 1 0 0 1 1 0 1 0 1 0 0\n";
+        let gradient = colors(parse_ggr(text).unwrap());
+        assert_eq!(gradient.len(), 64);
+        assert_eq!(gradient[0], rgb(0, 0, 0));
+        assert_eq!(gradient[63], rgb(0, 255, 0));
+        // The midpoint of the first segment is half way to red.
+        let quarter = gradient[16];
+        assert!(quarter.r.abs_diff(128) <= 4 && quarter.g == 0 && quarter.b == 0);
+
+        assert!(parse_ggr("GIMP Gradient\n2\n0 0.5 1 0 0 0 1 1 1 1 1 0 0\n").is_err());
+        assert!(parse_ggr("Not a gradient\n").is_err());
+    }
+
+    #[test]
+    fn color_stops_are_spread_between_positions() {
+        let even = colors(parse_color_stops("#000000,#ff8000,#ffffff").unwrap());
+        assert_eq!(even, [rgb(0, 0, 0), rgb(255, 128, 0), rgb(255, 255, 255)]);
+
+        let placed = colors(parse_color_stops("#000000,#ffffff@0.25,#ffffff").unwrap());
+        assert_eq!(placed[0], rgb(0, 0, 0));
+        assert_eq!(placed[16], rgb(255, 255, 255));
+        assert!(placed[8].r.abs_diff(128) <= 8);
+
+        assert!(parse_color_stops("#fff,#000000").is_err());
+        assert!(parse_color_stops("#000000@x").is_err());
+    }
+}
@@ -0,0 +1,34 @@
+//! Cooperative Ctrl-C handling: the first SIGINT only raises a flag that the frame builders poll,
+//! a second one exits immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+mod ffi {
+    pub const SIGINT: i32 = 2;
+
+    extern "C" {
+        pub fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        pub fn _exit(status: i32) -> !;
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: i32) {
+    if REQUESTED.swap(true, Ordering::SeqCst) {
+        unsafe { ffi::_exit(130) }
+    }
+}
+
+pub fn install() {
+    #[cfg(unix)]
+    unsafe {
+        ffi::signal(ffi::SIGINT, on_interrupt);
+    }
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
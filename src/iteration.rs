@@ -0,0 +1,515 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{Bailout, Complex, DoubleDouble, Keyframe, Precision, Projection};
+
+/// Outcome of iterating a single point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EscapeResult {
+    pub escaped: bool,
+    /// Iterations done before escaping, or `max_iter` for interior points.
+    pub iterations: usize,
+    /// Continuous (renormalized) iteration count, only meaningful for escaped points.
+    pub smooth: f32,
+    /// Final orbit value.
+    pub z: (f32, f32),
+}
+
+impl EscapeResult {
+    /// Result of an orbit that ended at `z` after `iterations`. The smooth count renormalizes by
+    /// how far past the escape radius the orbit landed: `n + 1 - log_power(ln|z_n| / ln R)`,
+    /// which runs continuously from one band to the next. It is computed in f64, as the double
+    /// logarithm loses too much in f32 to hide the bands.
+    pub fn from_orbit(
+        z: Complex,
+        iterations: usize,
+        escaped: bool,
+        escape_norm: f32,
+        power: f64,
+    ) -> Self {
+        let smooth = if escaped {
+            let log_z = (z.norm() as f64).ln() / 2.0;
+            let log_radius = (escape_norm as f64).ln() / 2.0;
+            let nu = (log_z / log_radius).ln() / power.ln();
+            (iterations as f64 + 1.0 - nu) as f32
+        } else {
+            iterations as f32
+        };
+
+        EscapeResult {
+            escaped,
+            iterations,
+            smooth,
+            z: (z.x, z.y),
+        }
+    }
+
+    /// Result of an orbit that stopped for `bailout`, or ran out of iterations on `None`.
+    /// Converged orbits count as escaped, with their plain iteration count as smooth count.
+    pub fn from_bailout(
+        z: Complex,
+        iterations: usize,
+        bailout: Option<Bailout>,
+        escape_norm: f32,
+        power: f64,
+    ) -> Self {
+        match bailout {
+            Some(Bailout::Converged) => EscapeResult {
+                escaped: true,
+                iterations,
+                smooth: iterations as f32,
+                z: (z.x, z.y),
+            },
+            bailout => {
+                let escaped = bailout.is_some();
+                EscapeResult::from_orbit(z, iterations, escaped, escape_norm, power)
+            }
+        }
+    }
+}
+
+/// Brent's cycle detection along an orbit: each value is compared with one saved at the start
+/// of a stretch of iterations that doubles in length every time it runs out, which catches a
+/// cycle of any period soon after the orbit settles on it.
+pub(crate) struct CycleDetector<P = (f64, f64)> {
+    saved: P,
+    steps: usize,
+    stretch: usize,
+    tolerance: f64,
+}
+
+/// A value along an orbit that `CycleDetector` can compare, in whatever precision it is
+/// iterated in.
+pub(crate) trait OrbitPoint: Copy {
+    /// The offset from `other` to `self`, rounded to f64 only once it is taken.
+    fn offset(self, other: Self) -> (f64, f64);
+}
+
+impl OrbitPoint for (f64, f64) {
+    fn offset(self, other: Self) -> (f64, f64) {
+        (self.0 - other.0, self.1 - other.1)
+    }
+}
+
+impl OrbitPoint for (DoubleDouble, DoubleDouble) {
+    fn offset(self, other: Self) -> (f64, f64) {
+        ((self.0 - other.0).to_f64(), (self.1 - other.1).to_f64())
+    }
+}
+
+impl<P: OrbitPoint> CycleDetector<P> {
+    pub(crate) fn new(z0: P, tolerance: f64) -> Self {
+        Self {
+            saved: z0,
+            steps: 0,
+            stretch: 1,
+            tolerance,
+        }
+    }
+
+    /// Whether the orbit at `z` is back within `tolerance` of the saved value.
+    pub(crate) fn repeats(&mut self, z: P) -> bool {
+        let (dx, dy) = z.offset(self.saved);
+        if dx * dx + dy * dy < self.tolerance * self.tolerance {
+            return true;
+        }
+        self.steps += 1;
+        if self.steps == self.stretch {
+            (self.saved, self.steps, self.stretch) = (z, 0, self.stretch * 2);
+        }
+        false
+    }
+}
+
+/// Iteration results for every pixel of a frame, before any coloring is applied.
+pub struct IterationBuffer {
+    pub width: u32,
+    pub height: u32,
+    pub results: Vec<EscapeResult>,
+}
+
+/// Per-pixel disagreement between two renders of the same frame at different precisions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Divergence {
+    pub pixels: usize,
+    /// Pixels that escaped in one render but not in the other.
+    pub mismatched: usize,
+    /// Mismatched pixels plus those whose smooth iteration counts differ by more than one.
+    pub diverged: usize,
+    pub mean_error: f32,
+    pub max_error: f32,
+}
+
+impl Divergence {
+    pub fn between(a: &IterationBuffer, b: &IterationBuffer) -> Self {
+        let mut divergence = Divergence {
+            pixels: a.results.len(),
+            mismatched: 0,
+            diverged: 0,
+            mean_error: 0.0,
+            max_error: 0.0,
+        };
+        let mut total = 0.0;
+        for (a, b) in a.results.iter().zip(&b.results) {
+            let error = (a.smooth - b.smooth).abs();
+            if a.escaped != b.escaped {
+                divergence.mismatched += 1;
+            }
+            if a.escaped != b.escaped || error > 1.0 {
+                divergence.diverged += 1;
+            }
+            total += error as f64;
+            divergence.max_error = divergence.max_error.max(error);
+        }
+        divergence.mean_error = (total / divergence.pixels.max(1) as f64) as f32;
+        divergence
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.diverged as f32 / self.pixels.max(1) as f32
+    }
+}
+
+/// The escape norm of `Renderer::new`, for which keys add nothing so caches from before the
+/// norm was keyed stay valid.
+pub(crate) const DEFAULT_ESCAPE_NORM: f32 = 8192.0;
+
+/// The iteration round of `Renderer::new`, likewise keyed without any bytes.
+pub(crate) const DEFAULT_ROUND: usize = 256;
+
+/// Everything that influences the iteration phase of a frame: the view, its size and iteration
+/// limit, and every setting that changes which orbits are iterated or where they stop, down to
+/// the final orbit values interior textures sample. Coloring parameters are deliberately left
+/// out, so changing them keeps hitting the cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IterationKey {
+    view: [u64; 4],
+    rotation: u32,
+    z0: [u32; 2],
+    width: u32,
+    height: u32,
+    max_iter: usize,
+    escape_norm: u32,
+    border_tracing: bool,
+    periodicity_check: bool,
+    interior_shortcut: bool,
+    budget: Option<usize>,
+    round: usize,
+    projection: Projection,
+    precision: Precision,
+    fractal: u64,
+    julia: Option<[u32; 2]>,
+    morph: Option<[u32; 3]>,
+}
+
+impl IterationKey {
+    pub fn new(keyframe: &Keyframe, width: u32, height: u32, max_iter: usize) -> Self {
+        let view = [
+            keyframe.x_center,
+            keyframe.y_center,
+            keyframe.x_size,
+            keyframe.y_size,
+        ]
+        .map(f64::to_bits);
+        Self {
+            view,
+            rotation: keyframe.rotation.to_bits(),
+            z0: [keyframe.z0_x, keyframe.z0_y].map(f32::to_bits),
+            width,
+            height,
+            max_iter,
+            escape_norm: DEFAULT_ESCAPE_NORM.to_bits(),
+            border_tracing: false,
+            periodicity_check: true,
+            interior_shortcut: true,
+            budget: None,
+            round: DEFAULT_ROUND,
+            projection: Projection::Flat,
+            precision: Precision::Single,
+            fractal: 0,
+            julia: None,
+            morph: None,
+        }
+    }
+
+    /// Keys results of orbits escaping at `norm`, which the smooth counts and final orbit values
+    /// depend on.
+    pub fn with_escape_norm(self, norm: f32) -> Self {
+        let escape_norm = norm.to_bits();
+        Self {
+            escape_norm,
+            ..self
+        }
+    }
+
+    /// Keys results of a frame iterated by `mariani_silver`, whose filled regions aren't
+    /// iterated pixel by pixel.
+    pub fn with_border_tracing(self, border_tracing: bool) -> Self {
+        Self {
+            border_tracing,
+            ..self
+        }
+    }
+
+    /// Keys results of a frame with or without periodicity checks, which stop interior orbits
+    /// at another final value than running them to the iteration limit does.
+    pub fn with_periodicity_check(self, periodicity_check: bool) -> Self {
+        Self {
+            periodicity_check,
+            ..self
+        }
+    }
+
+    /// Keys results of a frame that does or doesn't skip points `Fractal::interior` knows,
+    /// which end at `z0` instead of where their orbits get to.
+    pub fn with_interior_shortcut(self, interior_shortcut: bool) -> Self {
+        Self {
+            interior_shortcut,
+            ..self
+        }
+    }
+
+    /// Keys results of a frame iterated under a total iteration budget, in rounds of `round`
+    /// iterations, which decide where the orbits the budget runs out on stop.
+    pub fn with_budget(self, budget: Option<usize>, round: usize) -> Self {
+        Self {
+            budget,
+            round,
+            ..self
+        }
+    }
+
+    /// Keys results of a frame iterated under a non-flat projection.
+    pub fn with_projection(self, projection: Projection) -> Self {
+        Self { projection, ..self }
+    }
+
+    /// Keys results of a frame iterated at a precision other than f32.
+    pub fn with_precision(self, precision: Precision) -> Self {
+        Self { precision, ..self }
+    }
+
+    /// Keys results of a formula other than the Mandelbrot set, by its `Fractal::name`.
+    pub fn with_fractal(self, name: &str) -> Self {
+        let fractal = match name {
+            "mandelbrot" => 0,
+            name => fnv1a(name.as_bytes()),
+        };
+        Self { fractal, ..self }
+    }
+
+    /// Keys results of the Julia set with parameter `c`.
+    pub fn with_julia(self, c: Option<(f32, f32)>) -> Self {
+        let julia = c.map(|(x, y)| [x, y].map(f32::to_bits));
+        Self { julia, ..self }
+    }
+
+    /// Keys results of a frame `morph` of the way from the parameter plane to the Julia set of
+    /// `c`. A `morph` of 0 keys the parameter plane itself.
+    pub fn with_morph(self, morph: f32, (x, y): (f32, f32)) -> Self {
+        let morph = (morph != 0.0).then_some([morph, x, y].map(f32::to_bits));
+        Self { morph, ..self }
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(32);
+        // Bumped whenever the stored results change meaning, so stale caches are ignored.
+        bytes.push(FORMAT_VERSION);
+        let view = self.view.map(f64::from_bits);
+        for part in view {
+            bytes.extend_from_slice(&(part as f32).to_bits().to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.width.to_le_bytes());
+        bytes.extend_from_slice(&self.height.to_le_bytes());
+        bytes.extend_from_slice(&(self.max_iter as u64).to_le_bytes());
+        if let Some(budget) = self.budget {
+            bytes.extend_from_slice(&(budget as u64).to_le_bytes());
+            if self.round != DEFAULT_ROUND {
+                bytes.push(b'n');
+                bytes.extend_from_slice(&(self.round as u64).to_le_bytes());
+            }
+        }
+        if self.z0.map(f32::from_bits) != [0.0, 0.0] {
+            for part in self.z0 {
+                bytes.extend_from_slice(&part.to_le_bytes());
+            }
+        }
+        // Flat keys keep their original layout, so existing caches stay valid.
+        if self.projection != Projection::Flat {
+            bytes.push(self.projection as u8);
+        }
+        if self.fractal != 0 {
+            bytes.extend_from_slice(&self.fractal.to_le_bytes());
+        }
+        if let Some(c) = self.julia {
+            bytes.push(b'j');
+            for part in c {
+                bytes.extend_from_slice(&part.to_le_bytes());
+            }
+        }
+        if let Some(morph) = self.morph {
+            bytes.push(b'm');
+            for part in morph {
+                bytes.extend_from_slice(&part.to_le_bytes());
+            }
+        }
+        match self.precision {
+            Precision::Single => {}
+            Precision::Double => bytes.extend_from_slice(&[b'p', 1]),
+            Precision::Auto => bytes.extend_from_slice(&[b'p', 4]),
+            Precision::DoubleDouble => bytes.extend_from_slice(&[b'p', 5]),
+            #[cfg(feature = "bigfloat")]
+            Precision::Arbitrary { fraction_bits } => {
+                bytes.extend_from_slice(&[b'p', 2]);
+                bytes.extend_from_slice(&fraction_bits.to_le_bytes());
+            }
+            #[cfg(feature = "bigfloat")]
+            Precision::Perturbation { fraction_bits } => {
+                bytes.extend_from_slice(&[b'p', 3]);
+                bytes.extend_from_slice(&fraction_bits.to_le_bytes());
+            }
+        }
+        if self.escape_norm != DEFAULT_ESCAPE_NORM.to_bits() {
+            bytes.push(b'e');
+            bytes.extend_from_slice(&self.escape_norm.to_le_bytes());
+        }
+        if self.border_tracing {
+            bytes.push(b'b');
+        }
+        if !self.periodicity_check {
+            bytes.push(b'c');
+        }
+        if !self.interior_shortcut {
+            bytes.push(b's');
+        }
+        if self.rotation != 0 {
+            bytes.push(b'r');
+            bytes.extend_from_slice(&self.rotation.to_le_bytes());
+        }
+        // Views that f32 holds exactly keep the layout from before views were f64.
+        if view.iter().any(|&part| part as f32 as f64 != part) {
+            bytes.push(b'v');
+            for part in self.view {
+                bytes.extend_from_slice(&part.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Stable across runs and platforms, unlike `std::hash`.
+    pub fn digest(&self) -> u64 {
+        fnv1a(&self.to_bytes())
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Iteration buffers stored in a directory on disk, so re-coloring a previous render skips the
+/// iteration phase entirely.
+pub struct IterationCache {
+    dir: PathBuf,
+}
+
+impl IterationCache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn get_or_compute(
+        &self,
+        key: IterationKey,
+        compute: impl FnOnce() -> IterationBuffer,
+    ) -> IterationBuffer {
+        let path = self.dir.join(format!("{:016x}.iter", key.digest()));
+        if let Some(buffer) = fs::read(&path).ok().and_then(|bytes| decode(key, &bytes)) {
+            return buffer;
+        }
+
+        let buffer = compute();
+        // The cache is an optimization only, a failed write just means recomputing next time.
+        let _ = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, encode(key, &buffer)));
+        buffer
+    }
+}
+
+const RESULT_BYTES: usize = 17;
+/// 2: smooth counts renormalized by the escape radius.
+const FORMAT_VERSION: u8 = 2;
+
+fn encode(key: IterationKey, buffer: &IterationBuffer) -> Vec<u8> {
+    let mut bytes = key.to_bytes();
+    bytes.reserve(buffer.results.len() * RESULT_BYTES);
+    for result in &buffer.results {
+        bytes.push(result.escaped as u8);
+        bytes.extend_from_slice(&(result.iterations as u32).to_le_bytes());
+        bytes.extend_from_slice(&result.smooth.to_le_bytes());
+        bytes.extend_from_slice(&result.z.0.to_le_bytes());
+        bytes.extend_from_slice(&result.z.1.to_le_bytes());
+    }
+    bytes
+}
+
+fn decode(key: IterationKey, bytes: &[u8]) -> Option<IterationBuffer> {
+    let header = key.to_bytes();
+    // Guards against digest collisions and truncated files.
+    let body = bytes.strip_prefix(header.as_slice())?;
+    if body.len() != (key.width * key.height) as usize * RESULT_BYTES {
+        return None;
+    }
+
+    let f32_at = |chunk: &[u8], i: usize| {
+        f32::from_le_bytes([chunk[i], chunk[i + 1], chunk[i + 2], chunk[i + 3]])
+    };
+    let results = body
+        .chunks_exact(RESULT_BYTES)
+        .map(|chunk| EscapeResult {
+            escaped: chunk[0] == 1,
+            iterations: u32::from_le_bytes([chunk[1], chunk[2], chunk[3], chunk[4]]) as usize,
+            smooth: f32_at(chunk, 5),
+            z: (f32_at(chunk, 9), f32_at(chunk, 13)),
+        })
+        .collect();
+
+    Some(IterationBuffer {
+        width: key.width,
+        height: key.height,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> IterationKey {
+        IterationKey::new(&Keyframe::view(-0.5, 0.0, 3.0, 2.0), 64, 48, 500)
+    }
+
+    #[test]
+    fn settings_change_the_key() {
+        let digest = key().digest();
+        let changed = [
+            key().with_border_tracing(true),
+            key().with_periodicity_check(false),
+            key().with_interior_shortcut(false),
+            key().with_budget(Some(1 << 20), DEFAULT_ROUND),
+        ];
+        for key in changed {
+            assert_ne!(key.digest(), digest, "{key:?}");
+        }
+    }
+
+    #[test]
+    fn budgets_are_keyed_with_their_rounds() {
+        let budgeted = |round| key().with_budget(Some(1 << 20), round).digest();
+        assert_ne!(budgeted(64), budgeted(DEFAULT_ROUND));
+        // Without a budget the round changes nothing.
+        assert_eq!(key().with_budget(None, 64).digest(), key().digest());
+    }
+}
@@ -0,0 +1,450 @@
+//! Native code for `Expression`s, compiled with cranelift when they are parsed. Arithmetic is
+//! emitted inline in the order of operations of `Complex`, so a compiled formula iterates to the
+//! same values as its closures, and functions and powers other than small integer ones call
+//! back into `Complex`.
+
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::{
+    types, AbiParam, BlockArg, FuncRef, InstBuilder, MemFlagsData, StackSlot, StackSlotData,
+    StackSlotKind, Type, UserFuncName, Value,
+};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+
+use crate::expression::{function, Node};
+use crate::{Complex, Expression};
+
+/// `z, c, out`, writing the orbit value following `z` to `out`.
+type Step = unsafe extern "C" fn(f32, f32, f32, f32, *mut f32);
+
+/// `c, z0, max_iter, escape_norm, out`, writing the final orbit value to `out` and returning
+/// the iteration count, which is below `max_iter` exactly when the orbit escaped.
+type Iterate = unsafe extern "C" fn(f32, f32, f32, f32, u64, f32, *mut f32) -> u64;
+
+/// A formula compiled to a step function and to a whole escape-time loop around it, which
+/// iterates an orbit without leaving native code.
+pub(crate) struct NativeFormula {
+    /// Owns the code, freed on drop.
+    module: Option<JITModule>,
+    step: Step,
+    iterate: Iterate,
+}
+
+// The compiled code is immutable once finalized and keeps no state between calls.
+unsafe impl Send for NativeFormula {}
+unsafe impl Sync for NativeFormula {}
+
+impl NativeFormula {
+    /// `root` compiled for the host, `None` if cranelift doesn't support it.
+    pub(crate) fn compile(root: &Node) -> Option<Self> {
+        let mut flags = settings::builder();
+        flags.set("opt_level", "speed").ok()?;
+        flags.set("use_colocated_libcalls", "false").ok()?;
+        flags.set("is_pic", "false").ok()?;
+        let isa = cranelift_native::builder()
+            .ok()?
+            .finish(settings::Flags::new(flags))
+            .ok()?;
+        let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
+        builder.symbol("formula_function", call_function as *const u8);
+        builder.symbol("formula_pow", call_pow as *const u8);
+        let mut module = JITModule::new(builder);
+
+        let compiled = Compiler::new(&mut module).and_then(|compiler| {
+            let step = compiler.define(&mut module, root, false)?;
+            let iterate = compiler.define(&mut module, root, true)?;
+            module.finalize_definitions().ok()?;
+            Some((step, iterate))
+        });
+        let Some((step, iterate)) = compiled else {
+            unsafe { module.free_memory() };
+            return None;
+        };
+        unsafe {
+            let step = std::mem::transmute::<*const u8, Step>(module.get_finalized_function(step));
+            let iterate =
+                std::mem::transmute::<*const u8, Iterate>(module.get_finalized_function(iterate));
+            Some(NativeFormula {
+                module: Some(module),
+                step,
+                iterate,
+            })
+        }
+    }
+
+    pub(crate) fn step(&self, z: Complex, c: Complex) -> Complex {
+        let mut out = [0.0; 2];
+        unsafe { (self.step)(z.x, z.y, c.x, c.y, out.as_mut_ptr()) };
+        Complex::new(out[0], out[1])
+    }
+
+    /// The final orbit value of `c` from `z0` and the iterations it took, as `Fractal::iterate`
+    /// counts them.
+    pub(crate) fn iterate(
+        &self,
+        c: Complex,
+        z0: Complex,
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> (Complex, usize) {
+        let mut out = [0.0; 2];
+        let max_iter = max_iter as u64;
+        let iterations = unsafe {
+            (self.iterate)(
+                c.x,
+                c.y,
+                z0.x,
+                z0.y,
+                max_iter,
+                escape_norm,
+                out.as_mut_ptr(),
+            )
+        };
+        (Complex::new(out[0], out[1]), iterations as usize)
+    }
+}
+
+impl Drop for NativeFormula {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            unsafe { module.free_memory() };
+        }
+    }
+}
+
+/// `Expression::FUNCTIONS[index]` of `x + iy`, for calls the compiled code makes.
+extern "C" fn call_function(index: u32, x: f32, y: f32, out: *mut f32) {
+    let value = function(Expression::FUNCTIONS[index as usize])(Complex::new(x, y));
+    unsafe {
+        out.write(value.x);
+        out.add(1).write(value.y);
+    }
+}
+
+/// `Complex::pow`, for calls the compiled code makes.
+extern "C" fn call_pow(x: f32, y: f32, ex: f32, ey: f32, out: *mut f32) {
+    let value = Complex::new(x, y).pow(Complex::new(ex, ey));
+    unsafe {
+        out.write(value.x);
+        out.add(1).write(value.y);
+    }
+}
+
+/// The helpers the compiled functions import.
+struct Compiler {
+    function: FuncId,
+    pow: FuncId,
+    pointer: Type,
+}
+
+impl Compiler {
+    fn new(module: &mut JITModule) -> Option<Self> {
+        let pointer = module.target_config().pointer_type();
+        let mut function = module.make_signature();
+        function.params.push(AbiParam::new(types::I32));
+        function.params.extend([AbiParam::new(types::F32); 2]);
+        function.params.push(AbiParam::new(pointer));
+        let mut pow = module.make_signature();
+        pow.params.extend([AbiParam::new(types::F32); 4]);
+        pow.params.push(AbiParam::new(pointer));
+        Some(Compiler {
+            function: module
+                .declare_function("formula_function", Linkage::Import, &function)
+                .ok()?,
+            pow: module
+                .declare_function("formula_pow", Linkage::Import, &pow)
+                .ok()?,
+            pointer,
+        })
+    }
+
+    /// Defines the `Step` of `root`, or its `Iterate` with `looped`.
+    fn define(&self, module: &mut JITModule, root: &Node, looped: bool) -> Option<FuncId> {
+        let mut context = module.make_context();
+        let signature = &mut context.func.signature;
+        signature.params.extend([AbiParam::new(types::F32); 4]);
+        if looped {
+            signature.params.push(AbiParam::new(types::I64));
+            signature.params.push(AbiParam::new(types::F32));
+            signature.returns.push(AbiParam::new(types::I64));
+        }
+        signature.params.push(AbiParam::new(self.pointer));
+        let name = if looped { "iterate" } else { "step" };
+        let id = module
+            .declare_function(name, Linkage::Local, &context.func.signature)
+            .ok()?;
+        context.func.name = UserFuncName::user(0, id.as_u32());
+
+        let mut builder_context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut builder_context);
+        let slot =
+            builder.create_sized_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, 8, 2));
+        let mut emitter = Emitter {
+            function: module.declare_func_in_func(self.function, builder.func),
+            pow: module.declare_func_in_func(self.pow, builder.func),
+            slot,
+            pointer: self.pointer,
+            builder,
+        };
+        match looped {
+            true => emitter.iterate(root),
+            false => emitter.step(root),
+        }
+        emitter.builder.seal_all_blocks();
+        emitter.builder.finalize(module.target_config());
+
+        module.define_function(id, &mut context).ok()?;
+        module.clear_context(&mut context);
+        Some(id)
+    }
+}
+
+/// Complex values as the pair of SSA values of their parts.
+type Pair = (Value, Value);
+
+struct Emitter<'a> {
+    builder: FunctionBuilder<'a>,
+    function: FuncRef,
+    pow: FuncRef,
+    /// Where the helpers write their results.
+    slot: StackSlot,
+    pointer: Type,
+}
+
+impl Emitter<'_> {
+    fn step(&mut self, root: &Node) {
+        let entry = self.builder.create_block();
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+        let &[zx, zy, cx, cy, out] = self.builder.block_params(entry) else {
+            unreachable!("the signature has five parameters");
+        };
+        let (x, y) = self.emit(root, (zx, zy), (cx, cy));
+        self.store((x, y), out);
+        self.builder.ins().return_(&[]);
+    }
+
+    /// The loop of `Fractal::iterate`: check for escape, step, count, until `max_iter`.
+    fn iterate(&mut self, root: &Node) {
+        let entry = self.builder.create_block();
+        let header = self.builder.create_block();
+        let check = self.builder.create_block();
+        let body = self.builder.create_block();
+        let exit = self.builder.create_block();
+        for block in [header, exit] {
+            self.builder.append_block_param(block, types::F32);
+            self.builder.append_block_param(block, types::F32);
+            self.builder.append_block_param(block, types::I64);
+        }
+
+        self.builder.append_block_params_for_function_params(entry);
+        self.builder.switch_to_block(entry);
+        let &[cx, cy, zx, zy, max_iter, escape_norm, out] = self.builder.block_params(entry) else {
+            unreachable!("the signature has seven parameters");
+        };
+        let zero = self.builder.ins().iconst(types::I64, 0);
+        self.builder.ins().jump(header, &arguments(&[zx, zy, zero]));
+
+        self.builder.switch_to_block(header);
+        let &[zx, zy, iterations] = self.builder.block_params(header) else {
+            unreachable!("the header has three parameters");
+        };
+        let done = self
+            .builder
+            .ins()
+            .icmp(IntCC::UnsignedGreaterThanOrEqual, iterations, max_iter);
+        let state = arguments(&[zx, zy, iterations]);
+        self.builder.ins().brif(done, exit, &state, check, &[]);
+
+        self.builder.switch_to_block(check);
+        let xx = self.builder.ins().fmul(zx, zx);
+        let yy = self.builder.ins().fmul(zy, zy);
+        let norm = self.builder.ins().fadd(xx, yy);
+        let escaped = self
+            .builder
+            .ins()
+            .fcmp(FloatCC::GreaterThanOrEqual, norm, escape_norm);
+        self.builder.ins().brif(escaped, exit, &state, body, &[]);
+
+        self.builder.switch_to_block(body);
+        let (x, y) = self.emit(root, (zx, zy), (cx, cy));
+        let next = self.builder.ins().iadd_imm_u(iterations, 1);
+        self.builder.ins().jump(header, &arguments(&[x, y, next]));
+
+        self.builder.switch_to_block(exit);
+        let &[zx, zy, iterations] = self.builder.block_params(exit) else {
+            unreachable!("the exit has three parameters");
+        };
+        self.store((zx, zy), out);
+        self.builder.ins().return_(&[iterations]);
+    }
+
+    fn store(&mut self, (x, y): Pair, out: Value) {
+        self.builder.ins().store(MemFlagsData::trusted(), x, out, 0);
+        self.builder.ins().store(MemFlagsData::trusted(), y, out, 4);
+    }
+
+    fn constant(&mut self, value: Complex) -> Pair {
+        (
+            self.builder.ins().f32const(value.x),
+            self.builder.ins().f32const(value.y),
+        )
+    }
+
+    /// The value of `node`, folding constant parts and multiplying out small integer powers as
+    /// the closures do.
+    fn emit(&mut self, node: &Node, z: Pair, c: Pair) -> Pair {
+        let zero = Complex::new(0.0, 0.0);
+        if node.is_constant() {
+            return self.constant(node.evaluate(zero, zero));
+        }
+        match node {
+            Node::Z => z,
+            Node::C => c,
+            Node::Constant(value) => self.constant(*value),
+            Node::Negate(node) => {
+                let value = self.emit(node, z, c);
+                let zero = self.constant(zero);
+                self.sub(zero, value)
+            }
+            Node::Add(a, b) => {
+                let (a, b) = (self.emit(a, z, c), self.emit(b, z, c));
+                self.add(a, b)
+            }
+            Node::Sub(a, b) => {
+                let (a, b) = (self.emit(a, z, c), self.emit(b, z, c));
+                self.sub(a, b)
+            }
+            Node::Mul(a, b) => {
+                let (a, b) = (self.emit(a, z, c), self.emit(b, z, c));
+                self.mul(a, b)
+            }
+            Node::Div(a, b) => {
+                let (a, b) = (self.emit(a, z, c), self.emit(b, z, c));
+                self.div(a, b)
+            }
+            Node::Pow(base, exponent) if exponent.is_constant() => {
+                let exponent = exponent.evaluate(zero, zero);
+                let base = self.emit(base, z, c);
+                match (exponent.x, exponent.y) {
+                    (2.0, 0.0) => self.mul(base, base),
+                    (3.0, 0.0) => {
+                        let square = self.mul(base, base);
+                        self.mul(square, base)
+                    }
+                    // `Complex::powi`.
+                    (d, 0.0) if d.fract() == 0.0 && (0.0..=64.0).contains(&d) => {
+                        let mut result = self.constant(Complex::new(1.0, 0.0));
+                        let (mut base, mut n) = (base, d as u32);
+                        while n > 0 {
+                            if n & 1 == 1 {
+                                result = self.mul(result, base);
+                            }
+                            n >>= 1;
+                            if n > 0 {
+                                base = self.mul(base, base);
+                            }
+                        }
+                        result
+                    }
+                    _ => {
+                        let exponent = self.constant(exponent);
+                        self.call_pow(base, exponent)
+                    }
+                }
+            }
+            Node::Pow(base, exponent) => {
+                let (base, exponent) = (self.emit(base, z, c), self.emit(exponent, z, c));
+                self.call_pow(base, exponent)
+            }
+            Node::Call(name, argument) => {
+                let argument = self.emit(argument, z, c);
+                let index = Expression::FUNCTIONS.iter().position(|f| f == name);
+                let index = index.expect("calls are to `Expression::FUNCTIONS`");
+                let index = self.builder.ins().iconst(types::I32, index as i64);
+                let out = self.builder.ins().stack_addr(self.pointer, self.slot, 0);
+                let (x, y) = argument;
+                self.builder.ins().call(self.function, &[index, x, y, out]);
+                self.load()
+            }
+        }
+    }
+
+    fn call_pow(&mut self, (x, y): Pair, (ex, ey): Pair) -> Pair {
+        let out = self.builder.ins().stack_addr(self.pointer, self.slot, 0);
+        self.builder.ins().call(self.pow, &[x, y, ex, ey, out]);
+        self.load()
+    }
+
+    /// The result a helper wrote to the stack slot.
+    fn load(&mut self) -> Pair {
+        let slot = self.slot;
+        (
+            self.builder
+                .ins()
+                .stack_load(self.pointer, types::F32, slot, 0),
+            self.builder
+                .ins()
+                .stack_load(self.pointer, types::F32, slot, 4),
+        )
+    }
+
+    fn add(&mut self, (ax, ay): Pair, (bx, by): Pair) -> Pair {
+        (
+            self.builder.ins().fadd(ax, bx),
+            self.builder.ins().fadd(ay, by),
+        )
+    }
+
+    fn sub(&mut self, (ax, ay): Pair, (bx, by): Pair) -> Pair {
+        (
+            self.builder.ins().fsub(ax, bx),
+            self.builder.ins().fsub(ay, by),
+        )
+    }
+
+    fn mul(&mut self, (ax, ay): Pair, (bx, by): Pair) -> Pair {
+        let ins = |builder: &mut FunctionBuilder, a, b| builder.ins().fmul(a, b);
+        let (xx, yy) = (
+            ins(&mut self.builder, ax, bx),
+            ins(&mut self.builder, ay, by),
+        );
+        let (xy, yx) = (
+            ins(&mut self.builder, ax, by),
+            ins(&mut self.builder, ay, bx),
+        );
+        (
+            self.builder.ins().fsub(xx, yy),
+            self.builder.ins().fadd(xy, yx),
+        )
+    }
+
+    fn div(&mut self, (ax, ay): Pair, (bx, by): Pair) -> Pair {
+        let ins = |builder: &mut FunctionBuilder, a, b| builder.ins().fmul(a, b);
+        let (bxx, byy) = (
+            ins(&mut self.builder, bx, bx),
+            ins(&mut self.builder, by, by),
+        );
+        let norm = self.builder.ins().fadd(bxx, byy);
+        let (axbx, ayby) = (
+            ins(&mut self.builder, ax, bx),
+            ins(&mut self.builder, ay, by),
+        );
+        let (aybx, axby) = (
+            ins(&mut self.builder, ay, bx),
+            ins(&mut self.builder, ax, by),
+        );
+        let x = self.builder.ins().fadd(axbx, ayby);
+        let y = self.builder.ins().fsub(aybx, axby);
+        (
+            self.builder.ins().fdiv(x, norm),
+            self.builder.ins().fdiv(y, norm),
+        )
+    }
+}
+
+fn arguments(values: &[Value]) -> Vec<BlockArg> {
+    values.iter().copied().map(BlockArg::Value).collect()
+}
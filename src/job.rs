@@ -0,0 +1,726 @@
+use std::path::Path;
+
+use crate::iteration::fnv1a;
+use crate::{
+    get_adaptive_frames, get_interpolated_frames, read_gif_comments, Buddhabrot,
+    ColorVisionDeficiency, Disposal, Dither, Json, Keyframe, ManifestError, Palette, Pixel,
+    Precision, Projection, Repeat, Transparency,
+};
+
+/// Everything needed to render an animation. Its canonical JSON form and the digest of it are
+/// what caches, checkpoints, manifests and work assignment use to refer to a render.
+#[derive(Clone, Debug)]
+pub struct RenderJob {
+    pub width: u16,
+    pub height: u16,
+    pub framerate: f32,
+    pub output_framerate: f32,
+    pub keyframes: Vec<Keyframe>,
+    pub projection: Projection,
+    pub precision: Precision,
+    /// Renders the Julia sets of the keyframes' `c` instead of the Mandelbrot set.
+    pub julia: bool,
+    pub doublings_per_second: Option<f32>,
+    /// `Fractal::name` of the formula iterated.
+    pub fractal: String,
+    pub max_iter: usize,
+    /// See `Renderer::with_iteration_scaling`.
+    pub iterations_per_doubling: Option<f32>,
+    /// Limit of the calibrated iteration limits, see `Renderer::with_iteration_calibration`.
+    pub max_iter_calibration: Option<usize>,
+    pub iteration_budget: Option<usize>,
+    /// Iterations per round of a budgeted frame, see `Renderer::with_iteration_budget`.
+    pub iteration_round: usize,
+    pub escape_norm: f32,
+    pub interior_shortcut: bool,
+    pub periodicity_check: bool,
+    pub border_tracing: bool,
+    pub basin_supersampling: Option<u32>,
+    pub palette: Palette,
+    pub auto_exposure: Option<f32>,
+    pub exterior_texture: Option<String>,
+    pub interior_texture: Option<String>,
+    pub simulate_cvd: Option<ColorVisionDeficiency>,
+    pub transparency: Option<Transparency>,
+    pub anaglyph: Option<f32>,
+    pub buddhabrot: Option<Buddhabrot>,
+    /// Playback speed of each keyframe segment, 1 playing it at `output_framerate`.
+    pub segment_speeds: Vec<f32>,
+    /// Frames sampled for a palette shared by all frames, see `GlobalPalette::from_frames`.
+    pub global_palette: Option<usize>,
+    /// Frames drawn straight in indices of the palette, see `Renderer::draw_indexed`.
+    pub indexed: bool,
+    pub dither: Option<Dither>,
+    pub disposal: Option<Disposal>,
+    pub delta_frames: bool,
+    pub interlaced: bool,
+    pub lossy: Option<u8>,
+    pub merge_duplicates: Option<u8>,
+    pub ping_pong: bool,
+    pub repeat: Repeat,
+}
+
+impl RenderJob {
+    /// One keyframe per rendered frame.
+    pub fn frames(&self) -> Vec<Keyframe> {
+        match self.doublings_per_second {
+            Some(doublings_per_second) => {
+                get_adaptive_frames(&self.keyframes, self.framerate, doublings_per_second)
+            }
+            None => get_interpolated_frames(&self.keyframes),
+        }
+    }
+
+    /// Frames assigned to worker `shard` out of `shards`, interleaved so every worker gets a
+    /// similar mix of cheap and expensive views.
+    pub fn shard(&self, shard: usize, shards: usize) -> Vec<(usize, Keyframe)> {
+        self.frames()
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| index % shards == shard)
+            .collect()
+    }
+
+    /// Iteration limit of `keyframe`, with any scaling by zoom depth. Calibrated limits are only
+    /// known to a renderer probing the frame, this is the one calibration starts from.
+    pub fn frame_max_iter(&self, keyframe: &Keyframe) -> usize {
+        match self.iterations_per_doubling {
+            Some(per_doubling) => keyframe.scaled_max_iter(self.max_iter, per_doubling),
+            None => self.max_iter,
+        }
+    }
+
+    /// Delay of every frame in hundredths of a second from `segment_speeds`, or none to show
+    /// them all at `output_framerate`.
+    pub fn frame_delays(&self) -> Vec<u16> {
+        let retimed = self.output_framerate > self.framerate;
+        if self.segment_speeds.is_empty() || self.doublings_per_second.is_some() || retimed {
+            return Vec::new();
+        }
+        // Holds count toward the segment they start.
+        let mut delays = Vec::new();
+        for (segment, window) in self.keyframes.windows(2).enumerate() {
+            let speed = self.segment_speeds.get(segment).copied().unwrap_or(1.0);
+            let frames = window[0].hold + window[1].index - window[0].index;
+            delays.extend(vec![
+                (100.0 / (self.output_framerate * speed)) as u16;
+                frames
+            ]);
+        }
+        delays
+    }
+
+    /// Canonical serialization: fixed key order and shortest round-tripping float formatting,
+    /// so equal jobs always serialize to identical text.
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("width", self.width.into()),
+            ("height", self.height.into()),
+            ("framerate", self.framerate.into()),
+            ("output_framerate", self.output_framerate.into()),
+            (
+                "keyframes",
+                Json::Array(self.keyframes.iter().map(Json::from).collect()),
+            ),
+            ("projection", format!("{:?}", self.projection).into()),
+            ("precision", self.precision.to_string().into()),
+            ("julia", self.julia.into()),
+            ("doublings_per_second", self.doublings_per_second.into()),
+            ("fractal", self.fractal.clone().into()),
+            ("max_iter", self.max_iter.into()),
+            (
+                "iterations_per_doubling",
+                self.iterations_per_doubling.into(),
+            ),
+            ("max_iter_calibration", self.max_iter_calibration.into()),
+            ("iteration_budget", self.iteration_budget.into()),
+            ("iteration_round", self.iteration_round.into()),
+            ("escape_norm", self.escape_norm.into()),
+            ("interior_shortcut", self.interior_shortcut.into()),
+            ("periodicity_check", self.periodicity_check.into()),
+            ("border_tracing", self.border_tracing.into()),
+            ("basin_supersampling", self.basin_supersampling.into()),
+            ("palette", palette_to_json(&self.palette)),
+            ("auto_exposure", self.auto_exposure.into()),
+            ("exterior_texture", self.exterior_texture.clone().into()),
+            ("interior_texture", self.interior_texture.clone().into()),
+            (
+                "simulate_cvd",
+                self.simulate_cvd.map(|cvd| format!("{:?}", cvd)).into(),
+            ),
+            (
+                "transparency",
+                self.transparency
+                    .map(|transparency| format!("{:?}", transparency))
+                    .into(),
+            ),
+            ("anaglyph", self.anaglyph.into()),
+            (
+                "buddhabrot",
+                self.buddhabrot.map_or(Json::Null, buddhabrot_to_json),
+            ),
+            (
+                "segment_speeds",
+                Json::Array(self.segment_speeds.iter().map(|&s| s.into()).collect()),
+            ),
+            ("global_palette", self.global_palette.into()),
+            ("indexed", self.indexed.into()),
+            (
+                "dither",
+                self.dither.map(|dither| format!("{:?}", dither)).into(),
+            ),
+            (
+                "disposal",
+                self.disposal
+                    .map(|disposal| format!("{:?}", disposal))
+                    .into(),
+            ),
+            ("delta_frames", self.delta_frames.into()),
+            ("interlaced", self.interlaced.into()),
+            ("lossy", self.lossy.map(u32::from).into()),
+            (
+                "merge_duplicates",
+                self.merge_duplicates.map(u32::from).into(),
+            ),
+            ("ping_pong", self.ping_pong.into()),
+            ("repeat", format!("{:?}", self.repeat).into()),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, ManifestError> {
+        let field = |key: &str| json.get(key).ok_or(ManifestError::ParseError);
+        let number = |key: &str| field(key)?.as_f32().ok_or(ManifestError::ParseError);
+        let count = |key: &str| field(key)?.as_usize().ok_or(ManifestError::ParseError);
+        let optional = |key: &str| json.get(key).filter(|value| **value != Json::Null);
+        let string = |key: &str| optional(key).and_then(Json::as_str).map(str::to_owned);
+
+        let keyframes = field("keyframes")?
+            .as_array()
+            .ok_or(ManifestError::ParseError)?
+            .iter()
+            .map(keyframe_from_json)
+            .collect::<Result<_, _>>()?;
+        let projection = match field("projection")?.as_str() {
+            Some("Flat") => Projection::Flat,
+            Some("Equirectangular") => Projection::Equirectangular,
+            Some("LittlePlanet") => Projection::LittlePlanet,
+            _ => return Err(ManifestError::ParseError),
+        };
+        // Older jobs predate the choice.
+        let precision = match string("precision") {
+            Some(name) => Precision::parse(&name).ok_or(ManifestError::ParseError)?,
+            None => Precision::Single,
+        };
+        let simulate_cvd = match string("simulate_cvd").as_deref() {
+            Some("Protanopia") => Some(ColorVisionDeficiency::Protanopia),
+            Some("Deuteranopia") => Some(ColorVisionDeficiency::Deuteranopia),
+            Some("Tritanopia") => Some(ColorVisionDeficiency::Tritanopia),
+            Some(_) => return Err(ManifestError::ParseError),
+            None => None,
+        };
+        let transparency = match string("transparency").as_deref() {
+            Some("Exterior") => Some(Transparency::Exterior),
+            Some("Interior") => Some(Transparency::Interior),
+            Some(_) => return Err(ManifestError::ParseError),
+            None => None,
+        };
+        let dither = match string("dither").as_deref() {
+            Some("FloydSteinberg") => Some(Dither::FloydSteinberg),
+            Some(name) => Some(Dither::Bayer(
+                parenthesized(name, "Bayer").ok_or(ManifestError::ParseError)?,
+            )),
+            None => None,
+        };
+        let disposal = match string("disposal").as_deref() {
+            Some("Keep") => Some(Disposal::Keep),
+            Some("Background") => Some(Disposal::Background),
+            Some("Previous") => Some(Disposal::Previous),
+            Some(_) => return Err(ManifestError::ParseError),
+            None => None,
+        };
+        let repeat = match string("repeat").as_deref() {
+            Some("Once") | None => Repeat::Once,
+            Some("Forever") => Repeat::Forever,
+            Some(name) => {
+                Repeat::Times(parenthesized(name, "Times").ok_or(ManifestError::ParseError)?)
+            }
+        };
+        let segment_speeds = match optional("segment_speeds") {
+            Some(speeds) => speeds
+                .as_array()
+                .ok_or(ManifestError::ParseError)?
+                .iter()
+                .map(|speed| speed.as_f32().ok_or(ManifestError::ParseError))
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let side = |key: &str| -> Result<u16, ManifestError> {
+            u16::try_from(count(key)?).map_err(|_| ManifestError::ParseError)
+        };
+        let flag = |key: &str, default| optional(key).and_then(Json::as_bool).unwrap_or(default);
+        let channel = |key: &str| -> Result<Option<u8>, ManifestError> {
+            optional(key)
+                .map(|value| {
+                    let value = value.as_usize().ok_or(ManifestError::ParseError)?;
+                    u8::try_from(value).map_err(|_| ManifestError::ParseError)
+                })
+                .transpose()
+        };
+
+        Ok(Self {
+            width: side("width")?,
+            height: side("height")?,
+            framerate: number("framerate")?,
+            output_framerate: number("output_framerate")?,
+            keyframes,
+            projection,
+            precision,
+            julia: optional("julia").and_then(Json::as_bool).unwrap_or(false),
+            doublings_per_second: optional("doublings_per_second").and_then(Json::as_f32),
+            fractal: string("fractal").unwrap_or_else(|| "mandelbrot".to_owned()),
+            max_iter: count("max_iter")?,
+            iterations_per_doubling: optional("iterations_per_doubling").and_then(Json::as_f32),
+            max_iter_calibration: optional("max_iter_calibration").and_then(Json::as_usize),
+            iteration_budget: optional("iteration_budget").and_then(Json::as_usize),
+            // Older jobs predate the settings below, and were rendered with their defaults.
+            iteration_round: optional("iteration_round")
+                .and_then(Json::as_usize)
+                .unwrap_or(256),
+            escape_norm: optional("escape_norm")
+                .and_then(Json::as_f32)
+                .unwrap_or(8192.0),
+            interior_shortcut: flag("interior_shortcut", true),
+            periodicity_check: flag("periodicity_check", true),
+            border_tracing: flag("border_tracing", false),
+            basin_supersampling: optional("basin_supersampling")
+                .and_then(Json::as_usize)
+                .map(|grid| grid as u32),
+            palette: palette_from_json(field("palette")?)?,
+            auto_exposure: optional("auto_exposure").and_then(Json::as_f32),
+            exterior_texture: string("exterior_texture"),
+            interior_texture: string("interior_texture"),
+            simulate_cvd,
+            transparency,
+            anaglyph: optional("anaglyph").and_then(Json::as_f32),
+            buddhabrot: optional("buddhabrot")
+                .map(buddhabrot_from_json)
+                .transpose()?,
+            segment_speeds,
+            global_palette: optional("global_palette").and_then(Json::as_usize),
+            indexed: flag("indexed", false),
+            dither,
+            disposal,
+            delta_frames: flag("delta_frames", false),
+            interlaced: flag("interlaced", false),
+            lossy: channel("lossy")?,
+            merge_duplicates: channel("merge_duplicates")?,
+            ping_pong: flag("ping_pong", false),
+            repeat,
+        })
+    }
+
+    /// The job embedded in a GIF rendered by it, the first of its comments that is one.
+    pub fn from_gif(path: impl AsRef<Path>) -> Result<Self, ManifestError> {
+        let comments = read_gif_comments(path).map_err(|_| ManifestError::FileReadError)?;
+        comments
+            .iter()
+            .find_map(|comment| Self::from_json(&Json::parse(comment).ok()?).ok())
+            .ok_or(ManifestError::ParseError)
+    }
+
+    /// Stable content hash of the canonical serialization.
+    pub fn digest(&self) -> u64 {
+        fnv1a(self.to_json().to_string().as_bytes())
+    }
+
+    /// Records how far an interrupted render of the job got, along with its digest so a resume
+    /// with a different configuration is refused.
+    pub fn checkpoint(&self, frames_completed: usize) -> Json {
+        Json::object([
+            ("job_digest", format!("{:016x}", self.digest()).into()),
+            ("frames_completed", frames_completed.into()),
+        ])
+    }
+
+    /// Frames completed by the interrupted render `checkpoint` was written for, or none if it
+    /// rendered a different job.
+    pub fn resumable_frames(&self, checkpoint: &Json) -> Option<usize> {
+        let digest = format!("{:016x}", self.digest());
+        if checkpoint.get("job_digest").and_then(Json::as_str) != Some(digest.as_str()) {
+            return None;
+        }
+        Some(
+            checkpoint
+                .get("frames_completed")
+                .and_then(Json::as_usize)
+                .unwrap_or(0),
+        )
+    }
+}
+
+/// The number in `text` of the form `name(number)`, as `Debug` writes tuple variants.
+fn parenthesized<T: std::str::FromStr>(text: &str, name: &str) -> Option<T> {
+    text.strip_prefix(name)?
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .parse()
+        .ok()
+}
+
+pub(crate) fn keyframe_from_json(json: &Json) -> Result<Keyframe, ManifestError> {
+    let number = |key: &str| {
+        json.get(key)
+            .and_then(Json::as_f32)
+            .ok_or(ManifestError::ParseError)
+    };
+    let view = |key: &str| {
+        json.get(key)
+            .and_then(Json::as_f64)
+            .ok_or(ManifestError::ParseError)
+    };
+    Ok(Keyframe {
+        x_center: view("x_center")?,
+        y_center: view("y_center")?,
+        x_size: view("x_size")?,
+        y_size: view("y_size")?,
+        rotation: number("rotation").unwrap_or(0.0),
+        // Older manifests predate z0.
+        z0_x: number("z0_x").unwrap_or(0.0),
+        z0_y: number("z0_y").unwrap_or(0.0),
+        c_x: number("c_x").unwrap_or(0.0),
+        c_y: number("c_y").unwrap_or(0.0),
+        power: number("power").unwrap_or(2.0),
+        p_x: number("p_x").unwrap_or(0.0),
+        p_y: number("p_y").unwrap_or(0.0),
+        morph: number("morph").unwrap_or(0.0),
+        slice_angle: number("slice_angle").unwrap_or(0.0),
+        slice_offset: number("slice_offset").unwrap_or(0.0),
+        hold: json.get("hold").and_then(Json::as_usize).unwrap_or(0),
+        index: json
+            .get("index")
+            .and_then(Json::as_usize)
+            .ok_or(ManifestError::ParseError)?,
+    })
+}
+
+fn buddhabrot_to_json(buddhabrot: Buddhabrot) -> Json {
+    Json::object([
+        ("samples", buddhabrot.samples.into()),
+        (
+            "max_iter",
+            Json::Array(buddhabrot.max_iter.map(Json::from).to_vec()),
+        ),
+        ("seed", Json::Number(buddhabrot.seed.to_string())),
+    ])
+}
+
+fn buddhabrot_from_json(json: &Json) -> Result<Buddhabrot, ManifestError> {
+    let count = |value: Option<&Json>| {
+        value
+            .and_then(Json::as_usize)
+            .ok_or(ManifestError::ParseError)
+    };
+    let max_iter = json
+        .get("max_iter")
+        .and_then(Json::as_array)
+        .filter(|limits| limits.len() == 3)
+        .ok_or(ManifestError::ParseError)?;
+    Ok(Buddhabrot {
+        samples: count(json.get("samples"))?,
+        max_iter: [
+            count(max_iter.first())?,
+            count(max_iter.get(1))?,
+            count(max_iter.get(2))?,
+        ],
+        seed: count(json.get("seed"))? as u64,
+    })
+}
+
+fn palette_to_json(palette: &Palette) -> Json {
+    match palette {
+        Palette::Classic => "Classic".into(),
+        Palette::Viridis => "Viridis".into(),
+        Palette::Cividis => "Cividis".into(),
+        Palette::Inferno => "Inferno".into(),
+        Palette::Magma => "Magma".into(),
+        Palette::Plasma => "Plasma".into(),
+        Palette::Turbo => "Turbo".into(),
+        Palette::Gradient(stops) => Json::Array(
+            stops
+                .iter()
+                .map(|p| format!("#{:02x}{:02x}{:02x}", p.r, p.g, p.b).into())
+                .collect(),
+        ),
+    }
+}
+
+fn palette_from_json(json: &Json) -> Result<Palette, ManifestError> {
+    match json {
+        Json::String(name) => match name.as_str() {
+            "Classic" => Ok(Palette::Classic),
+            "Viridis" => Ok(Palette::Viridis),
+            "Cividis" => Ok(Palette::Cividis),
+            "Inferno" => Ok(Palette::Inferno),
+            "Magma" => Ok(Palette::Magma),
+            "Plasma" => Ok(Palette::Plasma),
+            "Turbo" => Ok(Palette::Turbo),
+            _ => Err(ManifestError::ParseError),
+        },
+        Json::Array(stops) => stops
+            .iter()
+            .map(|stop| {
+                let hex = stop
+                    .as_str()
+                    .and_then(|s| s.strip_prefix('#'))
+                    .ok_or(ManifestError::ParseError)?;
+                let rgb = u32::from_str_radix(hex, 16).map_err(|_| ManifestError::ParseError)?;
+                Ok(Pixel {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                    a: 255,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Palette::Gradient),
+        _ => Err(ManifestError::ParseError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job() -> RenderJob {
+        RenderJob {
+            width: 320,
+            height: 240,
+            framerate: 24.0,
+            output_framerate: 24.0,
+            keyframes: vec![
+                Keyframe::view(-0.75, 0.0, 4.0, 3.0),
+                Keyframe {
+                    index: 48,
+                    ..Keyframe::view(-0.743643887, 0.131825904, 4e-6, 3e-6)
+                },
+            ],
+            projection: Projection::Flat,
+            precision: Precision::Auto,
+            julia: false,
+            doublings_per_second: None,
+            fractal: "mandelbrot".to_owned(),
+            max_iter: 1000,
+            iterations_per_doubling: None,
+            max_iter_calibration: None,
+            iteration_budget: None,
+            iteration_round: 256,
+            escape_norm: 8192.0,
+            interior_shortcut: true,
+            periodicity_check: true,
+            border_tracing: false,
+            basin_supersampling: None,
+            palette: Palette::Classic,
+            auto_exposure: None,
+            exterior_texture: None,
+            interior_texture: None,
+            simulate_cvd: None,
+            transparency: None,
+            anaglyph: None,
+            buddhabrot: None,
+            segment_speeds: Vec::new(),
+            global_palette: None,
+            indexed: false,
+            dither: None,
+            disposal: None,
+            delta_frames: false,
+            interlaced: false,
+            lossy: None,
+            merge_duplicates: None,
+            ping_pong: false,
+            repeat: Repeat::Once,
+        }
+    }
+
+    /// The job with every setting changed from those of `job`, one at a time.
+    fn changed() -> Vec<RenderJob> {
+        let job = job();
+        vec![
+            RenderJob {
+                escape_norm: 4.0,
+                ..job.clone()
+            },
+            RenderJob {
+                interior_shortcut: false,
+                ..job.clone()
+            },
+            RenderJob {
+                periodicity_check: false,
+                ..job.clone()
+            },
+            RenderJob {
+                border_tracing: true,
+                ..job.clone()
+            },
+            RenderJob {
+                iteration_round: 64,
+                ..job.clone()
+            },
+            RenderJob {
+                basin_supersampling: Some(3),
+                ..job.clone()
+            },
+            RenderJob {
+                segment_speeds: vec![0.5, 2.0],
+                ..job.clone()
+            },
+            RenderJob {
+                global_palette: Some(8),
+                ..job.clone()
+            },
+            RenderJob {
+                indexed: true,
+                ..job.clone()
+            },
+            RenderJob {
+                dither: Some(Dither::FloydSteinberg),
+                ..job.clone()
+            },
+            RenderJob {
+                dither: Some(Dither::Bayer(4)),
+                ..job.clone()
+            },
+            RenderJob {
+                disposal: Some(Disposal::Previous),
+                ..job.clone()
+            },
+            RenderJob {
+                delta_frames: true,
+                ..job.clone()
+            },
+            RenderJob {
+                interlaced: true,
+                ..job.clone()
+            },
+            RenderJob {
+                lossy: Some(40),
+                ..job.clone()
+            },
+            RenderJob {
+                merge_duplicates: Some(2),
+                ..job.clone()
+            },
+            RenderJob {
+                ping_pong: true,
+                ..job.clone()
+            },
+            RenderJob {
+                repeat: Repeat::Times(3),
+                ..job.clone()
+            },
+            RenderJob {
+                repeat: Repeat::Forever,
+                ..job.clone()
+            },
+        ]
+    }
+
+    #[test]
+    fn json_round_trips() {
+        for job in [job()].into_iter().chain(changed()) {
+            let json = job.to_json();
+            let read = RenderJob::from_json(&json).unwrap();
+            assert_eq!(read.to_json(), json);
+            assert_eq!(read.digest(), job.digest());
+        }
+    }
+
+    #[test]
+    fn every_setting_changes_the_digest() {
+        let digest = job().digest();
+        for changed in changed() {
+            assert_ne!(changed.digest(), digest, "{}", changed.to_json());
+        }
+    }
+
+    #[test]
+    fn segment_speeds_set_the_delays() {
+        let job = RenderJob {
+            segment_speeds: vec![0.5],
+            ..job()
+        };
+        assert_eq!(job.frame_delays(), vec![8; 48]);
+        assert!(RenderJob {
+            output_framerate: 48.0,
+            ..job
+        }
+        .frame_delays()
+        .is_empty());
+        assert!(self::job().frame_delays().is_empty());
+    }
+
+    #[test]
+    fn checkpoints_only_resume_the_same_job() {
+        let checkpoint = job().checkpoint(12);
+        let read = Json::parse(&checkpoint.to_string()).unwrap();
+        assert_eq!(job().resumable_frames(&read), Some(12));
+        for changed in changed() {
+            assert_eq!(changed.resumable_frames(&read), None);
+        }
+        let deeper = RenderJob {
+            max_iter: 2000,
+            ..job()
+        };
+        assert_eq!(deeper.resumable_frames(&read), None);
+    }
+
+    #[test]
+    fn older_jobs_take_the_defaults() {
+        let Json::Object(settings) = job().to_json() else {
+            unreachable!()
+        };
+        let older = [
+            "escape_norm",
+            "interior_shortcut",
+            "dither",
+            "repeat",
+            "segment_speeds",
+        ];
+        let settings = settings
+            .into_iter()
+            .filter(|(key, _)| !older.contains(&key.as_str()))
+            .collect();
+        let read = RenderJob::from_json(&Json::Object(settings)).unwrap();
+        assert_eq!(read.digest(), job().digest());
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let Json::Object(settings) = job().to_json() else {
+            unreachable!()
+        };
+        for key in ["width", "height"] {
+            let settings = settings
+                .iter()
+                .map(|(name, value)| match name == key {
+                    true => (name.clone(), 70_000usize.into()),
+                    false => (name.clone(), value.clone()),
+                })
+                .collect();
+            assert!(RenderJob::from_json(&Json::Object(settings)).is_err());
+        }
+    }
+
+    #[test]
+    fn shards_split_the_frames() {
+        let job = job();
+        let frames = job.frames();
+        let mut sharded: Vec<_> = (0..3).flat_map(|shard| job.shard(shard, 3)).collect();
+        sharded.sort_by_key(|(index, _)| *index);
+        assert_eq!(sharded.len(), frames.len());
+        for ((index, keyframe), (expected, frame)) in sharded.iter().zip(frames.iter().enumerate())
+        {
+            assert_eq!(*index, expected);
+            assert_eq!(keyframe.x_center, frame.x_center);
+        }
+    }
+}
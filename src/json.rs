@@ -0,0 +1,453 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::Keyframe;
+
+/// Minimal JSON document model used for render manifests.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Numbers keep their textual form so floats round-trip exactly.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug)]
+pub enum ManifestError {
+    FileWriteError,
+    FileReadError,
+    ParseError,
+}
+
+impl Json {
+    pub fn object<K: Into<String>>(entries: impl IntoIterator<Item = (K, Json)>) -> Self {
+        Json::Object(entries.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ManifestError> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            pos: 0,
+        };
+        let value = parser.value()?;
+        parser.whitespace();
+        if parser.pos != parser.chars.len() {
+            return Err(ManifestError::ParseError);
+        }
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Json::Number(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.whitespace();
+        self.chars.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ManifestError> {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(ManifestError::ParseError)
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Json) -> Result<Json, ManifestError> {
+        for c in word.chars() {
+            if self.chars.get(self.pos) != Some(&c) {
+                return Err(ManifestError::ParseError);
+            }
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, ManifestError> {
+        match self.peek().ok_or(ManifestError::ParseError)? {
+            'n' => self.keyword("null", Json::Null),
+            't' => self.keyword("true", Json::Bool(true)),
+            'f' => self.keyword("false", Json::Bool(false)),
+            '"' => self.string().map(Json::String),
+            '[' => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                if self.peek() == Some(']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(']') => {
+                            self.pos += 1;
+                            return Ok(Json::Array(values));
+                        }
+                        _ => return Err(ManifestError::ParseError),
+                    }
+                }
+            }
+            '{' => {
+                self.pos += 1;
+                let mut entries = Vec::new();
+                if self.peek() == Some('}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(entries));
+                }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    entries.push((key, self.value()?));
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some('}') => {
+                            self.pos += 1;
+                            return Ok(Json::Object(entries));
+                        }
+                        _ => return Err(ManifestError::ParseError),
+                    }
+                }
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+                {
+                    self.pos += 1;
+                }
+                let number: String = self.chars[start..self.pos].iter().collect();
+                number
+                    .parse::<f64>()
+                    .map(|_| Json::Number(number))
+                    .map_err(|_| ManifestError::ParseError)
+            }
+            _ => Err(ManifestError::ParseError),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ManifestError> {
+        if self.chars.get(self.pos) != Some(&'"') {
+            return Err(ManifestError::ParseError);
+        }
+        self.pos += 1;
+
+        let mut out = String::new();
+        loop {
+            let c = *self.chars.get(self.pos).ok_or(ManifestError::ParseError)?;
+            self.pos += 1;
+            match c {
+                '"' => return Ok(out),
+                '\\' => {
+                    let escape = *self.chars.get(self.pos).ok_or(ManifestError::ParseError)?;
+                    self.pos += 1;
+                    match escape {
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self
+                                .chars
+                                .get(self.pos..self.pos + 4)
+                                .ok_or(ManifestError::ParseError)?
+                                .iter()
+                                .collect();
+                            self.pos += 4;
+                            let code = u32::from_str_radix(&hex, 16)
+                                .map_err(|_| ManifestError::ParseError)?;
+                            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        c => out.push(c),
+                    }
+                }
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<f32> for Json {
+    fn from(value: f32) -> Self {
+        if value.is_finite() {
+            Json::Number(value.to_string())
+        } else {
+            Json::Null
+        }
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        if value.is_finite() {
+            Json::Number(value.to_string())
+        } else {
+            Json::Null
+        }
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value.to_string())
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Number(value.to_string())
+    }
+}
+
+impl From<u16> for Json {
+    fn from(value: u16) -> Self {
+        Json::Number(value.to_string())
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl From<&Keyframe> for Json {
+    fn from(keyframe: &Keyframe) -> Self {
+        Json::object([
+            ("x_center", keyframe.x_center.into()),
+            ("y_center", keyframe.y_center.into()),
+            ("x_size", keyframe.x_size.into()),
+            ("y_size", keyframe.y_size.into()),
+            ("rotation", keyframe.rotation.into()),
+            ("z0_x", keyframe.z0_x.into()),
+            ("z0_y", keyframe.z0_y.into()),
+            ("c_x", keyframe.c_x.into()),
+            ("c_y", keyframe.c_y.into()),
+            ("power", keyframe.power.into()),
+            ("p_x", keyframe.p_x.into()),
+            ("p_y", keyframe.p_y.into()),
+            ("morph", keyframe.morph.into()),
+            ("slice_angle", keyframe.slice_angle.into()),
+            ("slice_offset", keyframe.slice_offset.into()),
+            ("hold", keyframe.hold.into()),
+            ("index", keyframe.index.into()),
+        ])
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Reads a JSON document previously written with `write_sidecar` or `write_json`.
+pub fn read_json(path: impl AsRef<Path>) -> Result<Json, ManifestError> {
+    let text = fs::read_to_string(path).map_err(|_| ManifestError::FileReadError)?;
+    Json::parse(&text)
+}
+
+pub fn write_json(path: impl AsRef<Path>, value: &Json) -> Result<(), ManifestError> {
+    fs::write(path, value.to_string()).map_err(|_| ManifestError::FileWriteError)
+}
+
+/// Writes `manifest` next to `output`, replacing its extension with `.json`.
+pub fn write_sidecar(output: impl AsRef<Path>, manifest: &Json) -> Result<(), ManifestError> {
+    write_json(output.as_ref().with_extension("json"), manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_display() {
+        let value = Json::object([
+            ("null", Json::Null),
+            ("flags", Json::Array(vec![true.into(), false.into()])),
+            ("size", 4.0e-300f64.into()),
+            ("max_iter", 1000usize.into()),
+            ("name", "tab\tquote\"back\\slash\nbell\u{7}".into()),
+            ("empty", Json::object::<&str>([])),
+            ("nested", Json::Array(vec![Json::Array(Vec::new())])),
+        ]);
+        assert_eq!(Json::parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn numbers_keep_their_text() {
+        let value = Json::parse(" [ -0.1 , 1e-7, 0.30000000000000004 ] ").unwrap();
+        let numbers = value.as_array().unwrap();
+        assert_eq!(numbers[0], Json::Number("-0.1".to_owned()));
+        assert_eq!(numbers[1].as_f64(), Some(1e-7));
+        assert_eq!(numbers[2].as_f64(), Some(0.1 + 0.2));
+        assert_eq!(value.to_string(), "[-0.1,1e-7,0.30000000000000004]");
+    }
+
+    #[test]
+    fn escapes_are_read() {
+        let value = Json::parse(r#""\u00e9\n\/\"""#).unwrap();
+        assert_eq!(value.as_str(), Some("é\n/\""));
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        for text in [
+            "",
+            "{",
+            "[1,]",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{a:1}",
+            "tru",
+            "nul",
+            "1-",
+            "\"open",
+            "\"\\u12\"",
+            "{} {}",
+            "[1]]",
+        ] {
+            assert!(Json::parse(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn accessors_check_types() {
+        let value = Json::parse(r#"{"n":3,"f":-2.5,"s":"x","b":true}"#).unwrap();
+        assert_eq!(value.get("n").and_then(Json::as_usize), Some(3));
+        assert_eq!(value.get("f").and_then(Json::as_usize), None);
+        assert_eq!(value.get("f").and_then(Json::as_f32), Some(-2.5));
+        assert_eq!(value.get("s").and_then(Json::as_f64), None);
+        assert_eq!(value.get("b").and_then(Json::as_bool), Some(true));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(Json::from(f64::NAN), Json::Null);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::Fractal;
+
+/// Options to build `KernelLanguage::OpenCl` sources with, which make the unsuffixed constants
+/// of `Fractal::kernel` steps floats rather than doubles that not every device supports.
+pub const OPENCL_BUILD_OPTIONS: &str = "-cl-single-precision-constant";
+
+/// Languages `kernel_source` writes compute kernels in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KernelLanguage {
+    /// A WGSL compute shader for wgpu, entry point `main` in 8 by 8 workgroups, with the frame
+    /// uniform at binding 0 and the orbit storage buffer at binding 1, as `WgpuDevice` runs it.
+    Wgsl,
+    /// An OpenCL C kernel, `iterate`, taking the frame by value and the orbit buffer.
+    OpenCl,
+}
+
+/// Source of a kernel iterating `fractal` for every pixel of a flat frame, one work item per
+/// pixel, with the step given by `Fractal::kernel`. Both languages fill the same kernel with the
+/// same step, so every backend iterates the same formulas the same way. `None` for formulas
+/// without a kernel step.
+///
+/// The kernel takes a `Frame` of the view center and size, the keyframe's `c` and `z0`, the
+/// frame width, height and `max_iter` as u32 and the escape norm and morph as f32, and writes
+/// one `Orbit` of the final `z`, the iteration count and whether it escaped per pixel, row by
+/// row.
+pub fn kernel_source(fractal: &dyn Fractal, language: KernelLanguage) -> Option<String> {
+    let [x, y] = fractal.kernel()?;
+    let (template, x, y) = match language {
+        KernelLanguage::Wgsl => (include_str!("shaders/escape.wgsl"), x, y),
+        KernelLanguage::OpenCl => (
+            include_str!("shaders/escape.cl"),
+            x.replace("abs(", "fabs("),
+            y.replace("abs(", "fabs("),
+        ),
+    };
+    let formula = format!("`{}`", fractal.name());
+    Some(
+        template
+            .replace("{formula}", &formula)
+            .replace("{x}", &x)
+            .replace("{y}", &y),
+    )
+}
@@ -0,0 +1,67 @@
+use crate::{Complex, EscapeResult};
+
+/// Points `iterate_lanes` iterates together, as many f32 as fill a 256-bit vector register.
+pub const LANES: usize = 8;
+
+/// `z^2 + c` for `LANES` points at once, each from its own `z0`, with the same results as
+/// iterating them one by one. Every step runs on all lanes, and lanes that escaped or came back
+/// within `cycle_tolerance` of an earlier value, as in `Renderer::with_periodicity_check`, are
+/// masked out of it, until none is left running. The lanes are plain arrays the compiler turns
+/// into vector instructions, which needs neither the unstable `std::simd` nor another crate.
+pub fn iterate_lanes(
+    c: [Complex; LANES],
+    z0: [Complex; LANES],
+    max_iter: usize,
+    escape_norm: f32,
+    cycle_tolerance: Option<f64>,
+) -> [EscapeResult; LANES] {
+    let (cx, cy) = (c.map(|c| c.x), c.map(|c| c.y));
+    let (mut x, mut y) = (z0.map(|z| z.x), z0.map(|z| z.y));
+    let mut iters = [0u32; LANES];
+    // Masks of all ones for the lanes still running and all zeros for the others, which
+    // select between floats by their bits without branching.
+    let mut running = [u32::MAX; LANES];
+    let mut escaped = [0u32; LANES];
+    let select =
+        |mask: u32, a: f32, b: f32| f32::from_bits(a.to_bits() & mask | b.to_bits() & !mask);
+    // Brent's stretches, which all lanes start together.
+    let mut saved = (x.map(|x| x as f64), y.map(|y| y as f64));
+    let (mut steps, mut stretch) = (0, 1);
+
+    for _ in 0..max_iter {
+        let mut any = 0;
+        for i in 0..LANES {
+            let (xx, yy) = (x[i] * x[i], y[i] * y[i]);
+            let inside = ((xx + yy < escape_norm) as u32).wrapping_neg();
+            escaped[i] |= running[i] & !inside;
+            running[i] &= inside;
+            let next_y = 2.0 * x[i] * y[i] + cy[i];
+            x[i] = select(running[i], xx - yy + cx[i], x[i]);
+            y[i] = select(running[i], next_y, y[i]);
+            iters[i] += running[i] & 1;
+            any |= running[i];
+        }
+        if any == 0 {
+            break;
+        }
+        if let Some(tolerance) = cycle_tolerance {
+            for i in 0..LANES {
+                let (dx, dy) = (x[i] as f64 - saved.0[i], y[i] as f64 - saved.1[i]);
+                let repeats = ((dx * dx + dy * dy < tolerance * tolerance) as u32).wrapping_neg();
+                // Repeating lanes stop as interior, at `max_iter`.
+                iters[i] =
+                    iters[i] & !(running[i] & repeats) | max_iter as u32 & running[i] & repeats;
+                running[i] &= !repeats;
+            }
+            steps += 1;
+            if steps == stretch {
+                saved = (x.map(|x| x as f64), y.map(|y| y as f64));
+                (steps, stretch) = (0, stretch * 2);
+            }
+        }
+    }
+    std::array::from_fn(|i| {
+        let z = Complex::new(x[i], y[i]);
+        EscapeResult::from_orbit(z, iters[i] as usize, escaped[i] != 0, escape_norm, 2.0)
+    })
+}
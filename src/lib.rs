@@ -1,48 +1,342 @@
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::Write;
 use std::path::Path;
 
-#[derive(Clone, Copy)]
+mod antialias;
+mod apng;
+mod autopilot;
+#[cfg(feature = "bigfloat")]
+mod bigfloat;
+mod buddhabrot;
+mod budget;
+mod complex;
+mod contact;
+#[cfg(feature = "bigfloat")]
+mod deep;
+mod device;
+mod double_double;
+mod exposure;
+mod expression;
+mod exr;
+mod fractal;
+#[cfg(feature = "wgpu")]
+mod gpu;
+mod hybrid;
+mod image;
+mod import;
+mod iteration;
+#[cfg(feature = "jit")]
+mod jit;
+mod job;
+mod json;
+mod kernel;
+mod lanes;
+mod minibrot;
+mod motion;
+mod newton;
+mod npy;
+#[cfg(feature = "opencl")]
+mod opencl;
+mod palette;
+#[cfg(feature = "bigfloat")]
+mod perturbation;
+mod png;
+mod precision;
+mod projection;
+mod pyramid;
+mod quantize;
+mod quaternion;
+mod renderer;
+mod stereo;
+mod subdivision;
+mod tile;
+mod video;
+mod webp;
+mod y4m;
+
+pub use antialias::{basin_supersample, BASIN_SUBPIXELS};
+pub use apng::Apng;
+pub use autopilot::autopilot;
+#[cfg(feature = "bigfloat")]
+pub use bigfloat::{iterate_mandelbrot, BigFixed};
+pub use buddhabrot::Buddhabrot;
+pub use budget::{FrameStore, MemoryBudget, Reservation};
+pub use complex::Complex;
+pub use contact::contact_sheet;
+#[cfg(feature = "bigfloat")]
+pub use deep::{get_interpolated_deep_frames, DeepKeyframe};
+pub use device::{Device, DeviceFrame, DevicePool};
+pub use double_double::DoubleDouble;
+pub use exposure::{smooth_exposures, Exposure};
+pub use expression::{Expression, ExpressionError};
+pub use exr::write_exr;
+pub use fractal::{
+    fractal_from_name, AbsVariant, Bailout, BurningShip, Fractal, Mandelbrot, Multibrot, Phoenix,
+    Tricorn,
+};
+#[cfg(feature = "wgpu")]
+pub use gpu::WgpuDevice;
+pub use hybrid::Hybrid;
+pub use image::{read_gif_comments, Image, ImageError};
+pub use import::{
+    parse_color_stops, parse_ggr, parse_kfr, parse_map, parse_par, parse_upr, read_location,
+    read_palette, ImportError, Location,
+};
+pub use iteration::{Divergence, EscapeResult, IterationBuffer, IterationCache, IterationKey};
+pub use job::RenderJob;
+pub use json::{read_json, write_json, write_sidecar, Json, ManifestError};
+pub use kernel::{kernel_source, KernelLanguage, OPENCL_BUILD_OPTIONS};
+pub use lanes::{iterate_lanes, LANES};
+pub use minibrot::{find_minibrot, minibrot_path, Minibrot};
+pub use motion::{retime_frames, synthesize_frame};
+pub use newton::{Magnet, Newton, Nova};
+pub use npy::{read_npy, write_npy};
+#[cfg(feature = "opencl")]
+pub use opencl::OpenClDevice;
+pub use palette::{palette_from_name, ColorMap, ColorVisionDeficiency, Palette, Transparency};
+#[cfg(feature = "bigfloat")]
+pub use perturbation::{ReferenceCache, ReferenceOrbit, SeriesApproximation};
+pub use png::write_iteration_png;
+pub use precision::Precision;
+pub use projection::Projection;
+pub use pyramid::Pyramid;
+pub use quantize::{Dither, GlobalPalette};
+pub use quaternion::{Quaternion, QuaternionJulia, Slice};
+pub use renderer::Renderer;
+pub use stereo::anaglyph;
+pub use subdivision::mariani_silver;
+pub use tile::{make_tileable, tile_view};
+pub use video::Video;
+pub use webp::WebP;
+pub use y4m::Y4m;
+
+/// View width that shows the whole Mandelbrot set, the zoom depth 0 of
+/// `Keyframe::scaled_max_iter`.
+pub const FULL_VIEW_WIDTH: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Keyframe {
-    pub x_center: f32,
-    pub y_center: f32,
-    pub x_size: f32,
-    pub y_size: f32,
+    /// The view, in f64 so deep zooms can still be placed and interpolated precisely.
+    pub x_center: f64,
+    pub y_center: f64,
+    pub x_size: f64,
+    pub y_size: f64,
+    /// Angle in radians the view is turned counterclockwise by, around its center.
+    pub rotation: f32,
+    /// Starting value of the iteration, 0 for the Mandelbrot set itself. Other values give the
+    /// perturbed parameter planes.
+    pub z0_x: f32,
+    pub z0_y: f32,
+    /// Parameter of the Julia set rendered instead of the Mandelbrot set by
+    /// `Renderer::with_julia`, where the pixels are the starting values.
+    pub c_x: f32,
+    pub c_y: f32,
+    /// Exponent of `Multibrot::animated`.
+    pub power: f32,
+    /// Parameter of `Phoenix::animated`.
+    pub p_x: f32,
+    pub p_y: f32,
+    /// Blend from the parameter plane at 0 to the Julia set of `c` at 1, moving through the 4D
+    /// space of `(c, z0)`: pixels are iterated as `c` and start from `z0` at 0, and gradually
+    /// swap roles. Ignored under `Renderer::with_julia`.
+    pub morph: f32,
+    /// Plane through the quaternion Julia set rendered by `QuaternionJulia`, as in `Slice`.
+    pub slice_angle: f32,
+    pub slice_offset: f32,
+    /// Extra frames the view is shown for before moving on to the next keyframe, as a pause.
+    /// Frames after a hold are numbered on past it.
+    pub hold: usize,
     pub index: usize,
 }
 
 impl Keyframe {
     fn interpolate(&self, other: Keyframe, idx: usize) -> Self {
         let t = (idx - self.index) as f32 / (other.index - self.index) as f32;
+        self.lerp(other, t, idx)
+    }
+
+    pub(crate) fn lerp(&self, other: Keyframe, t: f32, idx: usize) -> Self {
         let flerp = |a, b| a + (b - a) * t;
+        let dlerp = |a, b| a + (b - a) * t as f64;
         Keyframe {
-            x_center: flerp(self.x_center, other.x_center),
-            y_center: flerp(self.y_center, other.y_center),
-            x_size: flerp(self.x_size, other.x_size),
-            y_size: flerp(self.y_size, other.y_size),
+            x_center: dlerp(self.x_center, other.x_center),
+            y_center: dlerp(self.y_center, other.y_center),
+            x_size: dlerp(self.x_size, other.x_size),
+            y_size: dlerp(self.y_size, other.y_size),
+            rotation: flerp(self.rotation, other.rotation),
+            z0_x: flerp(self.z0_x, other.z0_x),
+            z0_y: flerp(self.z0_y, other.z0_y),
+            c_x: flerp(self.c_x, other.c_x),
+            c_y: flerp(self.c_y, other.c_y),
+            power: flerp(self.power, other.power),
+            p_x: flerp(self.p_x, other.p_x),
+            p_y: flerp(self.p_y, other.p_y),
+            morph: flerp(self.morph, other.morph),
+            slice_angle: flerp(self.slice_angle, other.slice_angle),
+            slice_offset: flerp(self.slice_offset, other.slice_offset),
+            hold: 0,
             index: idx,
         }
     }
 
+    /// The frames of this keyframe's hold, numbered from `index`.
+    pub(crate) fn held(&self, index: usize) -> impl Iterator<Item = Keyframe> + '_ {
+        (0..self.hold).map(move |i| Keyframe {
+            hold: 0,
+            index: index + i,
+            ..*self
+        })
+    }
+
     pub fn get_coordinate(&self, x: u32, y: u32, width: u32, height: u32) -> (f32, f32) {
+        let (x, y) = self.get_coordinate_f64(x, y, width, height);
+        (x as f32, y as f32)
+    }
+
+    /// `get_coordinate` without rounding to f32, which can't tell neighboring pixels apart once
+    /// the view is below about a millionth of its distance from 0.
+    pub fn get_coordinate_f64(&self, x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+        if self.rotation != 0.0 {
+            let (dx, dy) = self.offset(x, y, width, height);
+            return (self.x_center + dx, self.y_center + dy);
+        }
         let x_offset = self.x_center - self.x_size / 2.0;
-        let x = (x as f32 / width as f32) * self.x_size + x_offset;
+        let x = (x as f64 / width as f64) * self.x_size + x_offset;
 
         let y_offset = self.y_center + self.y_size / 2.0;
-        let y = y_offset - (y as f32 / height as f32) * self.y_size;
+        let y = y_offset - (y as f64 / height as f64) * self.y_size;
 
         (x, y)
     }
+
+    /// The offset of a pixel from the view center, turned by `rotation`.
+    pub(crate) fn offset(&self, x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+        let dx = (x as f64 / width as f64 - 0.5) * self.x_size;
+        let dy = (0.5 - y as f64 / height as f64) * self.y_size;
+        rotate((dx, dy), self.rotation as f64)
+    }
+
+    /// `get_coordinate` in double-double, for views too small for f64 to resolve. The center and
+    /// the pixel's offset from it are both precise to an ulp of themselves in f64, and so is
+    /// their double-double sum.
+    pub fn get_coordinate_dd(
+        &self,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> (DoubleDouble, DoubleDouble) {
+        let (dx, dy) = self.offset(x, y, width, height);
+        (
+            DoubleDouble::from(self.x_center) + dx.into(),
+            DoubleDouble::from(self.y_center) + dy.into(),
+        )
+    }
+
+    /// `base` plus `per_doubling` iterations for every halving of the view width below
+    /// `FULL_VIEW_WIDTH`. Pixels near the boundary take longer to escape the deeper the view,
+    /// so a fixed limit leaves deep frames in flat interior color.
+    pub fn scaled_max_iter(&self, base: usize, per_doubling: f32) -> usize {
+        let doublings = (FULL_VIEW_WIDTH / self.x_size).log2().max(0.0);
+        base + (doublings * per_doubling as f64) as usize
+    }
+
+    /// Inverse of `get_coordinate`: maps a point of the complex plane to (fractional) pixel
+    /// coordinates of this view.
+    pub fn get_pixel(&self, cx: f32, cy: f32, width: u32, height: u32) -> (f32, f32) {
+        if self.rotation != 0.0 {
+            let offset = (cx as f64 - self.x_center, cy as f64 - self.y_center);
+            let (dx, dy) = rotate(offset, -self.rotation as f64);
+            let x = (dx / self.x_size + 0.5) * width as f64;
+            let y = (0.5 - dy / self.y_size) * height as f64;
+            return (x as f32, y as f32);
+        }
+        let x_offset = self.x_center - self.x_size / 2.0;
+        let x = (cx as f64 - x_offset) / self.x_size * width as f64;
+
+        let y_offset = self.y_center + self.y_size / 2.0;
+        let y = (y_offset - cy as f64) / self.y_size * height as f64;
+
+        (x as f32, y as f32)
+    }
 }
 
+/// `(x, y)` turned counterclockwise by `angle` radians.
+pub(crate) fn rotate((x, y): (f64, f64), angle: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+/// One keyframe per frame, between every pair of keyframes and for their holds. The last
+/// keyframe is only shown for its hold, as interpolation stops short of it.
 pub fn get_interpolated_frames(keyframes: &[Keyframe]) -> Vec<Keyframe> {
-    keyframes
-        .windows(2)
-        .flat_map(|window| {
-            let start = window[0];
-            let end = window[1];
-            (start.index..end.index).map(move |idx| start.interpolate(end, idx))
-        })
-        .collect()
+    let mut frames = Vec::new();
+    let mut held = 0;
+    for window in keyframes.windows(2) {
+        let start = window[0];
+        let end = window[1];
+        frames.extend(start.held(start.index + held));
+        held += start.hold;
+        frames.extend((start.index..end.index).map(|idx| Keyframe {
+            index: idx + held,
+            ..start.interpolate(end, idx)
+        }));
+    }
+    if let Some(last) = keyframes.last().filter(|_| keyframes.len() > 1) {
+        frames.extend(last.held(last.index + held));
+    }
+    frames
+}
+
+/// Like `get_interpolated_frames`, but ignores the keyframe indices and sizes every segment from
+/// its zoom ratio instead, so the camera moves at `doublings_per_second` magnification
+/// doublings (or view widths of panning, whichever is larger) throughout the animation.
+/// Sizes are interpolated geometrically so the zoom speed is constant within a segment, and
+/// centers along the path that keeps the end's center still on the screen.
+pub fn get_adaptive_frames(
+    keyframes: &[Keyframe],
+    framerate: f32,
+    doublings_per_second: f32,
+) -> Vec<Keyframe> {
+    let mut frames = Vec::new();
+    for window in keyframes.windows(2) {
+        let start = window[0];
+        let end = window[1];
+
+        let doublings = (end.x_size / start.x_size).log2().abs();
+        let view = (start.x_size * end.x_size).sqrt();
+        let pan = (end.x_center - start.x_center).hypot(end.y_center - start.y_center) / view;
+        let seconds = doublings.max(pan) as f32 / doublings_per_second;
+        let count = ((seconds * framerate).ceil() as usize).max(1);
+
+        frames.extend(start.held(frames.len()));
+        for step in 0..count {
+            let t = step as f32 / count as f32;
+            let mut frame = start.lerp(end, t, frames.len());
+            let ratio = end.x_size / start.x_size;
+            let remaining = zoom_remaining(ratio, t as f64);
+            frame.x_center = end.x_center - (end.x_center - start.x_center) * remaining;
+            frame.y_center = end.y_center - (end.y_center - start.y_center) * remaining;
+            frame.x_size = start.x_size * ratio.powf(t as f64);
+            frame.y_size = start.y_size * (end.y_size / start.y_size).powf(t as f64);
+            frames.push(frame);
+        }
+    }
+    if let Some(last) = keyframes.last().filter(|_| keyframes.len() > 1) {
+        frames.extend(last.held(frames.len()));
+    }
+    frames
+}
+
+/// The part of the way between two centers still ahead at `t` of a zoom whose view sizes change
+/// geometrically by `ratio`. Moving the center by it keeps one point still on the screen, all
+/// but the end's center on a deep zoom, like zooming into a point does.
+pub(crate) fn zoom_remaining(ratio: f64, t: f64) -> f64 {
+    match ratio == 1.0 {
+        true => 1.0 - t,
+        false => (ratio.powf(t) - ratio) / (1.0 - ratio),
+    }
 }
 
 #[derive(Debug)]
@@ -51,12 +345,78 @@ pub enum AnimationError {
     EncoderError,
     FrameCreateError,
     FrameEncodeError,
+    FrameSpillError,
+    FileOpenError,
+    FrameDecodeError,
+    ReorderOverflowError,
+    FrameIndexError,
+}
+
+/// How often a GIF, APNG or WebP plays. Viewers only loop the ones that say so.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    Once,
+    /// Plays this many times in all, so `Times(1)` is `Once`.
+    Times(u16),
+    Forever,
 }
 
-pub struct Animation {
+impl Repeat {
+    /// Times played in all, 0 for forever, as the loop counts of APNG and WebP have it.
+    pub(crate) fn plays(self) -> u32 {
+        match self {
+            Repeat::Once => 1,
+            Repeat::Times(times) => times.max(1) as u32,
+            Repeat::Forever => 0,
+        }
+    }
+}
+
+/// What viewers do with a frame once it has been shown, before drawing the next one over it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Disposal {
+    /// Leaves it, so pixels the next frame leaves transparent show the frames before.
+    Keep,
+    /// Clears it, so transparent pixels show whatever is behind the GIF.
+    Background,
+    /// Puts back what was there before it.
+    Previous,
+}
+
+/// A GIF being written to `W`, a file unless made `from_writer`.
+pub struct Animation<W: Write = File> {
+    /// Written ahead of the first frame along with `repeat`.
+    comment: Option<String>,
     delay: u16,
-    encoder: gif::Encoder<File>,
-    frames: Vec<gif::Frame<'static>>,
+    /// Of every frame, left to viewers unless set.
+    disposal: Option<Disposal>,
+    /// RGB colors of the global color table, empty unless made with a `GlobalPalette`.
+    palette: Vec<u8>,
+    /// Delays of the first frames, overriding `delay`, and how many frames were encoded so far.
+    delays: Vec<u16>,
+    encoded: usize,
+    encoder: gif::Encoder<W>,
+    frames: Vec<FrameStore>,
+    interlaced: bool,
+    /// How far off the colors of a pixel may be written, under `lossy`.
+    lossy: Option<u8>,
+    merge_tolerance: Option<u8>,
+    /// Written ahead of the first frame, which is when it is taken.
+    repeat: Option<Repeat>,
+    width: u16,
+    height: u16,
+    /// RGB colors shown after the frames written so far, kept under `delta_frames` to tell
+    /// which pixels the next frame changes.
+    canvas: Option<Vec<[u8; 3]>>,
+    delta_frames: bool,
+    /// Every frame encoded with its delay, kept under `ping_pong` to play them back in reverse.
+    ping_pong: Option<Vec<(gif::Frame<'static>, u16)>>,
+    /// The last frame encoded, which isn't written until the next shows whether it is merged.
+    pending: Option<gif::Frame<'static>>,
+    /// Frames pushed by `push_indexed` ahead of `next_index`, at most `reorder_capacity`.
+    reorder: BTreeMap<usize, Frame>,
+    reorder_capacity: usize,
+    next_index: usize,
 }
 
 impl Animation {
@@ -67,7 +427,54 @@ impl Animation {
         framerate: f32,
     ) -> Result<Self, AnimationError> {
         let file = File::create(path).map_err(|_| AnimationError::FileCreateError)?;
-        let encoder = gif::Encoder::new(file, width, height, &[])
+        Self::from_writer(file, width, height, framerate)
+    }
+
+    /// An animation whose frames share `palette` as the GIF's global color table, for frames
+    /// made with `Frame::from_pixels_with_palette`.
+    pub fn new_with_palette(
+        path: impl AsRef<Path>,
+        width: u16,
+        height: u16,
+        framerate: f32,
+        palette: &GlobalPalette,
+    ) -> Result<Self, AnimationError> {
+        let file = File::create(path).map_err(|_| AnimationError::FileCreateError)?;
+        Self::from_writer_with_palette(file, width, height, framerate, palette)
+    }
+}
+
+impl<W: Write> Animation<W> {
+    /// An animation written to `writer` instead of a file, such as a socket, stdout or a
+    /// `&mut Vec<u8>` that holds the whole GIF once the animation is written and dropped.
+    pub fn from_writer(
+        writer: W,
+        width: u16,
+        height: u16,
+        framerate: f32,
+    ) -> Result<Self, AnimationError> {
+        Self::create(writer, width, height, framerate, &[])
+    }
+
+    /// `new_with_palette` for a writer.
+    pub fn from_writer_with_palette(
+        writer: W,
+        width: u16,
+        height: u16,
+        framerate: f32,
+        palette: &GlobalPalette,
+    ) -> Result<Self, AnimationError> {
+        Self::create(writer, width, height, framerate, &palette.rgb())
+    }
+
+    fn create(
+        writer: W,
+        width: u16,
+        height: u16,
+        framerate: f32,
+        global_palette: &[u8],
+    ) -> Result<Self, AnimationError> {
+        let encoder = gif::Encoder::new(writer, width, height, global_palette)
             .map_err(|_| AnimationError::EncoderError)?;
 
         let delay = (100.0 / framerate) as u16;
@@ -75,30 +482,425 @@ impl Animation {
         let frames = Vec::new();
 
         Ok(Self {
+            comment: None,
             encoder,
             delay,
+            disposal: None,
+            palette: global_palette.to_vec(),
+            delays: Vec::new(),
+            encoded: 0,
             frames,
+            interlaced: false,
+            lossy: None,
+            merge_tolerance: None,
+            repeat: Some(Repeat::Once),
+            width,
+            height,
+            canvas: None,
+            delta_frames: false,
+            ping_pong: None,
+            pending: None,
+            reorder: BTreeMap::new(),
+            reorder_capacity: 0,
+            next_index: 0,
         })
     }
 
     pub fn add_frames(&mut self, frames: Vec<Frame>) {
-        self.frames.extend(frames.into_iter().map(|f| f.inner));
-    }
-
-    pub fn write_animation(self) -> Result<(), AnimationError> {
-        let mut encoder = self.encoder;
-        let delay = self.delay;
-        self.frames
-            .into_iter()
-            .map(|mut frame| {
-                frame.delay = delay;
-                encoder.write_frame(&frame)
-            })
-            .collect::<Result<(), _>>()
+        let store = FrameStore::new(frames.len());
+        for (index, frame) in frames.into_iter().enumerate() {
+            // Unbudgeted stores keep everything in memory, so this can't fail.
+            let _ = store.insert(index, frame);
+        }
+        self.frames.push(store);
+    }
+
+    /// Appends the frames of a (possibly partially spilled) store, in index order.
+    pub fn add_store(&mut self, store: FrameStore) {
+        self.frames.push(store);
+    }
+
+    /// Writes runs of consecutive frames whose colors differ by at most `tolerance` per channel
+    /// as a single frame shown for the whole run, e.g. during holds.
+    pub fn merge_duplicates(&mut self, tolerance: u8) {
+        self.merge_tolerance = Some(tolerance);
+    }
+
+    /// Shows frame `i` for `delays[i]` hundredths of a second instead of the delay of the
+    /// framerate, counting every frame added or pushed, so an animation can linger on some
+    /// stretches and hurry through others. Frames past the end of `delays` keep the framerate.
+    pub fn frame_delays(&mut self, delays: Vec<u16>) {
+        self.delays = delays;
+    }
+
+    /// Writes every frame after the first as only the rectangle of pixels that changed, with
+    /// those that didn't left transparent over the frame before, which shrinks slow zooms and
+    /// holds a lot. Keeps the colors on screen, one copy of the frame in RGB. Frames are written
+    /// whole under a `disposal` other than `Disposal::Keep`, which leaves nothing to draw over.
+    pub fn delta_frames(&mut self) {
+        self.delta_frames = true;
+    }
+
+    /// Sets what viewers do with every frame after showing it. Frames with transparent pixels,
+    /// such as those of `Renderer::with_transparency`, need `Disposal::Background` to show the
+    /// page behind them rather than the frames before.
+    pub fn disposal(&mut self, disposal: Disposal) {
+        self.disposal = Some(disposal);
+    }
+
+    /// Writes the rows of every frame interlaced, every eighth first, so browsers show a coarse
+    /// frame while the rest of it is still loading.
+    pub fn interlaced(&mut self) {
+        self.interlaced = true;
+    }
+
+    /// Lets the colors of every pixel be off by up to `error` per channel, reusing the color of
+    /// the pixel to its left or above wherever that is close enough, so the encoder finds long
+    /// runs in place of noise and a long animation gets much smaller. Under `delta_frames`
+    /// pixels changing by no more than `error` are left as they were as well. Around 8 is hard
+    /// to see, and 24 and up shows.
+    pub fn lossy(&mut self, error: u8) {
+        self.lossy = Some(error);
+    }
+
+    /// Embeds `text` in the GIF as a comment, which viewers don't show but `gifsicle --info`
+    /// and `exiftool` do, e.g. the JSON of the `RenderJob` that made it. Takes effect as long as
+    /// no frame has been written yet.
+    pub fn comment(&mut self, text: impl Into<String>) {
+        if self.repeat.is_some() {
+            self.comment = Some(text.into());
+        }
+    }
+
+    /// Sets how often the GIF plays, `Repeat::Once` unless changed. Takes effect as long as no
+    /// frame has been written yet.
+    pub fn repeat(&mut self, repeat: Repeat) {
+        if self.repeat.is_some() {
+            self.repeat = Some(repeat);
+        }
+    }
+
+    /// Ends the animation by playing it backwards to the second frame, so a looping GIF moves
+    /// back and forth without a jump and without rendering anything twice. Keeps a copy of
+    /// every frame until `write_animation`.
+    pub fn ping_pong(&mut self) {
+        self.ping_pong.get_or_insert_with(Vec::new);
+    }
+
+    /// Encodes `frame` right away instead of holding it until `write_animation`, after any
+    /// frames added before it, so a long animation needs memory for one frame at a time. Under
+    /// `merge_duplicates` the last frame waits for the next to know how long it is shown, and
+    /// `write_animation` writes it out at the end.
+    pub fn push_frame(&mut self, frame: Frame) -> Result<(), AnimationError> {
+        self.write_stores()?;
+        self.encode(frame.inner)
+    }
+
+    /// Holds up to `capacity` frames that `push_indexed` gets ahead of their turn.
+    pub fn reorder_frames(&mut self, capacity: usize) {
+        self.reorder_capacity = capacity;
+    }
+
+    /// `push_frame` for frame `index` of those pushed this way, counting from 0, which may come
+    /// before the frames preceding it, as when frames rendered in parallel finish out of order.
+    /// Early frames wait in the buffer of `reorder_frames` until the ones before them are
+    /// pushed. Fails if the buffer is full, so renderers must not get more than its capacity
+    /// ahead of the oldest frame still being rendered, or if frame `index` was pushed already.
+    pub fn push_indexed(&mut self, index: usize, frame: Frame) -> Result<(), AnimationError> {
+        if index < self.next_index || self.reorder.contains_key(&index) {
+            return Err(AnimationError::FrameIndexError);
+        }
+        if index > self.next_index {
+            if self.reorder.len() >= self.reorder_capacity {
+                return Err(AnimationError::ReorderOverflowError);
+            }
+            self.reorder.insert(index, frame);
+            return Ok(());
+        }
+        self.push_frame(frame)?;
+        self.next_index += 1;
+        while let Some(frame) = self.reorder.remove(&self.next_index) {
+            self.push_frame(frame)?;
+            self.next_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Frames still waiting in the buffer of `push_indexed` are dropped, as the ones before
+    /// them never came, which keeps the written frames a contiguous run as on interrupts.
+    pub fn write_animation(mut self) -> Result<(), AnimationError> {
+        self.write_stores()?;
+        // Both ends are shown once, where the direction turns.
+        if let Some(played) = self.ping_pong.take() {
+            let count = played.len();
+            for (frame, delay) in played
+                .into_iter()
+                .rev()
+                .skip(1)
+                .take(count.saturating_sub(2))
+            {
+                self.encode_delayed(frame, delay)?;
+            }
+        }
+        match self.pending.take() {
+            Some(last) => self.write_frame(&last),
+            None => Ok(()),
+        }
+    }
+
+    /// `write_animation`, followed by `frames` encoded as they come, e.g. from a channel fed by
+    /// the renderers, so encoding overlaps rendering and only the frames in flight are held.
+    pub fn write_streamed(
+        mut self,
+        frames: impl IntoIterator<Item = Frame>,
+    ) -> Result<(), AnimationError> {
+        for frame in frames {
+            self.push_frame(frame)?;
+        }
+        self.write_animation()
+    }
+
+    /// Encodes the frames of the added stores, in order.
+    fn write_stores(&mut self) -> Result<(), AnimationError> {
+        for store in std::mem::take(&mut self.frames) {
+            store.for_each(|frame| self.encode(frame))?;
+        }
+        Ok(())
+    }
+
+    /// Writes the frame before `frame`, unless `frame` merges into it.
+    fn encode(&mut self, frame: gif::Frame<'static>) -> Result<(), AnimationError> {
+        let delay = self.delays.get(self.encoded).copied().unwrap_or(self.delay);
+        self.encoded += 1;
+        if let Some(played) = self.ping_pong.as_mut() {
+            played.push((frame.clone(), delay));
+        }
+        self.encode_delayed(frame, delay)
+    }
+
+    /// `encode` for a frame shown for `delay`.
+    fn encode_delayed(
+        &mut self,
+        mut frame: gif::Frame<'static>,
+        delay: u16,
+    ) -> Result<(), AnimationError> {
+        if let (Some(previous), Some(tolerance)) = (self.pending.as_mut(), self.merge_tolerance) {
+            if previous.delay.checked_add(delay).is_some()
+                && frames_match(previous, &frame, tolerance, &self.palette)
+            {
+                previous.delay += delay;
+                return Ok(());
+            }
+        }
+        frame.delay = delay;
+        if let Some(disposal) = self.disposal {
+            frame.dispose = match disposal {
+                Disposal::Keep => gif::DisposalMethod::Keep,
+                Disposal::Background => gif::DisposalMethod::Background,
+                Disposal::Previous => gif::DisposalMethod::Previous,
+            };
+        }
+        match self.pending.replace(frame) {
+            Some(previous) => self.write_frame(&previous),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes `frame` to the encoder, after the loop extension and comment ahead of the first.
+    fn write_frame(&mut self, frame: &gif::Frame<'static>) -> Result<(), AnimationError> {
+        // The extension counts repetitions after the first play, and a count of 0 loops forever.
+        let extension = match self.repeat.take() {
+            Some(Repeat::Forever) => Some(gif::Repeat::Infinite),
+            Some(Repeat::Times(times)) if times > 1 => Some(gif::Repeat::Finite(times - 1)),
+            _ => None,
+        };
+        if let Some(extension) = extension {
+            self.encoder
+                .set_repeat(extension)
+                .map_err(|_| AnimationError::EncoderError)?;
+        }
+        if let Some(comment) = self.comment.take() {
+            self.encoder
+                .write_raw_extension(gif::Extension::Comment.into(), &[comment.as_bytes()])
+                .map_err(|_| AnimationError::EncoderError)?;
+        }
+        let lossy = self
+            .lossy
+            .map(|error| lossy_frame(frame, error, &self.palette));
+        let frame = lossy.as_ref().unwrap_or(frame);
+        let drawn_over = matches!(self.disposal, None | Some(Disposal::Keep));
+        let mut written = match self.delta_frames && drawn_over {
+            true => self.delta(frame),
+            false => None,
+        };
+        // The encoder writes rows as they are in the buffer, so they are put in order here.
+        if self.interlaced {
+            let mut frame = written.unwrap_or_else(|| frame.clone());
+            frame.buffer = interlace(&frame.buffer, frame.width as usize).into();
+            frame.interlaced = true;
+            written = Some(frame);
+        }
+        self.encoder
+            .write_frame(written.as_ref().unwrap_or(frame))
             .map_err(|_| AnimationError::FrameEncodeError)
     }
+
+    /// The part of `frame` that changes the canvas, after drawing it there. The first frame, and
+    /// frames that are partial already like those of a resumed delta GIF, are written whole.
+    fn delta(&mut self, frame: &gif::Frame<'static>) -> Option<gif::Frame<'static>> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let palette = frame.palette.as_deref().unwrap_or(&self.palette);
+        let color = |index: u8| palette_color(palette, index);
+        let tolerance = self.lossy.unwrap_or(0);
+        let whole = (frame.left, frame.top, frame.width, frame.height)
+            == (0, 0, self.width, self.height)
+            && frame.transparent.is_none();
+        if self.canvas.is_none() || !whole {
+            let canvas = self
+                .canvas
+                .get_or_insert_with(|| vec![[0; 3]; width * height]);
+            for (i, &index) in frame.buffer.iter().enumerate() {
+                let x = frame.left as usize + i % frame.width as usize;
+                let y = frame.top as usize + i / frame.width as usize;
+                if x < width && y < height && frame.transparent != Some(index) {
+                    canvas[y * width + x] = color(index);
+                }
+            }
+            return Some(gif::Frame {
+                dispose: gif::DisposalMethod::Keep,
+                ..frame.clone()
+            });
+        }
+        let canvas = self.canvas.as_mut().unwrap();
+
+        // Bounds of the changed pixels, an empty range if there are none.
+        let (mut left, mut top, mut right, mut bottom) = (width, height, 0, 0);
+        let mut changed = vec![false; width * height];
+        let mut used = [false; 256];
+        for (i, &index) in frame.buffer.iter().enumerate() {
+            let new = color(index);
+            if !colors_match(canvas[i], new, tolerance) {
+                let (x, y) = (i % width, i / width);
+                (left, top) = (left.min(x), top.min(y));
+                (right, bottom) = (right.max(x + 1), bottom.max(y + 1));
+                (changed[i], used[index as usize]) = (true, true);
+                canvas[i] = new;
+            }
+        }
+        if left >= right {
+            (left, top, right, bottom) = (0, 0, 1, 1);
+        }
+
+        // Unchanged pixels are left transparent if any color of the palette goes unused, as
+        // runs of one index compress better than the colors they cover.
+        let transparent = (0..palette.len() / 3)
+            .find(|&index| !used[index])
+            .map(|index| index as u8);
+        let mut buffer = Vec::with_capacity((right - left) * (bottom - top));
+        for y in top..bottom {
+            for x in left..right {
+                let i = y * width + x;
+                // Pixels left within the tolerance are drawn anyway without a transparent color.
+                if transparent.is_none() {
+                    canvas[i] = color(frame.buffer[i]);
+                }
+                buffer.push(match transparent {
+                    Some(transparent) if !changed[i] => transparent,
+                    _ => frame.buffer[i],
+                });
+            }
+        }
+        Some(gif::Frame {
+            left: left as u16,
+            top: top as u16,
+            width: (right - left) as u16,
+            height: (bottom - top) as u16,
+            buffer: buffer.into(),
+            transparent,
+            dispose: gif::DisposalMethod::Keep,
+            delay: frame.delay,
+            interlaced: frame.interlaced,
+            palette: frame.palette.clone(),
+            ..gif::Frame::default()
+        })
+    }
+}
+
+/// The rows of `buffer` in the order of GIF's four interlacing passes: every eighth row from
+/// the first, every eighth from the fifth, every fourth from the third and every second.
+fn interlace(buffer: &[u8], width: usize) -> Vec<u8> {
+    let rows: Vec<&[u8]> = buffer.chunks(width.max(1)).collect();
+    [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .into_iter()
+        .flat_map(|(start, step)| rows.iter().skip(start).step_by(step))
+        .flat_map(|row| row.iter().copied())
+        .collect()
+}
+
+/// RGB color `index` of `palette`, or gray for indices past its end.
+fn palette_color(palette: &[u8], index: u8) -> [u8; 3] {
+    let i = 3 * index as usize;
+    palette
+        .get(i..i + 3)
+        .map_or([index; 3], |c| [c[0], c[1], c[2]])
+}
+
+fn colors_match(a: [u8; 3], b: [u8; 3], tolerance: u8) -> bool {
+    (0..3).all(|c| a[c].abs_diff(b[c]) <= tolerance)
+}
+
+/// `frame` with every pixel repeating the index of the pixel to its left, or else above, if
+/// that color is within `error` of its own, comparing against the frame's colors so errors
+/// don't add up along a run. Transparent pixels are kept as they are.
+fn lossy_frame(frame: &gif::Frame<'static>, error: u8, global: &[u8]) -> gif::Frame<'static> {
+    let palette = frame.palette.as_deref().unwrap_or(global);
+    let width = frame.width as usize;
+    let mut buffer = frame.buffer.to_vec();
+    for i in 0..buffer.len() {
+        let original = frame.buffer[i];
+        if frame.transparent == Some(original) {
+            continue;
+        }
+        let left = (i % width > 0).then(|| buffer[i - 1]);
+        let above = (i >= width).then(|| buffer[i - width]);
+        let reused = [left, above].into_iter().flatten().find(|&index| {
+            frame.transparent != Some(index)
+                && colors_match(
+                    palette_color(palette, index),
+                    palette_color(palette, original),
+                    error,
+                )
+        });
+        if let Some(index) = reused {
+            buffer[i] = index;
+        }
+    }
+    let mut lossy = frame.clone();
+    lossy.buffer = buffer.into();
+    lossy
 }
 
+/// Whether every pixel of `a` and `b` is within `tolerance` per channel, comparing colors
+/// rather than indices since every frame has its own palette, or else the `global` one.
+fn frames_match(a: &gif::Frame, b: &gif::Frame, tolerance: u8, global: &[u8]) -> bool {
+    if (a.width, a.height, a.left, a.top) != (b.width, b.height, b.left, b.top) {
+        return false;
+    }
+    let color = |frame: &gif::Frame, index: u8| -> [u8; 3] {
+        let i = index as usize * 3;
+        match frame.palette.as_deref() {
+            Some(palette) if i + 2 < palette.len() => [palette[i], palette[i + 1], palette[i + 2]],
+            None if i + 2 < global.len() => [global[i], global[i + 1], global[i + 2]],
+            _ => [index; 3],
+        }
+    };
+    a.buffer.iter().zip(b.buffer.iter()).all(|(&x, &y)| {
+        let (x, y) = (color(a, x), color(b, y));
+        (0..3).all(|c| x[c].abs_diff(y[c]) <= tolerance)
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Pixel {
     pub r: u8,
     pub g: u8,
@@ -128,6 +930,36 @@ impl Frame {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.inner.width == 0 || self.inner.height == 0
+    }
+
+    /// Reads back the frames of a GIF, e.g. one written by an interrupted render.
+    pub fn load_gif(path: impl AsRef<Path>) -> Result<Vec<Self>, AnimationError> {
+        let file = File::open(path).map_err(|_| AnimationError::FileOpenError)?;
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options
+            .read_info(file)
+            .map_err(|_| AnimationError::FrameDecodeError)?;
+
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|_| AnimationError::FrameDecodeError)?
+        {
+            frames.push(Self {
+                inner: frame.clone(),
+            });
+        }
+        Ok(frames)
+    }
+
+    /// Rough peak memory needed to render and quantize one frame of this size.
+    pub fn render_cost(width: u16, height: u16) -> usize {
+        12 * width as usize * height as usize
+    }
+
     pub fn from_pixels(width: u16, height: u16, pixels: Vec<Pixel>) -> Self {
         assert!(pixels.len() == width as usize * height as usize);
 
@@ -143,4 +975,288 @@ impl Frame {
 
         Self { inner: frame }
     }
+
+    /// A frame of indices into the global palette, such as those of `Renderer::draw_indexed`.
+    pub fn from_indices(width: u16, height: u16, indices: Vec<u8>) -> Self {
+        assert!(indices.len() == width as usize * height as usize);
+
+        Self {
+            inner: gif::Frame {
+                width,
+                height,
+                buffer: indices.into(),
+                ..gif::Frame::default()
+            },
+        }
+    }
+
+    /// `from_pixels` dithered onto a palette of the frame's own, learned like the global palette.
+    pub fn from_pixels_dithered(
+        width: u16,
+        height: u16,
+        pixels: Vec<Pixel>,
+        dither: Dither,
+    ) -> Self {
+        assert!(pixels.len() == width as usize * height as usize);
+
+        let palette = GlobalPalette::from_frames(std::slice::from_ref(&pixels));
+        let mut frame = Self::from_indices(
+            width,
+            height,
+            palette.dither(width as usize, &pixels, dither),
+        );
+        frame.inner.palette = Some(palette.rgb());
+        frame
+    }
+
+    /// `from_pixels` mapped onto `palette` instead of a palette of the frame's own, for an
+    /// animation made with `Animation::new_with_palette`.
+    pub fn from_pixels_with_palette(
+        width: u16,
+        height: u16,
+        pixels: Vec<Pixel>,
+        palette: &GlobalPalette,
+    ) -> Self {
+        let indices = pixels.into_iter().map(|p| palette.index_of(p)).collect();
+        Self::from_indices(width, height, indices)
+    }
+}
+
+#[cfg(test)]
+impl Keyframe {
+    /// A keyframe of the Mandelbrot set showing `x_size` by `y_size` around the center.
+    pub(crate) fn view(x_center: f64, y_center: f64, x_size: f64, y_size: f64) -> Self {
+        Keyframe {
+            x_center,
+            y_center,
+            x_size,
+            y_size,
+            rotation: 0.0,
+            z0_x: 0.0,
+            z0_y: 0.0,
+            c_x: 0.0,
+            c_y: 0.0,
+            power: 2.0,
+            p_x: 0.0,
+            p_y: 0.0,
+            morph: 0.0,
+            slice_angle: 0.0,
+            slice_offset: 0.0,
+            hold: 0,
+            index: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` by `height` frame whose pixel at `(x, y)` is `color(x, y)`.
+    fn frame(width: u16, height: u16, color: impl Fn(u16, u16) -> Pixel) -> Frame {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| color(x, y))
+            .collect();
+        Frame::from_pixels(width, height, pixels)
+    }
+
+    fn gray(value: u8) -> Pixel {
+        Pixel {
+            r: value,
+            g: value,
+            b: value,
+            a: 255,
+        }
+    }
+
+    /// The frames of the GIF in `bytes` as they were written.
+    fn decode(bytes: &[u8]) -> Vec<gif::Frame<'static>> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(bytes).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.clone());
+        }
+        frames
+    }
+
+    /// RGB colors of the pixels of `frame`.
+    fn colors(frame: &gif::Frame) -> Vec<[u8; 3]> {
+        let palette = frame.palette.as_deref().unwrap();
+        frame
+            .buffer
+            .iter()
+            .map(|&index| palette_color(palette, index))
+            .collect()
+    }
+
+    #[test]
+    fn adaptive_frames_zoom_into_the_end_center() {
+        let start = Keyframe::view(-0.75, 0.0, 4.0, 3.0);
+        let end = Keyframe::view(-0.743643887, 0.131825904, 4e-6, 3e-6);
+        let frames = get_adaptive_frames(&[start, end], 24.0, 2.0);
+        assert_eq!(frames[0], start);
+        // The point that stays still, all but the end center on a zoom this deep.
+        let ratio = end.x_size / start.x_size;
+        let still = |a: f64, b: f64| b + (a - b) * ratio / (ratio - 1.0);
+        let point = (
+            still(start.x_center, end.x_center),
+            still(start.y_center, end.y_center),
+        );
+        let on_screen = |frame: &Keyframe| {
+            let x = (point.0 - frame.x_center) / frame.x_size;
+            let y = (point.1 - frame.y_center) / frame.y_size;
+            (x, y)
+        };
+        let first = on_screen(&frames[0]);
+        for frame in &frames {
+            let (x, y) = on_screen(frame);
+            assert!((x - first.0).abs() < 1e-6 && (y - first.1).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn rotated_pixels_map_back() {
+        let view = Keyframe {
+            rotation: 1.0,
+            ..Keyframe::view(-0.75, 0.1, 4.0, 3.0)
+        };
+        let (cx, cy) = view.get_coordinate_f64(400, 100, 500, 500);
+        let turned = rotate((cx + 0.75, cy - 0.1), -1.0);
+        assert!((turned.0 - 1.2).abs() < 1e-9 && (turned.1 - 0.9).abs() < 1e-9);
+        let (x, y) = view.get_pixel(cx as f32, cy as f32, 500, 500);
+        assert!((x - 400.0).abs() < 1e-3 && (y - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn delta_frames_draw_only_changes() {
+        let before = |x: u16, y: u16| gray((8 * x + 24 * y) as u8);
+        let after = |x: u16, y: u16| match (x, y) {
+            (3, 4) | (4, 5) => Pixel { r: 255, ..gray(0) },
+            _ => before(x, y),
+        };
+        let mut out = Vec::new();
+        let mut animation = Animation::from_writer(&mut out, 8, 8, 10.0).unwrap();
+        animation.delta_frames();
+        animation.push_frame(frame(8, 8, before)).unwrap();
+        animation.push_frame(frame(8, 8, after)).unwrap();
+        animation.write_animation().unwrap();
+
+        let frames = decode(&out);
+        assert_eq!(frames.len(), 2);
+        let delta = &frames[1];
+        let bounds = (delta.left, delta.top, delta.width, delta.height);
+        assert_eq!(bounds, (3, 4, 2, 2));
+        assert_eq!(delta.dispose, gif::DisposalMethod::Keep);
+        // The two unchanged pixels between them are left transparent.
+        let transparent = delta.transparent.unwrap();
+        assert_eq!(delta.buffer[1], transparent);
+        assert_eq!(delta.buffer[2], transparent);
+        // Drawn over the first frame, the delta shows the second.
+        let mut canvas = colors(&frames[0]);
+        for (i, color) in colors(delta).into_iter().enumerate() {
+            let (x, y) = (3 + i % 2, 4 + i / 2);
+            if delta.buffer[i] != transparent {
+                canvas[y * 8 + x] = color;
+            }
+        }
+        let expected = frame(8, 8, after);
+        assert_eq!(canvas, colors(&expected.inner));
+    }
+
+    #[test]
+    fn lossy_frames_stay_close_and_shrink() {
+        // Noise of up to 6 levels over a gradient, as left by dithering.
+        let mut random = crate::buddhabrot::SplitMix(7);
+        let noise: Vec<u8> = (0..64 * 64)
+            .map(|_| (random.next_u64() % 7) as u8)
+            .collect();
+        let noisy = frame(64, 64, |x, y| {
+            gray(64 + 2 * x as u8 + noise[64 * y as usize + x as usize])
+        });
+        let encode = |lossy: Option<u8>| {
+            let mut out = Vec::new();
+            let mut animation = Animation::from_writer(&mut out, 64, 64, 10.0).unwrap();
+            if let Some(error) = lossy {
+                animation.lossy(error);
+            }
+            animation.push_frame(noisy.clone()).unwrap();
+            animation.write_animation().unwrap();
+            out
+        };
+        let (exact, lossy) = (encode(None), encode(Some(8)));
+        assert!(lossy.len() < exact.len());
+        let written = colors(&decode(&lossy)[0]);
+        for (written, wanted) in written.iter().zip(colors(&noisy.inner)) {
+            assert!(colors_match(*written, wanted, 8));
+        }
+    }
+
+    #[test]
+    fn interlaced_rows_go_in_four_passes() {
+        let rows: Vec<u8> = (0..10).collect();
+        assert_eq!(interlace(&rows, 1), [0, 8, 4, 2, 6, 1, 3, 5, 7, 9]);
+
+        let stripes = frame(3, 10, |x, y| gray((25 * y + x) as u8));
+        let mut out = Vec::new();
+        let mut animation = Animation::from_writer(&mut out, 3, 10, 10.0).unwrap();
+        animation.interlaced();
+        animation.push_frame(stripes.clone()).unwrap();
+        animation.write_animation().unwrap();
+        let mut decoder = gif::DecodeOptions::new().read_info(&out[..]).unwrap();
+        assert!(decoder.next_frame_info().unwrap().unwrap().interlaced);
+        // Decoders put the rows back in order.
+        assert_eq!(colors(&decode(&out)[0]), colors(&stripes.inner));
+    }
+
+    /// The gray of every frame of the GIF in `bytes`, for frames of a single `gray`.
+    fn solid_grays(bytes: &[u8]) -> Vec<u8> {
+        decode(bytes)
+            .iter()
+            .map(|frame| colors(frame)[0][0])
+            .collect()
+    }
+
+    #[test]
+    fn indexed_frames_are_written_in_order() {
+        let solid = |value: u8| frame(2, 2, |_, _| gray(value));
+        let mut out = Vec::new();
+        let mut animation = Animation::from_writer(&mut out, 2, 2, 10.0).unwrap();
+        animation.reorder_frames(2);
+        animation.push_indexed(2, solid(20)).unwrap();
+        animation.push_indexed(1, solid(10)).unwrap();
+        assert!(matches!(
+            animation.push_indexed(4, solid(40)),
+            Err(AnimationError::ReorderOverflowError)
+        ));
+        animation.push_indexed(0, solid(0)).unwrap();
+        assert!(matches!(
+            animation.push_indexed(1, solid(10)),
+            Err(AnimationError::FrameIndexError)
+        ));
+        // Frame 4 never gets its turn without frame 3.
+        animation.push_indexed(4, solid(40)).unwrap();
+        animation.write_animation().unwrap();
+        assert_eq!(solid_grays(&out), [0, 10, 20]);
+    }
+
+    #[test]
+    fn ping_pong_plays_back_without_repeating_the_ends() {
+        let mut out = Vec::new();
+        let mut animation = Animation::from_writer(&mut out, 2, 2, 10.0).unwrap();
+        animation.ping_pong();
+        animation.frame_delays(vec![1, 2, 3, 4]);
+        for value in [0, 10, 20, 30] {
+            animation
+                .push_frame(frame(2, 2, |_, _| gray(value)))
+                .unwrap();
+        }
+        animation.write_animation().unwrap();
+        assert_eq!(solid_grays(&out), [0, 10, 20, 30, 20, 10]);
+        // Frames played back keep their own delays.
+        let delays: Vec<u16> = decode(&out).iter().map(|frame| frame.delay).collect();
+        assert_eq!(delays, [1, 2, 3, 4, 3, 2]);
+    }
 }
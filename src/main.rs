@@ -1,23 +1,49 @@
-use core::f32::consts::LOG2_10;
-use std::ops::{Add, Mul};
-use std::sync::{Arc, Mutex};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, OnceLock};
 use std::thread;
 
 use rayon::prelude::*;
 
 use mandelbrot::*;
 
+mod dashboard;
+mod eco;
+mod interrupt;
+
+use eco::Eco;
+
+const OUTPUT: &str = "anim.gif";
+
 const WIDTH: u16 = 500;
 const HEIGHT: u16 = 500;
 
 const FRAMERATE: f32 = 24.0;
 
+/// Framerate of the written animation. When higher than `FRAMERATE`, the missing frames are
+/// synthesized from their rendered neighbors instead of being iterated.
+const OUTPUT_FRAMERATE: f32 = 24.0;
+
 const KEYFRAMES: [Keyframe; 3] = [
     Keyframe {
         x_center: -0.75,
         y_center: 0.0,
         x_size: 3.5,
         y_size: 3.5,
+        rotation: 0.0,
+        z0_x: 0.0,
+        z0_y: 0.0,
+        c_x: -0.8,
+        c_y: 0.156,
+        power: 2.0,
+        p_x: 0.0,
+        p_y: 0.0,
+        morph: 0.0,
+        slice_angle: 0.0,
+        slice_offset: 0.0,
+        hold: 0,
         index: 0,
     },
     Keyframe {
@@ -25,6 +51,18 @@ const KEYFRAMES: [Keyframe; 3] = [
         y_center: 0.0,
         x_size: 0.2,
         y_size: 0.2,
+        rotation: 0.0,
+        z0_x: 0.0,
+        z0_y: 0.0,
+        c_x: -0.7269,
+        c_y: 0.1889,
+        power: 2.0,
+        p_x: 0.0,
+        p_y: 0.0,
+        morph: 0.0,
+        slice_angle: 0.0,
+        slice_offset: 0.0,
+        hold: 0,
         index: 100,
     },
     Keyframe {
@@ -32,143 +70,1471 @@ const KEYFRAMES: [Keyframe; 3] = [
         y_center: 0.0,
         x_size: 3.5,
         y_size: 3.5,
+        rotation: 0.0,
+        z0_x: 0.0,
+        z0_y: 0.0,
+        c_x: -0.8,
+        c_y: 0.156,
+        power: 2.0,
+        p_x: 0.0,
+        p_y: 0.0,
+        morph: 0.0,
+        slice_angle: 0.0,
+        slice_offset: 0.0,
+        hold: 0,
         index: 300,
     },
 ];
 
+/// How pixels are mapped onto the plane. Frame synthesis for `OUTPUT_FRAMERATE` assumes
+/// `Projection::Flat`.
+const PROJECTION: Projection = Projection::Flat;
+
+/// `Precision::Double` keeps zooms past about 1e-6 sharp, at about half the speed, and
+/// `Precision::DoubleDouble` Mandelbrot zooms past about 1e-14 at a fifth of that. Built with
+/// `--features bigfloat`, `Precision::Arbitrary` keeps Mandelbrot zooms past about 1e-14, and
+/// `Precision::Perturbation` does so at close to the speed of f64. `Precision::Auto` switches
+/// between them frame by frame as the zoom deepens.
+const PRECISION: Precision = Precision::Single;
+
+/// Renders the Julia sets of the keyframes' `c_x`, `c_y` instead of the Mandelbrot set.
+/// Keyframing `morph` from 0 to 1 instead deforms one into the other over the animation.
+const JULIA: bool = false;
+
+/// Merges consecutive frames that differ by at most this much per color channel into one
+/// longer frame. Checkpoints count frames, so interrupted renders are written unmerged.
+const MERGE_DUPLICATES: Option<u8> = None;
+
+/// Playback speed of each keyframe segment relative to `OUTPUT_FRAMERATE`, in order, e.g.
+/// `&[1.0, 0.25]` to play the second in slow motion. Missing segments play at full speed.
+/// Frames are matched to segments by their keyframe index, so this is ignored under
+/// `DOUBLINGS_PER_SECOND` and retiming.
+const SEGMENT_SPEEDS: &[f32] = &[];
+
+/// Quantizes every frame to one palette learned from this many evenly spaced frames, e.g.
+/// `Some(8)`, instead of a palette per frame, which keeps the colors from flickering.
+const GLOBAL_PALETTE: Option<usize> = None;
+
+/// Writes frames as indices into `PALETTE` sampled at 255 steps, picked straight from the
+/// escape times, instead of quantizing every frame's colors, which is faster and loses no
+/// color to the quantizer. Takes the place of `GLOBAL_PALETTE`. Frames colored by anything but
+/// the palette, such as textures, as well as retimed ones, are mapped onto the sampled colors.
+const INDEXED: bool = false;
+
+/// Dithers the colors of every frame onto its palette, e.g. `Some(Dither::FloydSteinberg)` or
+/// `Some(Dither::Bayer(4))`, which hides the bands of smooth gradients. Bayer patterns don't
+/// crawl over animations as diffused errors do. Under `INDEXED` frames are drawn in colors and
+/// dithered onto the sampled palette.
+const DITHER: Option<Dither> = None;
+
+/// What viewers do with a frame once shown, `None` to leave it to them, or to clear frames with
+/// `TRANSPARENCY`.
+const DISPOSAL: Option<Disposal> = None;
+
+/// Writes only the pixels that changed since the previous frame, which makes slow zooms and
+/// holds a lot smaller.
+const DELTA_FRAMES: bool = false;
+
+/// Interlaces the rows of every frame, so the GIF shows up progressively while it loads on a
+/// web page.
+const INTERLACED: bool = false;
+
+/// How far off, per color channel, pixels may be written for a smaller GIF, e.g. `Some(8)` to
+/// share a long animation. `None` keeps the colors exact.
+const LOSSY: Option<u8> = None;
+
+/// Plays the animation backwards after it ends, which loops seamlessly under `Repeat::Forever`.
+const PING_PONG: bool = false;
+
+/// How often the GIF, APNG or WebP plays, `Repeat::Once`, e.g. `Repeat::Times(3)`, or
+/// `Repeat::Forever`.
+const REPEAT: Repeat = Repeat::Once;
+
+/// Frames spent zooming from the first of `KEYFRAMES` into a location given with
+/// `--location <file>`.
+const LOCATION_FRAMES: usize = 200;
+
+/// Zoom steps generated by `--autopilot`, each zooming in by `AUTOPILOT_ZOOM` over
+/// `AUTOPILOT_FRAMES` frames toward the most detailed part of the view.
+const AUTOPILOT_STEPS: usize = 12;
+const AUTOPILOT_FRAMES: usize = 24;
+const AUTOPILOT_ZOOM: f32 = 2.0;
+
+/// Frames per magnification doubling of the zoom generated by `--minibrot <width>`, and the
+/// highest period searched for.
+const MINIBROT_FRAMES: usize = 24;
+const MINIBROT_MAX_PERIOD: usize = 10000;
+
+/// When set, the number of frames per keyframe segment is derived from its zoom ratio at this
+/// many magnification doublings per second, instead of from the keyframe indices.
+const DOUBLINGS_PER_SECOND: Option<f32> = None;
+
+/// Caps the memory used by in-flight renders and buffered frames, in bytes. Workers pause when
+/// it is reached and frames that don't fit are spilled to the temporary directory.
+const MEMORY_BUDGET: Option<usize> = None;
+
+/// Encodes frames while the others render, with this many finished frames per worker waiting
+/// for the encoder at most, so memory stays the same however long the animation. Takes the
+/// place of `MEMORY_BUDGET`, `ECO` and retiming, and doesn't merge duplicates, as an
+/// interrupted render has written its frames already.
+const PIPELINE: Option<usize> = None;
+
+/// Renders on a few low-priority threads that idle between frames and, if enabled, pause while
+/// the machine runs on battery.
+const ECO: Option<Eco> = None;
+
+/// Formula iterated unless `--fractal <name>` is given, by its `Fractal::name`: `"mandelbrot"`,
+/// `"burning ship"`, `"tricorn"`, `"celtic"`, `"perpendicular mandelbrot"`,
+/// `"perpendicular burning ship"`, `"heart"`, `"buffalo"`, `"magnet 1"`, `"magnet 2"`, e.g.
+/// `"multibrot 3"`, `"phoenix -0.5"` or `"nova 3 0.5"` with the degree and relaxation, or
+/// `"newton"` followed by polynomial coefficients, highest degree first. A bare `"multibrot"` or
+/// `"phoenix"` animates its parameter through the keyframes' `power` or `p_x`, `p_y`, and a bare
+/// `"newton"` is `z^3 - 1`. Hybrids cycle through formulas, e.g.
+/// `"hybrid 2 mandelbrot; 1 burning ship"`, and custom formulas start with `z =`, e.g.
+/// `"z = z^3 + c*sin(z)"`. `"quaternion"` renders slices of the quaternion Julia set of `c`, set
+/// by the keyframes' `slice_angle` and `slice_offset`.
+const FRACTAL: &str = "mandelbrot";
+
 const MAX_ITER: usize = 255;
 
+/// Extra iterations per halving of the view width below `FULL_VIEW_WIDTH`, added to `MAX_ITER`
+/// frame by frame so deep zooms keep their detail, e.g. `Some(20.0)`.
+const ITERATIONS_PER_DOUBLING: Option<f32> = None;
+
+/// Upper bound of iteration limits picked per frame by probing it on a `CALIBRATION_PROBE`
+/// pixel square, doubling the limit from the one above until the share of interior pixels
+/// settles, e.g. `Some(65536)`.
+const MAX_ITER_CALIBRATION: Option<usize> = None;
+const CALIBRATION_PROBE: u32 = 64;
+
+/// Counts points of the Mandelbrot set's main cardioid and period-2 bulb as interior without
+/// iterating them, which speeds up wide views a lot. Has no effect on other formulas.
+const INTERIOR_SHORTCUT: bool = true;
+
+/// Stops orbits as interior once they come back to within a fraction of a pixel of an earlier
+/// value, which saves most of the iterations of interior points with a large `MAX_ITER`.
+const PERIODICITY_CHECK: bool = true;
+
+/// Iterates only the borders of rectangles that split until their borders are uniform, and
+/// fills in the rest, which skips most pixels of flat regions. Exact for the Mandelbrot set,
+/// but can lose islands of disconnected sets like most Julia sets.
+const BORDER_TRACING: bool = false;
+
+/// Orbits escape once `|z|^2` reaches this, i.e. past an escape radius of about 90.5. A large
+/// radius keeps the smooth iteration count accurate.
+const ESCAPE_NORM: f32 = 8192.0;
+
+/// Total iterations allowed per frame. Pixels are iterated `ITERATION_ROUND` iterations at a
+/// time and revisited until this runs out, so deep regions can't stall a frame indefinitely.
+const ITERATION_BUDGET: Option<usize> = None;
+const ITERATION_ROUND: usize = 256;
+
+/// Directory where iteration results are cached, so renders that only change coloring
+/// parameters (palette, textures, CVD preview) skip the iteration phase. `--cache` names another.
+const ITERATION_CACHE: Option<&str> = None;
+
+/// Colors of the escape times, unless `--palette` names a built-in one such as `inferno`, a
+/// `.map` or `.ggr` file, or colors such as `#000000,#ff8000@0.2,#ffffff`.
+const PALETTE: Palette = Palette::Classic;
+
+/// Stretches every frame's range of escape times over the whole palette instead of mapping
+/// 0..`MAX_ITER`, following it with an exponential moving average of this weight per frame so
+/// the brightness doesn't flicker. The range is measured on an `EXPOSURE_PROBE` pixel square
+/// preview, ignoring the `EXPOSURE_CLIP` fraction of extreme values on either end.
+const AUTO_EXPOSURE: Option<f32> = None;
+const EXPOSURE_PROBE: u32 = 64;
+const EXPOSURE_CLIP: f32 = 0.02;
+
+/// Reference image (GIF, PNG or PPM) whose dominant colors replace `PALETTE`.
+const PALETTE_IMAGE: Option<&str> = None;
+
+/// Image (GIF, PNG or PPM) wrapped around the set, using the escape time and the angle at
+/// escape as texture coordinates instead of `PALETTE`.
+const EXTERIOR_TEXTURE: Option<&str> = None;
+
+/// Image (GIF, PNG or PPM) filling the interior, using the final orbit value as texture
+/// coordinates instead of flat black.
+const INTERIOR_TEXTURE: Option<&str> = None;
+
+/// Previews the render as seen with a color vision deficiency.
+const SIMULATE_CVD: Option<ColorVisionDeficiency> = None;
+
+/// Leaves the exterior or the interior of the set transparent, to lay the animation over a web
+/// page. Frames keep palettes of their own, as shared palettes and dithering have no
+/// transparent color, and are cleared after being shown unless `DISPOSAL` says otherwise.
+const TRANSPARENCY: Option<Transparency> = None;
+
+/// Renders red-cyan anaglyph frames for 3D glasses, with up to this many pixels of parallax
+/// between the two eyes.
+const ANAGLYPH: Option<f32> = None;
+
+/// Renders the density of escaping orbits instead of escape times, e.g.
+/// `Buddhabrot::nebulabrot(10_000_000, [5000, 500, 50])`.
+const BUDDHABROT: Option<Buddhabrot> = None;
+
+/// Supersamples the basin boundaries of convergent fractals (`"newton ..."`) on this size of
+/// grid per pixel.
+const BASIN_SUPERSAMPLING: Option<u32> = None;
+
+/// Pixels rendered past the right and bottom edges of a `--tile` texture and blended into the
+/// opposite ones.
+const TILE_OVERLAP: u32 = 64;
+
+/// Every how many frames `--contact-sheet` takes one, how many go on a row and how wide their
+/// thumbnails are.
+const CONTACT_SHEET_EVERY: usize = 10;
+const CONTACT_SHEET_COLUMNS: u32 = 6;
+const CONTACT_SHEET_THUMBNAIL: u32 = 160;
+
+/// Frames of `--cycle` over which the palette turns once, so it loops seamlessly under
+/// `Repeat::Forever`.
+const CYCLE_FRAMES: usize = 64;
+
+/// Format of the frames of `--sequence`, `png` or `ppm`.
+const SEQUENCE_EXTENSION: &str = "png";
+
+/// Frames sampled by `--check-precision`, and the fraction of diverging pixels from which a
+/// frame is reported as beyond what its precision can render.
+const PRECISION_CHECK_FRAMES: usize = 8;
+const PRECISION_TOLERANCE: f32 = 0.01;
+
+/// Frames taken over from an interrupted render when resuming it.
+static RESUMED_FRAMES: AtomicUsize = AtomicUsize::new(0);
+
 fn main() {
-    let mut animation =
-        Animation::new("anim.gif", WIDTH, HEIGHT, FRAMERATE).expect("Error creating animation.");
+    interrupt::install();
+    // Also reads the GIF before any output replaces it.
+    if let Some(original) = rerendered_job() {
+        report_rerender(original);
+    }
+    if std::env::args().any(|arg| arg == "--check-precision") {
+        check_precision();
+        return;
+    }
+    if let Some(path) = arg_value("--contact-sheet") {
+        write_contact_sheet(path);
+        return;
+    }
+    if let Some(path) = arg_value("--tile") {
+        write_tile(path);
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--cycle") {
+        write_cycle();
+        return;
+    }
+    if let Some(path) = arg_value("--still") {
+        write_still(path);
+        return;
+    }
+    if let Some(path) = arg_value("--sequence") {
+        write_sequence(path);
+        return;
+    }
+    if let Some(path) = arg_value("--exr") {
+        export_iterations(path, "exr", |buffer, _, file| write_exr(buffer, file));
+        return;
+    }
+    if let Some(path) = arg_value("--npy") {
+        export_iterations(path, "npy", |buffer, _, file| write_npy(buffer, file));
+        return;
+    }
+    if let Some(path) = arg_value("--recolor") {
+        recolor(path);
+        return;
+    }
+    if let Some(path) = arg_value("--iteration-png") {
+        export_iterations(path, "png", |buffer, max_iter, file| {
+            write_iteration_png(buffer, max_iter, file)
+        });
+        return;
+    }
+    if let Some(path) = arg_value("--apng") {
+        write_apng(path);
+        return;
+    }
+    if let Some(path) = arg_value("--webp") {
+        write_webp(path);
+        return;
+    }
+    if let Some(path) = arg_value("--video") {
+        write_video(path);
+        return;
+    }
+    if let Some(path) = arg_value("--y4m") {
+        write_y4m(path);
+        return;
+    }
+    if let Some(shard) = arg_value("--shard") {
+        cache_shard(&shard);
+        return;
+    }
+    let resume = std::env::args().any(|arg| arg == "--resume");
+
+    // Read the partial output before `Animation::new` truncates it.
+    let resumed = if resume {
+        load_checkpoint()
+    } else {
+        Vec::new()
+    };
+    RESUMED_FRAMES.store(resumed.len(), Ordering::Relaxed);
+
+    let mut animation = create_animation();
+    let mut written = resumed.len();
+    animation.add_frames(resumed);
 
     println!("Collecting frames...");
-    let frames = frames_native();
-    // let frames = frames_rayon();
+    let show_dashboard = std::env::args().any(|arg| arg == "--dashboard");
+    if show_dashboard {
+        dashboard::start(frames_to_render().len());
+    }
+    if let Some(depth) = PIPELINE {
+        written += write_pipelined(animation, depth);
+        if show_dashboard {
+            dashboard::finish();
+        }
+    } else {
+        if let Some(limit) = MEMORY_BUDGET {
+            let build = || frames_budgeted(MemoryBudget::new(limit));
+            let store = match &ECO {
+                Some(eco) => eco.run(build),
+                None => build(),
+            };
+            // On interrupt only the contiguous prefix is kept, so the output plays correctly.
+            store.truncate(store.completed_prefix());
+            written += store.len();
+            animation.add_store(store);
+        } else {
+            let mut frames = match &ECO {
+                Some(eco) if job().output_framerate > job().framerate => eco.run(frames_retimed),
+                Some(eco) => eco.run(frames_rayon),
+                None if job().output_framerate > job().framerate => frames_retimed(),
+                None => frames_native(),
+            };
+            // let frames = frames_rayon();
 
+            frames.truncate(frames.iter().take_while(|frame| !frame.is_empty()).count());
+            written += frames.len();
+            animation.add_frames(frames);
+        }
+        if show_dashboard {
+            dashboard::finish();
+        }
+        if let Some(tolerance) = job().merge_duplicates.filter(|_| !interrupt::requested()) {
+            animation.merge_duplicates(tolerance);
+        }
+        animation
+            .write_animation()
+            .expect("Error saving animation.");
+    }
+
+    if interrupt::requested() {
+        write_checkpoint(written);
+        println!(
+            "Interrupted after {} of {} frames, run with --resume to continue.",
+            written,
+            interpolated_frames().len()
+        );
+    } else {
+        let _ = fs::remove_file(checkpoint_path());
+        write_sidecar(OUTPUT, &manifest()).expect("Error saving manifest.");
+    }
+}
+
+/// Renders the deepest keyframe as a single seamlessly repeating `WIDTH` by `HEIGHT` image.
+fn write_tile(path: String) {
+    let view = keyframes()
+        .iter()
+        .min_by(|a, b| a.x_size.total_cmp(&b.x_size))
+        .unwrap();
+    let (width, height) = (job().width as u32, job().height as u32);
+    let region = tile_view(view, width, height, TILE_OVERLAP);
+    let renderer = configure_renderer(width + TILE_OVERLAP, height + TILE_OVERLAP);
+    let pixel_data = renderer.draw_frame(&region);
+    let pixel_data = make_tileable(&pixel_data, width, height, TILE_OVERLAP);
+
+    let mut animation = Animation::new(&path, job().width, job().height, job().output_framerate)
+        .expect("Error creating texture.");
+    animation.add_frames(vec![Frame::from_pixels(
+        job().width,
+        job().height,
+        pixel_data,
+    )]);
+    animation.write_animation().expect("Error saving texture.");
+    println!("Wrote tileable texture to {}.", path);
+}
+
+/// Renders the deepest keyframe as a single full color `WIDTH` by `HEIGHT` PNG.
+fn write_still(path: String) {
+    let view = keyframes()
+        .iter()
+        .min_by(|a, b| a.x_size.total_cmp(&b.x_size))
+        .unwrap();
+    let (width, height) = (job().width as u32, job().height as u32);
+    let pixels = configure_renderer(width, height).draw_frame(view);
+    let image = Image {
+        width,
+        height,
+        pixels,
+    };
+    image.save_png(&path).expect("Error saving still.");
+    println!("Wrote still to {}.", path);
+}
+
+/// Iterates the deepest keyframe once and animates it into `OUTPUT` by cycling its colors
+/// through the palette over `CYCLE_FRAMES`, with no further iterations.
+fn write_cycle() {
+    let view = keyframes()
+        .iter()
+        .min_by(|a, b| a.x_size.total_cmp(&b.x_size))
+        .unwrap();
+    let buffer = renderer().iterate_frame(view);
+    let frames = (0..CYCLE_FRAMES)
+        .into_par_iter()
+        .map(|index| {
+            let offset = index as f32 / CYCLE_FRAMES as f32;
+            to_frame(renderer().color_cycled(view, &buffer, offset))
+        })
+        .collect();
+
+    let mut animation = create_animation();
+    // Delays set for the frames of the zoom don't apply.
+    animation.frame_delays(Vec::new());
     animation.add_frames(frames);
     animation
         .write_animation()
         .expect("Error saving animation.");
+    println!(
+        "Wrote {} frames of color cycling to {}.",
+        CYCLE_FRAMES, OUTPUT
+    );
 }
 
-/// Parallel frame builder that only uses Rust threads and synchronization primitives.
-pub fn frames_native() -> Vec<Frame> {
-    let keyframes = &KEYFRAMES;
-    let interpolated_frames = get_interpolated_frames(keyframes);
+/// Writes the frames of the animation to the directory `path` as numbered images, such as
+/// `00042.png`, in the format of `SEQUENCE_EXTENSION`.
+fn write_sequence(path: String) {
+    fs::create_dir_all(&path).expect("Error creating sequence directory.");
+    let (width, height) = (job().width as u32, job().height as u32);
+    let mut index = 0;
+    render_pixels(|pixels| {
+        let file = Path::new(&path).join(format!("{:05}.{}", index, SEQUENCE_EXTENSION));
+        let image = Image {
+            width,
+            height,
+            pixels,
+        };
+        image.save(file).expect("Error saving sequence frame.");
+        index += 1;
+    });
+    println!("Wrote {} frames to {}.", index, path);
+}
+
+/// Iterates the frames `RenderJob::shard` assigns to worker `i` of `n`, given as `i/n`, into
+/// the iteration cache. Workers on several machines sharing the cache directory split a render
+/// between them, and the run that writes the animation afterwards only colors the frames.
+fn cache_shard(shard: &str) {
+    let (shard, shards) = shard
+        .split_once('/')
+        .and_then(|(shard, shards)| Some((shard.parse().ok()?, shards.parse().ok()?)))
+        .filter(|&(shard, shards): &(usize, usize)| shard < shards)
+        .unwrap_or_else(|| panic!("Invalid shard {shard}, expected one such as 0/4."));
+    assert!(
+        iteration_cache().is_some(),
+        "Sharding needs an iteration cache, set with --cache or ITERATION_CACHE."
+    );
+    let frames = job().shard(shard, shards);
+    frames.par_iter().for_each(|(_, keyframe)| {
+        if interrupt::requested() {
+            return;
+        }
+        let _working = dashboard::working(keyframe.index);
+        renderer().cache_frame(keyframe);
+    });
+    println!(
+        "Cached {} frames of shard {}/{}.",
+        frames.len(),
+        shard,
+        shards
+    );
+}
 
-    let frames: Vec<Frame> = interpolated_frames
+/// Writes the iteration results of every frame to the directory `path`, numbered like
+/// `--sequence` with the given `extension`, before any coloring. `write` also gets the frame's
+/// iteration limit.
+fn export_iterations(
+    path: String,
+    extension: &str,
+    write: impl Fn(&IterationBuffer, usize, &Path) -> Result<(), ImageError> + Sync,
+) {
+    fs::create_dir_all(&path).expect("Error creating export directory.");
+    let frames = interpolated_frames();
+    frames.par_iter().enumerate().for_each(|(index, keyframe)| {
+        if interrupt::requested() {
+            return;
+        }
+        let _working = dashboard::working(keyframe.index);
+        let buffer = renderer().iterate_frame(keyframe);
+        let max_iter = renderer().frame_max_iter(keyframe);
+        let file = Path::new(&path).join(format!("{:05}.{}", index, extension));
+        write(&buffer, max_iter, &file).expect("Error exporting iterations.");
+    });
+    println!("Wrote {} frames to {}.", frames.len(), path);
+}
+
+/// Colors the iteration results exported to the directory `path` with `--npy` and encodes them
+/// into `OUTPUT`, so trying another palette or exposure takes seconds rather than a render.
+/// Stops at the first missing frame.
+fn recolor(path: String) {
+    let frames: Vec<Frame> = interpolated_frames()
+        .par_iter()
+        .enumerate()
+        .map(|(index, keyframe)| {
+            let file = Path::new(&path).join(format!("{:05}.npy", index));
+            let max_iter = renderer().frame_max_iter(keyframe);
+            match read_npy(file, max_iter) {
+                Ok(buffer)
+                    if (buffer.width, buffer.height)
+                        == (job().width as u32, job().height as u32) =>
+                {
+                    let pixel_data = renderer().color_buffer(keyframe, &buffer);
+                    to_frame(pixel_data)
+                }
+                _ => Frame::empty(),
+            }
+        })
+        .collect();
+    let count = frames.iter().take_while(|frame| !frame.is_empty()).count();
+
+    let mut animation = create_animation();
+    animation.add_frames(frames.into_iter().take(count).collect());
+    animation
+        .write_animation()
+        .expect("Error saving animation.");
+    println!("Recolored {} frames from {} into {}.", count, path, OUTPUT);
+}
+
+/// Renders the animation as a full color APNG instead of a GIF.
+fn write_apng(path: String) {
+    let mut apng = Apng::new(&path, job().width, job().height, job().output_framerate)
+        .expect("Error creating APNG.");
+    apng.repeat(job().repeat);
+    render_pixels(|pixels| apng.push_frame(&pixels).expect("Error encoding APNG."));
+    apng.write_animation().expect("Error saving APNG.");
+    println!("Wrote APNG to {}.", path);
+}
+
+/// Renders the animation as a full color animated WebP instead of a GIF.
+fn write_webp(path: String) {
+    let mut webp = WebP::new(&path, job().width, job().height, job().output_framerate)
+        .expect("Error creating WebP.");
+    webp.repeat(job().repeat);
+    render_pixels(|pixels| webp.push_frame(&pixels).expect("Error encoding WebP."));
+    webp.write_animation().expect("Error saving WebP.");
+    println!("Wrote WebP to {}.", path);
+}
+
+/// Renders the animation as an H.264 video through ffmpeg instead of a GIF.
+fn write_video(path: String) {
+    let mut video = Video::new(&path, job().width, job().height, job().output_framerate)
+        .expect("Error starting ffmpeg.");
+    render_pixels(|pixels| video.push_frame(&pixels).expect("Error encoding video."));
+    video.write_animation().expect("Error saving video.");
+    println!("Wrote video to {}.", path);
+}
+
+/// Streams the animation as uncompressed y4m video to `path`, or to stdout for `-`, e.g.
+/// `mandelbrot --y4m - | mpv -`.
+fn write_y4m(path: String) {
+    fn stream(mut y4m: Y4m<impl std::io::Write>) {
+        render_pixels(|pixels| y4m.push_frame(&pixels).expect("Error writing y4m."));
+        y4m.write_animation().expect("Error writing y4m.");
+    }
+    if path == "-" {
+        let stdout = std::io::stdout().lock();
+        stream(Y4m::from_writer(
+            stdout,
+            job().width,
+            job().height,
+            job().output_framerate,
+        ));
+    } else {
+        stream(
+            Y4m::new(&path, job().width, job().height, job().output_framerate)
+                .expect("Error creating y4m."),
+        );
+        println!("Wrote y4m to {}.", path);
+    }
+}
+
+/// Hands the unquantized pixels of every frame to `push`, in order, drawn by the same
+/// `draw_frame` as the frames of the GIF before they are quantized. Renders as many frames
+/// at a time as there are threads, so only those are held uncompressed.
+fn render_pixels(mut push: impl FnMut(Vec<Pixel>)) {
+    for keyframes in interpolated_frames().chunks(rayon::current_num_threads()) {
+        if interrupt::requested() {
+            break;
+        }
+        let frames: Vec<Vec<Pixel>> = keyframes.par_iter().map(|k| draw_frame(*k)).collect();
+        frames.into_iter().for_each(&mut push);
+    }
+}
+
+/// Composes every `CONTACT_SHEET_EVERY`th frame of the finished `OUTPUT` into one labeled
+/// image, with the frame number and the magnification relative to the first frame.
+fn write_contact_sheet(path: String) {
+    let images = Image::open_frames(OUTPUT).expect("Error reading animation.");
+    // The manifest describes the frames actually written, the current job may have changed.
+    let widths: Vec<f64> = read_json(Path::new(OUTPUT).with_extension("json"))
+        .ok()
+        .and_then(|manifest| {
+            let frames = manifest.get("frames")?.as_array()?;
+            frames
+                .iter()
+                .map(|frame| frame.get("x_size")?.as_f64())
+                .collect()
+        })
+        .unwrap_or_else(|| interpolated_frames().iter().map(|k| k.x_size).collect());
+
+    let count = images.len();
+    let frames: Vec<(Image, String)> = images
+        .into_iter()
+        .enumerate()
+        .step_by(CONTACT_SHEET_EVERY)
+        .map(|(i, image)| {
+            // Retimed animations have more frames than were rendered.
+            let width = widths[(i * widths.len() / count).min(widths.len() - 1)];
+            (image, format!("#{} x{:.1e}", i, widths[0] / width))
+        })
+        .collect();
+
+    let sheet = contact_sheet(&frames, CONTACT_SHEET_COLUMNS, CONTACT_SHEET_THUMBNAIL);
+    let (width, height) = (sheet.width as u16, sheet.height as u16);
+    let mut animation = Animation::new(&path, width, height, job().output_framerate)
+        .expect("Error creating sheet.");
+    animation.add_frames(vec![Frame::from_pixels(width, height, sheet.pixels)]);
+    animation.write_animation().expect("Error saving sheet.");
+    println!(
+        "Wrote contact sheet of {} frames to {}.",
+        frames.len(),
+        path
+    );
+}
+
+/// Renders evenly spaced frames in `PRECISION`, resolved per frame under `Precision::Auto`, and
+/// in the next tier up, and reports how far the two disagree, to show where the zoom outruns
+/// the precision it is rendered in.
+fn check_precision() {
+    let mut frames = interpolated_frames();
+    let step = (frames.len() / PRECISION_CHECK_FRAMES).max(1);
+    // Always check the last frame too, it is usually the deepest one.
+    let last = frames.pop();
+    let mut sampled: Vec<Keyframe> = frames.into_iter().step_by(step).collect();
+    sampled.extend(last);
+
+    let width = job().width as u32;
+    let mut reports: Vec<(Keyframe, Precision, Precision, Divergence)> = sampled
+        .par_iter()
+        .filter_map(|keyframe| {
+            let tier = job().precision.resolve(keyframe, width);
+            let Some(finer) = tier.finer(keyframe, width) else {
+                println!(
+                    "Frame {}: nothing finer than {} to check it against.",
+                    keyframe.index, tier
+                );
+                return None;
+            };
+            let rendered = renderer().iterate_frame_in(keyframe, tier);
+            let reference = renderer().iterate_frame_in(keyframe, finer);
+            let divergence = Divergence::between(&rendered, &reference);
+            Some((*keyframe, tier, finer, divergence))
+        })
+        .collect();
+    reports.sort_by_key(|(keyframe, ..)| keyframe.index);
+
+    for (keyframe, tier, finer, divergence) in &reports {
+        println!(
+            "Frame {} (width {:e}, {} against {}): {:.2}% of pixels diverge, {} escape differently, mean error {:.3}, max error {:.1} iterations.",
+            keyframe.index,
+            keyframe.x_size,
+            tier,
+            finer,
+            divergence.fraction() * 100.0,
+            divergence.mismatched,
+            divergence.mean_error,
+            divergence.max_error,
+        );
+    }
+    match reports
         .iter()
-        .map(|_| Frame::empty())
-        .collect::<Vec<Frame>>();
+        .find(|(.., divergence)| divergence.fraction() > PRECISION_TOLERANCE)
+    {
+        Some((keyframe, tier, finer, _)) => println!(
+            "{} is no longer accurate from frame {} on, at a view width of {:e}, where {} disagrees.",
+            tier, keyframe.index, keyframe.x_size, finer
+        ),
+        None => println!("Every checked frame is accurate in the precision it is rendered in."),
+    }
+}
 
-    let frames_arc = Arc::new(Mutex::new(frames));
+fn checkpoint_path() -> PathBuf {
+    Path::new(OUTPUT).with_extension("checkpoint")
+}
 
-    let mut handles = vec![];
+/// Records how far an interrupted render got, see `RenderJob::checkpoint`.
+fn write_checkpoint(frames_completed: usize) {
+    // Retimed output frames don't map back onto rendered ones, so they can't be resumed.
+    if job().output_framerate > job().framerate {
+        return;
+    }
+    write_json(checkpoint_path(), &job().checkpoint(frames_completed))
+        .expect("Error saving checkpoint.");
+}
+
+/// Returns the frames already written by an interrupted render of the same job.
+fn load_checkpoint() -> Vec<Frame> {
+    let checkpoint = match read_json(checkpoint_path()) {
+        Ok(checkpoint) => checkpoint,
+        Err(_) => {
+            println!("No checkpoint found, starting from scratch.");
+            return Vec::new();
+        }
+    };
+    let Some(completed) = job().resumable_frames(&checkpoint) else {
+        println!("Checkpoint is for a different configuration, starting from scratch.");
+        return Vec::new();
+    };
+    let mut frames = Frame::load_gif(OUTPUT).unwrap_or_default();
+    if frames.len() < completed {
+        println!("Partial output is missing frames, starting from scratch.");
+        return Vec::new();
+    }
+    frames.truncate(completed);
+    println!("Resuming after {} frames.", completed);
+    frames
+}
+
+/// The resolved job plus how it was derived, enough to regenerate the output.
+fn manifest() -> Json {
+    let job = job();
+    let inputs = Json::object([
+        ("location", arg_value("--location").into()),
+        (
+            "autopilot",
+            std::env::args().any(|arg| arg == "--autopilot").into(),
+        ),
+        ("minibrot", arg_value("--minibrot").into()),
+        ("palette_image", PALETTE_IMAGE.into()),
+    ]);
 
-    for (index, keyframe) in interpolated_frames.iter().enumerate() {
-        let frames_clone = Arc::clone(&frames_arc);
-        let keyframe = *keyframe;
+    let seeds = job
+        .buddhabrot
+        .map(|buddhabrot| {
+            (
+                "buddhabrot".to_owned(),
+                Json::Number(buddhabrot.seed.to_string()),
+            )
+        })
+        .into_iter()
+        .collect();
 
-        let handle = thread::spawn(move || {
-            let pixel_data = draw_frame(WIDTH as u32, HEIGHT as u32, keyframe);
-            let frame = Frame::from_pixels(WIDTH, HEIGHT, pixel_data);
+    // What each frame is iterated in, which `Precision::Auto` picks frame by frame.
+    let frames = interpolated_frames();
+    let precision = match job.precision {
+        Precision::Auto => Json::Array(
+            frames
+                .iter()
+                .map(|keyframe| {
+                    let resolved = job.precision.resolve(keyframe, job.width as u32);
+                    resolved.to_string().into()
+                })
+                .collect(),
+        ),
+        precision => precision.to_string().into(),
+    };
 
-            let mut frames = frames_clone.lock().unwrap();
-            frames[index] = frame;
+    Json::object([
+        ("crate_version", env!("CARGO_PKG_VERSION").into()),
+        ("precision", precision),
+        // Rendering is deterministic, density renders draw from a fixed seed.
+        ("seeds", Json::Object(seeds)),
+        ("job", job.to_json()),
+        ("digest", format!("{:016x}", job.digest()).into()),
+        ("inputs", inputs),
+        (
+            "frames",
+            Json::Array(frames.iter().map(Json::from).collect()),
+        ),
+    ])
+}
+
+/// The render: the settings of `settings` along the path of `keyframes`.
+fn job() -> &'static RenderJob {
+    static RESOLVED: OnceLock<RenderJob> = OnceLock::new();
+    RESOLVED.get_or_init(|| RenderJob {
+        keyframes: keyframes().to_vec(),
+        ..settings().clone()
+    })
+}
+
+/// Everything about the render but its path: the settings of the job given with `--rerender`,
+/// or else the constants above, with `--width`, `--height`, `--max-iter`, `--fractal` and
+/// `--palette` taking precedence over either.
+fn settings() -> &'static RenderJob {
+    static RESOLVED: OnceLock<RenderJob> = OnceLock::new();
+    RESOLVED.get_or_init(|| {
+        let base = rerendered_job().cloned().unwrap_or_else(|| RenderJob {
+            width: WIDTH,
+            height: HEIGHT,
+            framerate: FRAMERATE,
+            output_framerate: OUTPUT_FRAMERATE,
+            keyframes: Vec::new(),
+            projection: PROJECTION,
+            precision: PRECISION,
+            julia: JULIA,
+            doublings_per_second: DOUBLINGS_PER_SECOND,
+            fractal: FRACTAL.to_owned(),
+            max_iter: MAX_ITER,
+            iterations_per_doubling: ITERATIONS_PER_DOUBLING,
+            max_iter_calibration: MAX_ITER_CALIBRATION,
+            iteration_budget: ITERATION_BUDGET,
+            iteration_round: ITERATION_ROUND,
+            escape_norm: ESCAPE_NORM,
+            interior_shortcut: INTERIOR_SHORTCUT,
+            periodicity_check: PERIODICITY_CHECK,
+            border_tracing: BORDER_TRACING,
+            basin_supersampling: BASIN_SUPERSAMPLING,
+            palette: PALETTE,
+            auto_exposure: AUTO_EXPOSURE,
+            exterior_texture: EXTERIOR_TEXTURE.map(str::to_owned),
+            interior_texture: INTERIOR_TEXTURE.map(str::to_owned),
+            simulate_cvd: SIMULATE_CVD,
+            transparency: TRANSPARENCY,
+            anaglyph: ANAGLYPH,
+            buddhabrot: BUDDHABROT,
+            segment_speeds: SEGMENT_SPEEDS.to_vec(),
+            global_palette: GLOBAL_PALETTE,
+            indexed: INDEXED,
+            dither: DITHER,
+            disposal: DISPOSAL,
+            delta_frames: DELTA_FRAMES,
+            interlaced: INTERLACED,
+            lossy: LOSSY,
+            merge_duplicates: MERGE_DUPLICATES,
+            ping_pong: PING_PONG,
+            repeat: REPEAT,
         });
-        handles.push(handle);
+        RenderJob {
+            width: number_argument("--width").unwrap_or(base.width),
+            height: number_argument("--height").unwrap_or(base.height),
+            max_iter: number_argument("--max-iter").unwrap_or(base.max_iter),
+            fractal: fractal_name(),
+            palette: palette().clone(),
+            ..base
+        }
+    })
+}
+
+/// Number following `name` on the command line.
+fn number_argument<T: std::str::FromStr>(name: &str) -> Option<T> {
+    arg_value(name).map(|value| {
+        value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid number after {name}."))
+    })
+}
+
+/// Directory given with `--cache`, or else `ITERATION_CACHE`.
+fn iteration_cache() -> Option<String> {
+    arg_value("--cache").or(ITERATION_CACHE.map(String::from))
+}
+
+/// Value following `name` on the command line.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+/// Location file given with `--location`.
+fn location() -> Option<&'static Location> {
+    static LOADED: OnceLock<Option<Location>> = OnceLock::new();
+    LOADED
+        .get_or_init(|| {
+            arg_value("--location")
+                .map(|path| read_location(path).expect("Error reading location."))
+        })
+        .as_ref()
+}
+
+/// Job embedded in the GIF given with `--rerender`, rendered again with every setting it was
+/// rendered with, but for those given on the command line, e.g. `--width` and `--height` to
+/// render a GIF again larger or `--max-iter` deeper.
+fn rerendered_job() -> Option<&'static RenderJob> {
+    static LOADED: OnceLock<Option<RenderJob>> = OnceLock::new();
+    LOADED
+        .get_or_init(|| {
+            arg_value("--rerender")
+                .map(|path| RenderJob::from_gif(path).expect("Error reading embedded job."))
+        })
+        .as_ref()
+}
+
+/// Prints the settings of the job that differ from those it was rendered with.
+fn report_rerender(original: &RenderJob) {
+    let current = job().to_json();
+    let Json::Object(settings) = original.to_json() else {
+        return;
+    };
+    for (key, before) in settings {
+        // The keyframes are the original ones, only fitted to the aspect ratio.
+        let now = current.get(&key).cloned().unwrap_or(Json::Null);
+        if key != "keyframes" && now != before {
+            println!("Rerendering with {} {} instead of {}.", key, now, before);
+        }
     }
+}
+
+/// `KEYFRAMES`, or a zoom from its first view into the location given with `--location`. With
+/// `--autopilot`, or `--minibrot` followed by the width of the minibrot to end on, the path is
+/// generated instead, starting from the location if there is one. `--rerender` takes the path
+/// of the GIF it is given.
+fn keyframes() -> &'static [Keyframe] {
+    static RESOLVED: OnceLock<Vec<Keyframe>> = OnceLock::new();
+    RESOLVED.get_or_init(|| {
+        let aspect = settings().width as f32 / settings().height as f32;
+        if let Some(original) = rerendered_job() {
+            // Views keep their width at another aspect ratio.
+            let stretch = (original.width as f32 / original.height as f32 / aspect) as f64;
+            return original
+                .keyframes
+                .iter()
+                .map(|keyframe| Keyframe {
+                    y_size: keyframe.y_size * stretch,
+                    ..*keyframe
+                })
+                .collect();
+        }
+        let start = location().map_or(KEYFRAMES[0], |l| l.to_keyframe(0, aspect));
+        if let Some(depth) = arg_value("--minibrot") {
+            let depth = depth.parse().expect("Invalid minibrot width.");
+            return minibrot_path(start, depth, MINIBROT_FRAMES, MINIBROT_MAX_PERIOD, probe)
+                .expect("No minibrot found near the starting view.");
+        }
+        if std::env::args().any(|arg| arg == "--autopilot") {
+            return autopilot(
+                start,
+                AUTOPILOT_STEPS,
+                AUTOPILOT_FRAMES,
+                AUTOPILOT_ZOOM,
+                probe,
+            );
+        }
+        match location() {
+            Some(location) => {
+                let target = location.to_keyframe(LOCATION_FRAMES, aspect);
+                // Interpolation stops short of the last keyframe, repeat it to end on the target.
+                let end = Keyframe {
+                    index: LOCATION_FRAMES + 1,
+                    ..target
+                };
+                vec![KEYFRAMES[0], target, end]
+            }
+            None => KEYFRAMES.to_vec(),
+        }
+    })
+}
 
-    for handle in handles {
-        handle.join().expect("Thread panick.");
+/// One keyframe per rendered frame, those of `deep_frames` rounded to f64 when there are any.
+fn interpolated_frames() -> Vec<Keyframe> {
+    #[cfg(feature = "bigfloat")]
+    if let Some(frames) = deep_frames() {
+        return frames.iter().map(DeepKeyframe::to_keyframe).collect();
     }
-    Arc::try_unwrap(frames_arc).unwrap().into_inner().unwrap()
+    job().frames()
+}
+
+/// The part of `interpolated_frames` that still has to be rendered.
+fn frames_to_render() -> Vec<Keyframe> {
+    let mut frames = interpolated_frames();
+    frames.drain(..RESUMED_FRAMES.load(Ordering::Relaxed).min(frames.len()));
+    frames
+}
+
+/// Parallel frame builder that only uses Rust threads and synchronization primitives: one
+/// worker per core claims the next frame to draw from an atomic counter and sends it back
+/// tagged with its index, so neither taking work nor handing in frames waits on a lock.
+pub fn frames_native() -> Vec<Frame> {
+    let interpolated_frames = frames_to_render();
+
+    let mut frames: Vec<Frame> = interpolated_frames
+        .iter()
+        .map(|_| Frame::empty())
+        .collect::<Vec<Frame>>();
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<(usize, Frame)>();
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (next, interpolated_frames) = (&next, &interpolated_frames);
+
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(keyframe) = interpolated_frames.get(index) else {
+                    return;
+                };
+                if interrupt::requested() {
+                    return;
+                }
+                let frame = render_frame(*keyframe);
+                sender.send((index, frame)).unwrap();
+            });
+        }
+        // Only the workers' senders are left, so the loop ends when the last one returns.
+        drop(sender);
+        for (index, frame) in receiver {
+            frames[index] = frame;
+        }
+    });
+    frames
+}
+
+/// Renders the frames on one worker per core while `animation` encodes them, in order, and
+/// returns how many were written. Worker `i` of `n` renders frames `i`, `i + n` and so on into a
+/// channel of its own that holds `depth` frames, and the encoder takes one from each in turn,
+/// so the frames arrive in sequence and a worker that gets ahead waits rather than buffering.
+fn write_pipelined(animation: Animation, depth: usize) -> usize {
+    let interpolated_frames = frames_to_render();
+    let workers = thread::available_parallelism().map_or(1, usize::from);
+    let mut written = 0;
+
+    thread::scope(|scope| {
+        let receivers: Vec<mpsc::Receiver<Frame>> = (0..workers)
+            .map(|worker| {
+                let (sender, receiver) = mpsc::sync_channel(depth);
+                let interpolated_frames = &interpolated_frames;
+                scope.spawn(move || {
+                    for keyframe in interpolated_frames.iter().skip(worker).step_by(workers) {
+                        if interrupt::requested() {
+                            return;
+                        }
+                        let frame = render_frame(*keyframe);
+                        if sender.send(frame).is_err() {
+                            return;
+                        }
+                    }
+                });
+                receiver
+            })
+            .collect();
+
+        // A worker that stopped early ends the stream at its next frame, which keeps the
+        // written frames a contiguous prefix.
+        let frames = (0..)
+            .map_while(|index| receivers[index % workers].recv().ok())
+            .inspect(|_| written += 1);
+        animation
+            .write_streamed(frames)
+            .expect("Error saving animation.");
+    });
+    written
 }
 
 /// Parallel frame builder that uses Rayon.
 pub fn frames_rayon() -> Vec<Frame> {
-    let keyframes = &KEYFRAMES;
-    let interpolated_frames: Vec<Keyframe> = get_interpolated_frames(keyframes);
+    let interpolated_frames: Vec<Keyframe> = frames_to_render();
 
     interpolated_frames
         .par_iter()
         .map(|keyframe| {
-            let pixel_data = draw_frame(WIDTH as u32, HEIGHT as u32, *keyframe);
-            Frame::from_pixels(WIDTH, HEIGHT, pixel_data)
+            if interrupt::requested() {
+                return Frame::empty();
+            }
+            let frame = render_frame(*keyframe);
+            eco_idle();
+            frame
         })
         .collect()
 }
 
-pub fn calc_pixel((x, y): (f32, f32)) -> Pixel {
-    let c = Complex::new(x, y);
-    let mut z = Complex::new(0.0, 0.0);
-    let mut iters = 0;
+fn eco_idle() {
+    if let Some(eco) = &ECO {
+        eco.idle();
+    }
+}
+
+/// Frame builder that keeps within a memory budget, pausing workers and spilling frames to disk.
+pub fn frames_budgeted(budget: Arc<MemoryBudget>) -> FrameStore {
+    let interpolated_frames: Vec<Keyframe> = frames_to_render();
+    let store = FrameStore::with_budget(
+        interpolated_frames.len(),
+        Arc::clone(&budget),
+        std::env::temp_dir(),
+    );
 
-    while z.norm() < 8192.0 && iters < MAX_ITER {
-        z = z * z + c;
-        iters += 1;
+    interpolated_frames
+        .par_iter()
+        .enumerate()
+        .for_each(|(index, keyframe)| {
+            if interrupt::requested() {
+                return;
+            }
+            let _working_set = budget.acquire(Frame::render_cost(job().width, job().height));
+            let frame = render_frame(*keyframe);
+            store.insert(index, frame).expect("Error spilling frame.");
+            eco_idle();
+        });
+    store
+}
+
+/// Frame builder that renders at `FRAMERATE` and synthesizes the rest up to `OUTPUT_FRAMERATE`.
+pub fn frames_retimed() -> Vec<Frame> {
+    let interpolated_frames: Vec<Keyframe> = frames_to_render();
+
+    let rendered: Vec<Option<(Keyframe, Vec<Pixel>)>> = interpolated_frames
+        .par_iter()
+        .map(|keyframe| {
+            if interrupt::requested() {
+                return None;
+            }
+            let pixel_data = draw_frame(*keyframe);
+            eco_idle();
+            Some((*keyframe, pixel_data))
+        })
+        .collect();
+    let rendered: Vec<(Keyframe, Vec<Pixel>)> = rendered.into_iter().map_while(|f| f).collect();
+
+    retime_frames(
+        job().width as u32,
+        job().height as u32,
+        &rendered,
+        job().framerate,
+        job().output_framerate,
+    )
+    .into_par_iter()
+    .map(to_frame)
+    .collect()
+}
+
+/// The GIF written to `OUTPUT`, set up by the constants above.
+fn create_animation() -> Animation {
+    let mut animation = match global_palette() {
+        Some(palette) => Animation::new_with_palette(
+            OUTPUT,
+            job().width,
+            job().height,
+            job().output_framerate,
+            palette,
+        ),
+        None => Animation::new(OUTPUT, job().width, job().height, job().output_framerate),
     }
-    if iters < MAX_ITER {
-        let log_zn = (z.norm().log2() / 2.0).log2() / LOG2_10;
-        let nu = log_zn;
-        let intensity = (iters as f32 + 1.0 - nu) / MAX_ITER as f32;
-        let r = intensity.powi(2);
-        let g = intensity;
-        let b = intensity.sqrt();
-        Pixel::from_rgb(r, g, b)
-    } else {
-        Pixel::from_rgb(0.0, 0.0, 0.0)
+    .expect("Error creating animation.");
+    // Every GIF carries the job that renders it again.
+    animation.comment(job().to_json().to_string());
+    animation.repeat(job().repeat);
+    animation.frame_delays(job().frame_delays());
+    if job().ping_pong {
+        animation.ping_pong();
+    }
+    if job().delta_frames {
+        animation.delta_frames();
+    }
+    if let Some(error) = job().lossy {
+        animation.lossy(error);
+    }
+    if let Some(disposal) = job()
+        .disposal
+        .or(job().transparency.map(|_| Disposal::Background))
+    {
+        animation.disposal(disposal);
     }
+    if job().interlaced {
+        animation.interlaced();
+    }
+    animation
 }
 
-pub fn draw_frame(width: u32, height: u32, keyframe: Keyframe) -> Vec<Pixel> {
-    let mut pixels = Vec::with_capacity((width * height) as usize);
+/// A frame of the animation, quantized to the `GLOBAL_PALETTE` if there is one and dithered
+/// under `DITHER`, unless it has `TRANSPARENCY`.
+fn to_frame(pixel_data: Vec<Pixel>) -> Frame {
+    if job().transparency.is_some() {
+        return Frame::from_pixels(job().width, job().height, pixel_data);
+    }
+    match (global_palette(), job().dither) {
+        (Some(palette), Some(dither)) => {
+            let indices = palette.dither(job().width as usize, &pixel_data, dither);
+            Frame::from_indices(job().width, job().height, indices)
+        }
+        (Some(palette), None) => {
+            Frame::from_pixels_with_palette(job().width, job().height, pixel_data, palette)
+        }
+        (None, Some(dither)) => {
+            Frame::from_pixels_dithered(job().width, job().height, pixel_data, dither)
+        }
+        (None, None) => Frame::from_pixels(job().width, job().height, pixel_data),
+    }
+}
 
-    for y in 0..height {
-        for x in 0..width {
-            let (cx, cy) = keyframe.get_coordinate(x, y, width, height);
-            let pixel = calc_pixel((cx, cy));
-            pixels.push(pixel);
+/// A frame of the animation, drawn straight in palette indices under `INDEXED` when the
+/// coloring allows.
+fn render_frame(keyframe: Keyframe) -> Frame {
+    // Deep frames are drawn in full color and mapped onto the palette afterwards.
+    #[cfg(feature = "bigfloat")]
+    let indexed = job().indexed && deep_frame(&keyframe).is_none();
+    #[cfg(not(feature = "bigfloat"))]
+    let indexed = job().indexed;
+    if indexed && job().dither.is_none() {
+        let working = dashboard::working(keyframe.index);
+        if let Some(indices) = renderer().draw_indexed(&keyframe) {
+            return Frame::from_indices(job().width, job().height, indices);
+        }
+        // `draw_frame` counts the frame instead.
+        if let Some(working) = working {
+            working.hand_over();
         }
     }
-    pixels
+    to_frame(draw_frame(keyframe))
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Complex {
-    x: f32,
-    y: f32,
+/// The palette of `INDEXED`, or of `GLOBAL_PALETTE`, learned from frames drawn up front.
+fn global_palette() -> Option<&'static GlobalPalette> {
+    static LEARNED: OnceLock<Option<GlobalPalette>> = OnceLock::new();
+    LEARNED
+        .get_or_init(|| {
+            if job().transparency.is_some() {
+                return None;
+            }
+            if job().indexed {
+                return Some(renderer().indexed_palette());
+            }
+            let samples = job().global_palette?;
+            let frames = interpolated_frames();
+            let step = (frames.len() / samples.max(1)).max(1);
+            let sampled: Vec<Keyframe> = frames.into_iter().step_by(step).collect();
+            let pixels: Vec<Vec<Pixel>> = sampled.par_iter().map(|k| draw_frame(*k)).collect();
+            Some(GlobalPalette::from_frames(&pixels))
+        })
+        .as_ref()
 }
 
-impl Complex {
-    pub fn new(x: f32, y: f32) -> Self {
-        Complex { x, y }
-    }
+fn palette() -> &'static Palette {
+    static RESOLVED: OnceLock<Palette> = OnceLock::new();
+    RESOLVED.get_or_init(
+        || match (arg_value("--palette"), rerendered_job(), PALETTE_IMAGE) {
+            (Some(name), _, _) => palette_argument(&name),
+            (None, Some(original), _) => original.palette.clone(),
+            (None, None, Some(path)) => {
+                let image = Image::open(path).expect("Error loading palette image.");
+                Palette::from_image(&image, 8)
+            }
+            // Locations that carry their own colors override the default palette.
+            (None, None, None) => location()
+                .and_then(|location| {
+                    location
+                        .palette
+                        .clone()
+                        .or_else(|| location_palette_file(location))
+                })
+                .unwrap_or(PALETTE),
+        },
+    )
+}
 
-    pub fn norm(&self) -> f32 {
-        self.x * self.x + self.y * self.y
+/// The palette `--palette` gives, by name, file or list of colors.
+fn palette_argument(value: &str) -> Palette {
+    if let Some(palette) = palette_from_name(value) {
+        return palette;
     }
+    if Path::new(value).is_file() {
+        return read_palette(value).expect("Error reading palette.");
+    }
+    parse_color_stops(value).expect("Unknown palette.")
+}
+
+/// The map file a location refers to, looked up next to the location file.
+fn location_palette_file(location: &Location) -> Option<Palette> {
+    let file = location.palette_file.as_ref()?;
+    let location_path = PathBuf::from(arg_value("--location")?);
+    let path = location_path.parent().unwrap_or(Path::new("")).join(file);
+    Some(read_palette(path).expect("Error reading the location's palette."))
 }
 
-impl Add for Complex {
-    type Output = Self;
+/// The renderer configured by the constants above, with the exposures of the animation
+/// measured up front when `AUTO_EXPOSURE` is set.
+fn renderer() -> &'static Renderer {
+    static CONFIGURED: OnceLock<Renderer> = OnceLock::new();
+    CONFIGURED.get_or_init(|| configure_renderer(job().width as u32, job().height as u32))
+}
 
-    fn add(self, rhs: Self) -> Self {
-        Complex {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
+fn configure_renderer(width: u32, height: u32) -> Renderer {
+    let mut renderer = Renderer::new(width, height, job().max_iter)
+        .with_fractal(fractal())
+        .with_escape_norm(job().escape_norm)
+        .with_projection(job().projection)
+        .with_precision(job().precision)
+        .with_interior_shortcut(job().interior_shortcut)
+        .with_periodicity_check(job().periodicity_check)
+        .with_palette(palette().clone());
+    if job().julia {
+        renderer = renderer.with_julia();
+    }
+    if job().border_tracing {
+        renderer = renderer.with_border_tracing();
+    }
+    if let Some(per_doubling) = job().iterations_per_doubling {
+        renderer = renderer.with_iteration_scaling(per_doubling);
+    }
+    if let Some(limit) = job().max_iter_calibration {
+        renderer = renderer.with_iteration_calibration(limit, CALIBRATION_PROBE);
+    }
+    if let Some(budget) = job().iteration_budget {
+        renderer = renderer.with_iteration_budget(budget, job().iteration_round);
+    }
+    if let Some(path) = &job().exterior_texture {
+        renderer = renderer.with_exterior_texture(load_texture(path));
+    }
+    if let Some(path) = &job().interior_texture {
+        renderer = renderer.with_interior_texture(load_texture(path));
+    }
+    if let Some(deficiency) = job().simulate_cvd {
+        renderer = renderer.with_simulated_cvd(deficiency);
+    }
+    if let Some(transparency) = job().transparency {
+        renderer = renderer.with_transparency(transparency);
+    }
+    if let Some(parallax) = job().anaglyph {
+        renderer = renderer.with_anaglyph(parallax);
+    }
+    if let Some(buddhabrot) = job().buddhabrot {
+        renderer = renderer.with_density(buddhabrot);
+    }
+    if let Some(grid) = job().basin_supersampling {
+        renderer = renderer.with_basin_supersampling(grid);
+    }
+    if let Some(dir) = iteration_cache() {
+        renderer = renderer.with_cache(IterationCache::new(dir));
+    }
+    #[cfg(feature = "opencl")]
+    if std::env::args().any(|arg| arg == "--opencl") {
+        for device in OpenClDevice::all() {
+            println!("Iterating on {}.", device.name());
+            renderer = renderer.with_device(device);
+        }
+    }
+    #[cfg(feature = "wgpu")]
+    if std::env::args().any(|arg| arg == "--gpu") {
+        for device in WgpuDevice::all() {
+            println!("Iterating on {}.", device.name());
+            renderer = renderer.with_device(device);
         }
     }
+    if let Some(weight) = job().auto_exposure {
+        let exposures = renderer.measure_exposures(
+            &interpolated_frames(),
+            weight,
+            EXPOSURE_PROBE,
+            EXPOSURE_CLIP,
+        );
+        renderer = renderer.with_exposures(exposures);
+    }
+    renderer
 }
 
-impl Mul for Complex {
-    type Output = Self;
+/// Iterates single points for the path generators, without any of the coloring setup.
+fn probe(x: f32, y: f32) -> f32 {
+    static PROBE: OnceLock<Renderer> = OnceLock::new();
+    PROBE
+        .get_or_init(|| {
+            Renderer::new(
+                settings().width as u32,
+                settings().height as u32,
+                settings().max_iter,
+            )
+            .with_fractal(fractal())
+            .with_escape_norm(settings().escape_norm)
+        })
+        .iterate_point((x, y))
+        .smooth
+}
 
-    fn mul(self, rhs: Self) -> Self {
-        Complex {
-            x: self.x * rhs.x - self.y * rhs.y,
-            y: self.x * rhs.y + self.y * rhs.x,
-        }
+fn fractal_name() -> String {
+    arg_value("--fractal")
+        .or_else(|| rerendered_job().map(|original| original.fractal.clone()))
+        .unwrap_or_else(|| FRACTAL.to_owned())
+}
+
+fn fractal() -> Box<dyn Fractal> {
+    let name = &settings().fractal;
+    if name.starts_with("z =") {
+        let formula = Expression::parse(name);
+        return Box::new(formula.unwrap_or_else(|error| panic!("Invalid formula: {error}.")));
+    }
+    fractal_from_name(name).expect("Unknown fractal.")
+}
+
+fn load_texture(path: &str) -> Image {
+    Image::open(path).expect("Error loading texture.")
+}
+
+pub fn draw_frame(keyframe: Keyframe) -> Vec<Pixel> {
+    let _working = dashboard::working(keyframe.index);
+    #[cfg(feature = "bigfloat")]
+    if let Some(frame) = deep_frame(&keyframe) {
+        return renderer().draw_deep_frame(frame);
     }
+    renderer().draw_frame(&keyframe)
+}
+
+/// The frame at `keyframe`'s index with every digit of the location's center kept, when
+/// `PRECISION` needs more digits for `keyframe` than its f64 center holds.
+#[cfg(feature = "bigfloat")]
+fn deep_frame(keyframe: &Keyframe) -> Option<&'static DeepKeyframe> {
+    let deep = matches!(
+        job().precision.resolve(keyframe, job().width as u32),
+        Precision::Arbitrary { .. } | Precision::Perturbation { .. }
+    );
+    deep_frames()?
+        .iter()
+        .find(|frame| deep && frame.keyframe.index == keyframe.index)
+}
+
+/// The zoom into `--location` with every digit of the location's center kept, and the zoom
+/// exponent rather than the view size interpolated. Only for the plain zoom into the location,
+/// as generated paths and `DOUBLINGS_PER_SECOND` place their frames in f64.
+#[cfg(feature = "bigfloat")]
+fn deep_frames() -> Option<&'static [DeepKeyframe]> {
+    static RESOLVED: OnceLock<Option<Vec<DeepKeyframe>>> = OnceLock::new();
+    RESOLVED
+        .get_or_init(|| {
+            let generated = rerendered_job().is_some()
+                || arg_value("--minibrot").is_some()
+                || std::env::args().any(|arg| arg == "--autopilot")
+                || job().doublings_per_second.is_some();
+            let location = location().filter(|_| !generated)?;
+            let start = KEYFRAMES[0];
+            let start = DeepKeyframe::new(
+                &start.x_center.to_string(),
+                &start.y_center.to_string(),
+                start.y_size.log10(),
+                start.x_size / start.y_size,
+                start,
+            )
+            .expect("The first keyframe has a finite center.");
+            let aspect = job().width as f32 / job().height as f32;
+            let target = location
+                .to_deep_keyframe(LOCATION_FRAMES, aspect)
+                .expect("Invalid location center.");
+            // Interpolation stops short of the last keyframe, repeat it to end on the target.
+            let end = DeepKeyframe {
+                keyframe: Keyframe {
+                    index: LOCATION_FRAMES + 1,
+                    ..target.keyframe
+                },
+                ..target.clone()
+            };
+            Some(get_interpolated_deep_frames(&[start, target, end]))
+        })
+        .as_deref()
 }
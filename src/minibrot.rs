@@ -0,0 +1,204 @@
+use core::f64::consts::{PI, TAU};
+
+use crate::autopilot::most_detailed_cell;
+use crate::Keyframe;
+
+/// Newton steps spent converging on a nucleus.
+const NEWTON_STEPS: usize = 64;
+/// View widths spent framing the found minibrot.
+const FRAMING: f64 = 4.0;
+
+/// A minibrot located by `find_minibrot`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Minibrot {
+    /// Nucleus, the center of its cardioid.
+    pub re: f64,
+    pub im: f64,
+    pub period: usize,
+    /// Approximate width of the minibrot.
+    pub size: f64,
+    /// Angle in radians the minibrot is turned counterclockwise by against the whole set.
+    pub angle: f64,
+}
+
+/// Finds the lowest-period minibrot within `radius` of `center`: its period is the first
+/// iteration whose orbit of the disc around `center` contains 0, and Newton's method then
+/// converges on the nucleus. Done in f64, since minibrots worth finding are small.
+pub fn find_minibrot(center: (f64, f64), radius: f64, max_period: usize) -> Option<Minibrot> {
+    let period = ball_period(center, radius, max_period)?;
+
+    let mut c = center;
+    for _ in 0..NEWTON_STEPS {
+        let (mut z, mut dz) = ((0.0, 0.0), (0.0, 0.0));
+        for _ in 0..period {
+            dz = add(mul((2.0 * z.0, 2.0 * z.1), dz), (1.0, 0.0));
+            z = add(mul(z, z), c);
+        }
+        let step = div(z, dz);
+        c = (c.0 - step.0, c.1 - step.1);
+        if !(c.0.is_finite() && c.1.is_finite()) {
+            return None;
+        }
+        if step.0.hypot(step.1) <= f64::EPSILON * c.0.hypot(c.1).max(1.0) {
+            break;
+        }
+    }
+    if (c.0 - center.0).hypot(c.1 - center.1) > 2.0 * radius {
+        return None;
+    }
+
+    let scale = scale_estimate(c, period);
+    Some(Minibrot {
+        re: c.0,
+        im: c.1,
+        period,
+        // The estimate is of the cardioid's radius, the whole minibrot spans about twice that.
+        size: scale.0.hypot(scale.1) * 2.0,
+        angle: scale.1.atan2(scale.0),
+    })
+}
+
+/// The rotation nearest to `from` that shows something turned by `angle` upright.
+fn turned(from: f32, angle: f64) -> f32 {
+    let turn = (angle - from as f64 + PI).rem_euclid(TAU) - PI;
+    from + turn as f32
+}
+
+/// Keyframes zooming from `start` into a minibrot about `depth` wide, ending with the minibrot
+/// dead-center. The detail `probe` of `autopilot` steers the dive toward the boundary, and the
+/// zoom runs at `frames_per_doubling` frames per magnification doubling after first panning
+/// over at the starting scale. The view turns during the zoom, the shorter way around, until the
+/// minibrot is upright like the whole set. The f32 detail probes limit the usable depth to about
+/// 1e-6.
+pub fn minibrot_path(
+    start: Keyframe,
+    depth: f32,
+    frames_per_doubling: usize,
+    max_period: usize,
+    probe: impl Fn(f32, f32) -> f32,
+) -> Option<Vec<Keyframe>> {
+    let mut view = start;
+    while view.x_size > depth as f64 * FRAMING {
+        let (x_center, y_center) = most_detailed_cell(&view, &probe);
+        view = Keyframe {
+            x_center,
+            y_center,
+            x_size: view.x_size / 2.0,
+            y_size: view.y_size / 2.0,
+            ..view
+        };
+    }
+
+    let center = (view.x_center, view.y_center);
+    let minibrot = find_minibrot(center, view.x_size / 2.0, max_period)?;
+    let aspect = start.y_size / start.x_size;
+    let x_size = minibrot.size * FRAMING;
+    let target = Keyframe {
+        x_center: minibrot.re,
+        y_center: minibrot.im,
+        x_size,
+        y_size: x_size * aspect,
+        rotation: turned(start.rotation, minibrot.angle),
+        // Nuclei are found for the unperturbed set.
+        z0_x: 0.0,
+        z0_y: 0.0,
+        c_x: 0.0,
+        c_y: 0.0,
+        power: 2.0,
+        p_x: 0.0,
+        p_y: 0.0,
+        morph: 0.0,
+        slice_angle: 0.0,
+        slice_offset: 0.0,
+        hold: 0,
+        index: 0,
+    };
+
+    let start = Keyframe { index: 0, ..start };
+    let pan = Keyframe {
+        x_center: target.x_center,
+        y_center: target.y_center,
+        index: frames_per_doubling,
+        ..start
+    };
+    let doublings = (start.x_size / target.x_size).log2().max(1.0);
+    let zoomed = pan.index + (doublings * frames_per_doubling as f64).ceil() as usize;
+    // Interpolation stops short of the last keyframe, repeat it to end on the target.
+    Some(vec![
+        start,
+        pan,
+        Keyframe {
+            index: zoomed,
+            ..target
+        },
+        Keyframe {
+            index: zoomed + 1,
+            ..target
+        },
+    ])
+}
+
+/// First iteration at which the first-order orbit of the disc around `center` contains 0.
+fn ball_period(center: (f64, f64), radius: f64, max_period: usize) -> Option<usize> {
+    let (mut z, mut dz) = ((0.0, 0.0), (0.0, 0.0));
+    for period in 1..=max_period {
+        dz = add(mul((2.0 * z.0, 2.0 * z.1), dz), (1.0, 0.0));
+        z = add(mul(z, z), center);
+        let (distance, spread) = (z.0.hypot(z.1), dz.0.hypot(dz.1) * radius);
+        if distance < spread {
+            return Some(period);
+        }
+        if distance > 1e10 {
+            return None;
+        }
+    }
+    None
+}
+
+/// The factor the minibrot with nucleus `c` is scaled and turned by against the whole set,
+/// from the renormalization estimate `1 / (b * l^2)`, where `l` is the multiplier of the cycle
+/// and `b` the sum of its inverse partial products.
+fn scale_estimate(c: (f64, f64), period: usize) -> (f64, f64) {
+    let (mut z, mut l, mut b) = ((0.0, 0.0), (1.0, 0.0), (1.0, 0.0));
+    for _ in 1..period {
+        z = add(mul(z, z), c);
+        l = mul((2.0 * z.0, 2.0 * z.1), l);
+        b = add(b, div((1.0, 0.0), l));
+    }
+    div((1.0, 0.0), mul(b, mul(l, l)))
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let norm = b.0 * b.0 + b.1 * b.1;
+    (
+        (a.0 * b.0 + a.1 * b.1) / norm,
+        (a.1 * b.0 - a.0 * b.1) / norm,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn airship_minibrot_is_upright() {
+        let minibrot = find_minibrot((-1.75, 0.0), 0.01, 16).unwrap();
+        assert_eq!(minibrot.period, 3);
+        assert!((minibrot.re + 1.754877666).abs() < 1e-9);
+        assert!(minibrot.angle.abs() < 1e-9);
+    }
+
+    #[test]
+    fn views_turn_the_short_way() {
+        assert!((turned(3.0, 3.5 - TAU) - 3.5).abs() < 1e-6);
+        assert!((turned(0.0, -0.5) + 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Keyframe, Pixel};
+
+/// Synthesizes the view `target` from two rendered neighbors by reprojecting both through the
+/// known zoom/pan transform and cross-fading them by `t` (0.0 is `start`, 1.0 is `end`).
+pub fn synthesize_frame(
+    width: u32,
+    height: u32,
+    (start, start_pixels): (&Keyframe, &[Pixel]),
+    (end, end_pixels): (&Keyframe, &[Pixel]),
+    target: &Keyframe,
+    t: f32,
+) -> Vec<Pixel> {
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let (cx, cy) = target.get_coordinate(x, y, width, height);
+            let a = sample(start_pixels, start, width, height, cx, cy);
+            let b = sample(end_pixels, end, width, height, cx, cy);
+
+            let color = match (a, b) {
+                (Some(a), Some(b)) => mix(a, b, t),
+                (Some(a), None) => a,
+                (None, Some(b)) => b,
+                // Neither neighbor saw this point, fall back to the closest edge of the nearer one.
+                (None, None) if t < 0.5 => clamped(start_pixels, start, width, height, cx, cy),
+                (None, None) => clamped(end_pixels, end, width, height, cx, cy),
+            };
+            pixels.push(to_pixel(color));
+        }
+    }
+    pixels
+}
+
+/// Converts frames rendered at `source_framerate` into frames at `target_framerate`,
+/// synthesizing the missing in-between frames with `synthesize_frame`.
+pub fn retime_frames(
+    width: u32,
+    height: u32,
+    frames: &[(Keyframe, Vec<Pixel>)],
+    source_framerate: f32,
+    target_framerate: f32,
+) -> Vec<Vec<Pixel>> {
+    if frames.len() < 2 {
+        return frames.iter().map(|(_, pixels)| pixels.clone()).collect();
+    }
+
+    let step = source_framerate / target_framerate;
+    let count = ((frames.len() - 1) as f32 / step).floor() as usize + 1;
+
+    (0..count)
+        .map(|idx| {
+            let position = idx as f32 * step;
+            let i = (position.floor() as usize).min(frames.len() - 2);
+            let t = position - i as f32;
+
+            let (start, start_pixels) = &frames[i];
+            let (end, end_pixels) = &frames[i + 1];
+            if t <= f32::EPSILON {
+                start_pixels.clone()
+            } else if t >= 1.0 - f32::EPSILON {
+                end_pixels.clone()
+            } else {
+                let target = start.lerp(*end, t, idx);
+                synthesize_frame(
+                    width,
+                    height,
+                    (start, start_pixels),
+                    (end, end_pixels),
+                    &target,
+                    t,
+                )
+            }
+        })
+        .collect()
+}
+
+fn sample(
+    pixels: &[Pixel],
+    view: &Keyframe,
+    width: u32,
+    height: u32,
+    cx: f32,
+    cy: f32,
+) -> Option<[f32; 4]> {
+    let (px, py) = view.get_pixel(cx, cy, width, height);
+    let max_x = (width - 1) as f32;
+    let max_y = (height - 1) as f32;
+    if px < 0.0 || py < 0.0 || px > max_x || py > max_y {
+        return None;
+    }
+    Some(bilinear(pixels, width, height, px, py))
+}
+
+pub(crate) fn clamped(
+    pixels: &[Pixel],
+    view: &Keyframe,
+    width: u32,
+    height: u32,
+    cx: f32,
+    cy: f32,
+) -> [f32; 4] {
+    let (px, py) = view.get_pixel(cx, cy, width, height);
+    let px = px.clamp(0.0, (width - 1) as f32);
+    let py = py.clamp(0.0, (height - 1) as f32);
+    bilinear(pixels, width, height, px, py)
+}
+
+fn bilinear(pixels: &[Pixel], width: u32, height: u32, px: f32, py: f32) -> [f32; 4] {
+    let x0 = px.floor() as u32;
+    let y0 = py.floor() as u32;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = px - x0 as f32;
+    let fy = py - y0 as f32;
+
+    let at = |x: u32, y: u32| {
+        let p = pixels[(y * width + x) as usize];
+        [p.r as f32, p.g as f32, p.b as f32, p.a as f32]
+    };
+    let top = mix(at(x0, y0), at(x1, y0), fx);
+    let bottom = mix(at(x0, y1), at(x1, y1), fx);
+    mix(top, bottom, fy)
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+pub(crate) fn to_pixel([r, g, b, a]: [f32; 4]) -> Pixel {
+    Pixel {
+        r: r.round() as u8,
+        g: g.round() as u8,
+        b: b.round() as u8,
+        a: a.round() as u8,
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{Bailout, Complex, EscapeResult, Fractal};
+
+/// Orbits have converged once a step moves them by less than the square root of this.
+const TOLERANCE: f32 = 1e-10;
+/// Durand-Kerner iterations spent finding the roots.
+const ROOT_ITERATIONS: usize = 500;
+/// Magnet orbits have reached the fixed point 1 once within the square root of this.
+const MAGNET_TOLERANCE: f32 = 1e-8;
+
+/// Newton's method for the roots of a polynomial, `z - p(z) / p'(z)`, iterated from every pixel
+/// rather than over parameters, so keyframe `z0` and Julia mode are ignored. Points converge
+/// instead of escaping: `EscapeResult::escaped` means converged, and `Fractal::basin` tells
+/// which root the orbit ended on.
+#[derive(Clone, Debug)]
+pub struct Newton {
+    /// Highest degree first.
+    coefficients: Vec<Complex>,
+    derivative: Vec<Complex>,
+    roots: Vec<Complex>,
+}
+
+impl Newton {
+    /// The polynomial with `coefficients`, highest degree first, e.g. `[1, 0, 0, -1]` for
+    /// `z^3 - 1`. Leading zeros are dropped.
+    pub fn new(coefficients: Vec<Complex>) -> Self {
+        let zero = Complex::new(0.0, 0.0);
+        let coefficients: Vec<Complex> = coefficients
+            .into_iter()
+            .skip_while(|coefficient| *coefficient == zero)
+            .collect();
+        let degree = coefficients.len().saturating_sub(1);
+        let derivative = coefficients[..degree]
+            .iter()
+            .enumerate()
+            .map(|(i, coefficient)| *coefficient * Complex::new((degree - i) as f32, 0.0))
+            .collect();
+        let roots = find_roots(&coefficients);
+        Self {
+            coefficients,
+            derivative,
+            roots,
+        }
+    }
+
+    /// `z^n - 1`, whose roots are the `n`th roots of unity.
+    pub fn roots_of_unity(n: usize) -> Self {
+        let mut coefficients = vec![Complex::new(0.0, 0.0); n + 1];
+        coefficients[0] = Complex::new(1.0, 0.0);
+        coefficients[n] = Complex::new(-1.0, 0.0);
+        Self::new(coefficients)
+    }
+
+    pub fn roots(&self) -> &[Complex] {
+        &self.roots
+    }
+
+    /// Parses the part of a `Fractal::name` after `"newton "`.
+    pub(crate) fn parse(coefficients: &str) -> Option<Self> {
+        let coefficients: Vec<Complex> = coefficients
+            .split_whitespace()
+            .map(Complex::parse)
+            .collect::<Option<_>>()?;
+        let newton = Self::new(coefficients);
+        (!newton.roots.is_empty()).then_some(newton)
+    }
+}
+
+impl Fractal for Newton {
+    fn name(&self) -> String {
+        let coefficients: Vec<String> = self.coefficients.iter().map(Complex::to_string).collect();
+        format!("newton {}", coefficients.join(" "))
+    }
+
+    fn step(&self, z: Complex, _c: Complex) -> Complex {
+        z - horner(&self.coefficients, z) / horner(&self.derivative, z)
+    }
+
+    fn stepwise(&self) -> bool {
+        false
+    }
+
+    /// Converges from `c`.
+    fn iterate(&self, c: Complex, _z0: Complex, max_iter: usize, _norm: f32) -> EscapeResult {
+        converge(|z| self.step(z, c), c, max_iter)
+    }
+
+    fn basins(&self) -> usize {
+        self.roots.len()
+    }
+
+    fn basin(&self, result: &EscapeResult) -> Option<usize> {
+        if !result.escaped {
+            return None;
+        }
+        let z = Complex::new(result.z.0, result.z.1);
+        (0..self.roots.len()).min_by(|&a, &b| {
+            let distance = |i: usize| (self.roots[i] - z).norm();
+            distance(a).total_cmp(&distance(b))
+        })
+    }
+}
+
+/// Relaxed Newton's method on `z^degree - 1` plus the iterated point,
+/// `z - relaxation (z^d - 1) / (d z^(d-1)) + c`. Points are colored by how fast they converge,
+/// like escape times. Orbits start from `z0`, or from the critical point 1 when that is 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Nova {
+    degree: u32,
+    relaxation: Complex,
+}
+
+impl Nova {
+    pub fn new(degree: u32, relaxation: Complex) -> Self {
+        Self {
+            degree: degree.max(2),
+            relaxation,
+        }
+    }
+
+    /// Parses the part of a `Fractal::name` after `"nova "`: the degree, optionally followed by
+    /// the relaxation.
+    pub(crate) fn parse(parameters: &str) -> Option<Self> {
+        let mut parameters = parameters.split_whitespace();
+        let degree = parameters.next()?.parse().ok()?;
+        let relaxation = match parameters.next() {
+            Some(relaxation) => Complex::parse(relaxation)?,
+            None => Complex::new(1.0, 0.0),
+        };
+        Some(Self::new(degree, relaxation))
+    }
+}
+
+impl Fractal for Nova {
+    fn name(&self) -> String {
+        format!("nova {} {}", self.degree, self.relaxation)
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let d = self.degree;
+        let value = z.powi(d) - Complex::new(1.0, 0.0);
+        let slope = z.powi(d - 1) * Complex::new(d as f32, 0.0);
+        z - self.relaxation * value / slope + c
+    }
+
+    fn stepwise(&self) -> bool {
+        false
+    }
+
+    fn iterate(&self, c: Complex, z0: Complex, max_iter: usize, _norm: f32) -> EscapeResult {
+        let start = match z0 == Complex::new(0.0, 0.0) {
+            true => Complex::new(1.0, 0.0),
+            false => z0,
+        };
+        converge(|z| self.step(z, c), start, max_iter)
+    }
+}
+
+/// The magnet formulas from the renormalization of lattice models in physics. Both have the
+/// attracting fixed point 1 besides infinity, so orbits stop either way; the ones that reach 1
+/// form the single basin, the escaping ones are colored by escape time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Magnet {
+    /// `((z^2 + c - 1) / (2z + c - 2))^2`.
+    One,
+    /// `((z^3 + 3(c - 1)z + (c - 1)(c - 2)) / (3z^2 + 3(c - 2)z + (c - 1)(c - 2) + 1))^2`.
+    Two,
+}
+
+impl Fractal for Magnet {
+    fn name(&self) -> String {
+        match self {
+            Magnet::One => "magnet 1".to_owned(),
+            Magnet::Two => "magnet 2".to_owned(),
+        }
+    }
+
+    fn step(&self, z: Complex, c: Complex) -> Complex {
+        let one = Complex::new(1.0, 0.0);
+        let two = Complex::new(2.0, 0.0);
+        let three = Complex::new(3.0, 0.0);
+        let ratio = match self {
+            Magnet::One => (z * z + c - one) / (two * z + c - two),
+            Magnet::Two => {
+                let product = (c - one) * (c - two);
+                (z * z * z + three * (c - one) * z + product)
+                    / (three * z * z + three * (c - two) * z + product + one)
+            }
+        };
+        ratio * ratio
+    }
+
+    fn bailout(&self, z: Complex, escape_norm: f32) -> Option<Bailout> {
+        if z.norm() >= escape_norm {
+            Some(Bailout::Escaped)
+        } else if (z - Complex::new(1.0, 0.0)).norm() < MAGNET_TOLERANCE {
+            Some(Bailout::Converged)
+        } else {
+            None
+        }
+    }
+
+    fn basins(&self) -> usize {
+        1
+    }
+
+    fn basin(&self, result: &EscapeResult) -> Option<usize> {
+        let z = Complex::new(result.z.0, result.z.1);
+        let converged = result.escaped && (z - Complex::new(1.0, 0.0)).norm() < MAGNET_TOLERANCE;
+        converged.then_some(0)
+    }
+}
+
+/// Applies `step` from `start` until it moves the orbit by less than the tolerance, reported as
+/// escaped. The smooth count interpolates by how far below the tolerance the last step landed,
+/// which with quadratic convergence runs from one step to the next.
+fn converge(step: impl Fn(Complex) -> Complex, start: Complex, max_iter: usize) -> EscapeResult {
+    let mut z = start;
+    let mut iters = 0;
+    let mut converged = false;
+    let mut moved = 1.0;
+
+    while iters < max_iter {
+        let next = step(z);
+        moved = (next - z).norm();
+        z = next;
+        iters += 1;
+        if !moved.is_finite() {
+            break;
+        }
+        if moved < TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+
+    let smooth = match converged {
+        true => {
+            let depth = (moved.max(f32::MIN_POSITIVE) as f64).ln() / (TOLERANCE as f64).ln();
+            (iters as f64 - depth.log2()) as f32
+        }
+        false => iters as f32,
+    };
+    EscapeResult {
+        escaped: converged,
+        iterations: iters,
+        smooth,
+        z: (z.x, z.y),
+    }
+}
+
+fn horner(coefficients: &[Complex], z: Complex) -> Complex {
+    coefficients
+        .iter()
+        .fold(Complex::new(0.0, 0.0), |sum, coefficient| {
+            sum * z + *coefficient
+        })
+}
+
+/// All roots at once by the Durand-Kerner method, on the monic form of the polynomial.
+fn find_roots(coefficients: &[Complex]) -> Vec<Complex> {
+    let Some((&leading, rest)) = coefficients.split_first() else {
+        return Vec::new();
+    };
+    let mut monic = vec![Complex::new(1.0, 0.0)];
+    monic.extend(rest.iter().map(|coefficient| *coefficient / leading));
+
+    // Powers of a point that is neither real nor a root of unity, the usual starting guesses.
+    let seed = Complex::new(0.4, 0.9);
+    let mut roots: Vec<Complex> = (0..rest.len()).map(|i| seed.powi(i as u32)).collect();
+    for _ in 0..ROOT_ITERATIONS {
+        for i in 0..roots.len() {
+            let denominator = (0..roots.len())
+                .filter(|&j| j != i)
+                .fold(Complex::new(1.0, 0.0), |product, j| {
+                    product * (roots[i] - roots[j])
+                });
+            roots[i] = roots[i] - horner(&monic, roots[i]) / denominator;
+        }
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal_from_name;
+
+    fn close(a: Complex, b: Complex) -> bool {
+        (a - b).norm() < 1e-8
+    }
+
+    #[test]
+    fn roots_are_found() {
+        let cubic = Newton::roots_of_unity(3);
+        let third = std::f32::consts::TAU / 3.0;
+        for k in 0..3 {
+            let root = Complex::new((k as f32 * third).cos(), (k as f32 * third).sin());
+            assert!(cubic.roots().iter().any(|&found| close(found, root)));
+        }
+        let name = fractal_from_name("newton").unwrap().name();
+        assert_eq!(fractal_from_name(&name).unwrap().name(), name);
+    }
+
+    #[test]
+    fn points_near_a_root_end_in_its_basin() {
+        // z^2 - 4, leading zeros aside.
+        let zero = Complex::new(0.0, 0.0);
+        let newton = Newton::new(vec![
+            zero,
+            Complex::new(1.0, 0.0),
+            zero,
+            Complex::new(-4.0, 0.0),
+        ]);
+        assert_eq!(newton.basins(), 2);
+        for (i, &root) in newton.roots().iter().enumerate() {
+            let start = root * Complex::new(1.3, 0.2);
+            let result = newton.iterate(start, zero, 100, 4.0);
+            assert!(result.escaped);
+            assert!(close(Complex::new(result.z.0, result.z.1), root));
+            assert_eq!(newton.basin(&result), Some(i));
+        }
+        // The points between the roots never converge.
+        let between = newton.iterate(Complex::new(0.0, 1.0), zero, 100, 4.0);
+        assert!(!between.escaped);
+        assert_eq!(newton.basin(&between), None);
+    }
+}
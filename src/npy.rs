@@ -0,0 +1,141 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{EscapeResult, ImageError, IterationBuffer};
+
+/// Writes the smooth iteration counts of a frame as a NumPy `.npy` array of 32 bit floats, of
+/// shape `(height, width)`, to analyze or recolor in Python with `numpy.load`. Pixels inside
+/// the set are NaN, so `numpy.isnan` gives its mask.
+pub fn write_npy(buffer: &IterationBuffer, path: impl AsRef<Path>) -> Result<(), ImageError> {
+    let file = File::create(path).map_err(|_| ImageError::FileCreateError)?;
+    let mut writer = BufWriter::new(file);
+
+    // Format 1.0: the magic string, the version and a Python dict literal describing the array,
+    // padded with spaces to align the data to 64 bytes.
+    let mut dict = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        buffer.height, buffer.width
+    );
+    let unpadded = 10 + dict.len() + 1;
+    dict.extend(std::iter::repeat_n(
+        ' ',
+        unpadded.next_multiple_of(64) - unpadded,
+    ));
+    dict.push('\n');
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend((dict.len() as u16).to_le_bytes());
+    header.extend(dict.as_bytes());
+
+    let data: Vec<u8> = buffer
+        .results
+        .iter()
+        .flat_map(|result| match result.escaped {
+            true => result.smooth.to_le_bytes(),
+            false => f32::NAN.to_le_bytes(),
+        })
+        .collect();
+    writer
+        .write_all(&header)
+        .and_then(|_| writer.write_all(&data))
+        .and_then(|_| writer.flush())
+        .map_err(|_| ImageError::EncodeError)
+}
+
+/// Reads back the iteration results of a frame written by `write_npy` with an iteration limit
+/// of `max_iter`, for coloring it again without iterating. The arrays hold only the smooth
+/// counts, so the results carry no final orbit values and textures sample them at the origin.
+pub fn read_npy(path: impl AsRef<Path>, max_iter: usize) -> Result<IterationBuffer, ImageError> {
+    let bytes = fs::read(path).map_err(|_| ImageError::FileOpenError)?;
+    let (length, start) = match bytes.get(..8) {
+        Some(b"\x93NUMPY\x01\x00") if bytes.len() >= 10 => {
+            (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10)
+        }
+        Some([0x93, b'N', b'U', b'M', b'P', b'Y', 2 | 3, 0]) if bytes.len() >= 12 => {
+            let length = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]);
+            (length as usize, 12)
+        }
+        _ => return Err(ImageError::DecodeError),
+    };
+    let header = bytes
+        .get(start..start + length)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or(ImageError::DecodeError)?;
+    if !header.contains("'descr': '<f4'") || !header.contains("'fortran_order': False") {
+        return Err(ImageError::UnsupportedFormat);
+    }
+    let shape = header
+        .split("'shape': (")
+        .nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .ok_or(ImageError::DecodeError)?;
+    let sides: Vec<u32> = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|side| !side.is_empty())
+        .map(|side| side.parse().map_err(|_| ImageError::DecodeError))
+        .collect::<Result<_, _>>()?;
+    let [height, width] = sides[..] else {
+        return Err(ImageError::UnsupportedFormat);
+    };
+
+    let data = &bytes[start + length..];
+    if data.len() != width as usize * height as usize * 4 {
+        return Err(ImageError::DecodeError);
+    }
+    let results = data
+        .chunks_exact(4)
+        .map(|chunk| {
+            let smooth = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let (escaped, iterations, smooth) = match smooth.is_nan() {
+                true => (false, max_iter, max_iter as f32),
+                false => (true, smooth.max(0.0) as usize, smooth),
+            };
+            EscapeResult {
+                escaped,
+                iterations,
+                smooth,
+                z: (0.0, 0.0),
+            }
+        })
+        .collect();
+    Ok(IterationBuffer {
+        width,
+        height,
+        results,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let results: Vec<EscapeResult> = (0..7 * 5)
+            .map(|i| EscapeResult {
+                escaped: i % 4 != 0,
+                iterations: i,
+                smooth: i as f32 + 0.25,
+                z: (0.0, 0.0),
+            })
+            .collect();
+        let buffer = IterationBuffer {
+            width: 7,
+            height: 5,
+            results,
+        };
+        let path = std::env::temp_dir().join("mandelbrot-npy-test.npy");
+        write_npy(&buffer, &path).unwrap();
+        let read = read_npy(&path, 100).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((read.width, read.height), (7, 5));
+        for (read, written) in read.results.iter().zip(&buffer.results) {
+            assert_eq!(read.escaped, written.escaped);
+            match written.escaped {
+                true => assert_eq!(read.smooth, written.smooth),
+                false => assert_eq!(read.iterations, 100),
+            }
+        }
+    }
+}
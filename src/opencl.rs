@@ -0,0 +1,416 @@
+//! `Device` running the `KernelLanguage::OpenCl` kernel on the installed OpenCL runtime. The
+//! library is loaded when devices are first looked up rather than linked, so builds with the
+//! `opencl` feature still run, on the CPU, on machines without one.
+
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::ptr;
+use std::sync::{Mutex, OnceLock};
+
+use crate::{kernel_source, Complex, Device, DeviceFrame, EscapeResult, IterationBuffer};
+use crate::{KernelLanguage, OPENCL_BUILD_OPTIONS};
+
+/// Names the OpenCL ICD loader goes by, tried in order.
+const LIBRARIES: [&CStr; 2] = [c"libOpenCL.so.1", c"libOpenCL.so"];
+
+const CL_SUCCESS: i32 = 0;
+const CL_DEVICE_TYPE_ALL: u64 = 0xffff_ffff;
+const CL_DEVICE_NAME: u32 = 0x102b;
+const CL_MEM_WRITE_ONLY: u64 = 1 << 1;
+const CL_TRUE: u32 = 1;
+
+type Handle = *mut c_void;
+
+mod ffi {
+    use std::ffi::{c_char, c_int, c_void};
+
+    pub const RTLD_NOW: c_int = 2;
+
+    extern "C" {
+        pub fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
+        pub fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+}
+
+/// The OpenCL entry points used, looked up in the loaded library.
+struct Api {
+    get_platform_ids: unsafe extern "C" fn(u32, *mut Handle, *mut u32) -> i32,
+    get_device_ids: unsafe extern "C" fn(Handle, u64, u32, *mut Handle, *mut u32) -> i32,
+    get_device_info: unsafe extern "C" fn(Handle, u32, usize, *mut c_void, *mut usize) -> i32,
+    create_context: unsafe extern "C" fn(
+        *const isize,
+        u32,
+        *const Handle,
+        *const c_void,
+        *mut c_void,
+        *mut i32,
+    ) -> Handle,
+    create_command_queue: unsafe extern "C" fn(Handle, Handle, u64, *mut i32) -> Handle,
+    create_program_with_source:
+        unsafe extern "C" fn(Handle, u32, *const *const c_char, *const usize, *mut i32) -> Handle,
+    build_program: unsafe extern "C" fn(
+        Handle,
+        u32,
+        *const Handle,
+        *const c_char,
+        *const c_void,
+        *mut c_void,
+    ) -> i32,
+    create_kernel: unsafe extern "C" fn(Handle, *const c_char, *mut i32) -> Handle,
+    create_buffer: unsafe extern "C" fn(Handle, u64, usize, *mut c_void, *mut i32) -> Handle,
+    set_kernel_arg: unsafe extern "C" fn(Handle, u32, usize, *const c_void) -> i32,
+    enqueue_nd_range_kernel: unsafe extern "C" fn(
+        Handle,
+        Handle,
+        u32,
+        *const usize,
+        *const usize,
+        *const usize,
+        u32,
+        *const Handle,
+        *mut Handle,
+    ) -> i32,
+    enqueue_read_buffer: unsafe extern "C" fn(
+        Handle,
+        Handle,
+        u32,
+        usize,
+        usize,
+        *mut c_void,
+        u32,
+        *const Handle,
+        *mut Handle,
+    ) -> i32,
+    release_mem_object: unsafe extern "C" fn(Handle) -> i32,
+    release_kernel: unsafe extern "C" fn(Handle) -> i32,
+    release_program: unsafe extern "C" fn(Handle) -> i32,
+    release_command_queue: unsafe extern "C" fn(Handle) -> i32,
+    release_context: unsafe extern "C" fn(Handle) -> i32,
+}
+
+impl Api {
+    /// The installed runtime's entry points, `None` without one.
+    fn get() -> Option<&'static Api> {
+        static LOADED: OnceLock<Option<Api>> = OnceLock::new();
+        LOADED.get_or_init(|| unsafe { Api::load() }).as_ref()
+    }
+
+    unsafe fn load() -> Option<Api> {
+        let library = LIBRARIES.iter().find_map(|name| {
+            let library = ffi::dlopen(name.as_ptr(), ffi::RTLD_NOW);
+            (!library.is_null()).then_some(library)
+        })?;
+        Some(Api {
+            get_platform_ids: symbol(library, c"clGetPlatformIDs")?,
+            get_device_ids: symbol(library, c"clGetDeviceIDs")?,
+            get_device_info: symbol(library, c"clGetDeviceInfo")?,
+            create_context: symbol(library, c"clCreateContext")?,
+            create_command_queue: symbol(library, c"clCreateCommandQueue")?,
+            create_program_with_source: symbol(library, c"clCreateProgramWithSource")?,
+            build_program: symbol(library, c"clBuildProgram")?,
+            create_kernel: symbol(library, c"clCreateKernel")?,
+            create_buffer: symbol(library, c"clCreateBuffer")?,
+            set_kernel_arg: symbol(library, c"clSetKernelArg")?,
+            enqueue_nd_range_kernel: symbol(library, c"clEnqueueNDRangeKernel")?,
+            enqueue_read_buffer: symbol(library, c"clEnqueueReadBuffer")?,
+            release_mem_object: symbol(library, c"clReleaseMemObject")?,
+            release_kernel: symbol(library, c"clReleaseKernel")?,
+            release_program: symbol(library, c"clReleaseProgram")?,
+            release_command_queue: symbol(library, c"clReleaseCommandQueue")?,
+            release_context: symbol(library, c"clReleaseContext")?,
+        })
+    }
+}
+
+/// `name` in `library` as a function pointer of type `F`.
+unsafe fn symbol<F>(library: Handle, name: &CStr) -> Option<F> {
+    let symbol = ffi::dlsym(library, name.as_ptr());
+    (!symbol.is_null()).then(|| std::mem::transmute_copy::<Handle, F>(&symbol))
+}
+
+/// The kernel's `Frame`, laid out as OpenCL C lays out its `float2` members.
+#[repr(C, align(8))]
+struct FrameArgument {
+    center: [f32; 2],
+    size: [f32; 2],
+    c: [f32; 2],
+    z0: [f32; 2],
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    escape_norm: f32,
+    morph: f32,
+}
+
+/// The kernel's `Orbit`.
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Default)]
+struct Orbit {
+    z: [f32; 2],
+    iterations: u32,
+    escaped: u32,
+}
+
+/// A kernel built for one formula, with the program it came from.
+struct Built {
+    source: String,
+    program: Handle,
+    kernel: Handle,
+}
+
+/// An OpenCL device with its own context and queue. Programs are built the first time a
+/// formula is iterated and kept for the frames after.
+pub struct OpenClDevice {
+    api: &'static Api,
+    name: String,
+    device: Handle,
+    context: Handle,
+    queue: Handle,
+    built: Mutex<Vec<Built>>,
+}
+
+// OpenCL objects may be used from any thread, and kernels, whose arguments are shared state,
+// are only used under the `built` lock.
+unsafe impl Send for OpenClDevice {}
+unsafe impl Sync for OpenClDevice {}
+
+impl OpenClDevice {
+    /// Every device of every OpenCL platform, none without an OpenCL runtime.
+    pub fn all() -> Vec<OpenClDevice> {
+        let Some(api) = Api::get() else {
+            return Vec::new();
+        };
+        unsafe {
+            let platforms = list(|n, out, found| (api.get_platform_ids)(n, out, found));
+            platforms
+                .into_iter()
+                .flat_map(|platform| {
+                    list(|n, out, found| {
+                        (api.get_device_ids)(platform, CL_DEVICE_TYPE_ALL, n, out, found)
+                    })
+                })
+                .filter_map(|device| OpenClDevice::open(api, device))
+                .collect()
+        }
+    }
+
+    unsafe fn open(api: &'static Api, device: Handle) -> Option<OpenClDevice> {
+        let mut name = [0u8; 256];
+        let status = (api.get_device_info)(
+            device,
+            CL_DEVICE_NAME,
+            name.len(),
+            name.as_mut_ptr().cast(),
+            ptr::null_mut(),
+        );
+        let name = match status {
+            CL_SUCCESS => CStr::from_bytes_until_nul(&name).map_or_else(
+                |_| "OpenCL device".to_owned(),
+                |n| n.to_string_lossy().into(),
+            ),
+            _ => return None,
+        };
+        let mut status = CL_SUCCESS;
+        let context = (api.create_context)(
+            ptr::null(),
+            1,
+            &device,
+            ptr::null(),
+            ptr::null_mut(),
+            &mut status,
+        );
+        if status != CL_SUCCESS {
+            return None;
+        }
+        let queue = (api.create_command_queue)(context, device, 0, &mut status);
+        if status != CL_SUCCESS {
+            (api.release_context)(context);
+            return None;
+        }
+        Some(OpenClDevice {
+            api,
+            name,
+            device,
+            context,
+            queue,
+            built: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// The kernel of `source`, built now if it hasn't been. `None` if it doesn't build.
+    unsafe fn kernel(&self, built: &mut Vec<Built>, source: String) -> Option<Handle> {
+        if let Some(kernel) = built.iter().find(|b| b.source == source) {
+            return Some(kernel.kernel);
+        }
+        let api = self.api;
+        let mut status = CL_SUCCESS;
+        let text = source.as_ptr().cast::<c_char>();
+        let program =
+            (api.create_program_with_source)(self.context, 1, &text, &source.len(), &mut status);
+        if status != CL_SUCCESS {
+            return None;
+        }
+        let options = CString::new(OPENCL_BUILD_OPTIONS).unwrap();
+        let status = (api.build_program)(
+            program,
+            1,
+            &self.device,
+            options.as_ptr(),
+            ptr::null(),
+            ptr::null_mut(),
+        );
+        let mut kernel_status = CL_SUCCESS;
+        let kernel = match status {
+            CL_SUCCESS => (api.create_kernel)(program, c"iterate".as_ptr(), &mut kernel_status),
+            _ => ptr::null_mut(),
+        };
+        if status != CL_SUCCESS || kernel_status != CL_SUCCESS {
+            (api.release_program)(program);
+            return None;
+        }
+        built.push(Built {
+            source,
+            program,
+            kernel,
+        });
+        Some(kernel)
+    }
+
+    /// Runs `kernel` over `frame` and reads back its orbits.
+    unsafe fn run(&self, kernel: Handle, frame: &DeviceFrame) -> Option<Vec<Orbit>> {
+        let api = self.api;
+        let keyframe = frame.keyframe;
+        let argument = FrameArgument {
+            center: [keyframe.x_center as f32, keyframe.y_center as f32],
+            size: [keyframe.x_size as f32, keyframe.y_size as f32],
+            c: [keyframe.c_x, keyframe.c_y],
+            z0: [keyframe.z0_x, keyframe.z0_y],
+            width: frame.width,
+            height: frame.height,
+            max_iter: frame.max_iter.min(u32::MAX as usize) as u32,
+            escape_norm: frame.escape_norm,
+            morph: frame.morph,
+        };
+        let mut orbits = vec![Orbit::default(); (frame.width * frame.height) as usize];
+        let bytes = std::mem::size_of_val(orbits.as_slice());
+
+        let mut status = CL_SUCCESS;
+        let buffer = (api.create_buffer)(
+            self.context,
+            CL_MEM_WRITE_ONLY,
+            bytes,
+            ptr::null_mut(),
+            &mut status,
+        );
+        if status != CL_SUCCESS {
+            return None;
+        }
+        let global = [frame.width as usize, frame.height as usize];
+        let ran = (api.set_kernel_arg)(
+            kernel,
+            0,
+            std::mem::size_of::<FrameArgument>(),
+            (&argument as *const FrameArgument).cast(),
+        ) == CL_SUCCESS
+            && (api.set_kernel_arg)(
+                kernel,
+                1,
+                std::mem::size_of::<Handle>(),
+                (&buffer as *const Handle).cast(),
+            ) == CL_SUCCESS
+            && (api.enqueue_nd_range_kernel)(
+                self.queue,
+                kernel,
+                2,
+                ptr::null(),
+                global.as_ptr(),
+                ptr::null(),
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            ) == CL_SUCCESS
+            && (api.enqueue_read_buffer)(
+                self.queue,
+                buffer,
+                CL_TRUE,
+                0,
+                bytes,
+                orbits.as_mut_ptr().cast(),
+                0,
+                ptr::null(),
+                ptr::null_mut(),
+            ) == CL_SUCCESS;
+        (api.release_mem_object)(buffer);
+        ran.then_some(orbits)
+    }
+}
+
+impl Device for OpenClDevice {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn iterate(&self, frame: &DeviceFrame) -> Option<IterationBuffer> {
+        let source = kernel_source(frame.fractal, KernelLanguage::OpenCl)?;
+        let orbits = {
+            let mut built = self.built.lock().unwrap();
+            unsafe {
+                let kernel = self.kernel(&mut built, source)?;
+                self.run(kernel, frame)?
+            }
+        };
+        let power = frame.fractal.power();
+        let results = orbits
+            .iter()
+            .map(|orbit| {
+                let z = Complex::new(orbit.z[0], orbit.z[1]);
+                let escaped = orbit.escaped != 0;
+                let iterations = orbit.iterations as usize;
+                EscapeResult::from_orbit(z, iterations, escaped, frame.escape_norm, power)
+            })
+            .collect();
+        Some(IterationBuffer {
+            width: frame.width,
+            height: frame.height,
+            results,
+        })
+    }
+}
+
+impl Drop for OpenClDevice {
+    fn drop(&mut self) {
+        let api = self.api;
+        unsafe {
+            for built in self.built.get_mut().unwrap().drain(..) {
+                (api.release_kernel)(built.kernel);
+                (api.release_program)(built.program);
+            }
+            (api.release_command_queue)(self.queue);
+            (api.release_context)(self.context);
+        }
+    }
+}
+
+/// The handles an OpenCL query returns, asking first for their count.
+unsafe fn list(query: impl Fn(u32, *mut Handle, *mut u32) -> i32) -> Vec<Handle> {
+    let mut count = 0;
+    if query(0, ptr::null_mut(), &mut count) != CL_SUCCESS || count == 0 {
+        return Vec::new();
+    }
+    let mut handles = vec![ptr::null_mut(); count as usize];
+    if query(count, handles.as_mut_ptr(), ptr::null_mut()) != CL_SUCCESS {
+        return Vec::new();
+    }
+    handles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arguments_match_the_kernel_layout() {
+        // `float2` members are 8-byte aligned in OpenCL C.
+        assert_eq!(std::mem::size_of::<FrameArgument>(), 56);
+        assert_eq!(std::mem::size_of::<Orbit>(), 16);
+    }
+}
@@ -0,0 +1,277 @@
+use crate::{Image, Pixel};
+
+/// Colors escaped points by their palette position, the smooth iteration count over the
+/// iteration limit (or after an `Exposure`) from `0.0` to `1.0`, in place of the renderer's
+/// palette. Closures from a position to a `Pixel` are color maps too.
+pub trait ColorMap: Send + Sync {
+    fn color(&self, t: f32) -> Pixel;
+}
+
+impl<F: Fn(f32) -> Pixel + Send + Sync> ColorMap for F {
+    fn color(&self, t: f32) -> Pixel {
+        self(t)
+    }
+}
+
+/// Maps a normalized intensity in `0.0..=1.0` to a color.
+#[derive(Clone, Debug)]
+pub enum Palette {
+    /// The original `(t², t, √t)` gradient.
+    Classic,
+    /// Perceptually uniform blue-green-yellow, readable under all common color vision deficiencies.
+    Viridis,
+    /// Blue-yellow palette optimized to look the same with and without red-green deficiency.
+    Cividis,
+    /// Perceptually uniform black-purple-orange-yellow, like a glowing ember.
+    Inferno,
+    /// Perceptually uniform black-purple-pink-cream.
+    Magma,
+    /// Perceptually uniform blue-magenta-yellow, without black at the low end.
+    Plasma,
+    /// Rainbow from dark blue through green to dark red, with smooth lightness but not
+    /// perceptually uniform, for telling escape times apart over the whole range.
+    Turbo,
+    /// Evenly spaced color stops, from `0.0` to `1.0`. Black all over without any.
+    Gradient(Vec<Pixel>),
+}
+
+const VIRIDIS: [u32; 10] = [
+    0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58, 0xb5de2b,
+    0xfde725,
+];
+
+const CIVIDIS: [u32; 10] = [
+    0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173, 0x8a8779, 0xa69d75, 0xc4b56c, 0xe4cf5b,
+    0xfee838,
+];
+
+const INFERNO: [u32; 10] = [
+    0x000004, 0x1b0c42, 0x4b0c6b, 0x781c6d, 0xa52c60, 0xcf4446, 0xed6925, 0xfb9a06, 0xf7d03c,
+    0xfcffa4,
+];
+
+const MAGMA: [u32; 10] = [
+    0x000004, 0x180f3e, 0x451077, 0x721f81, 0x9f2f7f, 0xcd4071, 0xf1605d, 0xfd9567, 0xfec98d,
+    0xfcfdbf,
+];
+
+const PLASMA: [u32; 10] = [
+    0x0d0887, 0x47039f, 0x7301a8, 0x9c179e, 0xbd3786, 0xd8576b, 0xed7953, 0xfa9e3b, 0xfdc926,
+    0xf0f921,
+];
+
+const TURBO: [u32; 10] = [
+    0x30123b, 0x4662d7, 0x36aaf9, 0x1ae4b6, 0x72fe5e, 0xc7ef34, 0xfaba39, 0xf66b19, 0xcb2a04,
+    0x7a0403,
+];
+
+/// The built-in palette called `name`, the name of its variant in lower case such as
+/// `"inferno"`.
+pub fn palette_from_name(name: &str) -> Option<Palette> {
+    match name {
+        "classic" => Some(Palette::Classic),
+        "viridis" => Some(Palette::Viridis),
+        "cividis" => Some(Palette::Cividis),
+        "inferno" => Some(Palette::Inferno),
+        "magma" => Some(Palette::Magma),
+        "plasma" => Some(Palette::Plasma),
+        "turbo" => Some(Palette::Turbo),
+        _ => None,
+    }
+}
+
+impl Palette {
+    pub fn color(&self, t: f32) -> Pixel {
+        match self {
+            Palette::Classic => Pixel::from_rgb(t.powi(2), t, t.sqrt()),
+            Palette::Viridis => sample_stops(&VIRIDIS, t, hex_rgb),
+            Palette::Cividis => sample_stops(&CIVIDIS, t, hex_rgb),
+            Palette::Inferno => sample_stops(&INFERNO, t, hex_rgb),
+            Palette::Magma => sample_stops(&MAGMA, t, hex_rgb),
+            Palette::Plasma => sample_stops(&PLASMA, t, hex_rgb),
+            Palette::Turbo => sample_stops(&TURBO, t, hex_rgb),
+            Palette::Gradient(stops) => sample_stops(stops, t, pixel_rgb),
+        }
+    }
+
+    /// Builds a gradient from the `colors` dominant colors of `image`, ordered dark to light.
+    pub fn from_image(image: &Image, colors: usize) -> Self {
+        let step = (image.pixels.len() / 10_000).max(1);
+        let mut samples: Vec<[f32; 3]> = image
+            .pixels
+            .iter()
+            .step_by(step)
+            .filter(|p| p.a > 0)
+            .map(|p| pixel_rgb(*p))
+            .collect();
+        if samples.is_empty() || colors == 0 {
+            return Palette::Gradient(vec![Pixel::from_rgb(0.0, 0.0, 0.0)]);
+        }
+
+        // Seed k-means with luminance quantiles so the result is deterministic.
+        samples.sort_by(|a, b| luminance(*a).total_cmp(&luminance(*b)));
+        let mut centroids: Vec<[f32; 3]> = (0..colors)
+            .map(|k| samples[(2 * k + 1) * samples.len() / (2 * colors)])
+            .collect();
+        let mut counts = vec![0; colors];
+
+        for _ in 0..16 {
+            let mut sums = vec![[0.0; 3]; colors];
+            counts = vec![0; colors];
+            for sample in &samples {
+                let nearest = (0..colors)
+                    .min_by(|&a, &b| {
+                        distance(*sample, centroids[a]).total_cmp(&distance(*sample, centroids[b]))
+                    })
+                    .unwrap();
+                for c in 0..3 {
+                    sums[nearest][c] += sample[c];
+                }
+                counts[nearest] += 1;
+            }
+            for k in 0..colors {
+                if counts[k] > 0 {
+                    centroids[k] = sums[k].map(|sum| sum / counts[k] as f32);
+                }
+            }
+        }
+
+        // Drop colors that barely occur in the image.
+        let min_count = samples.len() / (colors * 10);
+        let mut dominant: Vec<[f32; 3]> = centroids
+            .into_iter()
+            .zip(counts)
+            .filter(|(_, count)| *count > min_count)
+            .map(|(centroid, _)| centroid)
+            .collect();
+        dominant.sort_by(|a, b| luminance(*a).total_cmp(&luminance(*b)));
+
+        Palette::Gradient(
+            dominant
+                .into_iter()
+                .map(|[r, g, b]| Pixel::from_rgb(r, g, b))
+                .collect(),
+        )
+    }
+}
+
+impl ColorMap for Palette {
+    fn color(&self, t: f32) -> Pixel {
+        Palette::color(self, t)
+    }
+}
+
+fn sample_stops<T: Copy>(stops: &[T], t: f32, rgb: impl Fn(T) -> [f32; 3]) -> Pixel {
+    if stops.is_empty() {
+        return Pixel::from_rgb(0.0, 0.0, 0.0);
+    }
+    if stops.len() == 1 {
+        let [r, g, b] = rgb(stops[0]);
+        return Pixel::from_rgb(r, g, b);
+    }
+
+    let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+    let i = (position.floor() as usize).min(stops.len() - 2);
+    let f = position - i as f32;
+
+    let a = rgb(stops[i]);
+    let b = rgb(stops[i + 1]);
+    let lerp = |c: usize| a[c] + (b[c] - a[c]) * f;
+    Pixel::from_rgb(lerp(0), lerp(1), lerp(2))
+}
+
+fn hex_rgb(color: u32) -> [f32; 3] {
+    [16, 8, 0].map(|shift| ((color >> shift) & 0xff) as f32 / 255.0)
+}
+
+fn pixel_rgb(pixel: Pixel) -> [f32; 3] {
+    [pixel.r, pixel.g, pixel.b].map(|c| c as f32 / 255.0)
+}
+
+fn luminance([r, g, b]: [f32; 3]) -> f32 {
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/// The pixels left transparent, so the frames can be laid over a web page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transparency {
+    /// Everything around the set, leaving the set as a silhouette.
+    Exterior,
+    /// The set itself, leaving a hole in the bands around it.
+    Interior,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ColorVisionDeficiency {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl ColorVisionDeficiency {
+    /// Machado, Oliveira & Fernandes (2009) simulation matrices at full severity, in linear RGB.
+    fn matrix(&self) -> [[f32; 3]; 3] {
+        match self {
+            ColorVisionDeficiency::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorVisionDeficiency::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorVisionDeficiency::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+impl Pixel {
+    /// Returns how this color appears to a viewer with the given color vision deficiency.
+    pub fn simulate(&self, deficiency: ColorVisionDeficiency) -> Self {
+        let rgb = [self.r, self.g, self.b].map(|c| to_linear(c as f32 / 255.0));
+        let m = deficiency.matrix();
+        let mix = |row: [f32; 3]| to_srgb(row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+
+        let mut pixel = Pixel::from_rgb(mix(m[0]), mix(m[1]), mix(m[2]));
+        pixel.a = self.a;
+        pixel
+    }
+}
+
+fn to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_gradient_is_black() {
+        let black = Pixel::from_rgb(0.0, 0.0, 0.0);
+        assert_eq!(Palette::Gradient(Vec::new()).color(0.5), black);
+    }
+}
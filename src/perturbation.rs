@@ -0,0 +1,344 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{BigFixed, Complex, EscapeResult};
+
+/// Reference orbits a `ReferenceCache` keeps. Frames render in parallel, so enough for the
+/// frames in flight to find each other's.
+const CACHED_REFERENCES: usize = 16;
+
+/// An orbit with the point of the plane it was iterated for.
+type Entry = ((BigFixed, BigFixed), Arc<ReferenceOrbit>);
+
+/// Pixels whose orbit comes within this fraction of the squared size of the reference orbit's
+/// from 0 are glitched: their delta has grown as large as the orbit and lost its precision.
+const GLITCH_TOLERANCE: f64 = 1e-6;
+
+/// Largest error of a `SeriesApproximation`, relative to its linear terms, at which it is still
+/// trusted.
+const SERIES_TOLERANCE: f64 = 1e-14;
+
+/// Exponents `(i, j)` of the terms `dz0^i dc^j` of a `SeriesApproximation`, by degree.
+const TERMS: [(usize, usize); 9] = [
+    (1, 0),
+    (0, 1),
+    (2, 0),
+    (1, 1),
+    (0, 2),
+    (3, 0),
+    (2, 1),
+    (1, 2),
+    (0, 3),
+];
+
+fn mul((ax, ay): (f64, f64), (bx, by): (f64, f64)) -> (f64, f64) {
+    (ax * bx - ay * by, ax * by + ay * bx)
+}
+
+fn add((ax, ay): (f64, f64), (bx, by): (f64, f64)) -> (f64, f64) {
+    (ax + bx, ay + by)
+}
+
+/// Orbit of `z^2 + c` for one point of a frame, iterated in fixed point and rounded to f64.
+/// Nearby pixels are iterated as f64 deltas from it, which hold their precision however deep
+/// the zoom is, as long as the deltas stay small next to the orbit.
+#[derive(Clone, Debug)]
+pub struct ReferenceOrbit {
+    /// `Z_0` to the first value past the escape radius, or to `Z_max_iter`.
+    orbit: Vec<(f64, f64)>,
+    c: (BigFixed, BigFixed),
+    z0: (BigFixed, BigFixed),
+    /// Last value of `orbit` before rounding, to extend it from.
+    z: (BigFixed, BigFixed),
+}
+
+impl ReferenceOrbit {
+    pub fn new(
+        c: (BigFixed, BigFixed),
+        z0: (BigFixed, BigFixed),
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> Self {
+        let mut orbit = Self {
+            orbit: vec![(z0.0.to_f64(), z0.1.to_f64())],
+            c,
+            z0: z0.clone(),
+            z: z0,
+        };
+        orbit.extend(max_iter, escape_norm);
+        orbit
+    }
+
+    /// `c` and `z0` the orbit was iterated from.
+    pub fn start(&self) -> (&(BigFixed, BigFixed), &(BigFixed, BigFixed)) {
+        (&self.c, &self.z0)
+    }
+
+    /// Continues the orbit up to `max_iter` iterations, unless it has already escaped.
+    pub fn extend(&mut self, max_iter: usize, escape_norm: f32) {
+        while self.orbit.len() <= max_iter {
+            let (x, y) = &self.z;
+            let (xx, yy) = (x * x, y * y);
+            if xx.to_f64() + yy.to_f64() >= escape_norm as f64 {
+                break;
+            }
+            let y = &(x * y).double() + &self.c.1;
+            let x = &(&xx - &yy) + &self.c.0;
+            self.orbit.push((x.to_f64(), y.to_f64()));
+            self.z = (x, y);
+        }
+    }
+
+    /// `orbit` extended to `max_iter`, only copying it if it falls short.
+    pub fn extended(orbit: &Arc<Self>, max_iter: usize, escape_norm: f32) -> Arc<Self> {
+        let mut extended = Arc::clone(orbit);
+        if orbit.orbit.len() <= max_iter {
+            Arc::make_mut(&mut extended).extend(max_iter, escape_norm);
+        }
+        extended
+    }
+
+    /// Iterates the point `dc` from the orbit's `c`, starting `dz0` from its `z0`. `None` if it
+    /// glitched or outlived the orbit, and needs an orbit closer to its own.
+    pub fn iterate(
+        &self,
+        dc: (f64, f64),
+        dz0: (f64, f64),
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> Option<EscapeResult> {
+        self.iterate_from(dc, dz0, 0, max_iter, escape_norm)
+    }
+
+    /// `iterate`, starting from where `series` leaves the pixel.
+    pub fn iterate_series(
+        &self,
+        series: &SeriesApproximation,
+        dc: (f64, f64),
+        dz0: (f64, f64),
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> Option<EscapeResult> {
+        let dz = series.evaluate(dz0, dc);
+        self.iterate_from(dc, dz, series.skip, max_iter, escape_norm)
+    }
+
+    /// Continues from delta `dz` at iteration `iters`.
+    fn iterate_from(
+        &self,
+        (dcx, dcy): (f64, f64),
+        (mut dx, mut dy): (f64, f64),
+        mut iters: usize,
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> Option<EscapeResult> {
+        let escaped = |(x, y): (f64, f64), iters| {
+            let z = Complex::new(x as f32, y as f32);
+            Some(EscapeResult::from_orbit(
+                z,
+                iters,
+                iters < max_iter,
+                escape_norm,
+                2.0,
+            ))
+        };
+
+        while iters < max_iter {
+            let (zx, zy) = self.orbit[iters];
+            let (x, y) = (zx + dx, zy + dy);
+            let norm = x * x + y * y;
+            if norm >= escape_norm as f64 {
+                return escaped((x, y), iters);
+            }
+            // Pauldelbrot's criterion.
+            if norm < GLITCH_TOLERANCE * (zx * zx + zy * zy) {
+                return None;
+            }
+            // Past the end of an escaped orbit the pixel has no reference left, and would only
+            // be iterated as precisely as f64 holds it.
+            if iters + 1 == self.orbit.len() {
+                return None;
+            }
+            (dx, dy) = (
+                2.0 * (zx * dx - zy * dy) + dx * dx - dy * dy + dcx,
+                2.0 * (zx * dy + zy * dx + dx * dy) + dcy,
+            );
+            iters += 1;
+        }
+        let (zx, zy) = self.orbit[iters.min(self.orbit.len() - 1)];
+        escaped((zx + dx, zy + dy), iters)
+    }
+}
+
+/// The last few reference orbits of a renderer, for later frames to reuse while their point is
+/// still in view. A zoom keeps the same point in view for many frames, and its orbit only needs
+/// extending when the iteration limit grows.
+#[derive(Debug, Default)]
+pub struct ReferenceCache {
+    /// Most recent last.
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl ReferenceCache {
+    /// The most recent orbit that is `usable` for its point.
+    pub fn find(
+        &self,
+        usable: impl Fn(&(BigFixed, BigFixed), &ReferenceOrbit) -> bool,
+    ) -> Option<Entry> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .rev()
+            .find(|(point, orbit)| usable(point, orbit))
+            .cloned()
+    }
+
+    pub fn insert(&self, point: (BigFixed, BigFixed), orbit: Arc<ReferenceOrbit>) {
+        let mut entries = self.entries.lock().unwrap();
+        // Replaces the shorter orbit an extended one was copied from.
+        entries.retain(|(cached_point, cached)| {
+            *cached_point != point || cached.start() != orbit.start()
+        });
+        if entries.len() == CACHED_REFERENCES {
+            entries.remove(0);
+        }
+        entries.push((point, orbit));
+    }
+}
+
+/// The delta of a pixel from a `ReferenceOrbit` after its first `skip` iterations, as a
+/// polynomial in the pixel's `dz0` and `dc` up to the third degree. The coefficients are
+/// iterated once per frame, and as far as they stay accurate for every pixel within its radius
+/// the pixels can start right at `skip`.
+#[derive(Clone, Debug)]
+pub struct SeriesApproximation {
+    /// Coefficients of the `TERMS`.
+    coefficients: [(f64, f64); 9],
+    pub skip: usize,
+}
+
+impl SeriesApproximation {
+    /// Iterates the coefficients along `orbit` for as long as their error stays negligible next
+    /// to the linear terms and no pixel can have escaped, for pixels up to `radius` away, as
+    /// `(|dz0|, |dc|)`.
+    pub fn new(
+        orbit: &ReferenceOrbit,
+        radius: (f64, f64),
+        max_iter: usize,
+        escape_norm: f32,
+    ) -> Self {
+        let escape_radius = (escape_norm as f64).sqrt();
+        // `dz0` itself before any iteration.
+        let mut series = Self {
+            coefficients: [(0.0, 0.0); 9],
+            skip: 0,
+        };
+        series.coefficients[0] = (1.0, 0.0);
+        // Bound on the difference between any pixel's delta and the series.
+        let mut error = 0.0;
+        let last = (orbit.orbit.len() - 1).min(max_iter);
+
+        while series.skip < last {
+            let (zx, zy) = orbit.orbit[series.skip];
+            let delta = series.bound(0..9, radius) + error;
+            if zx.hypot(zy) + delta >= escape_radius {
+                break;
+            }
+            let (next, dropped) = series.step((zx, zy), radius);
+            error = 2.0 * (zx.hypot(zy) + delta) * error + error * error + dropped;
+            // Also false once the coefficients overflow.
+            let accurate = error <= SERIES_TOLERANCE * next.bound(0..2, radius);
+            if !accurate {
+                break;
+            }
+            series = next;
+        }
+        series
+    }
+
+    /// Largest size of the `terms` for pixels up to `radius` away.
+    fn bound(&self, terms: std::ops::Range<usize>, (u, v): (f64, f64)) -> f64 {
+        terms
+            .map(|term| {
+                let (x, y) = self.coefficients[term];
+                let (i, j) = TERMS[term];
+                x.hypot(y) * u.powi(i as i32) * v.powi(j as i32)
+            })
+            .sum()
+    }
+
+    /// The series one iteration past `z`, by `dz' = 2 z dz + dz^2 + dc`, and the largest size
+    /// of the terms above the third degree that it drops.
+    fn step(&self, (zx, zy): (f64, f64), radius: (f64, f64)) -> (Self, f64) {
+        let mut next = self.coefficients.map(|a| mul((2.0 * zx, 2.0 * zy), a));
+        next[1] = add(next[1], (1.0, 0.0));
+        let mut dropped = 0.0;
+        for (p, &(pi, pj)) in TERMS.iter().enumerate() {
+            for (q, &(qi, qj)) in TERMS.iter().enumerate() {
+                let exponents = (pi + qi, pj + qj);
+                match TERMS.iter().position(|&term| term == exponents) {
+                    Some(term) => {
+                        next[term] =
+                            add(next[term], mul(self.coefficients[p], self.coefficients[q]))
+                    }
+                    None => dropped += self.bound(p..p + 1, radius) * self.bound(q..q + 1, radius),
+                }
+            }
+        }
+        let next = Self {
+            coefficients: next,
+            skip: self.skip + 1,
+        };
+        (next, dropped)
+    }
+
+    pub fn evaluate(&self, dz0: (f64, f64), dc: (f64, f64)) -> (f64, f64) {
+        let powers = |base| {
+            let square = mul(base, base);
+            [(1.0, 0.0), base, square, mul(square, base)]
+        };
+        let (u, v) = (powers(dz0), powers(dc));
+        TERMS
+            .iter()
+            .zip(self.coefficients)
+            .fold((0.0, 0.0), |sum, (&(i, j), a)| {
+                add(sum, mul(a, mul(u[i], v[j])))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big((x, y): (f64, f64)) -> (BigFixed, BigFixed) {
+        (BigFixed::from_f64(x, 128), BigFixed::from_f64(y, 128))
+    }
+
+    #[test]
+    fn deltas_follow_the_orbit() {
+        let orbit = ReferenceOrbit::new(big((-0.75, 0.1)), big((0.0, 0.0)), 500, 4.0);
+        let near = orbit.iterate((1e-9, 0.0), (0.0, 0.0), 500, 4.0).unwrap();
+        let (cx, cy) = (-0.75 + 1e-9, 0.1);
+        let (mut x, mut y, mut iterations) = (0.0f64, 0.0f64, 0);
+        while x * x + y * y < 4.0 && iterations < 500 {
+            (x, y) = (x * x - y * y + cx, 2.0 * x * y + cy);
+            iterations += 1;
+        }
+        assert_eq!(
+            (near.escaped, near.iterations),
+            (iterations < 500, iterations)
+        );
+    }
+
+    #[test]
+    fn pixels_outliving_the_orbit_need_another() {
+        // The reference escapes after a few iterations, the pixel stays in the set.
+        let orbit = ReferenceOrbit::new(big((0.3, 0.0)), big((0.0, 0.0)), 100, 4.0);
+        assert!(orbit.orbit.len() < 20);
+        assert!(orbit.iterate((-0.6, 0.0), (0.0, 0.0), 100, 4.0).is_none());
+        // Its own orbit iterates it to the end.
+        let own = ReferenceOrbit::new(big((-0.3, 0.0)), big((0.0, 0.0)), 100, 4.0);
+        let result = own.iterate((0.0, 0.0), (0.0, 0.0), 100, 4.0).unwrap();
+        assert!(!result.escaped);
+    }
+}
@@ -0,0 +1,606 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{ImageError, IterationBuffer};
+
+/// Bytes every PNG starts with.
+pub(crate) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Longest and farthest back match of the deflate format.
+const MAX_MATCH: usize = 258;
+const WINDOW: usize = 1 << 15;
+/// Earlier positions with the same hash tried per byte, trading speed for compression.
+const MAX_CHAIN: usize = 32;
+
+/// Order in which the lengths of the code length code are read.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+fn crc32(bytes: impl IntoIterator<Item = u8>) -> u32 {
+    let crc = bytes.into_iter().fold(u32::MAX, |crc, byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
+/// Writes a chunk of type `kind`, with its length and checksum.
+pub(crate) fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data).copied());
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Writes the smooth iteration counts of a frame as a 16 bit grayscale PNG, from 0 up to
+/// `max_iter` iterations in full white, which the set itself is as well. The steps are fine
+/// enough to recolor the frame, or to use it as a heightfield, without visible bands.
+pub fn write_iteration_png(
+    buffer: &IterationBuffer,
+    max_iter: usize,
+    path: impl AsRef<Path>,
+) -> Result<(), ImageError> {
+    let file = File::create(path).map_err(|_| ImageError::FileCreateError)?;
+    let raw: Vec<u8> = buffer
+        .results
+        .iter()
+        .flat_map(|result| {
+            let level = match result.escaped {
+                true => (result.smooth / max_iter as f32).clamp(0.0, 1.0),
+                false => 1.0,
+            };
+            ((level * u16::MAX as f32).round() as u16).to_be_bytes()
+        })
+        .collect();
+    let size = (buffer.width, buffer.height);
+    write_png(&mut BufWriter::new(file), size, 16, 0, &raw, 2).map_err(|_| ImageError::EncodeError)
+}
+
+/// Writes a still PNG of `raw` rows, with the sample format of `header`.
+pub(crate) fn write_png(
+    writer: &mut impl Write,
+    (width, height): (u32, u32),
+    bit_depth: u8,
+    color_type: u8,
+    raw: &[u8],
+    pixel_size: usize,
+) -> io::Result<()> {
+    writer.write_all(&SIGNATURE)?;
+    write_chunk(
+        writer,
+        b"IHDR",
+        &header(width, height, bit_depth, color_type),
+    )?;
+    let stride = raw.len() / height.max(1) as usize;
+    write_chunk(writer, b"IDAT", &image_data(raw, stride, pixel_size))?;
+    write_chunk(writer, b"IEND", &[])?;
+    writer.flush()
+}
+
+/// The IHDR data of a `width` by `height` image of `bit_depth` bit samples, with PNG's
+/// `color_type` (0 for grayscale, 2 for RGB, 6 for RGBA).
+pub(crate) fn header(width: u32, height: u32, bit_depth: u8, color_type: u8) -> Vec<u8> {
+    let mut data = Vec::with_capacity(13);
+    data.extend(width.to_be_bytes());
+    data.extend(height.to_be_bytes());
+    // Deflate compression, adaptive filtering, no interlacing.
+    data.extend([bit_depth, color_type, 0, 0, 0]);
+    data
+}
+
+/// Compressed image data of `rows` of `stride` bytes with `pixel_size` bytes per pixel, each row
+/// filtered with whichever of PNG's filters leaves the smallest differences, as zlib.
+pub(crate) fn image_data(raw: &[u8], stride: usize, pixel_size: usize) -> Vec<u8> {
+    let mut filtered = Vec::with_capacity(raw.len() + raw.len() / stride.max(1));
+    let zeros = vec![0; stride];
+    let mut previous: &[u8] = &zeros;
+    for row in raw.chunks(stride) {
+        let candidates = (0..5u8).map(|filter| {
+            let line: Vec<u8> = (0..row.len())
+                .map(|i| {
+                    let left = if i >= pixel_size {
+                        row[i - pixel_size]
+                    } else {
+                        0
+                    };
+                    let up = previous[i];
+                    let up_left = if i >= pixel_size {
+                        previous[i - pixel_size]
+                    } else {
+                        0
+                    };
+                    let predicted = match filter {
+                        0 => 0,
+                        1 => left,
+                        2 => up,
+                        3 => ((left as u16 + up as u16) / 2) as u8,
+                        _ => paeth(left, up, up_left),
+                    };
+                    row[i].wrapping_sub(predicted)
+                })
+                .collect();
+            (filter, line)
+        });
+        // Smallest sum of the differences as signed bytes, the usual heuristic.
+        let cost =
+            |line: &[u8]| -> u64 { line.iter().map(|&b| (b as i8).unsigned_abs() as u64).sum() };
+        let (filter, line) = candidates.min_by_key(|(_, line)| cost(line)).unwrap();
+        filtered.push(filter);
+        filtered.extend(line);
+        previous = row;
+    }
+    zlib(&filtered)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// `data` in the zlib format, compressed with a single block of deflate's fixed Huffman codes.
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    // Final block, fixed codes.
+    bits.write(0b011, 3);
+
+    let hash = |i: usize| {
+        let key = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+        (key.wrapping_mul(0x9e37_79b1) >> 17) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let insert = |i: usize, head: &mut [usize], prev: &mut [usize]| {
+        if i + 3 <= data.len() {
+            let h = hash(i);
+            prev[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let mut best = (0, 0);
+        if i + 3 <= data.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let limit = MAX_MATCH.min(data.len() - i);
+                let length = (0..limit)
+                    .take_while(|&k| data[candidate + k] == data[i + k])
+                    .count();
+                if length > best.0 {
+                    best = (length, i - candidate);
+                }
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        if best.0 >= 3 {
+            bits.length(best.0);
+            bits.distance(best.1);
+            for k in i..i + best.0 {
+                insert(k, &mut head, &mut prev);
+            }
+            i += best.0;
+        } else {
+            bits.literal(data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    bits.literal(256);
+
+    // Deflate at the fastest level, no dictionary.
+    let mut out = vec![0x78, 0x01];
+    out.extend(bits.finish());
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        (a, b) = (a % 65521, b % 65521);
+    }
+    b << 16 | a
+}
+
+/// Deflate's bit stream, which lossless WebP uses as well. It packs values from the least
+/// significant bit of each byte but Huffman codes from their most significant bit.
+#[derive(Default)]
+pub(crate) struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    pub(crate) fn write(&mut self, value: u32, bits: u32) {
+        self.buffer |= (value as u64) << self.count;
+        self.count += bits;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    pub(crate) fn code(&mut self, code: u32, bits: u32) {
+        self.write(code.reverse_bits() >> (32 - bits), bits);
+    }
+
+    /// A literal byte, or 256 for the end of the block, or a length code from 257.
+    fn literal(&mut self, symbol: u16) {
+        match symbol {
+            0..=143 => self.code(0b0011_0000 + symbol as u32, 8),
+            144..=255 => self.code(0b1_1001_0000 + (symbol - 144) as u32, 9),
+            256..=279 => self.code((symbol - 256) as u32, 7),
+            _ => self.code(0b1100_0000 + (symbol - 280) as u32, 8),
+        }
+    }
+
+    fn length(&mut self, length: usize) {
+        if length == MAX_MATCH {
+            return self.literal(285);
+        }
+        let (symbol, extra, base) = match length {
+            3..=10 => (257 + length - 3, 0, length),
+            _ => {
+                // Lengths from 11 come in groups of four codes, each with one more extra bit.
+                let extra = (usize::BITS - (length - 3).leading_zeros() - 3) as usize;
+                let offset = (length - 3) >> extra;
+                let symbol = 257 + 4 * extra + offset;
+                (symbol, extra, ((offset) << extra) + 3)
+            }
+        };
+        self.literal(symbol as u16);
+        self.write((length - base) as u32, extra as u32);
+    }
+
+    fn distance(&mut self, distance: usize) {
+        let (symbol, extra, base) = match distance {
+            1..=4 => (distance - 1, 0, distance),
+            _ => {
+                // As for lengths, but in groups of two.
+                let extra = (usize::BITS - (distance - 1).leading_zeros() - 2) as usize;
+                let offset = (distance - 1) >> extra;
+                (2 * extra + offset, extra, (offset << extra) + 1)
+            }
+        };
+        self.code(symbol as u32, 5);
+        self.write((distance - base) as u32, extra as u32);
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Reads deflate's bit stream, least significant bit of each byte first, which lossless WebP
+/// shares.
+pub(crate) struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub(crate) fn read(&mut self, bits: u32) -> Option<u32> {
+        let mut value = 0;
+        for i in 0..bits {
+            let byte = self.bytes.get(self.position / 8)?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << i;
+            self.position += 1;
+        }
+        Some(value)
+    }
+
+    /// Skips to the next byte boundary and takes `count` bytes from there.
+    fn take_bytes(&mut self, count: usize) -> Option<&[u8]> {
+        let start = self.position.div_ceil(8);
+        let bytes = self.bytes.get(start..start + count)?;
+        self.position = (start + count) * 8;
+        Some(bytes)
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of every length and the symbols in order
+/// of their codes.
+pub(crate) struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    /// The code with the given length for each symbol, 0 for unused ones.
+    pub(crate) fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|&symbol| lengths[symbol as usize] > 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+        Self { counts, symbols }
+    }
+
+    /// Codes are read a bit at a time, from their most significant bit, until they fall among
+    /// the codes of their length.
+    pub(crate) fn decode(&self, bits: &mut BitReader) -> Option<u16> {
+        let (mut code, mut first, mut index) = (0, 0, 0);
+        for &count in &self.counts[1..] {
+            code |= bits.read(1)? as usize;
+            if code < first + count as usize {
+                return self.symbols.get(index + code - first).copied();
+            }
+            index += count as usize;
+            first = (first + count as usize) << 1;
+            code <<= 1;
+        }
+        None
+    }
+}
+
+/// The data compressed in the zlib stream `data`, `None` if it isn't valid deflate.
+pub(crate) fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    // A preset dictionary is never used for PNG image data.
+    if data.len() < 2 || data[0] & 0x0f != 8 || data[1] & 0x20 != 0 {
+        return None;
+    }
+    let mut bits = BitReader::new(&data[2..]);
+    let mut out = Vec::new();
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => {
+                let header = bits.take_bytes(4)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                out.extend(bits.take_bytes(length as usize)?);
+            }
+            1 => {
+                let lengths: Vec<u8> = (0..288)
+                    .map(|symbol| match symbol {
+                        0..=143 => 8,
+                        144..=255 => 9,
+                        256..=279 => 7,
+                        _ => 8,
+                    })
+                    .collect();
+                inflate_block(
+                    &mut bits,
+                    &mut out,
+                    &Huffman::new(&lengths),
+                    &Huffman::new(&[5; 30]),
+                )?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut out, &literals, &distances)?;
+            }
+            _ => return None,
+        }
+        if last {
+            return Some(out);
+        }
+    }
+}
+
+/// The literal and distance codes at the start of a block with dynamic codes, which are given
+/// as code lengths in a code of their own.
+fn dynamic_codes(bits: &mut BitReader) -> Option<(Huffman, Huffman)> {
+    let literals = bits.read(5)? as usize + 257;
+    let distances = bits.read(5)? as usize + 1;
+    let code_lengths = bits.read(4)? as usize + 4;
+    let mut lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[symbol] = bits.read(3)? as u8;
+    }
+    let code = Huffman::new(&lengths);
+
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        // 16 repeats the last length, 17 and 18 repeat zeros.
+        let (length, repeat) = match code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last()?, bits.read(2)? + 3),
+            17 => (0, bits.read(3)? + 3),
+            _ => (0, bits.read(7)? + 11),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() != literals + distances {
+        return None;
+    }
+    let (literals, distances) = lengths.split_at(literals);
+    Some((Huffman::new(literals), Huffman::new(distances)))
+}
+
+/// Decodes literals and matches into `out` up to the end of the block.
+fn inflate_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Option<()> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        let length = match symbol {
+            0..=255 => {
+                out.push(symbol as u8);
+                continue;
+            }
+            256 => return Some(()),
+            257..=264 => symbol - 254,
+            265..=284 => {
+                // The inverse of `BitWriter::length`.
+                let extra = (symbol - 257) / 4 - 1;
+                let base = (((symbol - 257) % 4 + 4) << extra) + 3;
+                base + bits.read(extra as u32)? as usize
+            }
+            285 => MAX_MATCH,
+            _ => return None,
+        };
+        let symbol = distances.decode(bits)? as usize;
+        let distance = match symbol {
+            0..=3 => symbol + 1,
+            4..=29 => {
+                let extra = symbol / 2 - 1;
+                let base = ((symbol % 2 + 2) << extra) + 1;
+                base + bits.read(extra as u32)? as usize
+            }
+            _ => return None,
+        };
+        let start = out.len().checked_sub(distance)?;
+        // Matches may overlap the bytes they produce.
+        for i in start..start + length {
+            out.push(out[i]);
+        }
+    }
+}
+
+/// Reverses the filters of `filtered` rows, each a filter type byte and `stride` bytes, with
+/// `pixel_size` bytes per pixel (at least one) for the filters that look to the left.
+pub(crate) fn unfilter(filtered: &[u8], stride: usize, pixel_size: usize) -> Option<Vec<u8>> {
+    let mut raw: Vec<u8> = Vec::with_capacity(filtered.len());
+    for (y, line) in filtered.chunks(stride + 1).enumerate() {
+        if line.len() != stride + 1 {
+            return None;
+        }
+        let row = y * stride;
+        for i in 0..stride {
+            let left = match i >= pixel_size {
+                true => raw[row + i - pixel_size],
+                false => 0,
+            };
+            let up = match y > 0 {
+                true => raw[row + i - stride],
+                false => 0,
+            };
+            let up_left = match y > 0 && i >= pixel_size {
+                true => raw[row + i - stride - pixel_size],
+                false => 0,
+            };
+            let predicted = match line[0] {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return None,
+            };
+            raw.push(line[i + 1].wrapping_add(predicted));
+        }
+    }
+    Some(raw)
+}
+
+/// The chunks of a PNG after its signature, as their type and data, up to IEND. Checksums are
+/// not verified.
+pub(crate) fn read_chunks(reader: &mut impl Read) -> io::Result<Vec<([u8; 4], Vec<u8>)>> {
+    let mut signature = [0; 8];
+    reader.read_exact(&mut signature)?;
+    if signature != SIGNATURE {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    let mut chunks = Vec::new();
+    loop {
+        let mut header = [0; 8];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let kind = [header[4], header[5], header[6], header[7]];
+        let mut data = vec![0; length as usize + 4];
+        reader.read_exact(&mut data)?;
+        data.truncate(length as usize);
+        if &kind == b"IEND" {
+            return Ok(chunks);
+        }
+        chunks.push((kind, data));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inflate_reads_back_zlib() {
+        let data: Vec<u8> = (0..2000u32).map(|i| (i * i / 7 % 13) as u8).collect();
+        assert_eq!(inflate(&zlib(&data)).as_deref(), Some(&data[..]));
+    }
+
+    #[test]
+    fn inflate_reads_stored_and_dynamic_blocks() {
+        let stored = [
+            0x78, 0x01, 0x01, 0x03, 0x00, 0xfc, 0xff, b'z', b'o', b'o', 0, 0, 0, 0,
+        ];
+        assert_eq!(inflate(&stored).as_deref(), Some(&b"zoo"[..]));
+        // "the mandelbrot set " twice, with Huffman codes of its own.
+        let dynamic = [
+            0x78, 0x01, 0x05, 0xc1, 0xc9, 0x09, 0x00, 0x40, 0x08, 0x03, 0xc0, 0x56, 0xd2, 0x9a,
+            0x8b, 0x01, 0x1f, 0x1e, 0xa0, 0xe9, 0x9f, 0x9d, 0x51, 0x10, 0x65, 0xed, 0xcc, 0xb7,
+            0x23, 0x1c, 0x05, 0x05, 0x51, 0xd6, 0xce, 0x7c, 0x3b, 0xc2, 0x51, 0xf8, 0x17, 0xde,
+            0x0e, 0x2b,
+        ];
+        let expected = b"the mandelbrot set the mandelbrot set ";
+        assert_eq!(inflate(&dynamic).as_deref(), Some(&expected[..]));
+    }
+
+    #[test]
+    fn unfilter_reverses_image_data() {
+        let raw: Vec<u8> = (0..3 * 7 * 5).map(|i| (i * 37 % 256) as u8).collect();
+        let filtered = inflate(&image_data(&raw, 3 * 7, 3)).unwrap();
+        assert_eq!(unfilter(&filtered, 3 * 7, 3), Some(raw));
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use crate::Keyframe;
+
+/// Bits below a pixel's size that `Precision::Auto` keeps, for orbits to tell them apart.
+const AUTO_MARGIN: i32 = 8;
+
+/// Mantissa bits of a `DoubleDouble`, those of its two f64s.
+#[cfg(feature = "bigfloat")]
+const DOUBLE_DOUBLE_DIGITS: i32 = 2 * f64::MANTISSA_DIGITS as i32;
+
+/// Floating point type pixel coordinates and orbits are computed in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Precision {
+    /// f32, the fastest. Neighboring pixels merge once the view is below about a millionth of
+    /// its distance from 0.
+    #[default]
+    Single,
+    /// f64, good for views down to about 1e-14 of their distance from 0, at roughly half the
+    /// speed.
+    Double,
+    /// `DoubleDouble`, good for views down to about 1e-28 of their distance from 0 at a fifth of
+    /// the speed of f64. Only the Mandelbrot set is iterated this way, other formulas fall back
+    /// to f64.
+    DoubleDouble,
+    /// Fixed point with `fraction_bits` below the point, rounded up to whole 32-bit limbs, for
+    /// views as small as 2^-fraction_bits times a few hundred pixels. Only the Mandelbrot set is
+    /// iterated this way, other formulas fall back to f64. Some hundred times slower than f64.
+    #[cfg(feature = "bigfloat")]
+    Arbitrary { fraction_bits: u32 },
+    /// Iterates only the view center with `Arbitrary` precision, and every pixel in f64 as its
+    /// difference from the center's orbit, which stays precise at any depth. Nearly as fast as
+    /// f64, and faster on deep zooms where a series approximation skips the first iterations of
+    /// all pixels at once, but pixels that stray far from the center's orbit can come out wrong.
+    #[cfg(feature = "bigfloat")]
+    Perturbation { fraction_bits: u32 },
+    /// Picks one of the others per frame, the fastest that resolves its pixels, so one
+    /// animation can zoom through all of them. Past f64 that is `DoubleDouble`, and past that
+    /// `Perturbation` with the bits the pixels need, or still `DoubleDouble` without the
+    /// `bigfloat` feature.
+    Auto,
+}
+
+impl Precision {
+    /// The precision `Auto` picks for `keyframe` rendered `width` pixels wide, or `self` for
+    /// the others.
+    pub fn resolve(self, keyframe: &Keyframe, width: u32) -> Self {
+        if self != Precision::Auto {
+            return self;
+        }
+        let distance = keyframe
+            .x_center
+            .abs()
+            .max(keyframe.y_center.abs())
+            .max(keyframe.x_size);
+        // Exponent of a pixel's size relative to the distance from 0.
+        let pixel = (keyframe.x_size / width as f64 / distance).log2();
+        if pixel > (AUTO_MARGIN - f32::MANTISSA_DIGITS as i32) as f64 {
+            return Precision::Single;
+        }
+        if pixel > (AUTO_MARGIN - f64::MANTISSA_DIGITS as i32) as f64 {
+            return Precision::Double;
+        }
+        #[cfg(feature = "bigfloat")]
+        if pixel <= (AUTO_MARGIN - DOUBLE_DOUBLE_DIGITS) as f64 {
+            return Precision::Perturbation {
+                fraction_bits: fraction_bits(keyframe, width),
+            };
+        }
+        Precision::DoubleDouble
+    }
+
+    /// The next tier up from `self`, to check frames rendered in `self` against: f64 after f32,
+    /// `DoubleDouble` after f64, then `Arbitrary` with the bits `keyframe` rendered `width`
+    /// pixels wide needs, or 32 more than `self` has. `None` for `Auto`, which is to be resolved
+    /// first, and past `DoubleDouble` without the `bigfloat` feature.
+    pub fn finer(self, keyframe: &Keyframe, width: u32) -> Option<Self> {
+        // Only the fixed point tiers depend on the view.
+        #[cfg(not(feature = "bigfloat"))]
+        let _ = (keyframe, width);
+        match self {
+            Precision::Single => Some(Precision::Double),
+            Precision::Double => Some(Precision::DoubleDouble),
+            #[cfg(feature = "bigfloat")]
+            Precision::DoubleDouble => Some(Precision::Arbitrary {
+                fraction_bits: fraction_bits(keyframe, width),
+            }),
+            #[cfg(feature = "bigfloat")]
+            Precision::Perturbation { fraction_bits } => {
+                Some(Precision::Arbitrary { fraction_bits })
+            }
+            #[cfg(feature = "bigfloat")]
+            Precision::Arbitrary { fraction_bits } => Some(Precision::Arbitrary {
+                fraction_bits: fraction_bits + 32,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Parses the form written by `Display`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "Single" => Some(Precision::Single),
+            "Double" => Some(Precision::Double),
+            "DoubleDouble" => Some(Precision::DoubleDouble),
+            "Auto" => Some(Precision::Auto),
+            #[cfg(feature = "bigfloat")]
+            _ => {
+                let (kind, fraction_bits) = name.split_once(' ')?;
+                let fraction_bits = fraction_bits.parse().ok()?;
+                match kind {
+                    "Arbitrary" => Some(Precision::Arbitrary { fraction_bits }),
+                    "Perturbation" => Some(Precision::Perturbation { fraction_bits }),
+                    _ => None,
+                }
+            }
+            #[cfg(not(feature = "bigfloat"))]
+            _ => None,
+        }
+    }
+}
+
+/// Fixed point bits that resolve the pixels of `keyframe` rendered `width` pixels wide, in whole
+/// 32-bit limbs.
+#[cfg(feature = "bigfloat")]
+fn fraction_bits(keyframe: &Keyframe, width: u32) -> u32 {
+    let bits = -(keyframe.x_size / width as f64).log2().floor() as u32 + AUTO_MARGIN as u32;
+    bits.div_ceil(32) * 32
+}
+
+/// `Single`, `Double`, `DoubleDouble`, `Auto`, or `Arbitrary` or `Perturbation` followed by the
+/// fraction bits.
+impl fmt::Display for Precision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Precision::Single => write!(f, "Single"),
+            Precision::Double => write!(f, "Double"),
+            Precision::DoubleDouble => write!(f, "DoubleDouble"),
+            Precision::Auto => write!(f, "Auto"),
+            #[cfg(feature = "bigfloat")]
+            Precision::Arbitrary { fraction_bits } => write!(f, "Arbitrary {fraction_bits}"),
+            #[cfg(feature = "bigfloat")]
+            Precision::Perturbation { fraction_bits } => {
+                write!(f, "Perturbation {fraction_bits}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn auto(x_size: f64) -> Precision {
+        Precision::Auto.resolve(&Keyframe::view(-0.75, 0.1, x_size, x_size), 500)
+    }
+
+    #[test]
+    fn auto_steps_through_every_tier() {
+        assert_eq!(auto(3.0), Precision::Single);
+        assert_eq!(auto(1e-6), Precision::Double);
+        assert_eq!(auto(1e-14), Precision::DoubleDouble);
+        assert_eq!(auto(1e-24), Precision::DoubleDouble);
+        #[cfg(feature = "bigfloat")]
+        assert!(matches!(auto(1e-30), Precision::Perturbation { .. }));
+        #[cfg(not(feature = "bigfloat"))]
+        assert_eq!(auto(1e-30), Precision::DoubleDouble);
+    }
+
+    #[test]
+    fn parse_reads_display() {
+        let precisions = [
+            Precision::Single,
+            Precision::Double,
+            Precision::DoubleDouble,
+            Precision::Auto,
+        ];
+        #[cfg(feature = "bigfloat")]
+        let precisions = [
+            &precisions[..],
+            &[
+                Precision::Arbitrary { fraction_bits: 96 },
+                Precision::Perturbation { fraction_bits: 128 },
+            ],
+        ]
+        .concat();
+        for precision in precisions {
+            assert_eq!(Precision::parse(&precision.to_string()), Some(precision));
+        }
+    }
+}
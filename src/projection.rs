@@ -0,0 +1,73 @@
+use core::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::Keyframe;
+
+/// How pixels of a frame are mapped onto the complex plane around a view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Projection {
+    /// The view rectangle, as in `Keyframe::get_coordinate`. The panoramas turn with the view's
+    /// `rotation` as well.
+    Flat,
+    /// 360° panorama seen from above the view center, at a height of half the view width. The
+    /// lower half of the frame looks down onto the plane and the upper half at its reflection,
+    /// for use in spherical viewers.
+    Equirectangular,
+    /// Stereographic "little planet" of the same panorama: the plane wraps around a disc
+    /// covering half the shorter side of the frame, with the reflection as the sky around it.
+    LittlePlanet,
+}
+
+impl Projection {
+    pub fn map(&self, view: &Keyframe, x: u32, y: u32, width: u32, height: u32) -> (f32, f32) {
+        let (x, y) = self.map_f64(view, x, y, width, height);
+        (x as f32, y as f32)
+    }
+
+    /// `map` without rounding to f32. Only the offsets from the view center are computed in f32
+    /// for the panoramas, which can't zoom in deep anyway.
+    pub fn map_f64(&self, view: &Keyframe, x: u32, y: u32, width: u32, height: u32) -> (f64, f64) {
+        let eye = (view.x_size / 2.0) as f32;
+        let (distance, angle) = match self {
+            Projection::Flat => return view.get_coordinate_f64(x, y, width, height),
+            Projection::Equirectangular => {
+                let longitude = (x as f32 + 0.5) / width as f32 * TAU - PI;
+                let latitude = FRAC_PI_2 - (y as f32 + 0.5) / height as f32 * PI;
+                (eye / latitude.abs().tan(), longitude)
+            }
+            Projection::LittlePlanet => {
+                let short = width.min(height) as f32;
+                let qx = (x as f32 + 0.5 - width as f32 / 2.0) / (short / 2.0);
+                let qy = (height as f32 / 2.0 - y as f32 - 0.5) / (short / 2.0);
+                // `s` is the tangent of half the angle to the nadir, so the horizon is at s = 1.
+                let s = qx.hypot(qy) / 0.5;
+                (eye * (2.0 * s / (1.0 - s * s)).abs(), qy.atan2(qx))
+            }
+        };
+        // Keeps the horizon finite, so it escapes like any other distant point.
+        let distance = distance.min(eye * 1e6);
+        let angle = angle + view.rotation;
+        (
+            view.x_center + (distance * angle.cos()) as f64,
+            view.y_center + (distance * angle.sin()) as f64,
+        )
+    }
+
+    /// `map_f64` relative to the view center, which keeps all its precision however far the
+    /// center is from 0.
+    pub fn offset_f64(
+        &self,
+        view: &Keyframe,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> (f64, f64) {
+        match self {
+            Projection::Flat => view.offset(x, y, width, height),
+            _ => {
+                let (px, py) = self.map_f64(view, x, y, width, height);
+                (px - view.x_center, py - view.y_center)
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::motion::{clamped, to_pixel};
+use crate::{Keyframe, Pixel};
+
+/// Cached mipmap pyramid of the area around a view, for showing approximate imagery instantly
+/// while full-resolution renders are still running. Level 0 is full resolution and every
+/// following level halves it. Only for interactive viewers built on the crate: the binary
+/// writes nothing but finished frames and has no use for previews.
+pub struct Pyramid {
+    region: Keyframe,
+    width: u32,
+    height: u32,
+    levels: Vec<Option<Vec<Pixel>>>,
+}
+
+impl Pyramid {
+    /// Covers `view` enlarged by `margin` (e.g. 2.0 for twice the width and height), so nearby
+    /// pans and zoom-outs can be previewed from the cache too.
+    pub fn new(view: Keyframe, width: u32, height: u32, levels: usize, margin: f32) -> Self {
+        let region = Keyframe {
+            x_size: view.x_size * margin as f64,
+            y_size: view.y_size * margin as f64,
+            ..view
+        };
+        let width = (width as f32 * margin).ceil() as u32;
+        let height = (height as f32 * margin).ceil() as u32;
+
+        Self {
+            region,
+            width,
+            height,
+            levels: vec![None; levels.max(1)],
+        }
+    }
+
+    pub fn level_size(&self, level: usize) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.levels[0].is_some()
+    }
+
+    /// Renders the coarsest missing level, returning false once every level is cached. Calling
+    /// this repeatedly fills the pyramid from cheap to expensive.
+    pub fn refine(&mut self, render: impl FnOnce(&Keyframe, u32, u32) -> Vec<Pixel>) -> bool {
+        let Some(level) = (0..self.levels.len())
+            .rev()
+            .find(|&l| self.levels[l].is_none())
+        else {
+            return false;
+        };
+        let (width, height) = self.level_size(level);
+        self.levels[level] = Some(render(&self.region, width, height));
+        true
+    }
+
+    /// Approximates `view` at `width`×`height` from the finest cached level, or `None` if
+    /// nothing has been rendered yet.
+    pub fn preview(&self, view: &Keyframe, width: u32, height: u32) -> Option<Vec<Pixel>> {
+        let (level, pixels) = self
+            .levels
+            .iter()
+            .enumerate()
+            .find_map(|(level, pixels)| pixels.as_ref().map(|p| (level, p)))?;
+        let (level_width, level_height) = self.level_size(level);
+
+        let mut out = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let (cx, cy) = view.get_coordinate(x, y, width, height);
+                let color = clamped(pixels, &self.region, level_width, level_height, cx, cy);
+                out.push(to_pixel(color));
+            }
+        }
+        Some(out)
+    }
+
+    /// Whether `view` lies entirely inside the cached region, turned or not.
+    pub fn covers(&self, view: &Keyframe) -> bool {
+        let (width, height) = (self.width, self.height);
+        let corners = [(0, 0), (width, 0), (0, height), (width, height)];
+        corners.into_iter().all(|(x, y)| {
+            let (cx, cy) = view.get_coordinate(x, y, width, height);
+            let (px, py) = self.region.get_pixel(cx, cy, width, height);
+            let inside = |p: f32, size: u32| (-1e-3..=size as f32 + 1e-3).contains(&p);
+            inside(px, width) && inside(py, height)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Red rises from the left edge of `view` to its right, green from the top to the bottom.
+    fn gradient(view: &Keyframe, width: u32, height: u32) -> Vec<Pixel> {
+        let left = view.x_center - view.x_size / 2.0;
+        let top = view.y_center - view.y_size / 2.0;
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (cx, cy) = view.get_coordinate_f64(x, y, width, height);
+                Pixel {
+                    r: ((cx - left) / view.x_size * 255.0).round() as u8,
+                    g: ((cy - top) / view.y_size * 255.0).round() as u8,
+                    b: 0,
+                    a: 255,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn refines_from_coarse_to_fine() {
+        let view = Keyframe::view(-0.5, 0.0, 3.0, 2.0);
+        let mut pyramid = Pyramid::new(view, 64, 48, 3, 2.0);
+        assert_eq!(pyramid.level_size(0), (128, 96));
+        assert_eq!(pyramid.level_size(2), (32, 24));
+        assert!(pyramid.preview(&view, 64, 48).is_none());
+
+        let mut sizes = Vec::new();
+        while pyramid.refine(|region, width, height| {
+            sizes.push((width, height));
+            gradient(region, width, height)
+        }) {}
+        assert_eq!(sizes, [(32, 24), (64, 48), (128, 96)]);
+        assert!(pyramid.is_complete());
+    }
+
+    #[test]
+    fn previews_sample_the_region() {
+        let view = Keyframe::view(-0.5, 0.0, 3.0, 2.0);
+        let mut pyramid = Pyramid::new(view, 64, 48, 2, 2.0);
+        pyramid.refine(gradient);
+        // The coarse level alone already previews the view, as the middle of the region.
+        let coarse = pyramid.preview(&view, 64, 48).unwrap();
+        pyramid.refine(gradient);
+        let fine = pyramid.preview(&view, 64, 48).unwrap();
+        let expected = gradient(&pyramid.region, 128, 96);
+        for (x, y) in [(0, 0), (32, 24), (63, 47)] {
+            let pixel = expected[((y + 24) * 128 + x + 32) as usize];
+            for preview in [&coarse, &fine] {
+                let got = preview[(y * 64 + x) as usize];
+                assert!(got.r.abs_diff(pixel.r) <= 2 && got.g.abs_diff(pixel.g) <= 2);
+            }
+        }
+    }
+
+    #[test]
+    fn covers_only_views_inside_the_region() {
+        let view = Keyframe::view(-0.5, 0.0, 3.0, 2.0);
+        let pyramid = Pyramid::new(view, 64, 48, 2, 2.0);
+        assert!(pyramid.covers(&view));
+        assert!(pyramid.covers(&Keyframe::view(0.9, 0.4, 3.0, 2.0)));
+        assert!(!pyramid.covers(&Keyframe::view(1.2, 0.0, 3.0, 2.0)));
+        assert!(!pyramid.covers(&Keyframe::view(-0.5, 0.0, 7.0, 2.0)));
+        let turned = Keyframe {
+            rotation: 0.5,
+            ..Keyframe::view(-0.5, 0.0, 5.0, 3.5)
+        };
+        assert!(!pyramid.covers(&turned));
+    }
+}
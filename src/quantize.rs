@@ -0,0 +1,225 @@
+use color_quant::NeuQuant;
+
+use crate::{ColorMap, Pixel};
+
+/// Pixels sampled per frame to train the palette, which is plenty to find its colors.
+const SAMPLES_PER_FRAME: usize = 20_000;
+
+/// Amplitude of the pattern of ordered dithering, in steps of a color channel, about the
+/// distance between neighboring colors of a 256 color palette.
+const BAYER_SPREAD: f32 = 32.0;
+
+/// Colors of a GIF palette.
+pub(crate) const COLORS: usize = 256;
+
+/// How colors between those of a palette are approximated, trading the bands of smooth
+/// gradients for a fine pattern of palette colors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dither {
+    /// Spreads the error of every pixel over its unvisited neighbors, the classic weights of
+    /// 7/16 to the right and 3/16, 5/16 and 1/16 below.
+    FloydSteinberg,
+    /// Offsets every pixel by the threshold of a Bayer matrix of this side, rounded up to a
+    /// power of two such as 2, 4 or 8. The pattern stays put from frame to frame, unlike the
+    /// errors of `FloydSteinberg`, which crawl as the image changes.
+    Bayer(u32),
+}
+
+/// One 256 color palette for a whole animation, learned from a sample of its frames with the
+/// same NeuQuant quantizer the GIF encoder runs on every frame on its own, or taken from the
+/// renderer's `Palette`. Sharing it keeps colors from flickering as the palette of each frame
+/// drifts from the last.
+pub struct GlobalPalette {
+    /// RGB triples.
+    colors: Vec<u8>,
+    quantizer: Option<NeuQuant>,
+}
+
+impl GlobalPalette {
+    /// Learns the palette from the pixels of `frames`, e.g. a few evenly spaced frames of the
+    /// animation.
+    pub fn from_frames(frames: &[Vec<Pixel>]) -> Self {
+        let mut samples = Vec::new();
+        for pixels in frames {
+            let step = (pixels.len() / SAMPLES_PER_FRAME).max(1);
+            samples.extend(
+                pixels
+                    .iter()
+                    .step_by(step)
+                    .flat_map(|p| [p.r, p.g, p.b, 255]),
+            );
+        }
+        let quantizer = NeuQuant::new(10, COLORS, &samples);
+        Self {
+            colors: quantizer.color_map_rgb(),
+            quantizer: Some(quantizer),
+        }
+    }
+
+    /// Black for the interior at index 0, followed by `palette` at evenly spaced positions from
+    /// 0 to 1, the colors `Renderer::draw_indexed` picks from.
+    pub fn from_palette(palette: &dyn ColorMap) -> Self {
+        let mut colors = vec![0; 3];
+        for i in 1..COLORS {
+            let pixel = palette.color((i - 1) as f32 / (COLORS - 2) as f32);
+            colors.extend([pixel.r, pixel.g, pixel.b]);
+        }
+        Self {
+            colors,
+            quantizer: None,
+        }
+    }
+
+    /// The colors as RGB triples, for the GIF's global color table.
+    pub fn rgb(&self) -> Vec<u8> {
+        self.colors.clone()
+    }
+
+    /// Indices of the rows of `width` `pixels`, dithered onto the palette.
+    pub fn dither(&self, width: usize, pixels: &[Pixel], dither: Dither) -> Vec<u8> {
+        match dither {
+            Dither::FloydSteinberg => self.floyd_steinberg(width, pixels),
+            Dither::Bayer(size) => self.bayer(width, pixels, size.max(2).next_power_of_two()),
+        }
+    }
+
+    fn bayer(&self, width: usize, pixels: &[Pixel], size: u32) -> Vec<u8> {
+        let levels = size.trailing_zeros();
+        let threshold = |x: u32, y: u32| {
+            // The lowest bits of the position pick the coarsest of the recursive quadrants, so
+            // neighboring pixels get the most different thresholds.
+            let rank = (0..levels).fold(0, |rank, bit| {
+                let quadrant = [[0, 2], [3, 1]][(y >> bit & 1) as usize][(x >> bit & 1) as usize];
+                4 * rank + quadrant
+            });
+            (rank as f32 + 0.5) / (size * size) as f32 - 0.5
+        };
+        pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let offset = BAYER_SPREAD * threshold((i % width) as u32, (i / width) as u32);
+                let [r, g, b] = [pixel.r, pixel.g, pixel.b]
+                    .map(|c| (c as f32 + offset).clamp(0.0, 255.0).round() as u8);
+                self.index_of(Pixel { r, g, b, a: 255 })
+            })
+            .collect()
+    }
+
+    fn floyd_steinberg(&self, width: usize, pixels: &[Pixel]) -> Vec<u8> {
+        let mut indices = Vec::with_capacity(pixels.len());
+        // Errors carried into this row and the next, with a pixel of margin on either side.
+        let mut errors = vec![[0.0f32; 3]; width + 2];
+        let mut below = vec![[0.0f32; 3]; width + 2];
+        for row in pixels.chunks(width) {
+            for (x, pixel) in row.iter().enumerate() {
+                let color = [pixel.r, pixel.g, pixel.b];
+                let wanted =
+                    [0, 1, 2].map(|c| (color[c] as f32 + errors[x + 1][c]).clamp(0.0, 255.0));
+                let [r, g, b] = wanted.map(|c| c.round() as u8);
+                let index = self.index_of(Pixel { r, g, b, a: 255 });
+                indices.push(index);
+
+                let chosen = &self.colors[3 * index as usize..3 * index as usize + 3];
+                for c in 0..3 {
+                    let error = wanted[c] - chosen[c] as f32;
+                    errors[x + 2][c] += error * 7.0 / 16.0;
+                    below[x][c] += error * 3.0 / 16.0;
+                    below[x + 1][c] += error * 5.0 / 16.0;
+                    below[x + 2][c] += error / 16.0;
+                }
+            }
+            errors = std::mem::replace(&mut below, vec![[0.0; 3]; width + 2]);
+        }
+        indices
+    }
+
+    /// Index of the color closest to `pixel`.
+    pub fn index_of(&self, pixel: Pixel) -> u8 {
+        if let Some(quantizer) = &self.quantizer {
+            return quantizer.index_of(&[pixel.r, pixel.g, pixel.b, 255]) as u8;
+        }
+        let distance = |color: &[u8]| -> u32 {
+            let [r, g, b] = [0, 1, 2].map(|c| color[c].abs_diff([pixel.r, pixel.g, pixel.b][c]));
+            r as u32 * r as u32 + g as u32 * g as u32 + b as u32 * b as u32
+        };
+        let nearest = self
+            .colors
+            .chunks(3)
+            .enumerate()
+            .min_by_key(|(_, c)| distance(c));
+        nearest.map_or(0, |(index, _)| index as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(r: u8, g: u8, b: u8) -> Pixel {
+        Pixel { r, g, b, a: 255 }
+    }
+
+    /// How far `palette`'s pick for `pixel` is from it, summed over the channels.
+    fn error(palette: &GlobalPalette, pixel: Pixel) -> u32 {
+        let index = palette.index_of(pixel) as usize;
+        let chosen = &palette.rgb()[3 * index..3 * index + 3];
+        let wanted = [pixel.r, pixel.g, pixel.b];
+        (0..3).map(|c| chosen[c].abs_diff(wanted[c]) as u32).sum()
+    }
+
+    /// A palette of `levels` evenly spaced grays from black to white.
+    fn grays(levels: usize) -> GlobalPalette {
+        let colors = (0..levels)
+            .flat_map(|i| [(i * 255 / (levels - 1)) as u8; 3])
+            .collect();
+        GlobalPalette {
+            colors,
+            quantizer: None,
+        }
+    }
+
+    /// Mean gray of the colors `indices` pick from `palette`.
+    fn mean(palette: &GlobalPalette, indices: &[u8]) -> f32 {
+        let sum: f32 = indices
+            .iter()
+            .map(|&i| palette.colors[3 * i as usize] as f32)
+            .sum();
+        sum / indices.len() as f32
+    }
+
+    #[test]
+    fn one_palette_covers_every_frame() {
+        let reds: Vec<Pixel> = (0..64 * 64).map(|i| rgb((i % 256) as u8, 0, 0)).collect();
+        let blues: Vec<Pixel> = (0..64 * 64).map(|i| rgb(0, 0, (i % 256) as u8)).collect();
+        let palette = GlobalPalette::from_frames(&[reds.clone(), blues.clone()]);
+        assert_eq!(palette.rgb().len(), 3 * COLORS);
+        for pixel in reds.iter().chain(&blues).step_by(7) {
+            assert!(error(&palette, *pixel) <= 12, "{pixel:?}");
+        }
+    }
+
+    #[test]
+    fn error_diffusion_keeps_the_mean() {
+        let palette = grays(2);
+        let pixels = vec![rgb(100, 100, 100); 32 * 32];
+        let indices = palette.dither(32, &pixels, Dither::FloydSteinberg);
+        assert_eq!(indices.len(), pixels.len());
+        // Nearest colors would make the whole image black.
+        assert!((mean(&palette, &indices) - 100.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn ordered_dithering_tiles_its_matrix() {
+        let palette = grays(9);
+        let pixels = vec![rgb(143, 143, 143); 16 * 16];
+        let indices = palette.dither(16, &pixels, Dither::Bayer(4));
+        assert!((mean(&palette, &indices) - 143.0).abs() < 4.0);
+        for (i, &index) in indices.iter().enumerate() {
+            let (x, y) = (i % 16, i / 16);
+            assert_eq!(index, indices[(y % 4) * 16 + x % 4]);
+        }
+        // Sides round up to a power of two.
+        assert_eq!(palette.dither(16, &pixels, Dither::Bayer(3)), indices);
+    }
+}
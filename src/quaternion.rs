@@ -0,0 +1,210 @@
+use std::ops::{Add, Mul};
+
+use crate::{Complex, EscapeResult, Fractal, Keyframe};
+
+/// `w + xi + yj + zk`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Quaternion {
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Quaternion { w, x, y, z }
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z
+    }
+
+    /// `self * self`, cheaper than the general product as the vector part commutes with itself.
+    pub fn square(self) -> Self {
+        let w = 2.0 * self.w;
+        Quaternion {
+            w: self.w * self.w - self.x * self.x - self.y * self.y - self.z * self.z,
+            x: w * self.x,
+            y: w * self.y,
+            z: w * self.z,
+        }
+    }
+}
+
+impl Add for Quaternion {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Quaternion {
+            w: self.w + rhs.w,
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Quaternion {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+/// The plane through the 4D quaternion Julia set that is rendered. Pixel `a + bi` is the
+/// quaternion `a + b(cos(angle) i + sin(angle) j) + offset k`, so the default slice is the
+/// complex plane, holding the complex Julia set of `c`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Slice {
+    pub angle: f32,
+    pub offset: f32,
+}
+
+impl Slice {
+    pub fn point(&self, pixel: Complex) -> Quaternion {
+        let (sin, cos) = self.angle.sin_cos();
+        Quaternion::new(pixel.x, pixel.y * cos, pixel.y * sin, self.offset)
+    }
+}
+
+/// `q^2 + c` over the quaternions, iterated from the points of a `Slice`. Like Newton's method
+/// it works on pixels directly, so keyframe `z0` and Julia mode are ignored.
+#[derive(Clone, Copy, Debug)]
+pub struct QuaternionJulia {
+    c: Quaternion,
+    slice: Slice,
+    animated_c: bool,
+    animated_slice: bool,
+}
+
+impl QuaternionJulia {
+    pub fn new(c: Quaternion, slice: Slice) -> Self {
+        Self {
+            c,
+            slice,
+            animated_c: false,
+            animated_slice: false,
+        }
+    }
+
+    /// Takes the slice from every keyframe's `slice_angle` and `slice_offset`.
+    pub fn sliced(c: Quaternion) -> Self {
+        Self {
+            animated_slice: true,
+            ..Self::new(c, Slice::default())
+        }
+    }
+
+    /// Also takes `c` from the keyframes, as `c_x + c_y i`.
+    pub fn animated() -> Self {
+        Self {
+            animated_c: true,
+            ..Self::sliced(Quaternion::default())
+        }
+    }
+
+    /// Parses the part of a `Fractal::name` after `"quaternion "`: the four parts of `c`,
+    /// optionally followed by the slice angle and offset.
+    pub(crate) fn parse(parameters: &str) -> Option<Self> {
+        let numbers: Vec<f32> = parameters
+            .split_whitespace()
+            .map(|number| number.parse().ok())
+            .collect::<Option<_>>()?;
+        match numbers[..] {
+            [w, x, y, z] => Some(Self::sliced(Quaternion::new(w, x, y, z))),
+            [w, x, y, z, angle, offset] => Some(Self::new(
+                Quaternion::new(w, x, y, z),
+                Slice { angle, offset },
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Fractal for QuaternionJulia {
+    fn name(&self) -> String {
+        let Quaternion { w, x, y, z } = self.c;
+        match (self.animated_c, self.animated_slice) {
+            (true, _) => "quaternion".to_owned(),
+            (false, true) => format!("quaternion {w} {x} {y} {z}"),
+            (false, false) => {
+                let Slice { angle, offset } = self.slice;
+                format!("quaternion {w} {x} {y} {z} {angle} {offset}")
+            }
+        }
+    }
+
+    /// Within the complex plane, which holds the orbits of the default slice, this is
+    /// `z^2 + c`.
+    fn step(&self, z: Complex, _c: Complex) -> Complex {
+        z * z + Complex::new(self.c.w, self.c.x)
+    }
+
+    fn stepwise(&self) -> bool {
+        false
+    }
+
+    fn animate(&self, keyframe: &Keyframe) -> Option<Box<dyn Fractal>> {
+        if !self.animated_c && !self.animated_slice {
+            return None;
+        }
+        let c = match self.animated_c {
+            true => Quaternion::new(keyframe.c_x, keyframe.c_y, 0.0, 0.0),
+            false => self.c,
+        };
+        let slice = match self.animated_slice {
+            true => Slice {
+                angle: keyframe.slice_angle,
+                offset: keyframe.slice_offset,
+            },
+            false => self.slice,
+        };
+        Some(Box::new(Self::new(c, slice)))
+    }
+
+    /// Iterates the point of the slice at `c`. The resulting `z` only keeps the magnitude of the
+    /// orbit, enough for smooth coloring.
+    fn iterate(&self, c: Complex, _z0: Complex, max_iter: usize, escape_norm: f32) -> EscapeResult {
+        let mut q = self.slice.point(c);
+        let mut iters = 0;
+
+        while q.norm() < escape_norm && iters < max_iter {
+            q = q.square() + self.c;
+            iters += 1;
+        }
+        let z = Complex::new(q.norm().sqrt(), 0.0);
+        EscapeResult::from_orbit(z, iters, iters < max_iter, escape_norm, self.power())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Mandelbrot;
+
+    #[test]
+    fn complex_slices_hold_the_julia_set() {
+        let c = Complex::new(-0.8, 0.156);
+        let julia = QuaternionJulia::new(Quaternion::new(c.x, c.y, 0.0, 0.0), Slice::default());
+        let (mut escaped, mut total) = (0, 0);
+        for y in -15..=15 {
+            for x in -20..=20 {
+                let pixel = Complex::new(x as f32 * 0.08, y as f32 * 0.0667);
+                let quaternion = julia.iterate(pixel, Complex::new(0.0, 0.0), 200, 4.0);
+                let complex = Mandelbrot.iterate(c, pixel, 200, 4.0);
+                assert_eq!(quaternion.escaped, complex.escaped, "{pixel:?}");
+                assert_eq!(quaternion.iterations, complex.iterations, "{pixel:?}");
+                escaped += usize::from(complex.escaped);
+                total += 1;
+            }
+        }
+        assert!(escaped > 0 && escaped < total);
+    }
+}
@@ -0,0 +1,1507 @@
+use core::f32::consts::TAU;
+#[cfg(feature = "bigfloat")]
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::complex::Complex;
+use crate::iteration::{CycleDetector, DEFAULT_ESCAPE_NORM, DEFAULT_ROUND};
+use crate::quantize::COLORS;
+use crate::{
+    anaglyph, basin_supersample, iterate_lanes, mariani_silver, smooth_exposures, Bailout,
+    Buddhabrot, ColorMap, ColorVisionDeficiency, Device, DeviceFrame, DevicePool, DoubleDouble,
+    EscapeResult, Exposure, Fractal, Frame, GlobalPalette, Image, IterationBuffer, IterationCache,
+    IterationKey, Keyframe, Mandelbrot, Palette, Pixel, Precision, Projection, Transparency,
+    BASIN_SUBPIXELS, LANES,
+};
+#[cfg(feature = "bigfloat")]
+use crate::{
+    iterate_mandelbrot, rotate, BigFixed, DeepKeyframe, ReferenceCache, ReferenceOrbit,
+    SeriesApproximation,
+};
+
+/// Iterations over which the color of a basin darkens to about a third.
+const BASIN_FALLOFF: f32 = 16.0;
+
+/// Fraction of probe pixels a doubled iteration limit has to resolve for
+/// `Renderer::with_iteration_calibration` to keep doubling.
+const CALIBRATION_TOLERANCE: f32 = 0.005;
+
+/// Orbits found back within this fraction of a pixel of an earlier value count as interior
+/// under `Renderer::with_periodicity_check`.
+const CYCLE_TOLERANCE: f64 = 1.0 / 1024.0;
+
+/// Reference orbits a perturbation frame may iterate, counting the one at the view center.
+#[cfg(feature = "bigfloat")]
+const MAX_REFERENCES: usize = 8;
+
+/// Renders frames of the set at a fixed size. Everything but the size and iteration limit is
+/// optional and set with the `with_*` methods.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    max_iter: usize,
+    iterations_per_doubling: Option<f32>,
+    interior_shortcut: bool,
+    periodicity_check: bool,
+    border_tracing: bool,
+    calibration_limit: Option<usize>,
+    calibration_probe: u32,
+    fractal: Box<dyn Fractal>,
+    escape_norm: f32,
+    iteration_budget: Option<usize>,
+    iteration_round: usize,
+    projection: Projection,
+    precision: Precision,
+    julia: bool,
+    color_map: Box<dyn ColorMap>,
+    exterior_texture: Option<Image>,
+    interior_texture: Option<Image>,
+    simulate_cvd: Option<ColorVisionDeficiency>,
+    transparency: Option<Transparency>,
+    anaglyph: Option<f32>,
+    basin_grid: Option<u32>,
+    density: Option<Buddhabrot>,
+    cache: Option<IterationCache>,
+    devices: DevicePool,
+    exposures: Option<Vec<Exposure>>,
+    #[cfg(feature = "bigfloat")]
+    references: ReferenceCache,
+}
+
+impl Renderer {
+    pub fn new(width: u32, height: u32, max_iter: usize) -> Self {
+        Self {
+            width,
+            height,
+            max_iter,
+            iterations_per_doubling: None,
+            interior_shortcut: true,
+            periodicity_check: true,
+            border_tracing: false,
+            calibration_limit: None,
+            calibration_probe: 64,
+            fractal: Box::new(Mandelbrot),
+            escape_norm: DEFAULT_ESCAPE_NORM,
+            iteration_budget: None,
+            iteration_round: DEFAULT_ROUND,
+            projection: Projection::Flat,
+            precision: Precision::Single,
+            julia: false,
+            color_map: Box::new(Palette::Classic),
+            exterior_texture: None,
+            interior_texture: None,
+            simulate_cvd: None,
+            transparency: None,
+            anaglyph: None,
+            basin_grid: None,
+            density: None,
+            cache: None,
+            devices: DevicePool::default(),
+            exposures: None,
+            #[cfg(feature = "bigfloat")]
+            references: ReferenceCache::default(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn max_iter(&self) -> usize {
+        self.max_iter
+    }
+
+    /// Iteration limit of `keyframe`, see `with_iteration_scaling` and
+    /// `with_iteration_calibration`.
+    pub fn frame_max_iter(&self, keyframe: &Keyframe) -> usize {
+        let max_iter = match self.iterations_per_doubling {
+            Some(per_doubling) => keyframe.scaled_max_iter(self.max_iter, per_doubling),
+            None => self.max_iter,
+        };
+        match self.calibration_limit {
+            Some(limit) => self.calibrate_max_iter(keyframe, max_iter, limit),
+            None => max_iter,
+        }
+    }
+
+    /// Doubles `max_iter` on a probe of `keyframe` until that stops turning more than
+    /// `CALIBRATION_TOLERANCE` of its pixels from interior to escaped, or would pass `limit`.
+    /// Interior pixels of a limit that is too low are nearly all ones that escape later, so the
+    /// ratio falls with every doubling until only the true interior is left. Probes all in the
+    /// interior double all the way to `limit`.
+    fn calibrate_max_iter(&self, keyframe: &Keyframe, mut max_iter: usize, limit: usize) -> usize {
+        let probe = self.calibration_probe;
+        let interior = |max_iter| {
+            let preview = self.iterate_sized(probe, probe, keyframe, max_iter);
+            let interior = preview.results.iter().filter(|result| !result.escaped);
+            interior.count() as f32 / preview.results.len().max(1) as f32
+        };
+        let mut ratio = interior(max_iter);
+        while max_iter * 2 <= limit {
+            let doubled = interior(max_iter * 2);
+            // Until anything escapes there is no telling escaping pixels from interior ones.
+            if ratio < 1.0 && ratio - doubled <= CALIBRATION_TOLERANCE {
+                break;
+            }
+            (max_iter, ratio) = (max_iter * 2, doubled);
+        }
+        max_iter
+    }
+
+    /// Iterates `fractal` instead of the Mandelbrot set.
+    pub fn with_fractal(self, fractal: impl Fractal + 'static) -> Self {
+        Self {
+            fractal: Box::new(fractal),
+            ..self
+        }
+    }
+
+    /// Orbits escape once `|z|^2` reaches `norm`. A large radius keeps the smooth iteration
+    /// count accurate.
+    pub fn with_escape_norm(self, norm: f32) -> Self {
+        Self {
+            escape_norm: norm,
+            ..self
+        }
+    }
+
+    /// Raises the iteration limit of every frame by `per_doubling` for each halving of its view
+    /// width, as in `Keyframe::scaled_max_iter`, so deep frames keep their detail. Coloring
+    /// still spans the unscaled limit.
+    pub fn with_iteration_scaling(self, per_doubling: f32) -> Self {
+        Self {
+            iterations_per_doubling: Some(per_doubling),
+            ..self
+        }
+    }
+
+    /// Whether orbits that `Fractal::interior` knows to stay bounded skip their iterations, on
+    /// by default. Only has an effect for formulas that know any, like the Mandelbrot set.
+    pub fn with_interior_shortcut(self, enabled: bool) -> Self {
+        Self {
+            interior_shortcut: enabled,
+            ..self
+        }
+    }
+
+    /// Whether orbits stop as interior once they come back to where they were, on by default.
+    /// Saves most iterations of interior points outside `Fractal::interior`, which otherwise run
+    /// all the way to the limit. Only checked for `stepwise` formulas iterated in f32, f64 or
+    /// double-double.
+    pub fn with_periodicity_check(self, enabled: bool) -> Self {
+        Self {
+            periodicity_check: enabled,
+            ..self
+        }
+    }
+
+    /// Iterates frames by Mariani-Silver subdivision instead of pixel by pixel, see
+    /// `mariani_silver`. Only frames iterated in f32, f64 or double-double are traced.
+    pub fn with_border_tracing(self) -> Self {
+        Self {
+            border_tracing: true,
+            ..self
+        }
+    }
+
+    /// Picks the iteration limit of every frame by probing it at `probe` pixels square, from
+    /// the limit it would otherwise have up to `limit`, so each location gets as many
+    /// iterations as its detail needs without tuning them by hand.
+    pub fn with_iteration_calibration(self, limit: usize, probe: u32) -> Self {
+        Self {
+            calibration_limit: Some(limit),
+            calibration_probe: probe,
+            ..self
+        }
+    }
+
+    /// Caps the total iterations per frame. Pixels are iterated `round` iterations at a time and
+    /// revisited until the budget runs out, so deep regions can't stall a frame indefinitely.
+    pub fn with_iteration_budget(self, budget: usize, round: usize) -> Self {
+        Self {
+            iteration_budget: Some(budget),
+            iteration_round: round,
+            ..self
+        }
+    }
+
+    pub fn with_projection(self, projection: Projection) -> Self {
+        Self { projection, ..self }
+    }
+
+    /// Iterates in f64 under `Precision::Double`, for deep zooms. Only formulas that are
+    /// `Fractal::stepwise` have an f64 `step`, the others stay in f32, and the iteration budget
+    /// isn't applied. `Precision::Arbitrary` and `Precision::Perturbation` go deeper still for the
+    /// Mandelbrot set.
+    pub fn with_precision(self, precision: Precision) -> Self {
+        Self { precision, ..self }
+    }
+
+    /// Renders the Julia sets of the keyframes' `c` instead, iterating from every pixel. The
+    /// keyframes' `z0` is unused.
+    pub fn with_julia(self) -> Self {
+        Self {
+            julia: true,
+            ..self
+        }
+    }
+
+    pub fn with_palette(self, palette: Palette) -> Self {
+        self.with_color_map(palette)
+    }
+
+    /// Colors escaped points and basins with `color_map` instead of a `Palette`.
+    pub fn with_color_map(self, color_map: impl ColorMap + 'static) -> Self {
+        Self {
+            color_map: Box::new(color_map),
+            ..self
+        }
+    }
+
+    /// Wraps `texture` around the set, using the escape time and the angle at escape as texture
+    /// coordinates instead of the palette.
+    pub fn with_exterior_texture(self, texture: Image) -> Self {
+        Self {
+            exterior_texture: Some(texture),
+            ..self
+        }
+    }
+
+    /// Fills the interior with `texture`, using the final orbit value as texture coordinates
+    /// instead of flat black.
+    pub fn with_interior_texture(self, texture: Image) -> Self {
+        Self {
+            interior_texture: Some(texture),
+            ..self
+        }
+    }
+
+    pub fn with_simulated_cvd(self, deficiency: ColorVisionDeficiency) -> Self {
+        Self {
+            simulate_cvd: Some(deficiency),
+            ..self
+        }
+    }
+
+    /// Leaves the pixels of `transparency` transparent, in place of their colors.
+    pub fn with_transparency(self, transparency: Transparency) -> Self {
+        Self {
+            transparency: Some(transparency),
+            ..self
+        }
+    }
+
+    /// Renders red-cyan anaglyphs with up to `parallax` pixels between the two eyes.
+    pub fn with_anaglyph(self, parallax: f32) -> Self {
+        Self {
+            anaglyph: Some(parallax),
+            ..self
+        }
+    }
+
+    /// Supersamples pixels on basin boundaries of convergent fractals on a `grid` x `grid` grid,
+    /// see `basin_supersample`.
+    pub fn with_basin_supersampling(self, grid: u32) -> Self {
+        Self {
+            basin_grid: Some(grid),
+            ..self
+        }
+    }
+
+    /// Renders orbit densities, see `Buddhabrot`, instead of escape times. Coloring settings
+    /// don't apply.
+    pub fn with_density(self, buddhabrot: Buddhabrot) -> Self {
+        Self {
+            density: Some(buddhabrot),
+            ..self
+        }
+    }
+
+    /// Stores iteration results in `cache`, so renders that only change coloring skip the
+    /// iteration phase.
+    pub fn with_cache(self, cache: IterationCache) -> Self {
+        Self {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Iterates frames on `device` where it can, and on the CPU where it can't. Only f32 frames
+    /// of flat views without an iteration budget are offered to it. With several devices, the
+    /// frames rendered in parallel are spread over them, see `DevicePool`.
+    pub fn with_device(mut self, device: impl Device + 'static) -> Self {
+        self.devices.add(Box::new(device));
+        self
+    }
+
+    /// Colors frame `index` of an animation with the range of `exposures[index]` instead of
+    /// 0..`max_iter`, see `measure_exposures`.
+    pub fn with_exposures(self, exposures: Vec<Exposure>) -> Self {
+        Self {
+            exposures: Some(exposures),
+            ..self
+        }
+    }
+
+    /// Exposures of `frames` measured on `probe` pixel square previews, ignoring the `clip`
+    /// fraction of extreme values on either end, and smoothed in animation order with `weight`.
+    pub fn measure_exposures(
+        &self,
+        frames: &[Keyframe],
+        weight: f32,
+        probe: u32,
+        clip: f32,
+    ) -> Vec<Exposure> {
+        let measured: Vec<Option<Exposure>> = frames
+            .par_iter()
+            .map(|keyframe| {
+                let max_iter = self.frame_max_iter(keyframe);
+                let preview = self.iterate_sized(probe, probe, keyframe, max_iter);
+                Exposure::measure(&preview.results, clip)
+            })
+            .collect();
+        smooth_exposures(&measured, weight)
+    }
+
+    pub fn calc_pixel(&self, (x, y): (f32, f32)) -> Pixel {
+        self.color_pixel(&self.iterate_point((x, y)))
+    }
+
+    /// Iteration phase of `calc_pixel`, independent of any coloring parameter.
+    pub fn iterate_point(&self, (x, y): (f32, f32)) -> EscapeResult {
+        self.iterate_from((x, y), (0.0, 0.0))
+    }
+
+    /// `iterate_point` starting the orbit at `z0` instead of 0.
+    pub fn iterate_from(&self, (x, y): (f32, f32), (z0_x, z0_y): (f32, f32)) -> EscapeResult {
+        self.fractal.iterate(
+            Complex::new(x, y),
+            Complex::new(z0_x, z0_y),
+            self.max_iter,
+            self.escape_norm,
+        )
+    }
+
+    /// Whether `shortcut` skips anything, which it doesn't when an interior texture needs the
+    /// final orbit values.
+    fn shortcuts_interior(&self) -> bool {
+        self.interior_shortcut && self.interior_texture.is_none()
+    }
+
+    /// Result of an orbit of `fractal` that `with_interior_shortcut` skips, `None` for the ones
+    /// to iterate. The final orbit value of a skipped one is lost, so the shortcut stays off
+    /// under an interior texture.
+    fn shortcut(
+        &self,
+        fractal: &dyn Fractal,
+        c: (f64, f64),
+        z0: (f64, f64),
+        max_iter: usize,
+    ) -> Option<EscapeResult> {
+        (self.shortcuts_interior() && fractal.interior(c, z0)).then(|| {
+            let z0 = Complex::new(z0.0 as f32, z0.1 as f32);
+            EscapeResult::from_bailout(z0, max_iter, None, self.escape_norm, fractal.power())
+        })
+    }
+
+    /// How close an orbit of `keyframe` at `width` pixels has to come back to an earlier value
+    /// to stop as a cycle, `None` without periodicity checks.
+    fn cycle_tolerance(&self, keyframe: &Keyframe, width: u32) -> Option<f64> {
+        let pixel = keyframe.x_size / width as f64;
+        self.periodicity_check.then_some(pixel * CYCLE_TOLERANCE)
+    }
+
+    /// `Fractal::iterate` of a `stepwise` formula, with orbits that `CycleDetector` finds back
+    /// where they were stopped as interior.
+    fn iterate_cycling(
+        &self,
+        fractal: &dyn Fractal,
+        (c, z0): (Complex, Complex),
+        max_iter: usize,
+        tolerance: f64,
+    ) -> EscapeResult {
+        let mut cycle = CycleDetector::new((z0.x as f64, z0.y as f64), tolerance);
+        let mut z = z0;
+        let mut iters = 0;
+        let mut bailout = None;
+
+        while iters < max_iter {
+            bailout = fractal.bailout(z, self.escape_norm);
+            if bailout.is_some() {
+                break;
+            }
+            z = fractal.step(z, c);
+            iters += 1;
+            if cycle.repeats((z.x as f64, z.y as f64)) {
+                iters = max_iter;
+            }
+        }
+        EscapeResult::from_bailout(z, iters, bailout, self.escape_norm, fractal.power())
+    }
+
+    /// Coloring phase of `calc_pixel`.
+    pub fn color_pixel(&self, result: &EscapeResult) -> Pixel {
+        self.shade(result, result.smooth / self.max_iter as f32)
+    }
+
+    /// `color_pixel` with the palette position of escaped points given by `t`. Points of
+    /// convergent fractals get the palette color of their basin instead, darker the longer they
+    /// took to converge.
+    fn shade(&self, result: &EscapeResult, t: f32) -> Pixel {
+        let z = Complex::new(result.z.0, result.z.1);
+        let basin = self.fractal.basin(result);
+        let pixel = if let Some(basin) = basin {
+            let color = self
+                .color_map
+                .color((basin as f32 + 0.5) / self.fractal.basins() as f32);
+            let light = (-result.smooth / BASIN_FALLOFF).exp();
+            Pixel {
+                r: (color.r as f32 * light) as u8,
+                g: (color.g as f32 * light) as u8,
+                b: (color.b as f32 * light) as u8,
+                a: color.a,
+            }
+        } else if result.escaped {
+            match &self.exterior_texture {
+                Some(texture) => texture.sample(z.arg() / TAU + 0.5, result.smooth.fract()),
+                None => self.color_map.color(t),
+            }
+        } else if let Some(texture) = &self.interior_texture {
+            // Interior orbits settle on their attracting cycle, which lies within |z| < 2.
+            texture.sample(z.x / 4.0 + 0.5, 0.5 - z.y / 4.0)
+        } else {
+            Pixel::from_rgb(0.0, 0.0, 0.0)
+        };
+
+        let transparent = match self.transparency {
+            Some(Transparency::Exterior) => result.escaped,
+            Some(Transparency::Interior) => !result.escaped,
+            None => false,
+        };
+        // All in one color, which GIF frames give a single transparent index.
+        if transparent {
+            return Pixel {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            };
+        }
+        match self.simulate_cvd {
+            Some(deficiency) => pixel.simulate(deficiency),
+            None => pixel,
+        }
+    }
+
+    /// The point iterated for `point` of the plane and the value its orbit starts from.
+    fn orbit_start(&self, keyframe: &Keyframe, point: (f32, f32)) -> ((f32, f32), (f32, f32)) {
+        let (c, z0) = ((keyframe.c_x, keyframe.c_y), (keyframe.z0_x, keyframe.z0_y));
+        match self.morph(keyframe) {
+            1.0 => (c, point),
+            0.0 => (point, z0),
+            t => {
+                let flerp = |a: f32, b: f32| a + (b - a) * t;
+                (
+                    (flerp(point.0, c.0), flerp(point.1, c.1)),
+                    (flerp(z0.0, point.0), flerp(z0.1, point.1)),
+                )
+            }
+        }
+    }
+
+    /// How far `keyframe` is morphed toward the Julia set, all the way in Julia mode.
+    fn morph(&self, keyframe: &Keyframe) -> f32 {
+        match self.julia {
+            true => 1.0,
+            false => keyframe.morph,
+        }
+    }
+
+    pub fn iterate_frame(&self, keyframe: &Keyframe) -> IterationBuffer {
+        let max_iter = self.frame_max_iter(keyframe);
+        self.iterate_sized(self.width, self.height, keyframe, max_iter)
+    }
+
+    /// `iterate_frame` in `precision` instead of the renderer's own, resolved for `keyframe`
+    /// under `Precision::Auto`.
+    pub fn iterate_frame_in(&self, keyframe: &Keyframe, precision: Precision) -> IterationBuffer {
+        let max_iter = self.frame_max_iter(keyframe);
+        let precision = precision.resolve(keyframe, self.width);
+        self.iterate_precise(self.width, self.height, keyframe, max_iter, precision)
+    }
+
+    fn iterate_sized(
+        &self,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        let precision = self.precision.resolve(keyframe, width);
+        self.iterate_precise(width, height, keyframe, max_iter, precision)
+    }
+
+    fn iterate_precise(
+        &self,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+        precision: Precision,
+    ) -> IterationBuffer {
+        let animated = self.fractal.animate(keyframe);
+        let fractal = animated.as_deref().unwrap_or(&*self.fractal);
+        let offloadable = precision == Precision::Single
+            && self.projection == Projection::Flat
+            && keyframe.rotation == 0.0
+            && self.iteration_budget.is_none();
+        if offloadable && !self.devices.is_empty() {
+            let frame = DeviceFrame {
+                keyframe,
+                width,
+                height,
+                max_iter,
+                escape_norm: self.escape_norm,
+                fractal,
+                morph: self.morph(keyframe),
+            };
+            if let Some(buffer) = self.devices.iterate(&frame) {
+                return buffer;
+            }
+        }
+        if precision == Precision::DoubleDouble && fractal.name() == Mandelbrot.name() {
+            return self.iterate_double_double(width, height, keyframe, max_iter);
+        }
+        #[cfg(feature = "bigfloat")]
+        if fractal.name() == Mandelbrot.name() {
+            let center = |fraction_bits| {
+                let big = |n: f64| BigFixed::from_f64(n, fraction_bits);
+                (big(keyframe.x_center), big(keyframe.y_center))
+            };
+            match precision {
+                Precision::Arbitrary { fraction_bits } => {
+                    let center = center(fraction_bits);
+                    return self.iterate_arbitrary(
+                        fraction_bits,
+                        center,
+                        width,
+                        height,
+                        keyframe,
+                        max_iter,
+                    );
+                }
+                Precision::Perturbation { fraction_bits } => {
+                    let center = center(fraction_bits);
+                    return self.iterate_perturbed(
+                        fraction_bits,
+                        center,
+                        width,
+                        height,
+                        keyframe,
+                        max_iter,
+                    );
+                }
+                _ => {}
+            }
+        }
+        if precision != Precision::Single && fractal.stepwise() {
+            return self.iterate_f64(fractal, width, height, keyframe, max_iter);
+        }
+        // Budgeted rounds only know how to step orbits and detect escaping ones.
+        let budget = self.iteration_budget.filter(|_| fractal.stepwise());
+        if let Some(budget) = budget {
+            return self.iterate_budgeted(fractal, width, height, keyframe, budget, max_iter);
+        }
+        // Rounding in f32 leaves orbits on a cycle this far apart at least.
+        let cycle_tolerance = self
+            .cycle_tolerance(keyframe, width)
+            .filter(|_| fractal.stepwise())
+            .map(|tolerance| tolerance.max(f32::EPSILON as f64));
+        if fractal.name() == Mandelbrot.name() && !self.border_tracing {
+            return self.iterate_batched(width, height, keyframe, max_iter, cycle_tolerance);
+        }
+        let pixel = |x, y| {
+            let point = self.projection.map(keyframe, x, y, width, height);
+            let ((cx, cy), (zx, zy)) = self.orbit_start(keyframe, point);
+            let (c, z0) = ((cx as f64, cy as f64), (zx as f64, zy as f64));
+            if let Some(result) = self.shortcut(fractal, c, z0, max_iter) {
+                return result;
+            }
+            let orbit = (Complex::new(cx, cy), Complex::new(zx, zy));
+            match cycle_tolerance {
+                Some(tolerance) => self.iterate_cycling(fractal, orbit, max_iter, tolerance),
+                None => fractal.iterate(orbit.0, orbit.1, max_iter, self.escape_norm),
+            }
+        };
+        let results = self.scan(width, height, pixel);
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    /// The f32 pixel loop of the Mandelbrot set, `LANES` pixels at a time. Pixels that
+    /// `with_interior_shortcut` skips are left out of the batches, so they don't hold up the
+    /// lanes next to them until the iteration limit.
+    fn iterate_batched(
+        &self,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+        cycle_tolerance: Option<f64>,
+    ) -> IterationBuffer {
+        let (mut results, orbits): (Vec<_>, Vec<_>) = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let point = self.projection.map(keyframe, x, y, width, height);
+                let ((cx, cy), (zx, zy)) = self.orbit_start(keyframe, point);
+                let (c, z0) = ((cx as f64, cy as f64), (zx as f64, zy as f64));
+                let shortcut = self.shortcut(&Mandelbrot, c, z0, max_iter);
+                (shortcut, (Complex::new(cx, cy), Complex::new(zx, zy)))
+            })
+            .unzip();
+
+        let pending: Vec<usize> = (0..results.len())
+            .filter(|&i| results[i].is_none())
+            .collect();
+        let batches: Vec<[EscapeResult; LANES]> = pending
+            .par_chunks(LANES)
+            .map(|batch| {
+                // A short last batch repeats its first pixel in the spare lanes.
+                let lane = |i: usize| orbits[*batch.get(i).unwrap_or(&batch[0])];
+                let c = std::array::from_fn(|i| lane(i).0);
+                let z0 = std::array::from_fn(|i| lane(i).1);
+                iterate_lanes(c, z0, max_iter, self.escape_norm, cycle_tolerance)
+            })
+            .collect();
+        for (batch, escaped) in pending.chunks(LANES).zip(batches) {
+            for (&i, result) in batch.iter().zip(escaped) {
+                results[i] = Some(result);
+            }
+        }
+        IterationBuffer {
+            width,
+            height,
+            results: results.into_iter().map(Option::unwrap).collect(),
+        }
+    }
+
+    /// Results of `pixel` for every pixel of a `width` by `height` frame, by border tracing
+    /// under `with_border_tracing`. Rows are iterated in parallel, on the same rayon pool as the
+    /// frames of `render_frames`, so a single large frame keeps every thread busy as well.
+    fn scan(
+        &self,
+        width: u32,
+        height: u32,
+        pixel: impl Fn(u32, u32) -> EscapeResult + Sync,
+    ) -> Vec<EscapeResult> {
+        match self.border_tracing {
+            true => mariani_silver(width, height, pixel),
+            false => (0..height)
+                .into_par_iter()
+                .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| pixel(x, y))
+                .collect(),
+        }
+    }
+
+    fn iterate_f64(
+        &self,
+        fractal: &dyn Fractal,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        let view = |n: f32| n as f64;
+        let cycle_tolerance = self
+            .cycle_tolerance(keyframe, width)
+            .filter(|_| fractal.stepwise())
+            .map(|tolerance| tolerance.max(f64::EPSILON));
+        let pixel = |x, y| {
+            let (px, py) = self.projection.map_f64(keyframe, x, y, width, height);
+
+            let t = view(self.morph(keyframe));
+            let flerp = |a: f64, b: f64| a + (b - a) * t;
+            let (cx, cy) = (flerp(px, view(keyframe.c_x)), flerp(py, view(keyframe.c_y)));
+            let (mut zx, mut zy) = (
+                flerp(view(keyframe.z0_x), px),
+                flerp(view(keyframe.z0_y), py),
+            );
+            if let Some(result) = self.shortcut(fractal, (cx, cy), (zx, zy), max_iter) {
+                return result;
+            }
+            let mut cycle =
+                cycle_tolerance.map(|tolerance| CycleDetector::new((zx, zy), tolerance));
+            let mut iters = 0;
+            let mut bailout = None;
+            while iters < max_iter {
+                // Nothing is left of the extra precision by the time an orbit bails out.
+                bailout = fractal.bailout(Complex::new(zx as f32, zy as f32), self.escape_norm);
+                if bailout.is_some() {
+                    break;
+                }
+                (zx, zy) = fractal.step_f64((zx, zy), (cx, cy));
+                iters += 1;
+                if cycle.as_mut().is_some_and(|cycle| cycle.repeats((zx, zy))) {
+                    iters = max_iter;
+                }
+            }
+            let z = Complex::new(zx as f32, zy as f32);
+            EscapeResult::from_bailout(z, iters, bailout, self.escape_norm, fractal.power())
+        };
+        let results = self.scan(width, height, pixel);
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    /// `z^2 + c` with pixel coordinates and orbits in double-double.
+    fn iterate_double_double(
+        &self,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        let t = DoubleDouble::from(self.morph(keyframe) as f64);
+        let blend = |a: DoubleDouble, b: DoubleDouble| a + (b - a) * t;
+        let (c_x, c_y) = (keyframe.c_x as f64, keyframe.c_y as f64);
+        let (z0_x, z0_y) = (keyframe.z0_x as f64, keyframe.z0_y as f64);
+        let escape_norm = self.escape_norm as f64;
+        // Whole values are compared, as orbits deeper than f64 resolves only differ in their
+        // low parts.
+        let cycle_tolerance = self.cycle_tolerance(keyframe, width);
+        let pixel = |x, y| {
+            // Only flat views are mapped in double-double; the others are far from deep.
+            let (px, py) = match self.projection {
+                Projection::Flat => keyframe.get_coordinate_dd(x, y, width, height),
+                projection => {
+                    let (px, py) = projection.map_f64(keyframe, x, y, width, height);
+                    (px.into(), py.into())
+                }
+            };
+            let (cx, cy) = (blend(px, c_x.into()), blend(py, c_y.into()));
+            let (mut zx, mut zy) = (blend(z0_x.into(), px), blend(z0_y.into(), py));
+            let (c, z0) = ((cx.hi, cy.hi), (zx.hi, zy.hi));
+            if let Some(result) = self.shortcut(&Mandelbrot, c, z0, max_iter) {
+                return result;
+            }
+            let new_cycle = |tolerance| CycleDetector::new((zx, zy), tolerance);
+            let mut cycle = cycle_tolerance.map(new_cycle);
+            let mut iters = 0;
+            while iters < max_iter {
+                let (xx, yy) = (zx.square(), zy.square());
+                if xx.hi + yy.hi >= escape_norm {
+                    break;
+                }
+                zy = (zx * zy).double() + cy;
+                zx = xx - yy + cx;
+                iters += 1;
+                if cycle.as_mut().is_some_and(|cycle| cycle.repeats((zx, zy))) {
+                    iters = max_iter;
+                }
+            }
+            let z = Complex::new(zx.hi as f32, zy.hi as f32);
+            EscapeResult::from_orbit(z, iters, iters < max_iter, self.escape_norm, 2.0)
+        };
+        let results = self.scan(width, height, pixel);
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    /// `z^2 + c` with pixel coordinates and orbits in fixed point, around `center` in place of
+    /// the keyframe's. The pixel offsets are exact in f64, so only their sum with the center
+    /// needs the extra bits.
+    #[cfg(feature = "bigfloat")]
+    fn iterate_arbitrary(
+        &self,
+        fraction_bits: u32,
+        (x_center, y_center): (BigFixed, BigFixed),
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        let big = |n: f64| BigFixed::from_f64(n, fraction_bits);
+        let t = big(self.morph(keyframe) as f64);
+        let (c_x, c_y) = (big(keyframe.c_x as f64), big(keyframe.c_y as f64));
+        let (z0_x, z0_y) = (big(keyframe.z0_x as f64), big(keyframe.z0_y as f64));
+        // Exact at both ends, as fixed point products by 0 and 1 don't round.
+        let blend = |a: &BigFixed, b: &BigFixed| a + &(&(b - a) * &t);
+        let pixel = |x, y| {
+            let (dx, dy) = self.projection.offset_f64(keyframe, x, y, width, height);
+            let (px, py) = (&x_center + &big(dx), &y_center + &big(dy));
+            let c = (blend(&px, &c_x), blend(&py, &c_y));
+            let z0 = (blend(&z0_x, &px), blend(&z0_y, &py));
+            iterate_mandelbrot((&c.0, &c.1), z0, max_iter, self.escape_norm)
+        };
+        let results = self.scan(width, height, pixel);
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    /// `z^2 + c` as deltas from the orbit of the view center, or of a point still in view that
+    /// an earlier frame iterated, skipping the iterations a series approximation covers. With
+    /// the blend of `orbit_start`, a pixel offset from the center offsets `c` by its
+    /// `1 - morph` part and `z0` by the rest. Glitched pixels, and those outliving an escaped
+    /// orbit, are iterated again from the orbit of one of them, up to `MAX_REFERENCES` orbits
+    /// in all, and whatever still glitches then in fixed point throughout.
+    #[cfg(feature = "bigfloat")]
+    fn iterate_perturbed(
+        &self,
+        fraction_bits: u32,
+        (x_center, y_center): (BigFixed, BigFixed),
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        let big = |n: f64| BigFixed::from_f64(n, fraction_bits);
+        let morph = self.morph(keyframe) as f64;
+        let t = big(morph);
+        let blend = |a: &BigFixed, b: &BigFixed| a + &(&(b - a) * &t);
+        let (c_x, c_y) = (big(keyframe.c_x as f64), big(keyframe.c_y as f64));
+        let (z0_x, z0_y) = (big(keyframe.z0_x as f64), big(keyframe.z0_y as f64));
+        // `c` and `z0` of the point `(px, py)` of the plane.
+        let start_at = |(px, py): &(BigFixed, BigFixed)| {
+            let c = (blend(px, &c_x), blend(py, &c_y));
+            let z0 = (blend(&z0_x, px), blend(&z0_y, py));
+            (c, z0)
+        };
+        let point = |(dx, dy): (f64, f64)| (&x_center + &big(dx), &y_center + &big(dy));
+        let start = |offset| start_at(&point(offset));
+        let reference_at = |offset| {
+            let (c, z0) = start(offset);
+            ReferenceOrbit::new(c, z0, max_iter, self.escape_norm)
+        };
+        let offset_of = |(px, py): &(BigFixed, BigFixed)| {
+            ((px - &x_center).to_f64(), (py - &y_center).to_f64())
+        };
+        // `dc` and `dz0` of the point `offset` from the orbit at `reference`.
+        let deltas = |offset: (f64, f64), reference: (f64, f64)| {
+            let (dx, dy) = (offset.0 - reference.0, offset.1 - reference.1);
+            (
+                (dx * (1.0 - morph), dy * (1.0 - morph)),
+                (dx * morph, dy * morph),
+            )
+        };
+        let offsets: Vec<_> = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                (0..width).map(move |x| self.projection.offset_f64(keyframe, x, y, width, height))
+            })
+            .collect();
+        if offsets.is_empty() {
+            return IterationBuffer {
+                width,
+                height,
+                results: Vec::new(),
+            };
+        }
+
+        let in_view = |point: &(BigFixed, BigFixed)| {
+            let (dx, dy) = rotate(offset_of(point), -(keyframe.rotation as f64));
+            dx.abs() <= keyframe.x_size / 2.0 && dy.abs() <= keyframe.y_size / 2.0
+        };
+        let cached = self.references.find(|point, orbit| {
+            let (c, z0) = start_at(point);
+            in_view(point) && orbit.start() == (&c, &z0)
+        });
+        let (reference, reference_offset) = match cached {
+            Some((point, orbit)) => {
+                let orbit = ReferenceOrbit::extended(&orbit, max_iter, self.escape_norm);
+                let offset = offset_of(&point);
+                self.references.insert(point, Arc::clone(&orbit));
+                (orbit, offset)
+            }
+            None => {
+                let orbit = Arc::new(reference_at((0.0, 0.0)));
+                self.references
+                    .insert(point((0.0, 0.0)), Arc::clone(&orbit));
+                (orbit, (0.0, 0.0))
+            }
+        };
+        // The corners are the pixels farthest from any point in view.
+        let last = offsets.len() - 1;
+        let radius = [0, width as usize - 1, last + 1 - width as usize, last]
+            .map(|corner| {
+                let (dx, dy) = offsets[corner];
+                (dx - reference_offset.0).hypot(dy - reference_offset.1)
+            })
+            .into_iter()
+            .fold(0.0, f64::max);
+        let series = SeriesApproximation::new(
+            &reference,
+            (radius * morph, radius * (1.0 - morph)),
+            max_iter,
+            self.escape_norm,
+        );
+        let mut results: Vec<_> = offsets
+            .par_iter()
+            .map(|&offset| {
+                let (dc, dz0) = deltas(offset, reference_offset);
+                reference.iterate_series(&series, dc, dz0, max_iter, self.escape_norm)
+            })
+            .collect();
+
+        for _ in 1..MAX_REFERENCES {
+            let glitched: Vec<usize> = (0..results.len())
+                .filter(|&pixel| results[pixel].is_none())
+                .collect();
+            // A glitched pixel's own orbit never glitches, so every round fixes at least one.
+            let Some(&pixel) = glitched.get(glitched.len() / 2) else {
+                break;
+            };
+            let reference = reference_at(offsets[pixel]);
+            let fixed: Vec<_> = glitched
+                .par_iter()
+                .map(|&pixel_index| {
+                    let (dc, dz0) = deltas(offsets[pixel_index], offsets[pixel]);
+                    reference.iterate(dc, dz0, max_iter, self.escape_norm)
+                })
+                .collect();
+            for (pixel_index, result) in glitched.into_iter().zip(fixed) {
+                results[pixel_index] = result;
+            }
+        }
+        let results = results
+            .into_par_iter()
+            .zip(&offsets)
+            .map(|(result, &offset)| {
+                result.unwrap_or_else(|| {
+                    let (c, z0) = start(offset);
+                    iterate_mandelbrot((&c.0, &c.1), z0, max_iter, self.escape_norm)
+                })
+            })
+            .collect();
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    /// Iterates all pixels in rounds of the iteration round size, revisiting the unfinished ones
+    /// until `budget` iterations have been spent on the frame. Pixels still running when the
+    /// budget is exhausted are treated as interior.
+    fn iterate_budgeted(
+        &self,
+        fractal: &dyn Fractal,
+        width: u32,
+        height: u32,
+        keyframe: &Keyframe,
+        budget: usize,
+        max_iter: usize,
+    ) -> IterationBuffer {
+        struct Orbit {
+            c: Complex,
+            z: Complex,
+            iters: usize,
+            bailout: Option<Bailout>,
+            running: bool,
+        }
+
+        let mut orbits: Vec<Orbit> = (0..height)
+            .into_par_iter()
+            .flat_map_iter(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let point = self.projection.map(keyframe, x, y, width, height);
+                let ((cx, cy), (zx, zy)) = self.orbit_start(keyframe, point);
+                let (c, z0) = ((cx as f64, cy as f64), (zx as f64, zy as f64));
+                // Skipped orbits are done without spending any of the budget.
+                let skipped = self.shortcut(fractal, c, z0, max_iter).is_some();
+                Orbit {
+                    c: Complex::new(cx, cy),
+                    z: Complex::new(zx, zy),
+                    iters: if skipped { max_iter } else { 0 },
+                    bailout: None,
+                    running: !skipped && max_iter > 0,
+                }
+            })
+            .collect();
+        // Iterations spent on `orbit` in a round of up to `round` iterations.
+        let advance = |orbit: &mut Orbit, round: usize| {
+            let start = orbit.iters;
+            let limit = (orbit.iters + round).min(max_iter);
+            while orbit.iters < limit {
+                orbit.bailout = fractal.bailout(orbit.z, self.escape_norm);
+                if orbit.bailout.is_some() {
+                    orbit.running = false;
+                    return orbit.iters - start;
+                }
+                orbit.z = fractal.step(orbit.z, orbit.c);
+                orbit.iters += 1;
+            }
+            if orbit.iters < max_iter {
+                orbit.bailout = fractal.bailout(orbit.z, self.escape_norm);
+            }
+            orbit.running = orbit.iters < max_iter && orbit.bailout.is_none();
+            orbit.iters - start
+        };
+        let mut active = orbits.iter().filter(|orbit| orbit.running).count();
+        let mut remaining = budget;
+
+        while active > 0 && remaining > 0 {
+            // Share what is left evenly once a full round no longer fits, and once not even a
+            // single iteration each does, hand them out to the orbits first in line.
+            let round = self.iteration_round.min(remaining / active).max(1);
+            let cutoff = match remaining < active {
+                true => orbits
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, orbit)| orbit.running)
+                    .nth(remaining)
+                    .map_or(orbits.len(), |(i, _)| i),
+                false => orbits.len(),
+            };
+            let spent: usize = orbits[..cutoff]
+                .par_iter_mut()
+                .filter(|orbit| orbit.running)
+                .map(|orbit| advance(orbit, round))
+                .sum();
+            remaining -= spent;
+            active = orbits.iter().filter(|orbit| orbit.running).count();
+        }
+
+        let results = orbits
+            .into_par_iter()
+            .map(|orbit| {
+                let (norm, power) = (self.escape_norm, fractal.power());
+                EscapeResult::from_bailout(orbit.z, orbit.iters, orbit.bailout, norm, power)
+            })
+            .collect();
+        IterationBuffer {
+            width,
+            height,
+            results,
+        }
+    }
+
+    pub fn draw_frame(&self, keyframe: &Keyframe) -> Vec<Pixel> {
+        if let Some(buddhabrot) = &self.density {
+            return buddhabrot.render(&*self.fractal, keyframe, self.width, self.height);
+        }
+        if let Some(grid) = self.basin_grid.filter(|_| self.fractal.basins() > 0) {
+            let animated = self.fractal.animate(keyframe);
+            let fractal = animated.as_deref().unwrap_or(&*self.fractal);
+            let max_iter = self.frame_max_iter(keyframe);
+            let exposure = self
+                .exposures
+                .as_ref()
+                .and_then(|exposures| exposures.get(keyframe.index));
+            let scale = BASIN_SUBPIXELS * grid;
+            let (width, height) = (self.width * scale, self.height * scale);
+            return basin_supersample(self.width, self.height, grid, |x, y| {
+                let point = self.projection.map(keyframe, x, y, width, height);
+                let ((cx, cy), (zx, zy)) = self.orbit_start(keyframe, point);
+                let (c, z0) = (Complex::new(cx, cy), Complex::new(zx, zy));
+                let result = fractal.iterate(c, z0, max_iter, self.escape_norm);
+                let t = match exposure {
+                    Some(exposure) => exposure.apply(result.smooth),
+                    None => result.smooth / self.max_iter as f32,
+                };
+                let basin = fractal.basin(&result).unwrap_or(usize::MAX);
+                (basin, self.shade(&result, t))
+            });
+        }
+        let buffer = self.cached_frame(keyframe);
+        self.color_buffer(keyframe, &buffer)
+    }
+
+    /// `draw_frame` as indices into `indexed_palette`, mapping escape times straight onto its
+    /// colors rather than quantizing the colors they are drawn in. `None` when anything but
+    /// the palette colors the pixels: textures, basins, CVD previews, anaglyphs, densities or
+    /// transparency.
+    pub fn draw_indexed(&self, keyframe: &Keyframe) -> Option<Vec<u8>> {
+        let plain = self.exterior_texture.is_none()
+            && self.interior_texture.is_none()
+            && self.simulate_cvd.is_none()
+            && self.transparency.is_none()
+            && self.anaglyph.is_none()
+            && self.density.is_none()
+            && self.fractal.basins() == 0;
+        if !plain {
+            return None;
+        }
+        let buffer = self.cached_frame(keyframe);
+        let exposure = self
+            .exposures
+            .as_ref()
+            .and_then(|exposures| exposures.get(keyframe.index));
+        let steps = (COLORS - 2) as f32;
+        let indices = buffer
+            .results
+            .par_iter()
+            .map(|result| {
+                if !result.escaped {
+                    return 0;
+                }
+                let t = match exposure {
+                    Some(exposure) => exposure.apply(result.smooth),
+                    None => result.smooth / self.max_iter as f32,
+                };
+                1 + (t.clamp(0.0, 1.0) * steps).round() as u8
+            })
+            .collect();
+        Some(indices)
+    }
+
+    /// The colors `draw_indexed` indexes.
+    pub fn indexed_palette(&self) -> GlobalPalette {
+        GlobalPalette::from_palette(&*self.color_map)
+    }
+
+    /// Iterates `keyframe` into the cache, if there is one and it doesn't hold the frame yet,
+    /// so the renders sharing the cache later only color it.
+    pub fn cache_frame(&self, keyframe: &Keyframe) {
+        if self.cache.is_some() {
+            self.cached_frame(keyframe);
+        }
+    }
+
+    /// Iteration results of `keyframe`, from the cache if there is one.
+    fn cached_frame(&self, keyframe: &Keyframe) -> IterationBuffer {
+        let max_iter = self.frame_max_iter(keyframe);
+        let render = || self.iterate_sized(self.width, self.height, keyframe, max_iter);
+        match &self.cache {
+            Some(cache) => {
+                let name = match self.fractal.animate(keyframe) {
+                    Some(animated) => animated.name(),
+                    None => self.fractal.name(),
+                };
+                let key = IterationKey::new(keyframe, self.width, self.height, max_iter)
+                    .with_escape_norm(self.escape_norm)
+                    .with_border_tracing(self.border_tracing)
+                    .with_periodicity_check(self.periodicity_check)
+                    .with_interior_shortcut(self.shortcuts_interior())
+                    .with_budget(self.iteration_budget, self.iteration_round)
+                    .with_projection(self.projection)
+                    .with_precision(self.precision)
+                    .with_fractal(&name)
+                    .with_julia(self.julia.then_some((keyframe.c_x, keyframe.c_y)))
+                    .with_morph(
+                        if self.julia { 0.0 } else { keyframe.morph },
+                        (keyframe.c_x, keyframe.c_y),
+                    );
+                cache.get_or_compute(key, render)
+            }
+            None => render(),
+        }
+    }
+
+    /// Colors the iteration results of `keyframe`, e.g. ones read back with `read_npy`.
+    pub fn color_buffer(&self, keyframe: &Keyframe, buffer: &IterationBuffer) -> Vec<Pixel> {
+        self.color_turned(keyframe, buffer, |t| t)
+    }
+
+    /// `color_buffer` with the palette turned by `offset`, a fraction of its length, its end
+    /// wrapping around to its start. Coloring one buffer again at growing offsets cycles its
+    /// colors, which animates a still view for no more than the cost of coloring it.
+    pub fn color_cycled(
+        &self,
+        keyframe: &Keyframe,
+        buffer: &IterationBuffer,
+        offset: f32,
+    ) -> Vec<Pixel> {
+        self.color_turned(keyframe, buffer, |t| (t + offset).rem_euclid(1.0))
+    }
+
+    /// `color_buffer` with the palette positions of escaped points passed through `turn`.
+    fn color_turned(
+        &self,
+        keyframe: &Keyframe,
+        buffer: &IterationBuffer,
+        turn: impl Fn(f32) -> f32 + Sync,
+    ) -> Vec<Pixel> {
+        let exposure = self
+            .exposures
+            .as_ref()
+            .and_then(|exposures| exposures.get(keyframe.index));
+        let pixels: Vec<Pixel> = buffer
+            .results
+            .par_iter()
+            .map(|result| {
+                let t = match exposure {
+                    Some(exposure) => exposure.apply(result.smooth),
+                    None => result.smooth / self.max_iter as f32,
+                };
+                self.shade(result, turn(t))
+            })
+            .collect();
+
+        match self.anaglyph {
+            Some(parallax) => anaglyph(buffer, &pixels, self.max_iter, parallax),
+            None => pixels,
+        }
+    }
+
+    /// Iterates a frame around the full precision center of `frame`, in fixed point under
+    /// `Precision::Arbitrary` and by perturbation otherwise, with the bits `frame`'s zoom needs.
+    /// Formulas other than the Mandelbrot set only get its center rounded to f64.
+    #[cfg(feature = "bigfloat")]
+    pub fn iterate_deep_frame(&self, frame: &DeepKeyframe) -> IterationBuffer {
+        let keyframe = frame.to_keyframe();
+        let animated = self.fractal.animate(&keyframe);
+        let fractal = animated.as_deref().unwrap_or(&*self.fractal);
+        if fractal.name() != Mandelbrot.name() {
+            return self.iterate_frame(&keyframe);
+        }
+        let fraction_bits = frame.fraction_bits();
+        let center = frame.center(fraction_bits);
+        let (width, height) = (self.width, self.height);
+        let max_iter = self.frame_max_iter(&keyframe);
+        match self.precision {
+            Precision::Arbitrary { .. } => {
+                self.iterate_arbitrary(fraction_bits, center, width, height, &keyframe, max_iter)
+            }
+            _ => self.iterate_perturbed(fraction_bits, center, width, height, &keyframe, max_iter),
+        }
+    }
+
+    /// `draw_frame` for a deep keyframe. Bypasses the iteration cache, whose keys only hold
+    /// the center to f64.
+    #[cfg(feature = "bigfloat")]
+    pub fn draw_deep_frame(&self, frame: &DeepKeyframe) -> Vec<Pixel> {
+        let buffer = self.iterate_deep_frame(frame);
+        self.color_buffer(&frame.to_keyframe(), &buffer)
+    }
+
+    pub fn render_frame(&self, keyframe: &Keyframe) -> Frame {
+        let pixels = self.draw_frame(keyframe);
+        Frame::from_pixels(self.width as u16, self.height as u16, pixels)
+    }
+
+    /// Renders `keyframes` in parallel, in order.
+    pub fn render_frames(&self, keyframes: &[Keyframe]) -> Vec<Frame> {
+        keyframes
+            .par_iter()
+            .map(|keyframe| self.render_frame(keyframe))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A view deeper than f64 resolves at the tip of the needle, where nearly every pixel
+    /// escapes after thousands of iterations.
+    fn needle() -> Keyframe {
+        Keyframe::view(-2.0, 0.0, 1e-18, 0.75e-18)
+    }
+
+    fn interior(buffer: &IterationBuffer) -> usize {
+        buffer
+            .results
+            .iter()
+            .filter(|result| !result.escaped)
+            .count()
+    }
+
+    /// The whole set, where every precision resolves the pixels.
+    fn whole_set() -> Keyframe {
+        Keyframe::view(-0.5, 0.0, 3.0, 2.0)
+    }
+
+    fn escapes(buffer: &IterationBuffer) -> Vec<(bool, usize)> {
+        let results = buffer.results.iter();
+        results.map(|r| (r.escaped, r.iterations)).collect()
+    }
+
+    #[test]
+    fn frames_have_the_renderer_size_and_colors() {
+        let renderer = Renderer::new(48, 32, 200);
+        let frame = renderer.render_frame(&whole_set());
+        assert_eq!((frame.inner.width, frame.inner.height), (48, 32));
+        assert_eq!(frame.inner.buffer.len(), 48 * 32);
+
+        // Quantizing onto the frame's palette keeps each pixel near its drawn color.
+        let drawn = renderer.draw_frame(&whole_set());
+        let palette = frame.inner.palette.as_deref().unwrap();
+        for (index, pixel) in [(16 * 48 + 24, drawn[16 * 48 + 24]), (0, drawn[0])] {
+            let color = &palette[3 * frame.inner.buffer[index] as usize..][..3];
+            let channels = [pixel.r, pixel.g, pixel.b];
+            assert!(color.iter().zip(channels).all(|(a, b)| a.abs_diff(b) <= 8));
+        }
+        assert_ne!(drawn[16 * 48 + 24], drawn[0]);
+    }
+
+    #[test]
+    fn precisions_agree_on_shallow_views() {
+        let render = |precision| {
+            Renderer::new(64, 48, 500)
+                .with_precision(precision)
+                .iterate_frame(&whole_set())
+        };
+        let double = escapes(&render(Precision::Double));
+        assert_eq!(escapes(&render(Precision::DoubleDouble)), double);
+        // f32 rounding moves the odd pixel on the boundary by an iteration.
+        let single = escapes(&render(Precision::Single));
+        let mismatched = single.iter().zip(&double).filter(|(a, b)| a != b).count();
+        assert!(mismatched * 100 <= double.len());
+
+        #[cfg(feature = "bigfloat")]
+        {
+            let arbitrary = escapes(&render(Precision::Arbitrary { fraction_bits: 64 }));
+            assert_eq!(arbitrary, double);
+            let perturbation = escapes(&render(Precision::Perturbation { fraction_bits: 64 }));
+            let mismatched = perturbation
+                .iter()
+                .zip(&double)
+                .filter(|(a, b)| a != b)
+                .count();
+            assert!(mismatched * 100 <= double.len());
+        }
+    }
+
+    #[test]
+    fn double_double_cycles_compare_low_parts() {
+        let renderer = Renderer::new(64, 48, 3000).with_precision(Precision::DoubleDouble);
+        let checked = renderer.iterate_frame(&needle());
+        let unchecked = renderer
+            .with_periodicity_check(false)
+            .iterate_frame(&needle());
+        assert_eq!(interior(&checked), interior(&unchecked));
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn double_double_agrees_with_arbitrary_past_f64() {
+        let render = |precision| {
+            Renderer::new(64, 48, 3000)
+                .with_precision(precision)
+                .iterate_frame(&needle())
+        };
+        let double_double = render(Precision::DoubleDouble);
+        let arbitrary = render(Precision::Arbitrary { fraction_bits: 128 });
+        let mismatched = double_double
+            .results
+            .iter()
+            .zip(&arbitrary.results)
+            .filter(|(a, b)| a.escaped != b.escaped)
+            .count();
+        assert_eq!(mismatched, 0);
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn perturbation_agrees_with_arbitrary_deep_in() {
+        // Just off the tip of the needle, thinner than f64 resolves: the center's orbit escapes
+        // after 28 iterations, and most pixels' long after.
+        let view = Keyframe::view(-2.000_000_000_000_000_4, 0.0, 4e-15, 3e-30);
+        let render = |precision| {
+            Renderer::new(32, 24, 2000)
+                .with_precision(precision)
+                .iterate_frame(&view)
+        };
+        let perturbation = render(Precision::Perturbation { fraction_bits: 128 });
+        let arbitrary = render(Precision::Arbitrary { fraction_bits: 128 });
+        assert_eq!(escapes(&perturbation), escapes(&arbitrary));
+        for (a, b) in perturbation.results.iter().zip(&arbitrary.results) {
+            assert!((a.smooth - b.smooth).abs() < 1e-3);
+        }
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn empty_views_iterate_nothing() {
+        for (width, height) in [(0, 24), (32, 0)] {
+            let renderer = Renderer::new(width, height, 100)
+                .with_precision(Precision::Perturbation { fraction_bits: 128 });
+            assert!(renderer.iterate_frame(&needle()).results.is_empty());
+        }
+    }
+
+    #[test]
+    fn budgets_cap_the_iterations_of_a_frame() {
+        let render = |budget: Option<usize>| {
+            let renderer = Renderer::new(64, 48, 5000)
+                .with_interior_shortcut(false)
+                .with_periodicity_check(false);
+            let renderer = match budget {
+                Some(budget) => renderer.with_iteration_budget(budget, 256),
+                None => renderer,
+            };
+            renderer.iterate_frame(&whole_set())
+        };
+        let unbudgeted = render(None);
+        let spent = |buffer: &IterationBuffer| -> usize {
+            buffer.results.iter().map(|result| result.iterations).sum()
+        };
+        let total = spent(&unbudgeted);
+        // A budget the frame fits in changes nothing.
+        assert_eq!(escapes(&render(Some(total))), escapes(&unbudgeted));
+
+        let budgeted = render(Some(total / 4));
+        assert!(spent(&budgeted) <= total / 4);
+        // Every pixel gets its first round before the deep ones use up the rest.
+        for (budgeted, unbudgeted) in budgeted.results.iter().zip(&unbudgeted.results) {
+            if unbudgeted.iterations < 256 {
+                assert_eq!(budgeted.escaped, unbudgeted.escaped);
+                assert_eq!(budgeted.iterations, unbudgeted.iterations);
+            }
+        }
+        assert!(interior(&budgeted) > interior(&unbudgeted));
+    }
+
+    #[test]
+    fn cached_frames_follow_the_escape_norm() {
+        let dir = std::env::temp_dir().join("mandelbrot-escape-norm-cache");
+        let _ = std::fs::remove_dir_all(&dir);
+        let renderer = |norm| Renderer::new(32, 24, 100).with_escape_norm(norm);
+        let cached = |norm| {
+            let renderer = renderer(norm).with_cache(IterationCache::new(&dir));
+            renderer.draw_frame(&whole_set())
+        };
+        let wide = cached(8192.0);
+        let narrow = renderer(4.0).draw_frame(&whole_set());
+        assert_ne!(narrow, wide);
+        assert_eq!(cached(4.0), narrow);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn batches_match_iterating_pixels_one_by_one() {
+        // The set and its surroundings, in rows that end in a short batch.
+        let (width, height) = (37, 23);
+        assert_ne!(width as usize % LANES, 0);
+        let view = Keyframe::view(-0.75, 0.1, 3.0, 2.0);
+        for shortcut in [false, true] {
+            let renderer = Renderer::new(width, height, 300)
+                .with_periodicity_check(false)
+                .with_interior_shortcut(shortcut);
+            let batched = renderer.iterate_frame(&view);
+            let escaped = batched.results.iter().filter(|r| r.escaped).count();
+            assert!(escaped > 0 && escaped < batched.results.len());
+            for (i, result) in batched.results.iter().enumerate() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                let point = renderer.projection.map(&view, x, y, width, height);
+                let ((cx, cy), (zx, zy)) = renderer.orbit_start(&view, point);
+                let (c, z0) = (Complex::new(cx, cy), Complex::new(zx, zy));
+                let scalar = Mandelbrot.iterate(c, z0, 300, renderer.escape_norm);
+                assert_eq!(result.escaped, scalar.escaped, "pixel {x}, {y}");
+                assert_eq!(result.iterations, scalar.iterations, "pixel {x}, {y}");
+                if scalar.escaped {
+                    assert_eq!(result.smooth, scalar.smooth, "pixel {x}, {y}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// One work item per pixel of a flat view, iterating {formula} in float and writing back what
+// the CPU colors from: the final orbit value, the iteration count and whether it escaped.
+// Unsuffixed constants are meant as floats, see `OPENCL_BUILD_OPTIONS`.
+
+typedef struct {
+    float2 center;
+    float2 size;
+    float2 c;
+    float2 z0;
+    uint width;
+    uint height;
+    uint max_iter;
+    float escape_norm;
+    // How far the view is morphed toward the Julia set of `c`, as in `Renderer::orbit_start`.
+    float morph;
+} Frame;
+
+typedef struct {
+    float2 z;
+    uint iterations;
+    uint escaped;
+} Orbit;
+
+__kernel void iterate(Frame frame, __global Orbit *orbits) {
+    uint px = get_global_id(0);
+    uint py = get_global_id(1);
+    if (px >= frame.width || py >= frame.height) {
+        return;
+    }
+    // `Keyframe::get_coordinate`.
+    float2 corner = frame.center + (float2)(-frame.size.x, frame.size.y) / 2.0;
+    float2 fraction = (float2)((float)px / frame.width, (float)py / frame.height);
+    float2 point = corner + (float2)(fraction.x, -fraction.y) * frame.size;
+    float2 c = mix(point, frame.c, frame.morph);
+    float cx = c.x;
+    float cy = c.y;
+    float2 z = mix(frame.z0, point, frame.morph);
+
+    uint iterations = 0;
+    uint escaped = 0;
+    while (iterations < frame.max_iter) {
+        float x = z.x;
+        float y = z.y;
+        if (x * x + y * y >= frame.escape_norm) {
+            escaped = 1;
+            break;
+        }
+        z = (float2)({x}, {y});
+        iterations++;
+    }
+    Orbit orbit = {z, iterations, escaped};
+    orbits[py * frame.width + px] = orbit;
+}
@@ -0,0 +1,53 @@
+// One invocation per pixel of a flat view, iterating {formula} in f32 and writing back what
+// the CPU colors from: the final orbit value, the iteration count and whether it escaped.
+
+struct Frame {
+    center: vec2<f32>,
+    size: vec2<f32>,
+    c: vec2<f32>,
+    z0: vec2<f32>,
+    width: u32,
+    height: u32,
+    max_iter: u32,
+    escape_norm: f32,
+    // How far the view is morphed toward the Julia set of `c`, as in `Renderer::orbit_start`.
+    morph: f32,
+}
+
+struct Orbit {
+    z: vec2<f32>,
+    iterations: u32,
+    escaped: u32,
+}
+
+@group(0) @binding(0) var<uniform> frame: Frame;
+@group(0) @binding(1) var<storage, read_write> orbits: array<Orbit>;
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= frame.width || id.y >= frame.height {
+        return;
+    }
+    // `Keyframe::get_coordinate`.
+    let corner = frame.center + vec2<f32>(-frame.size.x, frame.size.y) / 2.0;
+    let fraction = vec2<f32>(f32(id.x) / f32(frame.width), f32(id.y) / f32(frame.height));
+    let point = corner + vec2<f32>(fraction.x, -fraction.y) * frame.size;
+    let c = mix(point, frame.c, frame.morph);
+    let cx = c.x;
+    let cy = c.y;
+    var z = mix(frame.z0, point, frame.morph);
+
+    var iterations = 0u;
+    var escaped = 0u;
+    while iterations < frame.max_iter {
+        let x = z.x;
+        let y = z.y;
+        if x * x + y * y >= frame.escape_norm {
+            escaped = 1u;
+            break;
+        }
+        z = vec2<f32>({x}, {y});
+        iterations += 1u;
+    }
+    orbits[id.y * frame.width + id.x] = Orbit(z, iterations, escaped);
+}
@@ -0,0 +1,132 @@
+use crate::{IterationBuffer, Pixel};
+
+/// Composites a red-cyan anaglyph from a colored frame and its iteration results. The escape
+/// time is used as a heightfield: the set itself stands out of the screen and the exterior sinks
+/// behind it, shifted by up to `parallax` pixels between the two eyes.
+pub fn anaglyph(
+    buffer: &IterationBuffer,
+    pixels: &[Pixel],
+    max_iter: usize,
+    parallax: f32,
+) -> Vec<Pixel> {
+    let width = buffer.width as usize;
+    if width == 0 {
+        return Vec::new();
+    }
+    let heights: Vec<f32> = buffer
+        .results
+        .iter()
+        .map(|result| {
+            if result.escaped {
+                // Logarithmic, so the first few bands around the set don't take all the depth.
+                (result.smooth.max(0.0) + 1.0).ln() / (max_iter as f32 + 1.0).ln()
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    let mut out = Vec::with_capacity(pixels.len());
+    for (row, row_heights) in pixels.chunks(width).zip(heights.chunks(width)) {
+        let left = view(row, row_heights, parallax / 2.0);
+        let right = view(row, row_heights, -parallax / 2.0);
+        out.extend(left.iter().zip(&right).map(|(l, r)| Pixel {
+            r: l.r,
+            g: r.g,
+            b: r.b,
+            a: 255,
+        }));
+    }
+    out
+}
+
+/// One eye's view of a row, with each pixel moved `shift * (height - 0.5)` pixels sideways so
+/// mid-height lies on the screen plane. Higher pixels hide lower ones, and the gaps opened up
+/// behind them are filled from the nearest lower neighbor.
+fn view(row: &[Pixel], heights: &[f32], shift: f32) -> Vec<Pixel> {
+    let mut target: Vec<Option<(Pixel, f32)>> = vec![None; row.len()];
+    for (x, (pixel, height)) in row.iter().zip(heights).enumerate() {
+        let moved = (x as f32 + shift * (height - 0.5)).round();
+        if moved < 0.0 || moved >= row.len() as f32 {
+            continue;
+        }
+        let slot = &mut target[moved as usize];
+        if slot.is_none_or(|(_, h)| *height >= h) {
+            *slot = Some((*pixel, *height));
+        }
+    }
+
+    let mut filled = Vec::with_capacity(row.len());
+    for x in 0..row.len() {
+        let pixel = match target[x] {
+            Some((pixel, _)) => pixel,
+            None => {
+                let before = target[..x].iter().rev().flatten().next();
+                let after = target[x + 1..].iter().flatten().next();
+                match (before, after) {
+                    (Some(a), Some(b)) => {
+                        if a.1 <= b.1 {
+                            a.0
+                        } else {
+                            b.0
+                        }
+                    }
+                    (Some(a), None) | (None, Some(a)) => a.0,
+                    (None, None) => row[x],
+                }
+            }
+        };
+        filled.push(pixel);
+    }
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EscapeResult;
+
+    #[test]
+    fn eyes_go_to_their_channels() {
+        // A row of the exterior at the bottom with one interior pixel standing out of it.
+        let results: Vec<EscapeResult> = (0..9)
+            .map(|x| EscapeResult {
+                escaped: x != 4,
+                iterations: 0,
+                smooth: 0.0,
+                z: (0.0, 0.0),
+            })
+            .collect();
+        let buffer = IterationBuffer {
+            width: 9,
+            height: 1,
+            results,
+        };
+        let pixels: Vec<Pixel> = (0..9)
+            .map(|x| Pixel {
+                r: 10 * x,
+                g: 10 * x + 1,
+                b: 10 * x + 2,
+                a: 255,
+            })
+            .collect();
+        let out = anaglyph(&buffer, &pixels, 100, 4.0);
+        assert_eq!(out.len(), 9);
+        // The left eye sees the interior pixel a pixel to the right, the right eye one to the left.
+        assert_eq!(out[5].r, pixels[4].r);
+        assert_eq!((out[3].g, out[3].b), (pixels[4].g, pixels[4].b));
+        // And the exterior around it the other way.
+        assert_eq!(out[1].r, pixels[2].r);
+        assert_eq!((out[7].g, out[7].b), (pixels[6].g, pixels[6].b));
+    }
+
+    #[test]
+    fn empty_frames_stay_empty() {
+        let buffer = IterationBuffer {
+            width: 0,
+            height: 3,
+            results: Vec::new(),
+        };
+        assert!(anaglyph(&buffer, &[], 100, 4.0).is_empty());
+    }
+}
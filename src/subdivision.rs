@@ -0,0 +1,100 @@
+use rayon::prelude::*;
+
+use crate::EscapeResult;
+
+/// Rectangles are never filled at a larger size than this, so a border that runs all around
+/// the set in a single escape band, as the edges of a zoomed out frame do, isn't mistaken for
+/// one with nothing inside.
+const MAX_FILL: u32 = 32;
+
+/// Rectangles with a side this short or shorter are iterated pixel by pixel once their border
+/// differs, as splitting them saves next to nothing.
+const MIN_SPLIT: u32 = 4;
+
+/// Mariani-Silver subdivision of a `width` by `height` frame: the border of a rectangle is
+/// iterated first, and if all of it agrees on escaping and the iteration count, so does the
+/// inside, which is filled in without iterating it. Rectangles with a mixed border are split in
+/// two and traced again. Filled escaped pixels get their smooth count blended from the corners.
+///
+/// The inside agrees with a uniform border only for formulas with connected sets, like the
+/// Mandelbrot set. Disconnected Julia sets and the like can lose small islands.
+///
+/// Bands of `MAX_FILL` rows are traced in parallel. The row two bands share is a border of
+/// both, so it is iterated twice rather than filled, and either band has the same results.
+pub fn mariani_silver(
+    width: u32,
+    height: u32,
+    pixel: impl Fn(u32, u32) -> EscapeResult + Sync,
+) -> Vec<EscapeResult> {
+    let bands: Vec<u32> = (0..height).step_by(MAX_FILL as usize).collect();
+    bands
+        .into_par_iter()
+        .flat_map_iter(|top| {
+            // The starting grid keeps every rectangle within `MAX_FILL`.
+            let bottom = (top + MAX_FILL).min(height - 1);
+            let rows = bottom - top + 1;
+            let mut results = vec![None; (width * rows) as usize];
+            let band = |x: u32, y: u32| pixel(x, top + y);
+            for x0 in (0..width).step_by(MAX_FILL as usize) {
+                let x1 = (x0 + MAX_FILL).min(width - 1);
+                trace(&mut results, width, (x0, 0, x1, rows - 1), &band);
+            }
+            // All but the shared row, which the next band has as well.
+            results.truncate((width * MAX_FILL.min(height - top)) as usize);
+            results.into_iter().map(Option::unwrap)
+        })
+        .collect()
+}
+
+/// Fills the rectangle from `(x0, y0)` to `(x1, y1)`, both included.
+fn trace(
+    results: &mut [Option<EscapeResult>],
+    width: u32,
+    (x0, y0, x1, y1): (u32, u32, u32, u32),
+    pixel: &impl Fn(u32, u32) -> EscapeResult,
+) {
+    let mut at =
+        |x: u32, y: u32| *results[(y * width + x) as usize].get_or_insert_with(|| pixel(x, y));
+    let first = at(x0, y0);
+    let mut uniform = true;
+    let rows = (x0..=x1).flat_map(|x| [(x, y0), (x, y1)]);
+    let columns = (y0..=y1).flat_map(|y| [(x0, y), (x1, y)]);
+    for (x, y) in rows.chain(columns) {
+        let result = at(x, y);
+        uniform &= result.escaped == first.escaped && result.iterations == first.iterations;
+    }
+    if x1 - x0 < 2 || y1 - y0 < 2 {
+        return;
+    }
+
+    if uniform {
+        let corners = [at(x0, y0), at(x1, y0), at(x0, y1), at(x1, y1)].map(|result| result.smooth);
+        for y in y0 + 1..y1 {
+            for x in x0 + 1..x1 {
+                let tx = (x - x0) as f32 / (x1 - x0) as f32;
+                let ty = (y - y0) as f32 / (y1 - y0) as f32;
+                let top = corners[0] + (corners[1] - corners[0]) * tx;
+                let bottom = corners[2] + (corners[3] - corners[2]) * tx;
+                let smooth = match first.escaped {
+                    true => top + (bottom - top) * ty,
+                    false => first.smooth,
+                };
+                results[(y * width + x) as usize] = Some(EscapeResult { smooth, ..first });
+            }
+        }
+    } else if x1 - x0 <= MIN_SPLIT || y1 - y0 <= MIN_SPLIT {
+        for y in y0 + 1..y1 {
+            for x in x0 + 1..x1 {
+                at(x, y);
+            }
+        }
+    } else if x1 - x0 >= y1 - y0 {
+        let middle = (x0 + x1) / 2;
+        trace(results, width, (x0, y0, middle, y1), pixel);
+        trace(results, width, (middle, y0, x1, y1), pixel);
+    } else {
+        let middle = (y0 + y1) / 2;
+        trace(results, width, (x0, y0, x1, middle), pixel);
+        trace(results, width, (x0, middle, x1, y1), pixel);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Keyframe, Pixel};
+
+/// `view` grown by `overlap` pixels to the right and bottom, the region to render at
+/// `width + overlap` by `height + overlap` for `make_tileable`.
+pub fn tile_view(view: &Keyframe, width: u32, height: u32, overlap: u32) -> Keyframe {
+    let x_size = view.x_size * (width + overlap) as f64 / width as f64;
+    let y_size = view.y_size * (height + overlap) as f64 / height as f64;
+    Keyframe {
+        x_center: view.x_center + (x_size - view.x_size) / 2.0,
+        y_center: view.y_center - (y_size - view.y_size) / 2.0,
+        x_size,
+        y_size,
+        ..*view
+    }
+}
+
+/// Turns a render of `tile_view` into a `width` by `height` image that repeats seamlessly, by
+/// cross-fading the overlap past each edge into the start of the opposite one.
+pub fn make_tileable(pixels: &[Pixel], width: u32, height: u32, overlap: u32) -> Vec<Pixel> {
+    let (width, height, overlap) = (width as usize, height as usize, overlap as usize);
+    let stride = width + overlap;
+    let fade = |near: Pixel, far: Pixel, i: usize| {
+        let t = (i as f32 + 0.5) / overlap as f32;
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Pixel {
+            r: mix(far.r, near.r),
+            g: mix(far.g, near.g),
+            b: mix(far.b, near.b),
+            a: mix(far.a, near.a),
+        }
+    };
+
+    // Columns first, over every row including the overlapping ones, then rows.
+    let columns: Vec<Pixel> = (0..height + overlap)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let near = pixels[y * stride + x];
+            if x < overlap {
+                fade(near, pixels[y * stride + x + width], x)
+            } else {
+                near
+            }
+        })
+        .collect();
+
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let near = columns[y * width + x];
+            if y < overlap {
+                fade(near, columns[(y + height) * width + x], y)
+            } else {
+                near
+            }
+        })
+        .collect()
+}
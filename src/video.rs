@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::{AnimationError, Pixel};
+
+/// An H.264 video encoded by an `ffmpeg` subprocess from the `PATH`, which gets the frames as
+/// raw RGB on its standard input, as the crate links no video encoder of its own. The container
+/// follows the extension of the output, e.g. `.mp4` or `.mkv`.
+pub struct Video {
+    ffmpeg: Child,
+    input: ChildStdin,
+    width: u16,
+    height: u16,
+}
+
+impl Video {
+    pub fn new(
+        path: impl AsRef<Path>,
+        width: u16,
+        height: u16,
+        framerate: f32,
+    ) -> Result<Self, AnimationError> {
+        let size = format!("{}x{}", width, height);
+        let framerate = framerate.to_string();
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(["-loglevel", "error", "-y"])
+            .args([
+                "-f", "rawvideo", "-pix_fmt", "rgb24", "-s", &size, "-r", &framerate,
+            ])
+            .args(["-i", "-"])
+            // Players expect 4:2:0 chroma, whose planes need even sides.
+            .args([
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .args(["-c:v", "libx264", "-crf", "18"])
+            .arg(path.as_ref())
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|_| AnimationError::EncoderError)?;
+        let input = ffmpeg.stdin.take().ok_or(AnimationError::EncoderError)?;
+        Ok(Self {
+            ffmpeg,
+            input,
+            width,
+            height,
+        })
+    }
+
+    /// Sends the `width` by `height` `pixels` of the next frame to the encoder.
+    pub fn push_frame(&mut self, pixels: &[Pixel]) -> Result<(), AnimationError> {
+        if pixels.len() != self.width as usize * self.height as usize {
+            return Err(AnimationError::FrameCreateError);
+        }
+        let raw: Vec<u8> = pixels.iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+        self.input
+            .write_all(&raw)
+            .map_err(|_| AnimationError::FrameEncodeError)
+    }
+
+    /// Closes the encoder's input and waits for it to finish the file.
+    pub fn write_animation(self) -> Result<(), AnimationError> {
+        let mut ffmpeg = self.ffmpeg;
+        drop(self.input);
+        match ffmpeg.wait() {
+            Ok(status) if status.success() => Ok(()),
+            _ => Err(AnimationError::FrameEncodeError),
+        }
+    }
+}
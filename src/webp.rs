@@ -0,0 +1,663 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::png::BitWriter;
+use crate::{AnimationError, Pixel, Repeat};
+
+/// Largest frame side lossless WebP can hold.
+const MAX_SIDE: u16 = 1 << 14;
+
+/// Longest match, and how many pixels back matches are looked for.
+const MAX_MATCH: usize = 4096;
+const WINDOW: usize = 1 << 16;
+/// Earlier positions with the same hash tried per pixel.
+const MAX_CHAIN: usize = 32;
+
+/// Green codes past the 256 literals that stand for match lengths.
+const LENGTH_CODES: usize = 24;
+const DISTANCE_CODES: usize = 40;
+/// Distance codes up to this one stand for pixels nearby in 2D, of which only the one above and
+/// the one to the left are used here. Distance `d` is code `d + NEIGHBORHOOD` otherwise.
+const NEIGHBORHOOD: usize = 120;
+
+/// Order in which the lengths of the code length code are written.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    17, 18, 0, 1, 2, 3, 4, 5, 16, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// An animated WebP of lossless frames, full color like `Apng` but usually a good deal
+/// smaller. Frames are compressed as they are pushed and written by `write_animation`, as the
+/// file starts with its own size.
+pub struct WebP<W: Write = BufWriter<File>> {
+    writer: W,
+    width: u16,
+    height: u16,
+    /// Frame duration in milliseconds.
+    delay: u32,
+    repeat: Repeat,
+    frames: Vec<Vec<u8>>,
+}
+
+impl WebP {
+    pub fn new(
+        path: impl AsRef<Path>,
+        width: u16,
+        height: u16,
+        framerate: f32,
+    ) -> Result<Self, AnimationError> {
+        let file = File::create(path).map_err(|_| AnimationError::FileCreateError)?;
+        Ok(Self::from_writer(
+            BufWriter::new(file),
+            width,
+            height,
+            framerate,
+        ))
+    }
+}
+
+impl<W: Write> WebP<W> {
+    pub fn from_writer(writer: W, width: u16, height: u16, framerate: f32) -> Self {
+        Self {
+            writer,
+            width,
+            height,
+            delay: (1000.0 / framerate).round() as u32,
+            repeat: Repeat::Once,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Sets how often the WebP plays, `Repeat::Once` unless changed.
+    pub fn repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+
+    /// Compresses the `width` by `height` `pixels` of the next frame. Alpha is dropped, as the
+    /// frames are written opaque.
+    pub fn push_frame(&mut self, pixels: &[Pixel]) -> Result<(), AnimationError> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let sides = 1..=MAX_SIDE;
+        if pixels.len() != width * height
+            || !sides.contains(&self.width)
+            || !sides.contains(&self.height)
+        {
+            return Err(AnimationError::FrameCreateError);
+        }
+        self.frames.push(lossless(pixels, width, height));
+        Ok(())
+    }
+
+    pub fn write_animation(mut self) -> Result<(), AnimationError> {
+        let u24 = |value: u32| value.to_le_bytes()[..3].to_vec();
+        let (width, height) = (self.width as u32, self.height as u32);
+        if width == 0 || height == 0 {
+            return Err(AnimationError::FrameEncodeError);
+        }
+        let mut body = b"WEBP".to_vec();
+
+        // Canvas flags: animated, without alpha or metadata.
+        let canvas = [vec![0x02, 0, 0, 0], u24(width - 1), u24(height - 1)].concat();
+        chunk(&mut body, b"VP8X", &canvas);
+        // A black background, and the loop count.
+        let plays = u16::try_from(self.repeat.plays())
+            .unwrap_or(u16::MAX)
+            .to_le_bytes();
+        chunk(&mut body, b"ANIM", &[0, 0, 0, 0xff, plays[0], plays[1]]);
+        for data in &self.frames {
+            // At the origin, replacing the canvas rather than blended over it.
+            let mut frame = [u24(0), u24(0), u24(width - 1), u24(height - 1)].concat();
+            frame.extend(u24(self.delay));
+            frame.push(0x02);
+            chunk(&mut frame, b"VP8L", data);
+            chunk(&mut body, b"ANMF", &frame);
+        }
+
+        let size = (body.len() as u32).to_le_bytes();
+        [b"RIFF", &size[..], &body]
+            .iter()
+            .try_for_each(|bytes| self.writer.write_all(bytes))
+            .and_then(|_| self.writer.flush())
+            .map_err(|_| AnimationError::FrameEncodeError)
+    }
+}
+
+/// Appends a RIFF chunk, padded to an even length.
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend(kind);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+enum Token {
+    Literal(u32),
+    Copy { length: usize, distance: usize },
+}
+
+/// A VP8L bitstream of `pixels`, with green subtracted from red and blue and runs that repeat
+/// earlier pixels, such as the rows above, coded as copies.
+fn lossless(pixels: &[Pixel], width: usize, height: usize) -> Vec<u8> {
+    let argb: Vec<u32> = pixels
+        .iter()
+        .map(|p| {
+            let (r, b) = (p.r.wrapping_sub(p.g), p.b.wrapping_sub(p.g));
+            u32::from_be_bytes([0xff, r, p.g, b])
+        })
+        .collect();
+    let tokens = backward_references(&argb, width);
+
+    let mut green = vec![0; 256 + LENGTH_CODES];
+    let (mut red, mut blue, mut alpha) = (vec![0; 256], vec![0; 256], vec![0; 256]);
+    let mut distances = vec![0; DISTANCE_CODES];
+    for token in &tokens {
+        match *token {
+            Token::Literal(pixel) => {
+                let [a, r, g, b] = pixel.to_be_bytes();
+                green[g as usize] += 1;
+                red[r as usize] += 1;
+                blue[b as usize] += 1;
+                alpha[a as usize] += 1;
+            }
+            Token::Copy { length, distance } => {
+                green[256 + prefix(length).0] += 1;
+                distances[prefix(distance_code(distance, width)).0] += 1;
+            }
+        }
+    }
+
+    let mut bits = BitWriter::default();
+    bits.write(0x2f, 8);
+    bits.write(width as u32 - 1, 14);
+    bits.write(height as u32 - 1, 14);
+    // No alpha, version 0.
+    bits.write(0, 4);
+    // The subtract green transform, and no others.
+    bits.write(1, 1);
+    bits.write(2, 2);
+    bits.write(0, 1);
+    // No color cache and a single group of codes for the whole frame.
+    bits.write(0, 2);
+    let green = write_code(&mut bits, &green);
+    let red = write_code(&mut bits, &red);
+    let blue = write_code(&mut bits, &blue);
+    let alpha = write_code(&mut bits, &alpha);
+    let distances = write_code(&mut bits, &distances);
+
+    let symbol = |bits: &mut BitWriter, (lengths, codes): &(Vec<u8>, Vec<u32>), s: usize| {
+        if lengths[s] > 0 {
+            bits.code(codes[s], lengths[s] as u32);
+        }
+    };
+    for token in tokens {
+        match token {
+            Token::Literal(pixel) => {
+                let [a, r, g, b] = pixel.to_be_bytes();
+                symbol(&mut bits, &green, g as usize);
+                symbol(&mut bits, &red, r as usize);
+                symbol(&mut bits, &blue, b as usize);
+                symbol(&mut bits, &alpha, a as usize);
+            }
+            Token::Copy { length, distance } => {
+                let (code, extra_bits, extra) = prefix(length);
+                symbol(&mut bits, &green, 256 + code);
+                bits.write(extra, extra_bits);
+                let (code, extra_bits, extra) = prefix(distance_code(distance, width));
+                symbol(&mut bits, &distances, code);
+                bits.write(extra, extra_bits);
+            }
+        }
+    }
+    bits.finish()
+}
+
+/// Greedy matches of at least three pixels, trying the pixel to the left and the one above
+/// before those with the same hash.
+fn backward_references(argb: &[u32], width: usize) -> Vec<Token> {
+    let hash = |i: usize| {
+        let key = argb[i] ^ argb[i + 1].rotate_left(16);
+        (key.wrapping_mul(0x9e37_79b1) >> 16) as usize
+    };
+    let mut head = vec![usize::MAX; 1 << 16];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let insert = |i: usize, head: &mut [usize], prev: &mut [usize]| {
+        if i + 2 <= argb.len() {
+            let h = hash(i);
+            prev[i % WINDOW] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < argb.len() {
+        let limit = MAX_MATCH.min(argb.len() - i);
+        let matching = |candidate: usize| {
+            (0..limit)
+                .take_while(|&k| argb[candidate + k] == argb[i + k])
+                .count()
+        };
+        let mut best = (0, 0);
+        for distance in [1, width] {
+            if distance <= i {
+                best = best.max((matching(i - distance), distance));
+            }
+        }
+        if i + 2 <= argb.len() {
+            let mut candidate = head[hash(i)];
+            let mut chain = 0;
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let length = matching(candidate);
+                if length > best.0 {
+                    best = (length, i - candidate);
+                }
+                let next = prev[candidate % WINDOW];
+                if next == usize::MAX || next >= candidate {
+                    break;
+                }
+                candidate = next;
+                chain += 1;
+            }
+        }
+
+        let (length, distance) = best;
+        if length >= 3 {
+            tokens.push(Token::Copy { length, distance });
+            for k in i..i + length {
+                insert(k, &mut head, &mut prev);
+            }
+            i += length;
+        } else {
+            tokens.push(Token::Literal(argb[i]));
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    tokens
+}
+
+/// Code of a match `distance` pixels back, with the short codes for the pixel above and the
+/// one to the left.
+fn distance_code(distance: usize, width: usize) -> usize {
+    match distance {
+        _ if distance == width => 1,
+        1 => 2,
+        _ => distance + NEIGHBORHOOD,
+    }
+}
+
+/// Prefix code, number of extra bits and extra bits of a length or distance code `value`.
+fn prefix(value: usize) -> (usize, u32, u32) {
+    if value <= 4 {
+        return (value - 1, 0, 0);
+    }
+    let offset = value - 1;
+    let high = usize::BITS - 1 - offset.leading_zeros();
+    let second = (offset >> (high - 1)) & 1;
+    let extra_bits = high - 1;
+    let extra = offset & ((1 << extra_bits) - 1);
+    (2 * high as usize + second, extra_bits, extra as u32)
+}
+
+/// Writes a prefix code for symbols occurring `counts` times and returns its lengths and codes.
+/// Codes of at most two symbols below 256 have a short form of their own.
+fn write_code(bits: &mut BitWriter, counts: &[u32]) -> (Vec<u8>, Vec<u32>) {
+    let used: Vec<usize> = (0..counts.len()).filter(|&s| counts[s] > 0).collect();
+    if used.len() <= 2 && used.iter().all(|&s| s < 256) {
+        let symbols = if used.is_empty() { vec![0] } else { used };
+        bits.write(1, 1);
+        bits.write(symbols.len() as u32 - 1, 1);
+        match symbols[0] {
+            first @ 0..=1 => {
+                bits.write(0, 1);
+                bits.write(first as u32, 1);
+            }
+            first => {
+                bits.write(1, 1);
+                bits.write(first as u32, 8);
+            }
+        }
+        // A single symbol takes no bits, two take one each.
+        let mut lengths = vec![0; counts.len()];
+        let mut codes = vec![0; counts.len()];
+        if let Some(&second) = symbols.get(1) {
+            bits.write(second as u32, 8);
+            lengths[symbols[0]] = 1;
+            (lengths[second], codes[second]) = (1, 1);
+        }
+        return (lengths, codes);
+    }
+
+    bits.write(0, 1);
+    let lengths = code_lengths(counts, 15);
+    let tokens = run_lengths(&lengths);
+    let mut length_counts = [0; 19];
+    for &(symbol, _, _) in &tokens {
+        length_counts[symbol] += 1;
+    }
+    let length_lengths = code_lengths(&length_counts, 7);
+    let length_codes = canonical(&length_lengths);
+    let written = CODE_LENGTH_ORDER
+        .iter()
+        .rposition(|&s| length_lengths[s] > 0)
+        .map_or(4, |last| (last + 1).max(4));
+    bits.write(written as u32 - 4, 4);
+    for &s in &CODE_LENGTH_ORDER[..written] {
+        bits.write(length_lengths[s] as u32, 3);
+    }
+    // Lengths for the whole alphabet follow, rather than up to a last symbol.
+    bits.write(0, 1);
+    for (symbol, extra_bits, extra) in tokens {
+        bits.code(length_codes[symbol], length_lengths[symbol] as u32);
+        bits.write(extra, extra_bits);
+    }
+    let codes = canonical(&lengths);
+    (lengths, codes)
+}
+
+/// Code lengths as symbols of the code length code, with their extra bits: 16 repeats the last
+/// nonzero length, 8 before the first, and 17 and 18 are runs of zeros.
+fn run_lengths(lengths: &[u8]) -> Vec<(usize, u32, u32)> {
+    let mut tokens = Vec::new();
+    let mut previous = 8;
+    let mut i = 0;
+    while i < lengths.len() {
+        let length = lengths[i];
+        let run = lengths[i..].iter().take_while(|&&l| l == length).count();
+        let (token, count) = match (length, run) {
+            (0, 11..) => ((18, 7, run.min(138) as u32 - 11), run.min(138)),
+            (0, 3..) => ((17, 3, run.min(10) as u32 - 3), run.min(10)),
+            (_, 3..) if length == previous => ((16, 2, run.min(6) as u32 - 3), run.min(6)),
+            _ => ((length as usize, 0, 0), 1),
+        };
+        if length > 0 {
+            previous = length;
+        }
+        tokens.push(token);
+        i += count;
+    }
+    tokens
+}
+
+/// Huffman code lengths for symbols occurring `counts` times, none longer than `limit`. Rare
+/// symbols are counted as more frequent until the lengths fit.
+fn code_lengths(counts: &[u32], limit: u8) -> Vec<u8> {
+    let mut counts = counts.to_vec();
+    // Decoders take a code of one symbol to have no bits, so it gets a second one.
+    while counts.iter().filter(|&&c| c > 0).count() < 2 {
+        let unused = counts.iter().position(|&c| c == 0).unwrap();
+        counts[unused] = 1;
+    }
+    let mut floor = 1;
+    loop {
+        let floored: Vec<u64> = counts
+            .iter()
+            .map(|&c| if c > 0 { c.max(floor) as u64 } else { 0 })
+            .collect();
+        let lengths = huffman(&floored);
+        if lengths.iter().all(|&l| l <= limit) {
+            return lengths;
+        }
+        floor *= 2;
+    }
+}
+
+fn huffman(counts: &[u64]) -> Vec<u8> {
+    let mut parents = vec![usize::MAX; counts.len()];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = (0..counts.len())
+        .filter(|&s| counts[s] > 0)
+        .map(|s| Reverse((counts[s], s)))
+        .collect();
+    while heap.len() > 1 {
+        let Reverse((a, i)) = heap.pop().unwrap();
+        let Reverse((b, j)) = heap.pop().unwrap();
+        let node = parents.len();
+        parents.push(usize::MAX);
+        (parents[i], parents[j]) = (node, node);
+        heap.push(Reverse((a + b, node)));
+    }
+    (0..counts.len())
+        .map(|s| {
+            let mut depth = 0;
+            let mut node = s;
+            while counts[s] > 0 && parents[node] != usize::MAX {
+                node = parents[node];
+                depth += 1;
+            }
+            depth
+        })
+        .collect()
+}
+
+/// Canonical codes of `lengths`, shorter codes first and in symbol order within a length.
+fn canonical(lengths: &[u8]) -> Vec<u32> {
+    let mut per_length = [0u32; 16];
+    for &length in lengths.iter().filter(|&&l| l > 0) {
+        per_length[length as usize] += 1;
+    }
+    let mut next = [0u32; 16];
+    let mut code = 0;
+    for length in 1..16 {
+        code = (code + per_length[length - 1]) << 1;
+        next[length] = code;
+    }
+    lengths
+        .iter()
+        .map(|&length| {
+            let code = next[length as usize];
+            next[length as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::png::{BitReader, Huffman};
+
+    /// A prefix code read back from a VP8L bitstream. Codes of one symbol take no bits.
+    enum Code {
+        Single(u16),
+        Huffman(Huffman),
+    }
+
+    impl Code {
+        fn read(bits: &mut BitReader, alphabet: usize) -> Option<Code> {
+            let mut lengths = vec![0; alphabet];
+            if bits.read(1)? == 1 {
+                let count = bits.read(1)? + 1;
+                let first = match bits.read(1)? {
+                    0 => bits.read(1)?,
+                    _ => bits.read(8)?,
+                } as usize;
+                if count == 1 {
+                    return Some(Code::Single(first as u16));
+                }
+                lengths[first] = 1;
+                lengths[bits.read(8)? as usize] = 1;
+                return Some(Code::Huffman(Huffman::new(&lengths)));
+            }
+            let mut length_lengths = [0; 19];
+            for &symbol in &CODE_LENGTH_ORDER[..bits.read(4)? as usize + 4] {
+                length_lengths[symbol] = bits.read(3)? as u8;
+            }
+            assert_eq!(bits.read(1)?, 0);
+            let length_code = Huffman::new(&length_lengths);
+            let (mut i, mut previous) = (0, 8);
+            while i < alphabet {
+                let (length, run) = match length_code.decode(bits)? {
+                    16 => (previous, 3 + bits.read(2)?),
+                    17 => (0, 3 + bits.read(3)?),
+                    18 => (0, 11 + bits.read(7)?),
+                    length => (length as u8, 1),
+                };
+                for _ in 0..run {
+                    lengths[i] = length;
+                    i += 1;
+                }
+                if length > 0 {
+                    previous = length;
+                }
+            }
+            Some(Code::Huffman(Huffman::new(&lengths)))
+        }
+
+        fn decode(&self, bits: &mut BitReader) -> Option<usize> {
+            match self {
+                Code::Single(symbol) => Some(*symbol as usize),
+                Code::Huffman(huffman) => huffman.decode(bits).map(usize::from),
+            }
+        }
+    }
+
+    /// The value of the length or distance prefix code `code`.
+    fn prefix_value(bits: &mut BitReader, code: usize) -> Option<usize> {
+        if code < 4 {
+            return Some(code + 1);
+        }
+        let extra_bits = (code as u32 - 2) >> 1;
+        let offset = (2 + (code & 1)) << extra_bits;
+        Some(offset + bits.read(extra_bits)? as usize + 1)
+    }
+
+    /// Decodes the VP8L bitstreams `lossless` writes, with only the subtract green transform.
+    fn decode(data: &[u8]) -> Option<(usize, usize, Vec<Pixel>)> {
+        let mut bits = BitReader::new(data);
+        assert_eq!(bits.read(8)?, 0x2f);
+        let width = bits.read(14)? as usize + 1;
+        let height = bits.read(14)? as usize + 1;
+        assert_eq!(bits.read(4)?, 0);
+        // Subtract green, then no further transforms, no color cache and one group of codes.
+        assert_eq!([bits.read(1)?, bits.read(2)?, bits.read(1)?], [1, 2, 0]);
+        assert_eq!(bits.read(2)?, 0);
+        let green = Code::read(&mut bits, 256 + LENGTH_CODES)?;
+        let red = Code::read(&mut bits, 256)?;
+        let blue = Code::read(&mut bits, 256)?;
+        let alpha = Code::read(&mut bits, 256)?;
+        let distances = Code::read(&mut bits, DISTANCE_CODES)?;
+
+        let mut argb: Vec<[u8; 4]> = Vec::with_capacity(width * height);
+        while argb.len() < width * height {
+            let g = green.decode(&mut bits)?;
+            if g < 256 {
+                let r = red.decode(&mut bits)? as u8;
+                let b = blue.decode(&mut bits)? as u8;
+                let a = alpha.decode(&mut bits)? as u8;
+                argb.push([a, r, g as u8, b]);
+                continue;
+            }
+            let length = prefix_value(&mut bits, g - 256)?;
+            let code = distances.decode(&mut bits)?;
+            let distance = match prefix_value(&mut bits, code)? {
+                1 => width,
+                2 => 1,
+                code => code - NEIGHBORHOOD,
+            };
+            for _ in 0..length {
+                argb.push(argb[argb.len() - distance]);
+            }
+        }
+        let pixels = argb
+            .into_iter()
+            .map(|[a, r, g, b]| Pixel {
+                r: r.wrapping_add(g),
+                g,
+                b: b.wrapping_add(g),
+                a,
+            })
+            .collect();
+        Some((width, height, pixels))
+    }
+
+    /// The chunks of a RIFF body, as their type and data.
+    fn chunks(mut body: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        let mut chunks = Vec::new();
+        while body.len() >= 8 {
+            let kind = body[..4].try_into().unwrap();
+            let size = u32::from_le_bytes(body[4..8].try_into().unwrap()) as usize;
+            chunks.push((kind, &body[8..8 + size]));
+            body = &body[(8 + size).next_multiple_of(2).min(body.len())..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn frames_decode_to_their_pixels() {
+        // Rows repeating the one above and runs for copies, and a sweep of colors for literals.
+        let frame = |shift: u32| -> Vec<Pixel> {
+            (0..37u32 * 23)
+                .map(|i| {
+                    let (x, y) = (i % 37, i / 37);
+                    let v = match y % 5 {
+                        0 => x / 8 * 40,
+                        _ => (x * 7 + y / 2 * 13 + shift) % 256,
+                    };
+                    Pixel {
+                        r: v as u8,
+                        g: (v * 3 % 256) as u8,
+                        b: (255 - v) as u8,
+                        a: 255,
+                    }
+                })
+                .collect()
+        };
+        let frames = [
+            frame(0),
+            frame(99),
+            vec![Pixel::from_rgb(0.0, 0.0, 0.0); 37 * 23],
+        ];
+        let mut out = Vec::new();
+        let mut webp = WebP::from_writer(&mut out, 37, 23, 20.0);
+        webp.repeat(Repeat::Forever);
+        for pixels in &frames {
+            webp.push_frame(pixels).unwrap();
+        }
+        webp.write_animation().unwrap();
+
+        assert_eq!(&out[..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(out[4..8].try_into().unwrap()) as usize,
+            out.len() - 8
+        );
+        assert_eq!(&out[8..12], b"WEBP");
+        let chunks = chunks(&out[12..]);
+        assert_eq!(&chunks[0].0, b"VP8X");
+        assert_eq!(chunks[1], (*b"ANIM", &[0, 0, 0, 0xff, 0, 0][..]));
+        assert_eq!(chunks.len(), 2 + frames.len());
+        for ((kind, frame), pixels) in chunks[2..].iter().zip(&frames) {
+            assert_eq!(kind, b"ANMF");
+            // 50 milliseconds per frame.
+            assert_eq!(frame[12..15], [50, 0, 0]);
+            let inner = self::chunks(&frame[16..]);
+            assert_eq!(&inner[0].0, b"VP8L");
+            let (width, height, decoded) = decode(inner[0].1).unwrap();
+            assert_eq!((width, height), (37, 23));
+            assert_eq!(&decoded, pixels);
+        }
+    }
+
+    #[test]
+    fn empty_sides_are_rejected() {
+        for (width, height) in [(0, 4), (4, 0)] {
+            let mut webp = WebP::from_writer(Vec::new(), width, height, 20.0);
+            assert!(webp.push_frame(&[]).is_err());
+            assert!(webp.write_animation().is_err());
+        }
+    }
+
+    #[test]
+    fn loop_counts_keep_sixteen_bits() {
+        let mut out = Vec::new();
+        let mut webp = WebP::from_writer(&mut out, 2, 2, 20.0);
+        webp.repeat(Repeat::Times(u16::MAX));
+        webp.push_frame(&[Pixel::from_rgb(0.0, 0.0, 0.0); 4])
+            .unwrap();
+        webp.write_animation().unwrap();
+        assert_eq!(chunks(&out[12..])[1].1[4..], [0xff, 0xff]);
+    }
+}
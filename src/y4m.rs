@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::{AnimationError, Pixel};
+
+/// A YUV4MPEG2 stream, the uncompressed video ffmpeg and mpv read from a pipe, so videos can be
+/// encoded outside the crate. Frames are converted to BT.601 4:2:0 and written as they are
+/// pushed.
+pub struct Y4m<W: Write = BufWriter<File>> {
+    writer: W,
+    width: u16,
+    height: u16,
+    framerate: f32,
+    started: bool,
+}
+
+impl Y4m {
+    pub fn new(
+        path: impl AsRef<Path>,
+        width: u16,
+        height: u16,
+        framerate: f32,
+    ) -> Result<Self, AnimationError> {
+        let file = File::create(path).map_err(|_| AnimationError::FileCreateError)?;
+        Ok(Self::from_writer(
+            BufWriter::new(file),
+            width,
+            height,
+            framerate,
+        ))
+    }
+}
+
+impl<W: Write> Y4m<W> {
+    /// A stream written to `writer`, e.g. `std::io::stdout().lock()` to pipe it into a player.
+    pub fn from_writer(writer: W, width: u16, height: u16, framerate: f32) -> Self {
+        Self {
+            writer,
+            width,
+            height,
+            framerate,
+            started: false,
+        }
+    }
+
+    /// Writes the `width` by `height` `pixels` of the next frame, after the stream header
+    /// before the first.
+    pub fn push_frame(&mut self, pixels: &[Pixel]) -> Result<(), AnimationError> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if pixels.len() != width * height {
+            return Err(AnimationError::FrameCreateError);
+        }
+        let mut out = Vec::with_capacity(2 * pixels.len());
+        if !self.started {
+            let rate = (self.framerate * 1000.0).round() as u32;
+            let header = format!(
+                "YUV4MPEG2 W{} H{} F{}:1000 Ip A1:1 C420jpeg\n",
+                width, height, rate
+            );
+            out.extend(header.as_bytes());
+            self.started = true;
+        }
+        out.extend(b"FRAME\n");
+
+        let rgb = |p: &Pixel| (p.r as f32, p.g as f32, p.b as f32);
+        out.extend(pixels.iter().map(|p| {
+            let (r, g, b) = rgb(p);
+            (16.0 + 0.257 * r + 0.504 * g + 0.098 * b).round() as u8
+        }));
+        // Chroma of every 2 by 2 block, clamped at the edges of odd sides.
+        let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+        let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+        let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+        for y in 0..chroma_height {
+            for x in 0..chroma_width {
+                let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sx = (2 * x + dx).min(width - 1);
+                    let sy = (2 * y + dy).min(height - 1);
+                    let (pr, pg, pb) = rgb(&pixels[sy * width + sx]);
+                    (r, g, b) = (r + pr / 4.0, g + pg / 4.0, b + pb / 4.0);
+                }
+                cb.push((128.0 - 0.148 * r - 0.291 * g + 0.439 * b).round() as u8);
+                cr.push((128.0 + 0.439 * r - 0.368 * g - 0.071 * b).round() as u8);
+            }
+        }
+        out.extend(cb);
+        out.extend(cr);
+        self.writer
+            .write_all(&out)
+            .map_err(|_| AnimationError::FrameEncodeError)
+    }
+
+    pub fn write_animation(mut self) -> Result<(), AnimationError> {
+        self.writer
+            .flush()
+            .map_err(|_| AnimationError::FrameEncodeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_convert_back_to_their_colors() {
+        // Flat 2 by 2 blocks, which keep their color through the chroma subsampling.
+        let colors = [(255, 0, 0), (0, 128, 255), (40, 200, 90), (250, 250, 250)];
+        let pixels: Vec<Pixel> = (0..4 * 4)
+            .map(|i| {
+                let (r, g, b) = colors[(i / 8) * 2 + (i % 4) / 2];
+                Pixel { r, g, b, a: 255 }
+            })
+            .collect();
+        let mut out = Vec::new();
+        let mut y4m = Y4m::from_writer(&mut out, 4, 4, 25.0);
+        y4m.push_frame(&pixels).unwrap();
+        y4m.push_frame(&pixels).unwrap();
+        y4m.write_animation().unwrap();
+
+        let header = b"YUV4MPEG2 W4 H4 F25000:1000 Ip A1:1 C420jpeg\n";
+        assert!(out.starts_with(header));
+        let frame = b"FRAME\n".len() + 16 + 4 + 4;
+        assert_eq!(out.len(), header.len() + 2 * frame);
+        let data = &out[header.len() + 6..header.len() + frame];
+        let (luma, cb, cr) = (&data[..16], &data[16..20], &data[20..]);
+        for (i, pixel) in pixels.iter().enumerate() {
+            let block = (i / 8) * 2 + (i % 4) / 2;
+            let y = 1.164 * (luma[i] as f32 - 16.0);
+            let (u, v) = (cb[block] as f32 - 128.0, cr[block] as f32 - 128.0);
+            let rgb = [y + 1.596 * v, y - 0.392 * u - 0.813 * v, y + 2.017 * u];
+            for (channel, value) in [pixel.r, pixel.g, pixel.b].into_iter().zip(rgb) {
+                assert!((channel as f32 - value.clamp(0.0, 255.0)).abs() < 3.0);
+            }
+        }
+    }
+}